@@ -1,9 +1,10 @@
+use std::cell::{Cell, RefCell};
 use std::collections::{BTreeMap, HashSet};
 use std::fs;
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime};
 
 use anyhow::{Context, Result};
 use crossterm::event::{
@@ -30,4597 +31,13045 @@ use std::os::unix::fs::PermissionsExt;
 const MAX_COLUMNS: u16 = 6;
 const CUSTOM_THEME_KEY: &str = "custom";
 const SAVED_THEME_PREFIX: &str = "saved:";
+const FILE_THEME_PREFIX: &str = "file:";
+
+const USAGE: &str = "Usage: menu-maker [--profile <name> | --config <menu-file>] [command]
+
+Without a command the interactive menu starts. Commands:
+  list [--json]                          Print all items
+  run <category>/<label>                 Run an item and exit with its status
+  add <category>/<label> <command> [--info <text>] [--pause]
+  edit <category>/<label> [--label <text>] [--command <text>] [--info <text>] [--pause | --no-pause]
+  remove <category>/<label>
+  move <category>/<label> <category> [--to-profile <name>]
+  theme list
+  theme set <name>
+  import <old-menu-file>                 Merge a menu file from an earlier release
+  convert <json|toml|yaml>               Rewrite the menu file in another format
+  export <file.md|file.html|file.sh|directory|-> [--format markdown|html|script|desktop]
+                                         Write a cheat sheet, a shell script or .desktop files";
 
 fn main() -> Result<()> {
-    let mut app = AppState::new()?;
+    let mut profile = None;
+    let mut config = None;
+    let mut args = Vec::new();
+    let mut raw = std::env::args().skip(1);
+    while let Some(arg) = raw.next() {
+        if let Some(name) = arg.strip_prefix("--profile=") {
+            profile = Some(name.to_string());
+        } else if let Some(path) = arg.strip_prefix("--config=") {
+            config = Some(PathBuf::from(path));
+        } else if arg == "--profile" {
+            profile = Some(raw.next().context("--profile needs a name")?);
+        } else if arg == "--config" {
+            config = Some(PathBuf::from(raw.next().context("--config needs a path")?));
+        } else {
+            args.push(arg);
+        }
+    }
+    let paths = AppPaths::new(profile.as_deref(), config)?;
+    if let Some(legacy) = &paths.migrated_from {
+        eprintln!(
+            "Moved {} to {}",
+            display_path(legacy),
+            display_path(&paths.config_dir)
+        );
+    }
+    if !args.is_empty() {
+        let code = run_cli(&paths, args)?;
+        std::process::exit(code);
+    }
+    let mut app = AppState::new(paths)?;
     run_app(&mut app)
 }
 
-fn run_app(app: &mut AppState) -> Result<()> {
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    stdout.execute(EnterAlternateScreen)?;
-    stdout.execute(EnableMouseCapture)?;
+/// Run a headless subcommand against the menu file and return the process
+/// exit code.
+fn run_cli(paths: &AppPaths, mut args: Vec<String>) -> Result<i32> {
+    let command = args.remove(0);
+    // Commands that change the menu hold the lock from reading to writing.
+    let _lock = match command.as_str() {
+        "add" | "edit" | "remove" | "move" | "theme" | "import" | "convert" => {
+            Some(lock_file(&paths.menu_file)?)
+        }
+        _ => None,
+    };
+    match command.as_str() {
+        "list" => {
+            let json = take_flag(&mut args, "--json");
+            expect_args(&args, 0)?;
+            cli_list(&cli_layers(paths)?, json)?;
+        }
+        "run" => {
+            expect_args(&args, 1)?;
+            for (source, menu_file) in cli_layers(paths)? {
+                if let Ok((category, index)) = find_cli_item(&menu_file, &args[0]) {
+                    let dir = match source {
+                        ItemSource::Project => paths.project_root(),
+                        _ => None,
+                    };
+                    return cli_run(&menu_file.categories[&category].items[index], dir);
+                }
+            }
+            anyhow::bail!("No menu item named '{}'", args[0]);
+        }
+        "add" => {
+            let info = take_option(&mut args, "--info")?;
+            let pause = take_flag(&mut args, "--pause");
+            expect_args(&args, 2)?;
+            let mut menu_file = paths.load_menu()?;
+            let (category, label) = split_item_path(&menu_file, &args[0])?;
+            let entry = menu_file
+                .categories
+                .entry(category.clone())
+                .or_insert_with(|| CategoryConfig {
+                    expanded: true,
+                    column: Some(1),
+                    items: Vec::new(),
+                    colors: None,
+                });
+            if entry.items.iter().any(|item| item.label == label) {
+                anyhow::bail!("{category}/{label} already exists");
+            }
+            entry.items.push(MenuItemConfig {
+                label: label.clone(),
+                cmd: args[1].clone(),
+                info,
+                pause: Some(pause),
+                style: ItemStyle::default(),
+            });
+            save_cli_menu(paths, menu_file)?;
+            println!("Added {category}/{label}");
+        }
+        "edit" => {
+            let label = take_option(&mut args, "--label")?;
+            let cmd = take_option(&mut args, "--command")?;
+            let info = take_option(&mut args, "--info")?;
+            let pause = if take_flag(&mut args, "--pause") {
+                Some(true)
+            } else if take_flag(&mut args, "--no-pause") {
+                Some(false)
+            } else {
+                None
+            };
+            expect_args(&args, 1)?;
+            let mut menu_file = paths.load_menu()?;
+            let (category, index) = find_user_cli_item(paths, &menu_file, &args[0])?;
+            let items = &mut menu_file.categories.get_mut(&category).unwrap().items;
+            if let Some(label) = &label {
+                if items
+                    .iter()
+                    .enumerate()
+                    .any(|(other, item)| other != index && &item.label == label)
+                {
+                    anyhow::bail!("{category}/{label} already exists");
+                }
+            }
+            let item = &mut items[index];
+            if let Some(label) = label {
+                item.label = label;
+            }
+            if let Some(cmd) = cmd {
+                item.cmd = cmd;
+            }
+            if info.is_some() {
+                item.info = info;
+            }
+            if pause.is_some() {
+                item.pause = pause;
+            }
+            let label = item.label.clone();
+            save_cli_menu(paths, menu_file)?;
+            println!("Updated {category}/{label}");
+        }
+        "remove" => {
+            expect_args(&args, 1)?;
+            let mut menu_file = paths.load_menu()?;
+            let (category, index) = find_user_cli_item(paths, &menu_file, &args[0])?;
+            let item = take_cli_item(&mut menu_file, &category, index);
+            save_cli_menu(paths, menu_file)?;
+            println!("Removed {category}/{}", item.label);
+        }
+        "move" => {
+            let to_profile = take_option(&mut args, "--to-profile")?
+                .filter(|profile| paths.profile.as_ref() != Some(profile));
+            expect_args(&args, 2)?;
+            let mut menu_file = paths.load_menu()?;
+            let (category, index) = find_user_cli_item(paths, &menu_file, &args[0])?;
+            let target_category = args[1].trim().to_string();
+            if target_category.is_empty() {
+                anyhow::bail!("Target category is empty");
+            }
+            let item = take_cli_item(&mut menu_file, &category, index);
+            let label = item.label.clone();
+            let mut target_file = match &to_profile {
+                Some(profile) => {
+                    validate_profile_name(profile).map_err(anyhow::Error::msg)?;
+                    let mut target_paths = paths.clone();
+                    target_paths.select_profile(profile);
+                    let lock = lock_file(&target_paths.menu_file)?;
+                    Some((target_paths.load_menu()?, target_paths, lock))
+                }
+                None => None,
+            };
+            let destination = match target_file.as_mut() {
+                Some((target, _, _)) => target,
+                None => &mut menu_file,
+            };
+            let entry = destination
+                .categories
+                .entry(target_category.clone())
+                .or_insert_with(|| CategoryConfig {
+                    expanded: true,
+                    column: Some(1),
+                    items: Vec::new(),
+                    colors: None,
+                });
+            if entry.items.iter().any(|existing| existing.label == label) {
+                anyhow::bail!("{target_category}/{label} already exists");
+            }
+            entry.items.push(item);
+            if let Some((target, target_paths, _lock)) = target_file {
+                save_cli_menu(&target_paths, target)?;
+            }
+            save_cli_menu(paths, menu_file)?;
+            match to_profile {
+                Some(profile) => println!(
+                    "Moved {category}/{label} to {target_category}/{label} in profile {profile}"
+                ),
+                None => println!("Moved {category}/{label} to {target_category}/{label}"),
+            }
+        }
+        "theme" => match args.first().map(String::as_str) {
+            Some("list") => {
+                expect_args(&args, 1)?;
+                let menu_file = paths.load_menu()?;
+                for (key, _) in THEME_PRESETS {
+                    println!("{key}");
+                }
+                for saved in &menu_file.saved_themes {
+                    println!("{}", saved.name);
+                }
+                for entry in load_theme_directory(&paths.themes_dir).0 {
+                    println!("{}", entry.stem);
+                }
+                println!("{CUSTOM_THEME_KEY}");
+            }
+            Some("set") => {
+                expect_args(&args, 2)?;
+                let mut menu_file = paths.load_menu()?;
+                let key = resolve_cli_theme(paths, &menu_file, &args[1])?;
+                menu_file.app_settings.theme_key = Some(key.clone());
+                save_cli_menu(paths, menu_file)?;
+                println!("Theme set to {key}");
+            }
+            _ => anyhow::bail!(USAGE),
+        },
+        "import" => {
+            expect_args(&args, 1)?;
+            import_legacy_menu(paths, Path::new(&args[0]))?;
+        }
+        "export" => {
+            let format = take_option(&mut args, "--format")?;
+            expect_args(&args, 1)?;
+            let format = match format {
+                Some(name) => ExportFormat::from_name(&name).with_context(|| {
+                    format!("Unknown export format '{name}'; use markdown, html, script or desktop")
+                })?,
+                None => ExportFormat::from_path(&expand_home(&args[0]))?,
+            };
+            let menu_file = paths.load_menu()?;
+            if args[0] == "-" {
+                print!("{}", format.render(&menu_file)?);
+            } else {
+                println!(
+                    "{}",
+                    export_menu(&menu_file, format, &expand_home(&args[0]))?
+                );
+            }
+        }
+        "convert" => {
+            expect_args(&args, 1)?;
+            let format = MenuFormat::from_name(&args[0])
+                .with_context(|| format!("Unknown format '{}'; use json, toml or yaml", args[0]))?;
+            convert_menu_file(paths, format)?;
+        }
+        "help" | "--help" | "-h" => println!("{USAGE}"),
+        _ => anyhow::bail!("Unknown command '{command}'\n\n{USAGE}"),
+    }
+    Ok(0)
+}
 
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
-    terminal.hide_cursor()?;
+fn take_flag(args: &mut Vec<String>, name: &str) -> bool {
+    match args.iter().position(|arg| arg == name) {
+        Some(index) => {
+            args.remove(index);
+            true
+        }
+        None => false,
+    }
+}
 
-    let result = event_loop(&mut terminal, app);
+fn take_option(args: &mut Vec<String>, name: &str) -> Result<Option<String>> {
+    let Some(index) = args.iter().position(|arg| arg == name) else {
+        return Ok(None);
+    };
+    args.remove(index);
+    if index >= args.len() {
+        anyhow::bail!("{name} needs a value");
+    }
+    Ok(Some(args.remove(index)))
+}
 
-    restore_terminal(&mut terminal)?;
-    result
+fn expect_args(args: &[String], count: usize) -> Result<()> {
+    if args.len() != count {
+        anyhow::bail!(USAGE);
+    }
+    Ok(())
 }
 
-fn event_loop<B>(terminal: &mut Terminal<B>, app: &mut AppState) -> Result<()>
-where
-    B: ratatui::backend::Backend + Write,
-{
-    let tick_rate = Duration::from_millis(200);
-    loop {
-        terminal.draw(|frame| render(frame, app))?;
+/// Split `category/label`. Category names may contain `/`, so an existing
+/// category matching a longer prefix wins.
+fn split_item_path(menu_file: &MenuFile, path: &str) -> Result<(String, String)> {
+    let splits: Vec<(&str, &str)> = path
+        .match_indices('/')
+        .map(|(index, _)| (&path[..index], &path[index + 1..]))
+        .filter(|(category, label)| !category.is_empty() && !label.is_empty())
+        .collect();
+    splits
+        .iter()
+        .rev()
+        .find(|(category, _)| menu_file.categories.contains_key(*category))
+        .or_else(|| splits.first())
+        .map(|(category, label)| (category.to_string(), label.to_string()))
+        .with_context(|| format!("Expected <category>/<label>, got '{path}'"))
+}
 
-        if event::poll(tick_rate)? {
-            match event::read()? {
-                Event::Key(key) => app.handle_key(key),
-                Event::Mouse(mouse) => {
-                    let size = terminal.size()?;
-                    app.handle_mouse(mouse, size);
-                }
-                Event::Resize(_, _) => {}
-                Event::FocusGained | Event::FocusLost | Event::Paste(_) => {}
-            };
+/// The user's menu followed by the read-only layers that could be read.
+fn cli_layers(paths: &AppPaths) -> Result<Vec<(ItemSource, MenuFile)>> {
+    let mut layers = vec![(ItemSource::User, paths.load_menu()?)];
+    for (source, path) in paths.layer_files() {
+        match MenuFile::read(&path) {
+            Ok(layer) => layers.push((source, layer)),
+            Err(err) => eprintln!("Skipping {}: {err}", display_path(&path)),
         }
+    }
+    Ok(layers)
+}
 
-        if let Some(pending) = app.take_pending_command() {
-            match run_command(terminal, &pending) {
-                Ok(code) => {
-                    app.set_status(Some(format!(
-                        "Command exited with status {}",
-                        code.unwrap_or_default()
-                    )));
-                }
-                Err(err) => app.set_status(Some(format!("Command failed: {err}"))),
+/// Like `find_cli_item` on the user's menu, explaining when the item exists
+/// only in a read-only layer.
+fn find_user_cli_item(
+    paths: &AppPaths,
+    menu_file: &MenuFile,
+    path: &str,
+) -> Result<(String, usize)> {
+    find_cli_item(menu_file, path).map_err(|err| {
+        for (_, layer_path) in paths.layer_files() {
+            if MenuFile::read(&layer_path).is_ok_and(|layer| find_cli_item(&layer, path).is_ok()) {
+                return anyhow::anyhow!(
+                    "{path} comes from {} and is read-only",
+                    display_path(&layer_path)
+                );
             }
         }
+        err
+    })
+}
 
-        if let Some(action) = app.take_pending_action() {
-            app.execute_deferred_action(terminal, action)?;
+fn find_cli_item(menu_file: &MenuFile, path: &str) -> Result<(String, usize)> {
+    for (index, _) in path.match_indices('/') {
+        let (category, label) = (&path[..index], &path[index + 1..]);
+        if let Some(position) = menu_file
+            .categories
+            .get(category)
+            .and_then(|config| config.items.iter().position(|item| item.label == label))
+        {
+            return Ok((category.to_string(), position));
         }
+    }
+    anyhow::bail!("No menu item named '{path}'")
+}
 
-        if app.should_quit {
-            break;
-        }
+/// Remove an item, dropping its category when it becomes empty as the
+/// interface does.
+fn take_cli_item(menu_file: &mut MenuFile, category: &str, index: usize) -> MenuItemConfig {
+    let config = menu_file.categories.get_mut(category).unwrap();
+    let item = config.items.remove(index);
+    if config.items.is_empty() {
+        menu_file.categories.remove(category);
     }
-    Ok(())
+    item
 }
 
-fn restore_terminal<B>(terminal: &mut Terminal<B>) -> Result<()>
-where
-    B: ratatui::backend::Backend + Write,
-{
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
-    terminal.show_cursor()?;
+/// Rewrite the active menu file in another format next to it. The old file
+/// goes to the backups directory once the new one reads back identically.
+fn convert_menu_file(paths: &AppPaths, format: MenuFormat) -> Result<()> {
+    let source = &paths.menu_file;
+    if MenuFormat::from_path(source) == format {
+        anyhow::bail!("{} is already {}", display_path(source), format.name());
+    }
+    let target = source.with_extension(format.name());
+    if target.exists() {
+        anyhow::bail!("{} already exists", display_path(&target));
+    }
+    let menu_file = MenuFile::load(source, &paths.backups_for(source))?;
+    menu_file.save(&target)?;
+    let converted = MenuFile::load(&target, &paths.backups_for(&target));
+    let lossless = match &converted {
+        Ok(converted) => serde_json::to_value(converted)? == serde_json::to_value(&menu_file)?,
+        Err(_) => false,
+    };
+    if !lossless {
+        let _ = fs::remove_file(&target);
+        anyhow::bail!(
+            "{} cannot hold this menu without changes; {} is untouched",
+            format.name().to_uppercase(),
+            display_path(source)
+        );
+    }
+    let keep = menu_file
+        .app_settings
+        .backup_count
+        .unwrap_or(DEFAULT_BACKUP_COUNT);
+    backup_file(source, &paths.backups_for(source), keep)?;
+    // The old lock file stays: it is still held here, and another process
+    // may be waiting on it. A running app switches to `target` once it sees
+    // `source` is gone.
+    fs::remove_file(source)?;
+    println!(
+        "Converted {} to {}",
+        display_path(source),
+        display_path(&target)
+    );
     Ok(())
 }
 
-fn render(frame: &mut Frame, app: &AppState) {
-    let size = frame.size();
-    frame.render_widget(
-        Block::default().style(Style::default().bg(app.theme.background)),
-        size,
-    );
+/// Ways to share a menu with people who do not run menu-maker.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ExportFormat {
+    Markdown,
+    Html,
+    Script,
+    Desktop,
+}
 
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(1),
-            Constraint::Length(1),
-            Constraint::Min(1),
-            Constraint::Length(1),
-        ])
-        .split(size);
+impl ExportFormat {
+    /// Picked from the extension; a path without one, or an existing
+    /// directory, is a directory for desktop entries.
+    fn from_path(path: &Path) -> Result<Self> {
+        if path == Path::new("-") {
+            return Ok(ExportFormat::Markdown);
+        }
+        if path.is_dir() {
+            return Ok(ExportFormat::Desktop);
+        }
+        match path.extension().map(|ext| ext.to_string_lossy()) {
+            None => Ok(ExportFormat::Desktop),
+            Some(ext) => match ext.to_ascii_lowercase().as_str() {
+                "md" | "markdown" => Ok(ExportFormat::Markdown),
+                "html" | "htm" => Ok(ExportFormat::Html),
+                "sh" => Ok(ExportFormat::Script),
+                _ => anyhow::bail!(
+                    "Cannot export to .{ext}; use .md, .html, .sh or a directory for .desktop files"
+                ),
+            },
+        }
+    }
 
-    let header = Paragraph::new(app.title.clone())
-        .alignment(Alignment::Center)
-        .style(
-            Style::default()
-                .fg(app.theme.text)
-                .bg(app.theme.primary)
-                .add_modifier(Modifier::BOLD),
-        );
-    frame.render_widget(header, chunks[0]);
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "markdown" | "md" => Some(ExportFormat::Markdown),
+            "html" | "htm" => Some(ExportFormat::Html),
+            "script" | "sh" => Some(ExportFormat::Script),
+            "desktop" => Some(ExportFormat::Desktop),
+            _ => None,
+        }
+    }
 
-    let shortcuts_bg = color_from_hex("#76B3C5").unwrap_or(app.theme.highlight);
-    let shortcuts = Paragraph::new(app.footer_line())
-        .alignment(Alignment::Center)
-        .style(Style::default().bg(shortcuts_bg));
-    frame.render_widget(shortcuts, chunks[1]);
+    /// Single-file formats; desktop entries are written by `export_menu`.
+    fn render(self, menu_file: &MenuFile) -> Result<String> {
+        match self {
+            ExportFormat::Markdown => Ok(export_markdown(menu_file)),
+            ExportFormat::Html => Ok(export_html(menu_file)),
+            ExportFormat::Script => Ok(export_script(menu_file)),
+            ExportFormat::Desktop => anyhow::bail!("Desktop entries need a directory"),
+        }
+    }
+}
 
-    let content_area = chunks[2];
-    frame.render_widget(
-        Block::default().style(Style::default().bg(app.theme.surface)),
-        content_area,
-    );
-    render_columns(
-        frame,
-        content_area.inner(&Margin {
-            vertical: 1,
-            horizontal: 1,
-        }),
-        app,
-    );
+/// Write `menu_file` to `target` and describe what was written.
+fn export_menu(menu_file: &MenuFile, format: ExportFormat, target: &Path) -> Result<String> {
+    if format == ExportFormat::Desktop {
+        let count = export_desktop_entries(menu_file, target)?;
+        return Ok(format!(
+            "Wrote {count} desktop entries to {}",
+            display_path(target)
+        ));
+    }
+    if let Some(parent) = target.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    write_atomic(target, format.render(menu_file)?.as_bytes())?;
+    #[cfg(unix)]
+    if format == ExportFormat::Script {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(target, fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(format!("Exported the menu to {}", display_path(target)))
+}
 
-    let status = Paragraph::new(app.status_text())
-        .alignment(Alignment::Center)
-        .style(
-            Style::default()
-                .bg(app.theme.primary)
-                .fg(app.theme.text)
-                .add_modifier(Modifier::BOLD),
-        );
-    frame.render_widget(status, chunks[3]);
+/// Categories in the order the app shows them: by column, then by name.
+fn export_categories(menu_file: &MenuFile) -> Vec<(&String, &CategoryConfig)> {
+    let mut categories: Vec<_> = menu_file.categories.iter().collect();
+    categories.sort_by_key(|(name, config)| (config.column.unwrap_or(1), name.as_str()));
+    categories
+}
 
-    if let Some(popup) = &app.active_popup {
-        render_popup(frame, popup, app);
+fn export_title(menu_file: &MenuFile) -> &str {
+    menu_file
+        .app_settings
+        .title
+        .as_deref()
+        .filter(|title| !title.trim().is_empty())
+        .unwrap_or("Menu Maker")
+}
+
+fn export_markdown(menu_file: &MenuFile) -> String {
+    let mut out = format!("# {}\n", markdown_escape(export_title(menu_file)));
+    for (name, category) in export_categories(menu_file) {
+        out.push_str(&format!("\n## {}\n\n", markdown_escape(name)));
+        for item in &category.items {
+            let label = markdown_escape(&item.label);
+            match item.info.as_deref().filter(|info| !info.trim().is_empty()) {
+                Some(info) => out.push_str(&format!("- **{label}**: {}\n", markdown_escape(info))),
+                None => out.push_str(&format!("- **{label}**\n")),
+            }
+            // The fence has to be longer than any run of backticks inside.
+            let longest_run = item
+                .cmd
+                .split(|c| c != '`')
+                .map(str::len)
+                .max()
+                .unwrap_or(0);
+            let fence = "`".repeat((longest_run + 1).max(3));
+            out.push_str(&format!("\n  {fence}sh\n"));
+            for line in item.cmd.lines() {
+                out.push_str(&format!("  {line}\n"));
+            }
+            out.push_str(&format!("  {fence}\n\n"));
+        }
     }
+    out
 }
 
-fn render_columns(frame: &mut Frame, area: Rect, app: &AppState) {
-    if area.width == 0 || area.height == 0 {
-        return;
+/// Backslash-escape the characters Markdown would read as formatting, and
+/// keep line breaks from ending a list item or heading.
+fn markdown_escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for ch in text.chars() {
+        match ch {
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|' | '~' | '&' => {
+                escaped.push('\\');
+                escaped.push(ch);
+            }
+            '\n' | '\r' => escaped.push(' '),
+            _ => escaped.push(ch),
+        }
     }
+    escaped
+}
 
-    let column_count = app.column_count.max(1);
-    let constraints = (0..column_count)
-        .map(|_| Constraint::Ratio(1, column_count as u32))
-        .collect::<Vec<_>>();
-    let column_chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints(constraints)
-        .split(area);
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
 
-    for (col_idx, chunk) in column_chunks.iter().enumerate() {
-        let mut items: Vec<ListItem> = Vec::new();
-        if let Some(entries) = app.column_map.get(col_idx) {
-            for entry_index in entries {
-                let (line, style) = app.entry_line(*entry_index);
-                let (mut display_line, mut entry_style) = (line, style);
-                if *entry_index == app.current_index {
-                    entry_style = entry_style
-                        .bg(app.theme.highlight)
-                        .fg(app.theme.background)
-                        .add_modifier(Modifier::BOLD);
-                    display_line = app.highlight_entry_line(display_line);
-                }
-                items.push(ListItem::new(display_line).style(entry_style));
+fn export_html(menu_file: &MenuFile) -> String {
+    let title = html_escape(export_title(menu_file));
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>\n\
+         body {{ font-family: sans-serif; max-width: 60rem; margin: 2rem auto; padding: 0 1rem; }}\n\
+         table {{ border-collapse: collapse; width: 100%; margin-bottom: 2rem; }}\n\
+         th, td {{ border: 1px solid #ccc; padding: 0.4rem 0.6rem; text-align: left; vertical-align: top; }}\n\
+         pre {{ margin: 0; white-space: pre-wrap; }}\n\
+         </style>\n</head>\n<body>\n<h1>{title}</h1>\n"
+    );
+    for (name, category) in export_categories(menu_file) {
+        out.push_str(&format!(
+            "<h2>{}</h2>\n<table>\n<tr><th>Item</th><th>Command</th><th>Description</th></tr>\n",
+            html_escape(name)
+        ));
+        for item in &category.items {
+            out.push_str(&format!(
+                "<tr><td>{}</td><td><pre><code>{}</code></pre></td><td>{}</td></tr>\n",
+                html_escape(&item.label),
+                html_escape(&item.cmd),
+                html_escape(item.info.as_deref().unwrap_or_default())
+            ));
+        }
+        out.push_str("</table>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+/// Quote for a POSIX shell: single quotes, with embedded ones spliced in.
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+/// `text` as one shell word, quoted only when it has to be.
+fn shell_word(text: &str) -> String {
+    let plain = !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-./:@%+=,".contains(c));
+    if plain {
+        text.to_string()
+    } else {
+        shell_quote(text)
+    }
+}
+
+/// A script that offers the same menu with bash's `select` prompt.
+/// `select` is not in POSIX sh, so the script asks for bash.
+fn export_script(menu_file: &MenuFile) -> String {
+    let categories = export_categories(menu_file);
+    let mut out = String::from("#!/usr/bin/env bash\n");
+    out.push_str("# Generated by menu-maker. Run it with bash.\n\n");
+    out.push_str("pause() {\n    printf '\\nPress Enter to continue...'\n    read -r _\n}\n");
+    for (index, (name, category)) in categories.iter().enumerate() {
+        out.push_str(&format!("\ncategory_{}() {{\n", index + 1));
+        out.push_str(&format!("    PS3={}\n", shell_quote(&format!("{name}> "))));
+        let labels: Vec<String> = category
+            .items
+            .iter()
+            .map(|item| shell_quote(&item.label))
+            .chain(std::iter::once("'Back'".to_string()))
+            .collect();
+        out.push_str(&format!("    select _ in {}; do\n", labels.join(" ")));
+        out.push_str("        case $REPLY in\n");
+        for (item_index, item) in category.items.iter().enumerate() {
+            out.push_str(&format!(
+                "            {}) sh -c {}",
+                item_index + 1,
+                shell_quote(&item.cmd)
+            ));
+            if item.pause.unwrap_or(false) {
+                out.push_str("; pause");
             }
+            out.push_str(" ;;\n");
         }
+        out.push_str(&format!(
+            "            {}) return ;;\n",
+            category.items.len() + 1
+        ));
+        out.push_str("            *) echo 'Invalid choice' ;;\n        esac\n    done\n}\n");
+    }
+    out.push_str(&format!(
+        "\necho {}\n",
+        shell_quote(export_title(menu_file))
+    ));
+    let names: Vec<String> = categories
+        .iter()
+        .map(|(name, _)| shell_quote(name))
+        .chain(std::iter::once("'Quit'".to_string()))
+        .collect();
+    // Both loops end at end of input, like the select prompt itself.
+    out.push_str("PS3='menu> '\n");
+    out.push_str(&format!("select _ in {}; do\n", names.join(" ")));
+    out.push_str("    case $REPLY in\n");
+    for index in 0..categories.len() {
+        out.push_str(&format!(
+            "        {}) category_{}; PS3='menu> ' ;;\n",
+            index + 1,
+            index + 1
+        ));
+    }
+    out.push_str(&format!("        {}) break ;;\n", categories.len() + 1));
+    out.push_str("        *) echo 'Invalid choice' ;;\n    esac\ndone\n");
+    out
+}
 
-        if items.is_empty() {
-            items.push(ListItem::new(""));
+/// Escape a value for a desktop entry: backslashes and line breaks.
+fn desktop_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+        .replace('\r', "\\r")
+}
+
+/// `sh -c "<command>"` quoted for the Exec key as the desktop entry
+/// specification asks.
+fn desktop_exec(command: &str) -> String {
+    let mut quoted = String::from("\"");
+    for ch in command.chars() {
+        if matches!(ch, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
         }
+        quoted.push(ch);
+    }
+    quoted.push('"');
+    desktop_escape(&format!("sh -c {quoted}")).replace('%', "%%")
+}
 
-        let list = List::new(items).block(
-            Block::default().style(Style::default().bg(app.theme.surface).fg(app.theme.text)),
-        );
-        frame.render_widget(list, *chunk);
+/// FNV-1a, which unlike `DefaultHasher` stays the same across releases, so
+/// names derived from it do too.
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Lowercase ASCII letters and digits joined by `-`. Text without any, such
+/// as a label in another script, becomes a hash of itself instead.
+fn slug(text: &str) -> String {
+    let mut slug = String::new();
+    for ch in text.chars() {
+        if ch.is_ascii_alphanumeric() {
+            slug.push(ch.to_ascii_lowercase());
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_matches('-');
+    if slug.is_empty() {
+        format!("{:08x}", fnv1a(text) as u32)
+    } else {
+        slug.to_string()
     }
 }
 
-fn render_popup(frame: &mut Frame, popup: &PopupState, app: &AppState) {
-    match popup {
-        PopupState::Info(info) => {
-            let area = centered_rect(frame.size(), 60, 40);
-            frame.render_widget(Clear, area);
-            let text = format!(
-                "Label: {}\nCommand: {}\nCategory: {}\nDescription: {}\n\nPress Enter or Esc to close.",
-                info.label, info.command, info.category, info.description
-            );
-            let block = Paragraph::new(text)
-                .style(Style::default().bg(app.theme.surface).fg(app.theme.text))
-                .block(
-                    Block::default()
-                        .title("Item Info")
-                        .borders(Borders::ALL)
-                        .style(Style::default().bg(app.theme.surface)),
-                );
-            frame.render_widget(block, area);
+/// One `menu-maker-<category>-<label>.desktop` file per item in `dir`.
+/// Items whose names reduce to the same file get `-2`, `-3`, ... appended.
+fn export_desktop_entries(menu_file: &MenuFile, dir: &Path) -> Result<usize> {
+    fs::create_dir_all(dir).with_context(|| format!("Unable to create {}", dir.display()))?;
+    let mut count = 0;
+    let mut used = HashSet::new();
+    for (name, category) in export_categories(menu_file) {
+        for item in &category.items {
+            let command = if item.pause.unwrap_or(false) {
+                format!(
+                    "{}\nprintf '\\nPress Enter to close...'; read -r _",
+                    item.cmd
+                )
+            } else {
+                item.cmd.clone()
+            };
+            let mut entry = String::from("[Desktop Entry]\nType=Application\n");
+            entry.push_str(&format!("Name={}\n", desktop_escape(&item.label)));
+            if let Some(info) = item.info.as_deref().filter(|info| !info.trim().is_empty()) {
+                entry.push_str(&format!("Comment={}\n", desktop_escape(info)));
+            }
+            entry.push_str(&format!("Exec={}\n", desktop_exec(&command)));
+            entry.push_str("Terminal=true\nCategories=Utility;\n");
+            entry.push_str(&format!("X-MenuMaker-Category={}\n", desktop_escape(name)));
+            let base = format!("menu-maker-{}-{}", slug(name), slug(&item.label));
+            let mut stem = base.clone();
+            let mut suffix = 1;
+            while !used.insert(stem.clone()) {
+                suffix += 1;
+                stem = format!("{base}-{suffix}");
+            }
+            write_atomic(&dir.join(format!("{stem}.desktop")), entry.as_bytes())?;
+            count += 1;
         }
-        PopupState::Message(msg) => {
-            let area = centered_rect(frame.size(), 50, 30);
-            frame.render_widget(Clear, area);
-            let block = Paragraph::new(format!("{msg}\n\nPress Enter or Esc to close."))
-                .style(Style::default().bg(app.theme.surface).fg(app.theme.text))
-                .block(
-                    Block::default()
-                        .title("Message")
-                        .borders(Borders::ALL)
-                        .style(Style::default().bg(app.theme.surface)),
-                );
-            frame.render_widget(block, area);
+    }
+    Ok(count)
+}
+
+fn save_cli_menu(paths: &AppPaths, mut menu_file: MenuFile) -> Result<()> {
+    if paths.shares_themes() {
+        menu_file.saved_themes.clear();
+        menu_file.custom_colors.clear();
+    }
+    menu_file.schema_version = MENU_SCHEMA_VERSION;
+    let keep = menu_file
+        .app_settings
+        .backup_count
+        .unwrap_or(DEFAULT_BACKUP_COUNT);
+    backup_file(&paths.menu_file, &paths.backups_for(&paths.menu_file), keep)?;
+    menu_file.save(&paths.menu_file)
+}
+
+fn cli_list(layers: &[(ItemSource, MenuFile)], json: bool) -> Result<()> {
+    // Keyed so that the project section is listed after the merged menu.
+    let mut categories: BTreeMap<(bool, &str), Vec<(ItemSource, &MenuItemConfig)>> =
+        BTreeMap::new();
+    for (source, menu_file) in layers {
+        for (category, config) in &menu_file.categories {
+            let key = (*source == ItemSource::Project, category.as_str());
+            let items = categories.entry(key).or_default();
+            items.extend(config.items.iter().map(|item| (*source, item)));
         }
-        PopupState::ItemForm(form) => {
-            let area = frame.size();
-            frame.render_widget(Clear, area);
-            render_item_form_popup(frame, area, app, form);
+    }
+    if json {
+        let items: Vec<serde_json::Value> = categories
+            .iter()
+            .flat_map(|((_, category), items)| {
+                items.iter().map(move |(source, item)| {
+                    serde_json::json!({
+                        "category": category,
+                        "label": item.label,
+                        "cmd": item.cmd,
+                        "info": item.info,
+                        "pause": item.pause.unwrap_or(false),
+                        "source": source.name(),
+                    })
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&items)?);
+        return Ok(());
+    }
+    for ((project, category), items) in &categories {
+        if *project {
+            println!("{category}  [project]");
+        } else {
+            println!("{category}");
         }
-        PopupState::CategoryForm(form) => {
-            let area = frame.size();
-            frame.render_widget(Clear, area);
-            render_category_form_popup(frame, area, app, form);
+        let width = items
+            .iter()
+            .map(|(_, item)| UnicodeWidthStr::width(item.label.as_str()))
+            .max()
+            .unwrap_or(0);
+        for (source, item) in items {
+            let padding = width - UnicodeWidthStr::width(item.label.as_str());
+            println!(
+                "  {}{}  {}{}",
+                item.label,
+                " ".repeat(padding),
+                // Multi-line commands from TOML or YAML show their first line.
+                match item.cmd.split_once('\n') {
+                    Some((first, _)) => format!("{first} …"),
+                    None => item.cmd.clone(),
+                },
+                if *project {
+                    String::new()
+                } else {
+                    source.marker()
+                }
+            );
         }
-        PopupState::SettingsForm(form) => {
-            let area = frame.size();
-            frame.render_widget(Clear, area);
-            render_settings_form_popup(frame, area, app, form);
+    }
+    Ok(())
+}
+
+/// Run an item with the terminal attached and map its status to an exit
+/// code; a signal becomes 128 + the signal number like in the shell.
+fn cli_run(item: &MenuItemConfig, dir: Option<&Path>) -> Result<i32> {
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(&item.cmd);
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    let status = command
+        .status()
+        .with_context(|| format!("Unable to run '{}'", item.cmd))?;
+    if let Some(code) = status.code() {
+        return Ok(code);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return Ok(128 + signal);
         }
     }
+    Ok(1)
 }
 
-fn popup_sections(area: Rect) -> Option<[Rect; 4]> {
-    let sections = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(1),
-            Constraint::Length(1),
-            Constraint::Min(3),
-            Constraint::Length(1),
-        ])
-        .split(area);
-    if sections.len() < 4 {
-        return None;
+/// Accept a preset key, a saved theme name, a theme file stem or `custom`.
+fn resolve_cli_theme(paths: &AppPaths, menu_file: &MenuFile, name: &str) -> Result<String> {
+    if name == CUSTOM_THEME_KEY || is_preset_theme_key(name) {
+        return Ok(name.to_string());
     }
-    Some([sections[0], sections[1], sections[2], sections[3]])
+    if let Some(index) = menu_file
+        .saved_themes
+        .iter()
+        .position(|saved| saved.name.eq_ignore_ascii_case(name))
+    {
+        return Ok(saved_theme_key(index));
+    }
+    if load_theme_directory(&paths.themes_dir)
+        .0
+        .iter()
+        .any(|entry| entry.stem == name)
+    {
+        return Ok(file_theme_key(name));
+    }
+    anyhow::bail!("Unknown theme '{name}'; see `menu-maker theme list`")
 }
 
-fn popup_content_margin() -> Margin {
-    Margin {
-        horizontal: 3,
-        vertical: 1,
+/// Merge a menu file from an earlier release (including the Python
+/// MenuMaker) into menus.json without starting the interface.
+fn import_legacy_menu(paths: &AppPaths, source: &Path) -> Result<()> {
+    let imported = MenuFile::read(source)?;
+    let mut menu_file = MenuFile::load(&paths.menu_file, &paths.backups_for(&paths.menu_file))?;
+    backup_file(
+        &paths.menu_file,
+        &paths.backups_for(&paths.menu_file),
+        menu_file
+            .app_settings
+            .backup_count
+            .unwrap_or(DEFAULT_BACKUP_COUNT),
+    )?;
+    let mut added = 0;
+    for (name, category) in imported.categories {
+        let target = menu_file
+            .categories
+            .entry(name)
+            .or_insert_with(|| CategoryConfig {
+                items: Vec::new(),
+                ..category.clone()
+            });
+        for item in category.items {
+            if target
+                .items
+                .iter()
+                .any(|existing| existing.label == item.label)
+            {
+                continue;
+            }
+            target.items.push(item);
+            added += 1;
+        }
     }
+    menu_file.save(&paths.menu_file)?;
+    println!(
+        "Imported {added} item(s) from {} into {}",
+        source.display(),
+        display_path(&paths.menu_file)
+    );
+    Ok(())
 }
 
-fn render_category_form_popup(
-    frame: &mut Frame,
-    area: Rect,
-    app: &AppState,
-    form: &CategoryFormState,
-) {
-    let (lines, layout) = form.render_lines(app);
-    if let Some(sections) = popup_sections(area) {
-        frame.render_widget(
-            Block::default().style(Style::default().bg(app.theme.background)),
-            area,
-        );
-        let [header_area, shortcuts_area, content_area, status_area] = sections;
-        let header = Paragraph::new(format!("{} - Edit Category", app.title))
-            .alignment(Alignment::Center)
-            .style(
-                Style::default()
-                    .bg(app.theme.primary)
-                    .fg(app.theme.text)
-                    .add_modifier(Modifier::BOLD),
-            );
-        frame.render_widget(header, header_area);
+/// A task found by `detect_tasks`, before it becomes a menu item.
+struct RunnerTask {
+    name: String,
+    command: String,
+    info: String,
+}
 
-        let shortcut_line = layout
-            .shortcut_line
-            .clone()
-            .unwrap_or_else(|| Line::from(""));
-        let shortcuts = Paragraph::new(shortcut_line)
-            .alignment(Alignment::Center)
-            .style(
-                Style::default()
-                    .bg(app.theme.highlight)
-                    .add_modifier(Modifier::BOLD),
-            );
-        frame.render_widget(shortcuts, shortcuts_area);
+/// Makefile targets, package.json scripts, justfile recipes and Cargo
+/// aliases declared in `dir`, one category per source. Every command
+/// changes into `dir` first.
+fn detect_tasks(dir: &Path) -> Result<Vec<ImportCandidate>> {
+    let dir = dir
+        .canonicalize()
+        .with_context(|| format!("Unable to open {}", display_path(dir)))?;
+    if !dir.is_dir() {
+        anyhow::bail!("{} is not a directory", display_path(&dir));
+    }
+    let project = dir
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| display_path(&dir));
+    let cd = format!("cd {} && ", shell_word(&dir.to_string_lossy()));
+    let mut candidates = Vec::new();
+    for (runner, tasks) in [
+        make_targets(&dir)?,
+        package_scripts(&dir)?,
+        just_recipes(&dir)?,
+        cargo_aliases(&dir)?,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        for task in tasks {
+            candidates.push(ImportCandidate {
+                category: format!("{project}: {runner}"),
+                label: task.name,
+                cmd: format!("{cd}{}", task.command),
+                info: task.info,
+                checked: true,
+            });
+        }
+    }
+    Ok(candidates)
+}
 
-        frame.render_widget(
-            Block::default().style(Style::default().bg(app.theme.surface)),
-            content_area,
-        );
-        let inner = content_area.inner(&popup_content_margin());
-        let rendered_lines = materialize_form_lines(&lines, inner.width as usize, app);
-        let paragraph = Paragraph::new(rendered_lines)
-            .wrap(Wrap { trim: true })
-            .style(Style::default().bg(app.theme.surface).fg(app.theme.text));
-        frame.render_widget(paragraph, inner);
+fn first_file(dir: &Path, names: &[&str]) -> Option<PathBuf> {
+    names
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.is_file())
+}
 
-        let status = Paragraph::new(app.status_text())
-            .alignment(Alignment::Center)
-            .style(
-                Style::default()
-                    .bg(app.theme.primary)
-                    .fg(app.theme.text)
-                    .add_modifier(Modifier::BOLD),
-            );
-        frame.render_widget(status, status_area);
+fn read_task_file(path: &Path) -> Result<String> {
+    fs::read_to_string(path).with_context(|| format!("Unable to read {}", display_path(path)))
+}
+
+fn is_task_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '/'))
+}
+
+/// Explicit targets of the Makefile. Pattern rules, special targets such as
+/// `.PHONY` and variable assignments are left out. A `## text` comment on
+/// the rule line becomes the description.
+fn make_targets(dir: &Path) -> Result<Option<(&'static str, Vec<RunnerTask>)>> {
+    let Some(path) = first_file(dir, &["GNUmakefile", "makefile", "Makefile"]) else {
+        return Ok(None);
+    };
+    let text = read_task_file(&path)?;
+    let mut tasks: Vec<RunnerTask> = Vec::new();
+    for line in text.lines() {
+        if line.starts_with(['\t', ' ', '#', '.']) {
+            continue;
+        }
+        let Some((targets, rest)) = line.split_once(':') else {
+            continue;
+        };
+        if rest.starts_with([':', '=']) || targets.contains(['=', '$', '%']) {
+            continue;
+        }
+        let info = match rest.split_once("##") {
+            Some((_, comment)) => comment.trim().to_string(),
+            None => format!("make target in {}", display_path(&path)),
+        };
+        for target in targets.split_whitespace() {
+            if is_task_name(target) && !tasks.iter().any(|task| task.name == target) {
+                tasks.push(RunnerTask {
+                    name: target.to_string(),
+                    command: format!("make {}", shell_word(target)),
+                    info: info.clone(),
+                });
+            }
+        }
     }
+    Ok(Some(("make", tasks)))
 }
 
-fn render_item_form_popup(frame: &mut Frame, area: Rect, app: &AppState, form: &ItemFormState) {
-    let mut lines: Vec<FormLine> = Vec::new();
-    lines.push(plain_line(Line::from("Fill in the menu item details below.")));
-    lines.push(make_field_line(
-        "Label",
-        &form.label,
-        form.selected_field == ItemField::Label,
-        app,
-    ));
-    lines.push(make_field_line(
-        "Command",
-        &form.command,
-        form.selected_field == ItemField::Command,
-        app,
-    ));
-    lines.push(make_field_line(
-        "Description",
-        &form.info,
-        form.selected_field == ItemField::Description,
-        app,
-    ));
-    lines.push(make_field_line(
-        "Category",
-        &form.category,
-        form.selected_field == ItemField::Category,
-        app,
-    ));
-    lines.push(make_toggle_line(
-        "Pause After Run",
-        form.pause,
-        form.selected_field == ItemField::Pause,
-        app,
-    ));
-    if let Some(error) = &form.error {
-        lines.push(plain_line(Line::from(vec![Span::styled(
-            error.clone(),
-            Style::default().fg(Color::Red),
-        )])));
+/// `scripts` of package.json, run with the package manager whose lock file
+/// is present.
+fn package_scripts(dir: &Path) -> Result<Option<(&'static str, Vec<RunnerTask>)>> {
+    let path = dir.join("package.json");
+    if !path.is_file() {
+        return Ok(None);
     }
-    if !form.available_categories.is_empty() {
-        lines.push(plain_line(Line::from("")));
-        lines.push(plain_line(Line::from(vec![Span::styled(
-            "Available Categories:",
-            Style::default()
-                .fg(app.theme.accent)
-                .add_modifier(Modifier::BOLD),
-        )])));
-        for category in &form.available_categories {
-            lines.push(plain_line(Line::from(format!("  • {}", category))));
+    let package: serde_json::Value = serde_json::from_str(&read_task_file(&path)?)
+        .with_context(|| format!("Unable to parse {}", display_path(&path)))?;
+    let runner = if dir.join("pnpm-lock.yaml").exists() {
+        "pnpm"
+    } else if dir.join("yarn.lock").exists() {
+        "yarn"
+    } else if dir.join("bun.lockb").exists() || dir.join("bun.lock").exists() {
+        "bun"
+    } else {
+        "npm"
+    };
+    let tasks = package
+        .get("scripts")
+        .and_then(serde_json::Value::as_object)
+        .into_iter()
+        .flatten()
+        .map(|(name, script)| RunnerTask {
+            name: name.clone(),
+            command: format!("{runner} run {}", shell_word(name)),
+            info: script.as_str().unwrap_or_default().to_string(),
+        })
+        .collect();
+    Ok(Some((runner, tasks)))
+}
+
+/// Public recipes of the justfile. The comment line above a recipe becomes
+/// its description, as in `just --list`.
+fn just_recipes(dir: &Path) -> Result<Option<(&'static str, Vec<RunnerTask>)>> {
+    let Some(path) = first_file(dir, &["justfile", "Justfile", ".justfile"]) else {
+        return Ok(None);
+    };
+    let text = read_task_file(&path)?;
+    let mut tasks: Vec<RunnerTask> = Vec::new();
+    let mut comment: Option<String> = None;
+    let mut private = false;
+    for line in text.lines() {
+        if line.starts_with([' ', '\t']) {
+            continue;
+        }
+        if let Some(text) = line.strip_prefix('#') {
+            if !text.starts_with('!') {
+                comment = Some(text.trim().to_string());
+            }
+            continue;
+        }
+        if line.starts_with('[') {
+            private |= line.contains("private");
+            continue;
+        }
+        let recipe = line
+            .split_once(':')
+            .filter(|(_, rest)| !rest.starts_with('='))
+            .and_then(|(head, _)| head.split_whitespace().next())
+            .map(|name| name.trim_start_matches('@'));
+        if let Some(name) = recipe {
+            if is_task_name(name)
+                && !name.starts_with('_')
+                && !private
+                && !tasks.iter().any(|task| task.name == name)
+            {
+                tasks.push(RunnerTask {
+                    name: name.to_string(),
+                    command: format!("just {name}"),
+                    info: comment
+                        .take()
+                        .unwrap_or_else(|| format!("just recipe in {}", display_path(&path))),
+                });
+            }
         }
+        comment = None;
+        private = false;
     }
+    Ok(Some(("just", tasks)))
+}
 
-    let shortcut_line = Line::from(vec![
-        Span::styled(
-            "Tab",
-            Style::default()
-                .fg(app.theme.accent)
-                .add_modifier(Modifier::BOLD),
-        ),
-        Span::raw("/"),
-        Span::styled(
-            "Shift+Tab",
-            Style::default()
-                .fg(app.theme.accent)
-                .add_modifier(Modifier::BOLD),
-        ),
-        Span::raw(" Move    "),
-        Span::styled(
-            "Enter",
-            Style::default()
-                .fg(app.theme.accent)
-                .add_modifier(Modifier::BOLD),
-        ),
-        Span::raw(" Save    "),
-        Span::styled(
-            "Esc",
-            Style::default()
-                .fg(app.theme.accent)
-                .add_modifier(Modifier::BOLD),
-        ),
-        Span::raw(" Cancel    "),
-        Span::styled(
-            "Space",
-            Style::default()
-                .fg(app.theme.accent)
-                .add_modifier(Modifier::BOLD),
-        ),
-        Span::raw(" Toggle Pause"),
-    ]);
-
-    if let Some(sections) = popup_sections(area) {
-        frame.render_widget(
-            Block::default().style(Style::default().bg(app.theme.background)),
-            area,
-        );
-        let [header_area, shortcuts_area, content_area, status_area] = sections;
-        let header = Paragraph::new(format!("{} - {}", app.title, form.mode_label))
-            .alignment(Alignment::Center)
-            .style(
-                Style::default()
-                    .bg(app.theme.primary)
-                    .fg(app.theme.text)
-                    .add_modifier(Modifier::BOLD),
-            );
-        frame.render_widget(header, header_area);
-
-        let shortcuts = Paragraph::new(shortcut_line)
-            .alignment(Alignment::Center)
-            .style(
-                Style::default()
-                    .bg(app.theme.highlight)
-                    .add_modifier(Modifier::BOLD),
-            );
-        frame.render_widget(shortcuts, shortcuts_area);
-
-        frame.render_widget(
-            Block::default().style(Style::default().bg(app.theme.surface)),
-            content_area,
-        );
-        let inner = content_area.inner(&popup_content_margin());
-        let rendered_lines = materialize_form_lines(&lines, inner.width as usize, app);
-        let paragraph = Paragraph::new(rendered_lines)
-            .wrap(Wrap { trim: true })
-            .style(Style::default().bg(app.theme.surface).fg(app.theme.text));
-        frame.render_widget(paragraph, inner);
-
-        let status = Paragraph::new(app.status_text())
-            .alignment(Alignment::Center)
-            .style(
-                Style::default()
-                    .bg(app.theme.primary)
-                    .fg(app.theme.text)
-                    .add_modifier(Modifier::BOLD),
-            );
-        frame.render_widget(status, status_area);
-    }
+/// `[alias]` entries of the project's Cargo configuration.
+fn cargo_aliases(dir: &Path) -> Result<Option<(&'static str, Vec<RunnerTask>)>> {
+    let Some(path) = first_file(dir, &[".cargo/config.toml", ".cargo/config"]) else {
+        return Ok(None);
+    };
+    let config: toml::Value = toml::from_str(&read_task_file(&path)?)
+        .with_context(|| format!("Unable to parse {}", display_path(&path)))?;
+    let tasks = config
+        .get("alias")
+        .and_then(toml::Value::as_table)
+        .into_iter()
+        .flatten()
+        .map(|(name, value)| {
+            let expansion = match value {
+                toml::Value::Array(words) => words
+                    .iter()
+                    .filter_map(toml::Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                other => other.as_str().unwrap_or_default().to_string(),
+            };
+            RunnerTask {
+                name: name.clone(),
+                command: format!("cargo {}", shell_word(name)),
+                info: format!("cargo {expansion}"),
+            }
+        })
+        .collect();
+    Ok(Some(("cargo", tasks)))
 }
 
-fn render_settings_form_popup(
-    frame: &mut Frame,
-    area: Rect,
-    app: &AppState,
-    form: &SettingsFormState,
-) {
-    let (lines, layout) = form.render_lines(app);
-    if let Some(sections) = popup_sections(area) {
-        frame.render_widget(
-            Block::default().style(Style::default().bg(app.theme.background)),
-            area,
-        );
-        let [header_area, shortcuts_area, content_area, status_area] = sections;
-        let header = Paragraph::new(format!("{} - Application Settings", app.title))
-            .alignment(Alignment::Center)
-            .style(
-                Style::default()
-                    .bg(app.theme.primary)
-                    .fg(app.theme.text)
-                    .add_modifier(Modifier::BOLD),
-            );
-        frame.render_widget(header, header_area);
-
-        let shortcut_line = layout
-            .shortcut_line
-            .clone()
-            .unwrap_or_else(|| Line::from(""));
-        let shortcuts = Paragraph::new(shortcut_line)
-            .alignment(Alignment::Center)
-            .style(
-                Style::default()
-                    .bg(app.theme.highlight)
-                    .add_modifier(Modifier::BOLD),
-            );
-        frame.render_widget(shortcuts, shortcuts_area);
-
-        frame.render_widget(
-            Block::default().style(Style::default().bg(app.theme.surface)),
-            content_area,
-        );
-        let inner = content_area.inner(&popup_content_margin());
-        let rendered_lines = materialize_form_lines(&lines, inner.width as usize, app);
-        let paragraph = Paragraph::new(rendered_lines)
-            .wrap(Wrap { trim: true })
-            .style(Style::default().bg(app.theme.surface).fg(app.theme.text));
-        frame.render_widget(paragraph, inner);
-
-        let status = Paragraph::new(app.status_text())
-            .alignment(Alignment::Center)
-            .style(
-                Style::default()
-                    .bg(app.theme.primary)
-                    .fg(app.theme.text)
-                    .add_modifier(Modifier::BOLD),
-            );
-        frame.render_widget(status, status_area);
-    }
-}
+/// Category that imported shell aliases and functions go to.
+const SHELL_ALIAS_CATEGORY: &str = "Shell Aliases";
 
-fn build_category_shortcut_line(
-    app: &AppState,
-    include_delete: bool,
-) -> (Line<'static>, Vec<CategoryShortcutSegment>, u16) {
-    let mut spans: Vec<Span<'static>> = Vec::new();
-    let mut segments: Vec<CategoryShortcutSegment> = Vec::new();
-    let mut cursor: u16 = 0;
-    let key_style = Style::default()
-        .fg(app.theme.accent)
-        .add_modifier(Modifier::BOLD);
-    let label_style = Style::default().fg(app.theme.surface);
-    let entries: Vec<(&str, &str, CategoryShortcutAction)> = vec![
-        ("Tab", " Move", CategoryShortcutAction::NextField),
-        ("↵", " Save", CategoryShortcutAction::Submit),
-        ("Esc", " Cancel", CategoryShortcutAction::Cancel),
+/// Aliases and functions defined in the user's bash and zsh startup files.
+/// Aliases run their body; functions only exist in an interactive shell,
+/// so they run through one.
+fn detect_shell_aliases() -> Result<Vec<ImportCandidate>> {
+    let Some(home) = dirs::home_dir() else {
+        anyhow::bail!("Unable to find the home directory");
+    };
+    let zsh_dir = std::env::var_os("ZDOTDIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home.clone());
+    let rc_files = [
+        (home.join(".bashrc"), "bash"),
+        (home.join(".bash_aliases"), "bash"),
+        (zsh_dir.join(".zshrc"), "zsh"),
     ];
-
-    for (idx, (key, label, action)) in entries.iter().enumerate() {
-        if idx > 0 {
-            spans.push(Span::styled(" | ", label_style));
-            cursor = cursor.saturating_add(3);
-        }
-        let entry_start = cursor;
-        spans.push(Span::styled(*key, key_style));
-        cursor = cursor.saturating_add(key.chars().count() as u16);
-        if !label.is_empty() {
-            spans.push(Span::styled(*label, label_style));
-            cursor = cursor.saturating_add(label.chars().count() as u16);
+    let mut candidates: Vec<ImportCandidate> = Vec::new();
+    for (path, shell) in rc_files {
+        if !path.is_file() {
+            continue;
+        }
+        let text = read_task_file(&path)?;
+        for definition in parse_rc_definitions(&text) {
+            let (name, cmd, kind) = match definition {
+                RcDefinition::Alias(name, body) => (name, body, "Alias"),
+                RcDefinition::Function(name) => {
+                    let cmd = format!("{shell} -ic {}", shell_word(&name));
+                    (name, cmd, "Function")
+                }
+            };
+            if candidates.iter().any(|candidate| candidate.label == name) {
+                continue;
+            }
+            candidates.push(ImportCandidate {
+                category: SHELL_ALIAS_CATEGORY.to_string(),
+                label: name,
+                cmd,
+                info: format!("{kind} from {}", display_path(&path)),
+                checked: true,
+            });
         }
-        segments.push(CategoryShortcutSegment {
-            start: entry_start,
-            end: cursor,
-            action: *action,
-        });
-    }
-
-    if !entries.is_empty() {
-        spans.push(Span::styled(" | ", label_style));
-        cursor = cursor.saturating_add(3);
-    }
-
-    let left_start = cursor;
-    spans.push(Span::styled("←", key_style));
-    cursor = cursor.saturating_add("←".chars().count() as u16);
-    segments.push(CategoryShortcutSegment {
-        start: left_start,
-        end: cursor,
-        action: CategoryShortcutAction::PreviousPalette,
-    });
-    spans.push(Span::styled("/", label_style));
-    cursor = cursor.saturating_add(1);
-    let right_start = cursor;
-    spans.push(Span::styled("→", key_style));
-    cursor = cursor.saturating_add("→".chars().count() as u16);
-    spans.push(Span::styled(" Select", label_style));
-    cursor = cursor.saturating_add(" Select".len() as u16);
-    segments.push(CategoryShortcutSegment {
-        start: right_start,
-        end: cursor,
-        action: CategoryShortcutAction::NextPalette,
-    });
-
-    if include_delete {
-        spans.push(Span::styled(" | ", label_style));
-        cursor = cursor.saturating_add(3);
-        let entry_start = cursor;
-        spans.push(Span::styled("d", key_style));
-        cursor = cursor.saturating_add(1);
-        spans.push(Span::styled(" Delete Theme", label_style));
-        cursor = cursor.saturating_add(" Delete Theme".len() as u16);
-        segments.push(CategoryShortcutSegment {
-            start: entry_start,
-            end: cursor,
-            action: CategoryShortcutAction::DeletePreset,
-        });
     }
-
-    (Line::from(spans), segments, cursor)
+    Ok(candidates)
 }
 
-#[derive(Clone)]
-struct FormLine {
-    line: Line<'static>,
-    highlight: bool,
+enum RcDefinition {
+    Alias(String, String),
+    Function(String),
 }
 
-impl FormLine {
-    fn plain(line: Line<'static>) -> Self {
-        Self {
-            line,
-            highlight: false,
+/// `alias name=body` definitions, also several on one line joined by `;`
+/// or `&&`, and function headers: `name()` or `function name` followed by
+/// `{` or `(` on the same or the next line. Global and suffix aliases of zsh
+/// are not commands and are skipped, as are names starting with `_`, which
+/// are usually helpers.
+fn parse_rc_definitions(text: &str) -> Vec<RcDefinition> {
+    let mut definitions = Vec::new();
+    let lines: Vec<&str> = text.lines().map(str::trim).collect();
+    for (index, line) in lines.iter().enumerate() {
+        let commands = shell_commands(line);
+        let aliases: Vec<&[String]> = commands
+            .iter()
+            .filter_map(|words| match words.split_first() {
+                Some((first, rest)) if first == "alias" => Some(rest),
+                _ => None,
+            })
+            .collect();
+        if !aliases.is_empty() {
+            for words in aliases {
+                if words.iter().any(|word| word == "-g" || word == "-s") {
+                    continue;
+                }
+                for word in words {
+                    if let Some((name, body)) = word.split_once('=') {
+                        if is_task_name(name) && !name.starts_with('_') && !body.trim().is_empty() {
+                            definitions
+                                .push(RcDefinition::Alias(name.to_string(), body.to_string()));
+                        }
+                    }
+                }
+            }
+            continue;
         }
-    }
-    
-    fn highlighted(line: Line<'static>) -> Self {
-        Self {
-            line,
-            highlight: true,
+        let (keyword, rest) = match line.strip_prefix("function ") {
+            Some(rest) => (true, rest.trim_start()),
+            None => (false, *line),
+        };
+        let name_end = rest
+            .find(|c: char| c.is_whitespace() || c == '(' || c == '{')
+            .unwrap_or(rest.len());
+        let (name, after) = rest.split_at(name_end);
+        let after = after.trim_start();
+        let body = match after.strip_prefix("()") {
+            Some(body) => body.trim_start(),
+            None if keyword => after,
+            None => continue,
+        };
+        let opens_body = |text: &str| text.starts_with(['{', '(']);
+        let header = if body.is_empty() {
+            lines[index + 1..]
+                .iter()
+                .find(|next| !next.is_empty() && !next.starts_with('#'))
+                .is_some_and(|next| opens_body(next))
+        } else {
+            opens_body(body)
+        };
+        if header && is_task_name(name) && !name.starts_with('_') {
+            definitions.push(RcDefinition::Function(name.to_string()));
         }
     }
+    definitions
 }
 
-fn materialize_form_lines(
-    lines: &[FormLine],
-    width: usize,
-    app: &AppState,
-) -> Vec<Line<'static>> {
-    lines
-        .iter()
-        .map(|form_line| {
-            if form_line.highlight {
-                highlight_line_with_width(form_line.line.clone(), width, app)
-            } else {
-                form_line.line.clone()
+/// The commands of a shell line, split at unquoted `;`, `&` and `|`, each
+/// as its words with the quoting removed. A comment ends the line.
+fn shell_commands(line: &str) -> Vec<Vec<String>> {
+    let mut commands = Vec::new();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_word = true;
+                for c in chars.by_ref() {
+                    if c == '\'' {
+                        break;
+                    }
+                    word.push(c);
+                }
             }
-        })
-        .collect()
-}
-
-fn highlight_line_with_width(
-    mut line: Line<'static>,
-    width: usize,
-    app: &AppState,
-) -> Line<'static> {
-    let mut text_width = 0usize;
-    let highlight_style = Style::default()
-        .fg(app.theme.background)
-        .bg(app.theme.highlight)
-        .add_modifier(Modifier::BOLD);
-    for span in &mut line.spans {
-        span.style = highlight_style;
-        text_width += UnicodeWidthStr::width(span.content.as_ref());
+            '"' => {
+                in_word = true;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' if matches!(chars.peek(), Some('"' | '\\' | '$' | '`')) => {
+                            word.extend(chars.next());
+                        }
+                        _ => word.push(c),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                word.extend(chars.next());
+            }
+            '#' if !in_word => break,
+            ';' | '&' | '|' => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+                if !words.is_empty() {
+                    commands.push(std::mem::take(&mut words));
+                }
+            }
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            c => {
+                in_word = true;
+                word.push(c);
+            }
+        }
     }
-    if width > text_width {
-        line.spans
-            .push(Span::styled(" ".repeat(width - text_width), highlight_style));
+    if in_word {
+        words.push(word);
     }
-    line
+    if !words.is_empty() {
+        commands.push(words);
+    }
+    commands
 }
 
-fn plain_line(line: impl Into<Line<'static>>) -> FormLine {
-    FormLine::plain(line.into())
-}
+fn run_app(app: &mut AppState) -> Result<()> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    stdout.execute(EnterAlternateScreen)?;
+    stdout.execute(EnableMouseCapture)?;
 
-fn make_action_line(label: &str, selected: bool, app: &AppState) -> FormLine {
-    let style = Style::default()
-        .fg(app.theme.accent)
-        .add_modifier(Modifier::BOLD);
-    let line = Line::from(vec![Span::styled(label.to_string(), style)]);
-    if selected {
-        FormLine::highlighted(line)
-    } else {
-        FormLine::plain(line)
-    }
-}
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+    terminal.hide_cursor()?;
 
-fn make_field_line(label: &str, value: &str, selected: bool, app: &AppState) -> FormLine {
-    let value_display = if value.trim().is_empty() {
-        "(empty)".to_string()
-    } else {
-        value.to_string()
-    };
-    let label_style = Style::default()
-        .fg(app.theme.accent)
-        .add_modifier(Modifier::BOLD);
-    let value_style = Style::default().fg(app.theme.text);
-    let label_span = Span::styled(format!("{label}: "), label_style);
-    let value_span = Span::styled(value_display, value_style);
-    if selected {
-        FormLine::highlighted(Line::from(vec![label_span, value_span]))
-    } else {
-        FormLine::plain(Line::from(vec![label_span, value_span]))
-    }
-}
+    let result = event_loop(&mut terminal, app);
 
-fn make_color_field_line(
-    label: &str,
-    value: &str,
-    selected: bool,
-    color: Option<Color>,
-    app: &AppState,
-) -> FormLine {
-    let value_display = if value.trim().is_empty() {
-        "(empty)".to_string()
-    } else {
-        value.to_string()
-    };
-    let label_style = Style::default()
-        .fg(app.theme.accent)
-        .add_modifier(Modifier::BOLD);
-    let value_style = Style::default()
-        .fg(color.unwrap_or(app.theme.text))
-        .add_modifier(Modifier::BOLD);
-    let label_span = Span::styled(format!("{label}: "), label_style);
-    let value_span = Span::styled(value_display, value_style);
-    if selected {
-        FormLine::highlighted(Line::from(vec![label_span, value_span]))
-    } else {
-        FormLine::plain(Line::from(vec![label_span, value_span]))
-    }
+    restore_terminal(&mut terminal)?;
+    result
 }
 
-fn make_toggle_line(label: &str, value: bool, selected: bool, app: &AppState) -> FormLine {
-    let status = if value { "Yes" } else { "No" };
-    let label_style = Style::default()
-        .fg(app.theme.accent)
-        .add_modifier(Modifier::BOLD);
-    let mut value_style = Style::default()
-        .fg(if value { Color::Green } else { Color::Red })
-        .add_modifier(Modifier::BOLD);
-    let label_span = Span::styled(format!("{label}: "), label_style);
-    let value_span = Span::styled(status, value_style);
-    if selected {
-        FormLine::highlighted(Line::from(vec![label_span, value_span]))
-    } else {
-        FormLine::plain(Line::from(vec![label_span, value_span]))
-    }
-}
+fn event_loop<B>(terminal: &mut Terminal<B>, app: &mut AppState) -> Result<()>
+where
+    B: ratatui::backend::Backend + Write,
+{
+    let tick_rate = Duration::from_millis(200);
+    loop {
+        app.refresh_segments();
+        terminal.draw(|frame| {
+            render(frame, app);
+            app.color_mode.apply(frame.buffer_mut());
+        })?;
 
-fn centered_rect(area: Rect, width_percent: u16, height_percent: u16) -> Rect {
-    let horizontal = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage((100 - width_percent) / 2),
-            Constraint::Percentage(width_percent),
-            Constraint::Percentage((100 - width_percent) / 2),
-        ])
-        .split(area);
-    let vertical = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Percentage((100 - height_percent) / 2),
-            Constraint::Percentage(height_percent),
-            Constraint::Percentage((100 - height_percent) / 2),
-        ])
-        .split(horizontal[1]);
-    vertical[1]
+        if event::poll(tick_rate)? {
+            match event::read()? {
+                Event::Key(key) => app.handle_key(key),
+                Event::Mouse(mouse) => {
+                    let size = terminal.size()?;
+                    app.handle_mouse(mouse, size);
+                }
+                Event::Resize(_, _) => {}
+                Event::FocusGained | Event::FocusLost | Event::Paste(_) => {}
+            };
+            app.sync_theme_preview();
+        }
+        app.check_config_files();
+
+        if let Some(pending) = app.take_pending_command() {
+            match run_command(terminal, &pending) {
+                Ok(code) => {
+                    app.last_exit_code = code;
+                    app.set_status(Some(format!(
+                        "Command exited with status {}",
+                        code.unwrap_or_default()
+                    )));
+                }
+                Err(err) => app.set_status(Some(format!("Command failed: {err}"))),
+            }
+        }
+
+        if let Some(action) = app.take_pending_action() {
+            app.execute_deferred_action(terminal, action)?;
+        }
+
+        if app.should_quit {
+            break;
+        }
+    }
+    Ok(())
 }
 
-fn with_terminal_suspension<B, F, T>(terminal: &mut Terminal<B>, f: F) -> Result<T>
+fn restore_terminal<B>(terminal: &mut Terminal<B>) -> Result<()>
 where
     B: ratatui::backend::Backend + Write,
-    F: FnOnce() -> Result<T>,
 {
-    terminal.show_cursor()?;
     disable_raw_mode()?;
     execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
         DisableMouseCapture
     )?;
-    let result = f();
-    enable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        EnterAlternateScreen,
-        EnableMouseCapture
-    )?;
-    terminal.hide_cursor()?;
-    terminal.clear()?;
-    result
+    terminal.show_cursor()?;
+    Ok(())
 }
 
-fn run_command<B>(terminal: &mut Terminal<B>, pending: &PendingCommand) -> Result<Option<i32>>
-where
-    B: ratatui::backend::Backend + Write,
-{
-    with_terminal_suspension(terminal, || {
-        let status = Command::new("sh").arg("-c").arg(&pending.command).status();
+fn render(frame: &mut Frame, app: &AppState) {
+    let size = frame.size();
+    frame.render_widget(
+        Block::default().style(Style::default().bg(app.theme.background)),
+        size,
+    );
 
-        let exit_code = match status {
-            Ok(status) => {
-                if pending.pause {
-                    println!(
-                        "\nCommand exited with code {:?}. Press Enter to return...",
-                        status.code()
-                    );
-                    let _ = io::stdin().read_line(&mut String::new());
-                }
-                status.code()
-            }
-            Err(err) => {
-                println!("Failed to run command: {err}");
-                println!("Press Enter to continue...");
-                let _ = io::stdin().read_line(&mut String::new());
-                None
-            }
-        };
-        Ok(exit_code)
-    })
-}
+    let sections = main_sections(size, app.show_profile_tabs());
 
-#[derive(Clone, Debug, Serialize, Deserialize, Default)]
-struct NamedColorPair {
-    name: Option<String>,
-    background: Option<String>,
-    text: Option<String>,
-}
+    let header = Paragraph::new(app.header_text())
+        .alignment(Alignment::Center)
+        .style(
+            Style::default()
+                .fg(app.theme.text)
+                .bg(app.theme.primary)
+                .add_modifier(Modifier::BOLD),
+        );
+    frame.render_widget(header, sections.header);
 
-const DEFAULT_CATEGORY_COLOR_PRESETS: &[(&str, &str, &str)] = &[
-    ("Teal Glow", "#034e68", "#caf0f8"),
-    ("Amber Pop", "#6f1d1b", "#ffe5d9"),
-    ("Purple Mist", "#240046", "#f8f9fa"),
-    ("Forest Tones", "#283618", "#fefae0"),
-    ("Slate Shine", "#2b2d42", "#edf2f4"),
-];
+    if let Some(tabs_area) = sections.tabs {
+        let tabs = Paragraph::new(app.profile_tabs().line)
+            .style(Style::default().bg(app.theme.footer_background));
+        frame.render_widget(tabs, tabs_area);
+    }
 
-#[derive(Clone)]
-struct ColorPreset {
-    name: String,
-    background: String,
-    text: String,
-    custom_index: Option<usize>,
-}
+    let shortcuts = Paragraph::new(app.footer_line())
+        .alignment(Alignment::Center)
+        .style(Style::default().bg(app.theme.footer_background));
+    frame.render_widget(shortcuts, sections.shortcuts);
 
-impl ColorPreset {
-    fn new(name: impl Into<String>, background: &str, text: &str) -> Self {
-        Self {
-            name: name.into(),
-            background: normalize_hex(background),
-            text: normalize_hex(text),
-            custom_index: None,
-        }
+    let content_area = sections.content;
+    frame.render_widget(
+        Block::default().style(Style::default().bg(app.theme.surface)),
+        content_area,
+    );
+    render_columns(
+        frame,
+        content_area.inner(&Margin {
+            vertical: 1,
+            horizontal: 1,
+        }),
+        app,
+    );
+
+    let status = Paragraph::new(app.status_text())
+        .alignment(Alignment::Center)
+        .style(
+            Style::default()
+                .bg(app.theme.status_bar)
+                .fg(app.theme.text)
+                .add_modifier(Modifier::BOLD),
+        );
+    frame.render_widget(status, sections.status);
+
+    if let Some(popup) = &app.active_popup {
+        render_popup(frame, popup, app);
     }
 
-    fn from_custom(name: impl Into<String>, background: &str, text: &str, index: usize) -> Self {
-        Self {
-            name: name.into(),
-            background: normalize_hex(background),
-            text: normalize_hex(text),
-            custom_index: Some(index),
-        }
+    if let Some(picker) = &app.color_picker {
+        render_color_picker(frame, picker, app);
     }
 
-    fn matches(&self, background: &str, text: &str) -> bool {
-        let bg = normalize_hex(background);
-        let txt = normalize_hex(text);
-        self.background.eq_ignore_ascii_case(&bg) && self.text.eq_ignore_ascii_case(&txt)
+    if let Some(overlay) = &app.help_overlay {
+        render_help_overlay(frame, overlay, app);
     }
-}
 
-#[derive(Clone)]
-struct ThemeOption {
-    key: String,
-    label: String,
-    primary_hex: String,
-    accent_hex: String,
-    background_hex: String,
-    surface_hex: String,
-    text_hex: String,
-    highlight_hex: String,
-}
+    if let Some(change) = &app.external_change {
+        render_external_change(frame, change, app);
+    }
 
-#[derive(Clone, Debug, Serialize, Deserialize, Default)]
-struct AppSettings {
-    title: Option<String>,
-    columns: Option<u16>,
-    #[serde(default)]
-    theme_key: Option<String>,
+    if let Some(recovery) = &app.recovery {
+        render_recovery_screen(frame, recovery, app);
+    }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, Default)]
-struct SavedTheme {
-    name: String,
-    primary: String,
-    accent: String,
-    background: String,
-    surface: String,
-    text: String,
-    #[serde(default)]
-    highlight: Option<String>,
+struct MainSections {
+    header: Rect,
+    tabs: Option<Rect>,
+    shortcuts: Rect,
+    content: Rect,
+    status: Rect,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, Default)]
-struct MenuFile {
-    categories: BTreeMap<String, CategoryConfig>,
-    #[serde(default)]
-    app_settings: AppSettings,
-    #[serde(default)]
-    custom_colors: Vec<NamedColorPair>,
-    #[serde(default)]
-    saved_themes: Vec<SavedTheme>,
+/// Rows of the main screen; the profile tab bar only takes a row when shown.
+fn main_sections(area: Rect, show_tabs: bool) -> MainSections {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Length(u16::from(show_tabs)),
+            Constraint::Length(1),
+            Constraint::Min(1),
+            Constraint::Length(1),
+        ])
+        .split(area);
+    MainSections {
+        header: chunks[0],
+        tabs: show_tabs.then_some(chunks[1]),
+        shortcuts: chunks[2],
+        content: chunks[3],
+        status: chunks[4],
+    }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-struct CategoryConfig {
-    #[serde(default = "default_true")]
-    expanded: bool,
-    column: Option<u16>,
-    #[serde(default)]
-    items: Vec<MenuItemConfig>,
-    #[serde(default)]
-    colors: Option<ColorConfig>,
+struct ProfileTabs {
+    line: Line<'static>,
+    /// Start and end column of each tab, with the profile it selects.
+    segments: Vec<(u16, u16, String)>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, Default)]
-struct ColorConfig {
-    background: Option<String>,
-    text: Option<String>,
-}
+fn render_columns(frame: &mut Frame, area: Rect, app: &AppState) {
+    if area.width == 0 || area.height == 0 {
+        return;
+    }
 
-#[derive(Clone, Debug, Serialize, Deserialize, Default)]
-struct MenuItemConfig {
-    label: String,
-    cmd: String,
-    info: Option<String>,
-    category: Option<String>,
-    pause: Option<bool>,
-}
+    let column_count = app.column_count.max(1);
+    let constraints = (0..column_count)
+        .map(|_| Constraint::Ratio(1, column_count as u32))
+        .collect::<Vec<_>>();
+    let column_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(constraints)
+        .split(area);
 
-fn default_true() -> bool {
-    true
-}
+    for (col_idx, chunk) in column_chunks.iter().enumerate() {
+        let mut items: Vec<ListItem> = Vec::new();
+        if let Some(entries) = app.column_map.get(col_idx) {
+            for entry_index in entries {
+                let (line, style) = app.entry_line(*entry_index);
+                let (mut display_line, mut entry_style) = (line, style);
+                if *entry_index == app.current_index {
+                    entry_style = entry_style.patch(app.selection_style());
+                    display_line = app.highlight_entry_line(display_line);
+                }
+                items.push(ListItem::new(display_line).style(entry_style));
+            }
+        }
 
-fn default_saved_theme() -> SavedTheme {
-    let base_theme = Theme::from_name("nord").unwrap_or_else(|| {
-        Theme::from_hexes(
-            "default".to_string(),
-            "#5E81AC",
-            "#D08770",
-            "#76B3C5",
-            "#3B4252",
-            "#4C566A",
-            "#ECEFF4",
-        )
-    });
-    SavedTheme {
-        name: "default".to_string(),
-        primary: base_theme.primary_hex.clone(),
-        accent: base_theme.accent_hex.clone(),
-        highlight: Some(base_theme.highlight_hex.clone()),
-        background: base_theme.background_hex.clone(),
-        surface: base_theme.surface_hex.clone(),
-        text: base_theme.text_hex.clone(),
+        if items.is_empty() {
+            items.push(ListItem::new(""));
+        }
+
+        let list = List::new(items).block(
+            Block::default().style(Style::default().bg(app.theme.surface).fg(app.theme.text)),
+        );
+        frame.render_widget(list, *chunk);
     }
 }
 
-impl MenuFile {
-    fn load(path: &Path) -> Result<Self> {
-        if path.exists() {
-            let data = fs::read_to_string(path)?;
-            let parsed: MenuFile = serde_json::from_str(&data)?;
-            Ok(parsed)
-        } else {
-            let default = Self::default_data();
-            if let Some(parent) = path.parent() {
-                fs::create_dir_all(parent)?;
-            }
-            fs::write(path, serde_json::to_string_pretty(&default)?)?;
-            Ok(default)
+fn render_popup(frame: &mut Frame, popup: &PopupState, app: &AppState) {
+    match popup {
+        PopupState::Info(info) => {
+            let area = centered_rect(frame.size(), 60, 40);
+            frame.render_widget(Clear, area);
+            let text = format!(
+                "Label: {}\nCommand: {}\nCategory: {}\nDescription: {}\nSource: {}\n\nPress Enter or Esc to close.",
+                info.label, info.command, info.category, info.description, info.source
+            );
+            let block = Paragraph::new(text)
+                .style(Style::default().bg(app.theme.surface).fg(app.theme.text))
+                .block(
+                    Block::default()
+                        .title("Item Info")
+                        .borders(Borders::ALL)
+                        .border_style(Style::default().fg(app.theme.popup_border))
+                        .style(Style::default().bg(app.theme.surface)),
+                );
+            frame.render_widget(block, area);
+        }
+        PopupState::Message(msg) => {
+            let area = centered_rect(frame.size(), 50, 30);
+            frame.render_widget(Clear, area);
+            let block = Paragraph::new(format!("{msg}\n\nPress Enter or Esc to close."))
+                .style(Style::default().bg(app.theme.surface).fg(app.theme.text))
+                .block(
+                    Block::default()
+                        .title("Message")
+                        .borders(Borders::ALL)
+                        .border_style(Style::default().fg(app.theme.popup_border))
+                        .style(Style::default().bg(app.theme.surface)),
+                );
+            frame.render_widget(block, area);
+        }
+        PopupState::ItemForm(form) => {
+            let area = frame.size();
+            frame.render_widget(Clear, area);
+            render_item_form_popup(frame, area, app, form);
+        }
+        PopupState::CategoryForm(form) => {
+            let area = frame.size();
+            frame.render_widget(Clear, area);
+            render_category_form_popup(frame, area, app, form);
         }
+        PopupState::SettingsForm(form) => {
+            let area = frame.size();
+            frame.render_widget(Clear, area);
+            render_settings_form_popup(frame, area, app, form);
+        }
+        PopupState::ImportList(list) => render_import_list(frame, list, app),
     }
+}
 
-    fn save(&self, path: &Path) -> Result<()> {
-        if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent)?;
-        }
-        let data = serde_json::to_string_pretty(self)?;
-        fs::write(path, data)?;
-        Ok(())
+fn render_import_list(frame: &mut Frame, list: &ImportListState, app: &AppState) {
+    let area = centered_rect(frame.size(), 70, 70);
+    frame.render_widget(Clear, area);
+    let heading_style = Style::default()
+        .fg(app.theme.accent)
+        .add_modifier(Modifier::BOLD);
+    let text_style = Style::default().fg(app.theme.text);
+    let mut lines: Vec<Line<'static>> = Vec::new();
+    let mut selected_line = 0;
+    if let Some(dir) = &list.dir {
+        let mut line = Line::from(vec![
+            Span::styled("Directory: ", heading_style),
+            Span::styled(dir.clone(), text_style),
+        ]);
+        if list.editing_dir {
+            line = line.patch_style(app.selection_style());
+        }
+        lines.push(line);
+        lines.push(Line::from(""));
+    }
+    if let Some(error) = &list.error {
+        lines.push(Line::from(Span::styled(
+            error.clone(),
+            Style::default().fg(Color::Red),
+        )));
+        lines.push(Line::from(""));
+    }
+    if list.editing_dir {
+        let submit = app
+            .keymap
+            .key_label(KeyContext::ImportList, KeyAction::Submit)
+            .unwrap_or_default();
+        lines.push(Line::from(Span::styled(
+            format!("Type a directory and press {submit} to look for Makefile targets, package.json scripts, justfile recipes and Cargo aliases."),
+            text_style,
+        )));
     }
+    let mut category = None;
+    for (index, candidate) in list.candidates.iter().enumerate() {
+        if list.editing_dir {
+            break;
+        }
+        if category != Some(&candidate.category) {
+            if category.is_some() {
+                lines.push(Line::from(""));
+            }
+            lines.push(Line::from(Span::styled(
+                candidate.category.clone(),
+                heading_style,
+            )));
+            category = Some(&candidate.category);
+        }
+        let mark = if candidate.checked { "[x]" } else { "[ ]" };
+        let mut line = Line::from(vec![
+            Span::styled(format!("  {mark} {}", candidate.label), text_style),
+            Span::styled(
+                format!("  {}", single_line_preview(&candidate.cmd)),
+                Style::default().fg(app.theme.highlight),
+            ),
+        ]);
+        if index == list.selected {
+            line = line.patch_style(app.selection_style());
+            selected_line = lines.len();
+        }
+        lines.push(line);
+    }
+    let visible = usize::from(area.height.saturating_sub(2)).max(1);
+    let scroll = (selected_line + 1).saturating_sub(visible);
+    let keys = [
+        (KeyAction::ToggleSelection, "toggle"),
+        (KeyAction::ToggleAll, "all"),
+        (KeyAction::Submit, "import"),
+        (
+            KeyAction::Cancel,
+            if list.dir.is_some() { "back" } else { "cancel" },
+        ),
+    ]
+    .iter()
+    .filter(|_| !list.editing_dir)
+    .filter_map(|(action, name)| {
+        app.keymap
+            .key_label(KeyContext::ImportList, *action)
+            .map(|key| format!("{key} {name}"))
+    })
+    .collect::<Vec<_>>();
+    let title = if keys.is_empty() {
+        list.title.to_string()
+    } else {
+        format!("{} ({})", list.title, keys.join(", "))
+    };
+    let paragraph = Paragraph::new(lines)
+        .scroll((u16::try_from(scroll).unwrap_or(u16::MAX), 0))
+        .style(Style::default().bg(app.theme.surface).fg(app.theme.text))
+        .block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(app.theme.popup_border))
+                .style(Style::default().bg(app.theme.surface)),
+        );
+    frame.render_widget(paragraph, area);
+}
 
-    fn default_data() -> Self {
-        let mut categories = BTreeMap::new();
-        categories.insert(
-            "System Tools".to_string(),
-            CategoryConfig {
-                expanded: true,
-                column: Some(1),
-                items: vec![MenuItemConfig {
-                    label: "System Monitor".into(),
-                    cmd: "htop".into(),
-                    info: Some("Interactive process viewer".into()),
-                    category: Some("System Tools".into()),
-                    pause: Some(false),
-                }],
-                colors: None,
-            },
+fn render_help_overlay(frame: &mut Frame, overlay: &HelpOverlay, app: &AppState) {
+    let area = help_overlay_area(frame.size());
+    frame.render_widget(Clear, area);
+    let lines = app.help_lines(overlay.context);
+    let scroll = overlay.scroll.min(help_max_scroll(lines.len(), area));
+    let paragraph = Paragraph::new(lines)
+        .scroll((scroll, 0))
+        .style(Style::default().bg(app.theme.surface).fg(app.theme.text))
+        .block(
+            Block::default()
+                .title(format!(
+                    "Help: {} (↑/↓ scroll, Esc close)",
+                    overlay.context.title()
+                ))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(app.theme.popup_border))
+                .style(Style::default().bg(app.theme.surface)),
         );
-        let saved_themes = vec![default_saved_theme()];
+    frame.render_widget(paragraph, area);
+}
 
-        MenuFile {
-            categories,
-            app_settings: AppSettings {
-                title: Some("Menu Maker — Enhanced Categorized Menu System".into()),
-                columns: Some(1),
-                theme_key: Some(saved_theme_key(0)),
-            },
-            custom_colors: Vec::new(),
-            saved_themes,
-        }
-    }
+fn render_recovery_screen(frame: &mut Frame, recovery: &MenuRecovery, app: &AppState) {
+    let area = recovery_area(frame.size());
+    frame.render_widget(Clear, area);
+    let (lines, _) = recovery_lines(recovery, app);
+    let paragraph = Paragraph::new(lines)
+        .style(Style::default().bg(app.theme.surface).fg(app.theme.text))
+        .block(
+            Block::default()
+                .title("Menu file error (↑/↓ select, Enter choose)")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(app.theme.popup_border))
+                .style(Style::default().bg(app.theme.surface)),
+        );
+    frame.render_widget(paragraph, area);
 }
 
-struct AppPaths {
-    config_dir: PathBuf,
-    menu_file: PathBuf,
-    theme_file: PathBuf,
+fn render_external_change(frame: &mut Frame, change: &ExternalChange, app: &AppState) {
+    let area = centered_rect(frame.size(), 60, 30);
+    frame.render_widget(Clear, area);
+    let files = change
+        .files
+        .iter()
+        .map(|path| display_path(path))
+        .collect::<Vec<_>>()
+        .join(" and ");
+    let key_style = Style::default()
+        .fg(app.theme.accent)
+        .add_modifier(Modifier::BOLD);
+    let lines = vec![
+        Line::from(format!("{files} changed on disk while you were editing.")),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("r", key_style),
+            Span::raw("  Reload now and discard the open form"),
+        ]),
+        Line::from(vec![
+            Span::styled("k", key_style),
+            Span::raw("  Keep editing; saving merges in the outside change"),
+        ]),
+    ];
+    let paragraph = Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .style(Style::default().bg(app.theme.surface).fg(app.theme.text))
+        .block(
+            Block::default()
+                .title("Configuration changed")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(app.theme.popup_border))
+                .style(Style::default().bg(app.theme.surface)),
+        );
+    frame.render_widget(paragraph, area);
 }
 
-impl AppPaths {
-    fn new() -> Result<Self> {
-        let home = dirs::home_dir().context("Unable to determine home directory")?;
-        let config_dir = home.join(".local/menu-maker");
-        fs::create_dir_all(&config_dir)?;
-        Ok(Self {
-            menu_file: config_dir.join("menus.json"),
-            theme_file: config_dir.join("theme.json"),
-            config_dir,
-        })
-    }
+fn recovery_area(area: Rect) -> Rect {
+    centered_rect(area, 80, 70)
 }
 
-struct AppState {
-    categories: Vec<CategoryState>,
-    custom_colors: Vec<NamedColorPair>,
-    saved_themes: Vec<SavedTheme>,
-    column_count: u16,
-    current_index: usize,
-    display_entries: Vec<DisplayEntry>,
-    column_map: Vec<Vec<usize>>,
-    should_quit: bool,
-    pending_command: Option<PendingCommand>,
-    pending_action: Option<DeferredAction>,
-    status_message: Option<String>,
-    paths: AppPaths,
-    theme: Theme,
-    theme_key: String,
-    title: String,
-    active_popup: Option<PopupState>,
-}
-
-impl AppState {
-    fn resolve_theme_key(
-        stored: Option<String>,
-        theme: &Theme,
-        saved_themes: &[SavedTheme],
-    ) -> String {
-        if let Some(key) = stored {
-            if key == CUSTOM_THEME_KEY || is_preset_theme_key(&key) {
-                return key;
-            }
-            if let Some(idx) = parse_saved_theme_key(&key) {
-                if idx < saved_themes.len() {
-                    return key;
-                }
-            }
-        }
-        if let Some(idx) = saved_themes
-            .iter()
-            .position(|saved| saved.name == theme.name)
-        {
-            return saved_theme_key(idx);
-        }
-        if is_preset_theme_key(&theme.name) {
-            return theme.name.clone();
-        }
-        CUSTOM_THEME_KEY.to_string()
+/// Lines of the recovery screen and the index of the first option line.
+fn recovery_lines(recovery: &MenuRecovery, app: &AppState) -> (Vec<Line<'static>>, usize) {
+    let heading_style = Style::default()
+        .fg(app.theme.accent)
+        .add_modifier(Modifier::BOLD);
+    let text_style = Style::default().fg(app.theme.text);
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!("{} could not be loaded", display_path(&recovery.path)),
+            heading_style,
+        )),
+        Line::from(""),
+    ];
+    if recovery.line > 0 {
+        lines.push(Line::from(Span::styled(
+            format!("Line {}, column {}", recovery.line, recovery.column),
+            text_style,
+        )));
     }
-    fn new() -> Result<Self> {
-        let paths = AppPaths::new()?;
-        let mut menu_file = MenuFile::load(&paths.menu_file)?;
-        if !menu_file
-            .saved_themes
-            .iter()
-            .any(|saved| saved.name.eq_ignore_ascii_case("default"))
-        {
-            menu_file.saved_themes.push(default_saved_theme());
-            let _ = menu_file.save(&paths.menu_file);
-        }
-        let theme = Theme::load(&paths.theme_file)?;
-        let saved_themes = menu_file.saved_themes.clone();
-
-        let mut categories: Vec<CategoryState> = menu_file
-            .categories
-            .iter()
-            .map(|(name, cfg)| CategoryState::from_config(name, cfg))
-            .collect();
-        categories.sort_by_key(|cat| (cat.column, cat.name.clone()));
-
-        let mut column_count = menu_file
-            .app_settings
-            .columns
-            .unwrap_or(1)
-            .clamp(1, MAX_COLUMNS);
-        if column_count == 0 {
-            column_count = 1;
-        }
-
-        let stored_theme_key = AppState::resolve_theme_key(
-            menu_file.app_settings.theme_key.clone(),
-            &theme,
-            &saved_themes,
-        );
-        let resolved_theme = if stored_theme_key == CUSTOM_THEME_KEY {
-            theme.clone()
-        } else if let Some(idx) = parse_saved_theme_key(&stored_theme_key) {
-            saved_themes.get(idx).and_then(|saved| {
-                let highlight = saved
-                    .highlight
-                    .as_deref()
-                    .unwrap_or_else(|| saved.accent.as_str());
-                Some(Theme::from_hexes(
-                    saved.name.clone(),
-                    &saved.primary,
-                    &saved.accent,
-                    highlight,
-                    &saved.background,
-                    &saved.surface,
-                    &saved.text,
-                ))
-            })
-            .unwrap_or_else(|| theme.clone())
-        } else if is_preset_theme_key(&stored_theme_key) {
-            Theme::from_name(&stored_theme_key).unwrap_or_else(|| theme.clone())
+    lines.push(Line::from(Span::styled(
+        recovery.message.clone(),
+        text_style,
+    )));
+    if let Some(source) = &recovery.source_line {
+        let gutter = format!("{:>5} │ ", recovery.line);
+        let gutter_width = UnicodeWidthStr::width(gutter.as_str());
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            Span::styled(gutter, Style::default().fg(app.theme.accent)),
+            Span::styled(source.clone(), text_style),
+        ]));
+        let caret_offset = source
+            .chars()
+            .take(recovery.column.saturating_sub(1))
+            .map(|ch| UnicodeWidthStr::width(ch.to_string().as_str()))
+            .sum::<usize>();
+        lines.push(Line::from(Span::styled(
+            format!("{}^", " ".repeat(gutter_width + caret_offset)),
+            Style::default()
+                .fg(app.theme.highlight)
+                .add_modifier(Modifier::BOLD),
+        )));
+    }
+    lines.push(Line::from(""));
+    let first_option = lines.len();
+    for (index, option) in RecoveryOption::ALL.iter().enumerate() {
+        let (key, label) = option.key_and_label(recovery);
+        let style = if index == recovery.selected {
+            app.selection_style()
         } else {
-            theme.clone()
-        };
-
-        let mut app = AppState {
-            categories,
-            custom_colors: menu_file.custom_colors,
-            saved_themes,
-            column_count,
-            current_index: 0,
-            display_entries: Vec::new(),
-            column_map: Vec::new(),
-            should_quit: false,
-            pending_command: None,
-            pending_action: None,
-            status_message: None,
-            paths,
-            theme_key: stored_theme_key,
-            theme: resolved_theme,
-            title: menu_file
-                .app_settings
-                .title
-                .unwrap_or_else(|| "Menu Maker".into()),
-            active_popup: None,
+            text_style
         };
-        app.rebuild_display();
-        Ok(app)
+        lines.push(Line::from(Span::styled(
+            format!(" [{key}] {label} "),
+            style,
+        )));
     }
+    if let Some(notice) = &recovery.notice {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            notice.clone(),
+            Style::default()
+                .fg(app.theme.highlight)
+                .add_modifier(Modifier::BOLD),
+        )));
+    }
+    (lines, first_option)
+}
 
-    fn rebuild_display(&mut self) {
-        self.sort_categories();
-        self.display_entries.clear();
-        let columns = self.column_count.max(1);
-        self.column_map = vec![Vec::new(); columns as usize];
-        for (idx, category) in self.categories.iter().enumerate() {
-            let column_index = ((category.column.saturating_sub(1)) as usize)
-                .min(self.column_map.len().saturating_sub(1));
-            let entry_index = self.display_entries.len();
-            self.display_entries.push(DisplayEntry::Category {
-                category_index: idx,
-            });
-            self.column_map[column_index].push(entry_index);
-            if category.expanded {
-                for item_index in 0..category.items.len() {
-                    let entry_index = self.display_entries.len();
-                    self.display_entries.push(DisplayEntry::Item {
-                        category_index: idx,
-                        item_index,
-                    });
-                    self.column_map[column_index].push(entry_index);
-                }
+fn render_color_picker(frame: &mut Frame, picker: &ColorPickerState, app: &AppState) {
+    let layout = color_picker_layout(frame.size());
+    frame.render_widget(Clear, layout.area);
+    let heading_style = Style::default()
+        .fg(app.theme.accent)
+        .add_modifier(Modifier::BOLD);
+    let text_style = Style::default().fg(app.theme.text);
+    let focus_style = app.selection_style();
+
+    let mut lines: Vec<Line<'static>> = Vec::new();
+    lines.push(Line::from(vec![
+        Span::styled("        ", Style::default().bg(picker.color())),
+        Span::raw("  "),
+        Span::styled(picker.hex(), heading_style),
+        Span::styled(format!("  (was {})", picker.original), text_style),
+    ]));
+    let key = |action| {
+        app.keymap
+            .key_label(KeyContext::ColorPicker, action)
+            .unwrap_or_else(|| "(unbound)".to_string())
+    };
+    let mode_label = if picker.hsl_mode { "HSL" } else { "RGB" };
+    lines.push(Line::from(Span::styled(
+        format!(
+            "Sliders: {mode_label} ({} switches)",
+            key(KeyAction::ToggleColorModel)
+        ),
+        text_style,
+    )));
+    for slider in 0..3 {
+        let (label, max) = picker.slider_spec(slider);
+        let value = picker.slider_value(slider);
+        let label_style = if picker.focus == ColorPickerFocus::Slider(slider) {
+            focus_style
+        } else {
+            text_style
+        };
+        let mut spans = vec![Span::styled(
+            format!("{label:<width$}", width = usize::from(PICKER_LABEL_WIDTH)),
+            label_style,
+        )];
+        let marker = slider_position(value, max, PICKER_SLIDER_WIDTH);
+        for cell in 0..PICKER_SLIDER_WIDTH {
+            let cell_value = slider_value_at(cell, max, PICKER_SLIDER_WIDTH);
+            let mut sample = picker.clone();
+            sample.set_slider_value(slider, cell_value);
+            let bg = sample.color();
+            if cell == marker {
+                let fg = if relative_luminance(sample.rgb) > 0.4 {
+                    Color::Black
+                } else {
+                    Color::White
+                };
+                spans.push(Span::styled("◆", Style::default().bg(bg).fg(fg)));
+            } else {
+                spans.push(Span::styled(" ", Style::default().bg(bg)));
             }
         }
-        if self.current_index >= self.display_entries.len() {
-            self.current_index = self.current_index.saturating_sub(1);
-            if self.display_entries.is_empty() {
-                self.current_index = 0;
+        spans.push(Span::styled(format!(" {value:>3}"), text_style));
+        lines.push(Line::from(spans));
+    }
+    lines.push(Line::from(""));
+    let palette_style = if picker.focus == ColorPickerFocus::Palette {
+        focus_style
+    } else {
+        heading_style
+    };
+    lines.push(Line::from(Span::styled(
+        "Terminal Palette (xterm 256)",
+        palette_style,
+    )));
+    for row in 0..PICKER_PALETTE_ROWS {
+        let mut spans = Vec::new();
+        for column in 0..PICKER_PALETTE_COLUMNS {
+            let index = (row * PICKER_PALETTE_COLUMNS + column) as u8;
+            let rgb = ansi256_to_rgb(index);
+            let (r, g, b) = rgb;
+            let style = Style::default().bg(Color::Rgb(r, g, b));
+            if index == picker.palette_index && picker.focus == ColorPickerFocus::Palette {
+                let fg = if relative_luminance(rgb) > 0.4 {
+                    Color::Black
+                } else {
+                    Color::White
+                };
+                spans.push(Span::styled("[]", style.fg(fg)));
+            } else {
+                spans.push(Span::styled("  ", style));
             }
         }
+        lines.push(Line::from(spans));
     }
+    lines.push(Line::from(""));
+    match &picker.contrast_with {
+        Some((label, partner)) => lines.push(contrast_line(
+            &format!("Contrast with {label}"),
+            picker.rgb,
+            *partner,
+            app,
+        )),
+        None => lines.push(Line::from("")),
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        format!(
+            "{}/{} choose slider or palette   {}/{} adjust   {} apply   {} cancel   {} help",
+            key(KeyAction::MoveUp),
+            key(KeyAction::MoveDown),
+            key(KeyAction::Decrease),
+            key(KeyAction::Increase),
+            key(KeyAction::Submit),
+            key(KeyAction::Cancel),
+            key(KeyAction::Help),
+        ),
+        text_style,
+    )));
+
+    let paragraph = Paragraph::new(lines)
+        .style(Style::default().bg(app.theme.surface).fg(app.theme.text))
+        .block(
+            Block::default()
+                .title(format!("Pick Color: {}", picker.label))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(app.theme.popup_border))
+                .style(Style::default().bg(app.theme.surface)),
+        );
+    frame.render_widget(paragraph, layout.area);
+}
 
-    fn entry_line(&self, entry_index: usize) -> (Line<'_>, Style) {
-        match &self.display_entries[entry_index] {
-            DisplayEntry::Category { category_index } => {
-                let category = &self.categories[*category_index];
-                let marker = if category.expanded { "▼" } else { "▶" };
-                let mut style = Style::default()
-                    .fg(self.theme.text)
-                    .bg(self.theme.surface)
-                    .add_modifier(Modifier::BOLD);
-                if let Some(colors) = &category.colors {
-                    if let Some(bg) = colors
-                        .background
-                        .as_ref()
-                        .and_then(|hex| color_from_hex(hex))
-                    {
-                        style = style.bg(bg);
-                    }
-                    if let Some(text) = colors.text.as_ref().and_then(|hex| color_from_hex(hex)) {
-                        style = style.fg(text);
-                    }
-                }
-                (Line::from(format!("{marker} {}", category.name)), style)
-            }
-            DisplayEntry::Item {
-                category_index,
-                item_index,
-            } => {
-                let item = &self.categories[*category_index].items[*item_index];
-                let mut style = Style::default().fg(self.theme.text).bg(self.theme.surface);
-                if let Some(colors) = self.categories[*category_index].colors.as_ref() {
-                    if let Some(bg) = colors
-                        .background
-                        .as_ref()
-                        .and_then(|hex| color_from_hex(hex))
-                    {
-                        style = style.bg(bg);
-                    }
-                    if let Some(text) = colors.text.as_ref().and_then(|hex| color_from_hex(hex)) {
-                        style = style.fg(text);
-                    }
-                }
-                (Line::from(format!("    {}", item.label)), style)
-            }
-        }
+fn contrast_line(label: &str, a: (u8, u8, u8), b: (u8, u8, u8), app: &AppState) -> Line<'static> {
+    let ratio = contrast_ratio(a, b);
+    let rating = wcag_rating(ratio);
+    let rating_color = match rating {
+        "AAA" | "AA" => Color::Green,
+        "AA Large" => Color::Yellow,
+        _ => Color::Red,
+    };
+    Line::from(vec![
+        Span::styled(format!("{label}: "), Style::default().fg(app.theme.text)),
+        Span::styled(
+            format!("{ratio:.2}:1 {rating}"),
+            Style::default()
+                .fg(rating_color)
+                .add_modifier(Modifier::BOLD),
+        ),
+    ])
+}
+
+fn help_overlay_area(area: Rect) -> Rect {
+    centered_rect(area, 70, 80)
+}
+
+fn help_max_scroll(line_count: usize, area: Rect) -> u16 {
+    let visible = usize::from(area.height.saturating_sub(2));
+    line_count
+        .saturating_sub(visible)
+        .min(usize::from(u16::MAX)) as u16
+}
+
+fn popup_sections(area: Rect) -> Option<[Rect; 4]> {
+    let sections = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Min(3),
+            Constraint::Length(1),
+        ])
+        .split(area);
+    if sections.len() < 4 {
+        return None;
     }
+    Some([sections[0], sections[1], sections[2], sections[3]])
+}
 
-    fn highlight_entry_line(&self, line: Line<'_>) -> Line<'static> {
-        let mut spans = Vec::new();
-        for span in line.spans {
-            let mut owned = Span::styled(span.content.to_string(), span.style);
-            owned.style = owned
-                .style
-                .fg(self.theme.background)
-                .bg(self.theme.highlight)
-                .add_modifier(Modifier::BOLD);
-            spans.push(owned);
-        }
-        Line::from(spans)
+fn popup_content_margin() -> Margin {
+    Margin {
+        horizontal: 3,
+        vertical: 1,
     }
+}
 
-    fn handle_key(&mut self, key: KeyEvent) {
-        if self.active_popup.is_some() {
-            let result = {
-                let popup = self.active_popup.as_mut().unwrap();
-                match popup {
-                    PopupState::Info(_) | PopupState::Message(_) => match key.code {
-                        KeyCode::Esc | KeyCode::Enter => PopupResult::Close(None),
-                        _ => PopupResult::None,
-                    },
-                    PopupState::ItemForm(form) => match form.handle_key(key) {
-                        ItemFormKeyResult::Continue => PopupResult::None,
-                        ItemFormKeyResult::Cancel => {
-                            PopupResult::Close(Some("Item edit cancelled".into()))
-                        }
-                        ItemFormKeyResult::Submit(data) => PopupResult::ItemSubmit(data),
+fn render_category_form_popup(
+    frame: &mut Frame,
+    area: Rect,
+    app: &AppState,
+    form: &CategoryFormState,
+) {
+    let (lines, layout) = form.render_lines(app);
+    if let Some(sections) = popup_sections(area) {
+        frame.render_widget(
+            Block::default().style(Style::default().bg(app.theme.background)),
+            area,
+        );
+        let [header_area, shortcuts_area, content_area, status_area] = sections;
+        let header = Paragraph::new(format!("{} - Edit Category", app.header_text()))
+            .alignment(Alignment::Center)
+            .style(
+                Style::default()
+                    .bg(app.theme.primary)
+                    .fg(app.theme.text)
+                    .add_modifier(Modifier::BOLD),
+            );
+        frame.render_widget(header, header_area);
+
+        let shortcut_line = layout
+            .shortcut_line
+            .clone()
+            .unwrap_or_else(|| Line::from(""));
+        let shortcuts = Paragraph::new(shortcut_line)
+            .alignment(Alignment::Center)
+            .style(
+                Style::default()
+                    .bg(app.theme.footer_background)
+                    .fg(app.theme.footer_label)
+                    .add_modifier(Modifier::BOLD),
+            );
+        frame.render_widget(shortcuts, shortcuts_area);
+
+        frame.render_widget(
+            Block::default().style(Style::default().bg(app.theme.surface)),
+            content_area,
+        );
+        let inner = content_area.inner(&popup_content_margin());
+        let rendered_lines = materialize_form_lines(&lines, inner.width as usize, app);
+        let paragraph = Paragraph::new(rendered_lines)
+            .wrap(Wrap { trim: true })
+            .style(Style::default().bg(app.theme.surface).fg(app.theme.text));
+        frame.render_widget(paragraph, inner);
+
+        let status = Paragraph::new(app.status_text())
+            .alignment(Alignment::Center)
+            .style(
+                Style::default()
+                    .bg(app.theme.status_bar)
+                    .fg(app.theme.text)
+                    .add_modifier(Modifier::BOLD),
+            );
+        frame.render_widget(status, status_area);
+    }
+}
+
+fn render_item_form_popup(frame: &mut Frame, area: Rect, app: &AppState, form: &ItemFormState) {
+    let mut lines: Vec<FormLine> = Vec::new();
+    match &form.read_only {
+        Some(source) => lines.push(plain_line(Line::from(Span::styled(
+            format!("Read-only: this item comes from {source}."),
+            Style::default()
+                .fg(app.theme.accent)
+                .add_modifier(Modifier::BOLD),
+        )))),
+        None => lines.push(plain_line(Line::from(
+            "Fill in the menu item details below.",
+        ))),
+    }
+    // The form does not scroll, so the error goes above the fields where even
+    // a small terminal shows it.
+    if let Some(error) = &form.error {
+        lines.push(plain_line(Line::from(vec![Span::styled(
+            error.clone(),
+            Style::default().fg(Color::Red),
+        )])));
+    }
+    lines.push(make_field_line(
+        "Label",
+        &form.label,
+        form.selected_field == ItemField::Label,
+        app,
+    ));
+    lines.push(make_field_line(
+        "Command",
+        &form.command,
+        form.selected_field == ItemField::Command,
+        app,
+    ));
+    lines.push(make_field_line(
+        "Description",
+        &form.info,
+        form.selected_field == ItemField::Description,
+        app,
+    ));
+    lines.push(make_field_line(
+        "Category",
+        &form.category,
+        form.selected_field == ItemField::Category,
+        app,
+    ));
+    let profile_value = match (form.profiles.get(form.profile_index), form.target_profile()) {
+        (Some(name), Some(_)) => format!("{name} (move on save, ←/→)"),
+        (Some(name), None) => format!("{name} (←/→ to move)"),
+        (None, _) => "this file".to_string(),
+    };
+    lines.push(make_field_line(
+        "Profile",
+        &profile_value,
+        form.selected_field == ItemField::Profile,
+        app,
+    ));
+    lines.push(make_toggle_line(
+        "Pause After Run",
+        form.pause,
+        form.selected_field == ItemField::Pause,
+        app,
+    ));
+    lines.push(plain_line(Line::from("")));
+    lines.push(plain_line(Line::from(vec![Span::styled(
+        "Appearance (leave blank to use the category colors)",
+        Style::default()
+            .fg(app.theme.accent)
+            .add_modifier(Modifier::BOLD),
+    )])));
+    lines.push(make_field_line(
+        "Icon",
+        &form.icon,
+        form.selected_field == ItemField::Icon,
+        app,
+    ));
+    lines.push(make_color_field_line(
+        "Text Color",
+        &form.text_color,
+        form.selected_field == ItemField::TextColor,
+        color_from_hex(&form.text_color),
+        app,
+    ));
+    lines.push(make_color_field_line(
+        "Background",
+        &form.background,
+        form.selected_field == ItemField::Background,
+        color_from_hex(&form.background),
+        app,
+    ));
+    for (label, value, field) in [
+        ("Bold", form.bold, ItemField::Bold),
+        ("Dim", form.dim, ItemField::Dim),
+        ("Italic", form.italic, ItemField::Italic),
+    ] {
+        lines.push(make_toggle_line(
+            label,
+            value,
+            form.selected_field == field,
+            app,
+        ));
+    }
+    let preview_base = Style::default().fg(app.theme.text).bg(app.theme.surface);
+    let preview_style = form.preview_style();
+    let preview_label = if form.label.trim().is_empty() {
+        "Menu item"
+    } else {
+        form.label.trim()
+    };
+    lines.push(plain_line(Line::from(vec![
+        Span::styled(
+            "Preview: ",
+            Style::default()
+                .fg(app.theme.accent)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(
+            format!(" {} ", preview_style.decorate(preview_label)),
+            preview_style.apply(preview_base),
+        ),
+    ])));
+    lines.push(plain_line(Line::from("")));
+    lines.push(plain_line(Line::from(vec![Span::styled(
+        "Style Presets (Tab to focus, ←/→ select)",
+        Style::default()
+            .fg(app.theme.accent)
+            .add_modifier(Modifier::BOLD),
+    )])));
+    for (idx, preset) in ITEM_STYLE_PRESETS.iter().enumerate() {
+        let is_selected = form.preset_index == idx;
+        let mut label_style = Style::default().fg(app.theme.text);
+        if is_selected {
+            label_style = label_style.add_modifier(Modifier::BOLD);
+        }
+        let sample = preset.to_style();
+        let line = Line::from(vec![
+            Span::styled(format!("{:>2}. {:<14}", idx + 1, preset.name), label_style),
+            Span::styled(
+                format!(" {} ", sample.decorate(preset.name)),
+                sample.apply(preview_base),
+            ),
+        ]);
+        if is_selected && form.selected_field == ItemField::StylePreset {
+            lines.push(FormLine::highlighted(line));
+        } else {
+            lines.push(FormLine::plain(line));
+        }
+    }
+    if !form.available_categories.is_empty() {
+        lines.push(plain_line(Line::from("")));
+        lines.push(plain_line(Line::from(vec![Span::styled(
+            "Available Categories:",
+            Style::default()
+                .fg(app.theme.accent)
+                .add_modifier(Modifier::BOLD),
+        )])));
+        for category in &form.available_categories {
+            lines.push(plain_line(Line::from(format!("  • {}", category))));
+        }
+    }
+
+    let key_style = Style::default()
+        .fg(app.theme.footer_key)
+        .add_modifier(Modifier::BOLD);
+    let keymap = &app.keymap;
+    let mut shortcut_spans: Vec<Span<'static>> = Vec::new();
+    let move_keys = [KeyAction::NextField, KeyAction::PreviousField]
+        .into_iter()
+        .filter_map(|action| keymap.key_label(KeyContext::ItemForm, action))
+        .collect::<Vec<_>>();
+    for (idx, key) in move_keys.iter().enumerate() {
+        if idx > 0 {
+            shortcut_spans.push(Span::raw("/"));
+        }
+        shortcut_spans.push(Span::styled(key.clone(), key_style));
+    }
+    if !move_keys.is_empty() {
+        shortcut_spans.push(Span::raw(" Move"));
+    }
+    for (action, label) in [
+        (KeyAction::Submit, " Save"),
+        (KeyAction::Cancel, " Cancel"),
+        (KeyAction::TogglePause, " Toggle Pause"),
+        (KeyAction::OpenEditor, " Open in $EDITOR"),
+        (KeyAction::PickColor, " Pick Color"),
+        (KeyAction::Help, " Help"),
+    ] {
+        if let Some(key) = keymap.key_label(KeyContext::ItemForm, action) {
+            if !shortcut_spans.is_empty() {
+                shortcut_spans.push(Span::raw("    "));
+            }
+            shortcut_spans.push(Span::styled(key, key_style));
+            shortcut_spans.push(Span::raw(label));
+        }
+    }
+    let shortcut_line = Line::from(shortcut_spans);
+
+    if let Some(sections) = popup_sections(area) {
+        frame.render_widget(
+            Block::default().style(Style::default().bg(app.theme.background)),
+            area,
+        );
+        let [header_area, shortcuts_area, content_area, status_area] = sections;
+        let header = Paragraph::new(format!("{} - {}", app.header_text(), form.mode_label))
+            .alignment(Alignment::Center)
+            .style(
+                Style::default()
+                    .bg(app.theme.primary)
+                    .fg(app.theme.text)
+                    .add_modifier(Modifier::BOLD),
+            );
+        frame.render_widget(header, header_area);
+
+        let shortcuts = Paragraph::new(shortcut_line)
+            .alignment(Alignment::Center)
+            .style(
+                Style::default()
+                    .bg(app.theme.footer_background)
+                    .fg(app.theme.footer_label)
+                    .add_modifier(Modifier::BOLD),
+            );
+        frame.render_widget(shortcuts, shortcuts_area);
+
+        frame.render_widget(
+            Block::default().style(Style::default().bg(app.theme.surface)),
+            content_area,
+        );
+        let inner = content_area.inner(&popup_content_margin());
+        let rendered_lines = materialize_form_lines(&lines, inner.width as usize, app);
+        let paragraph = Paragraph::new(rendered_lines)
+            .wrap(Wrap { trim: true })
+            .style(Style::default().bg(app.theme.surface).fg(app.theme.text));
+        frame.render_widget(paragraph, inner);
+
+        let status = Paragraph::new(app.status_text())
+            .alignment(Alignment::Center)
+            .style(
+                Style::default()
+                    .bg(app.theme.status_bar)
+                    .fg(app.theme.text)
+                    .add_modifier(Modifier::BOLD),
+            );
+        frame.render_widget(status, status_area);
+    }
+}
+
+fn render_settings_form_popup(
+    frame: &mut Frame,
+    area: Rect,
+    app: &AppState,
+    form: &SettingsFormState,
+) {
+    let (lines, layout) = form.render_lines(app);
+    if let Some(sections) = popup_sections(area) {
+        frame.render_widget(
+            Block::default().style(Style::default().bg(app.theme.background)),
+            area,
+        );
+        let [header_area, shortcuts_area, content_area, status_area] = sections;
+        let header = Paragraph::new(format!("{} - Application Settings", app.header_text()))
+            .alignment(Alignment::Center)
+            .style(
+                Style::default()
+                    .bg(app.theme.primary)
+                    .fg(app.theme.text)
+                    .add_modifier(Modifier::BOLD),
+            );
+        frame.render_widget(header, header_area);
+
+        let shortcut_line = layout
+            .shortcut_line
+            .clone()
+            .unwrap_or_else(|| Line::from(""));
+        let shortcuts = Paragraph::new(shortcut_line)
+            .alignment(Alignment::Center)
+            .style(
+                Style::default()
+                    .bg(app.theme.footer_background)
+                    .fg(app.theme.footer_label)
+                    .add_modifier(Modifier::BOLD),
+            );
+        frame.render_widget(shortcuts, shortcuts_area);
+
+        frame.render_widget(
+            Block::default().style(Style::default().bg(app.theme.surface)),
+            content_area,
+        );
+        let inner = content_area.inner(&popup_content_margin());
+        let rendered_lines = materialize_form_lines(&lines, inner.width as usize, app);
+        let paragraph = Paragraph::new(rendered_lines)
+            .wrap(Wrap { trim: true })
+            .style(Style::default().bg(app.theme.surface).fg(app.theme.text));
+        frame.render_widget(paragraph, inner);
+
+        let status = Paragraph::new(app.status_text())
+            .alignment(Alignment::Center)
+            .style(
+                Style::default()
+                    .bg(app.theme.status_bar)
+                    .fg(app.theme.text)
+                    .add_modifier(Modifier::BOLD),
+            );
+        frame.render_widget(status, status_area);
+    }
+}
+
+fn build_category_shortcut_line(
+    app: &AppState,
+    include_delete: bool,
+) -> (Line<'static>, Vec<CategoryShortcutSegment>, u16) {
+    let mut spans: Vec<Span<'static>> = Vec::new();
+    let mut segments: Vec<CategoryShortcutSegment> = Vec::new();
+    let mut cursor: u16 = 0;
+    let key_style = Style::default()
+        .fg(app.theme.footer_key)
+        .add_modifier(Modifier::BOLD);
+    let label_style = Style::default().fg(app.theme.footer_label);
+    let keymap = &app.keymap;
+    let entries: Vec<(String, &str, CategoryShortcutAction)> = [
+        (
+            KeyAction::NextField,
+            " Move",
+            CategoryShortcutAction::NextField,
+        ),
+        (KeyAction::Submit, " Save", CategoryShortcutAction::Submit),
+        (KeyAction::Cancel, " Cancel", CategoryShortcutAction::Cancel),
+        (
+            KeyAction::PickColor,
+            " Pick Color",
+            CategoryShortcutAction::PickColor,
+        ),
+        (KeyAction::Help, " Help", CategoryShortcutAction::Help),
+    ]
+    .into_iter()
+    .filter_map(|(key_action, label, action)| {
+        keymap
+            .key_label(KeyContext::CategoryForm, key_action)
+            .map(|key| (key, label, action))
+    })
+    .collect();
+
+    for (idx, (key, label, action)) in entries.iter().enumerate() {
+        if idx > 0 {
+            spans.push(Span::styled(" | ", label_style));
+            cursor = cursor.saturating_add(3);
+        }
+        let entry_start = cursor;
+        cursor = cursor.saturating_add(key.chars().count() as u16);
+        spans.push(Span::styled(key.clone(), key_style));
+        if !label.is_empty() {
+            spans.push(Span::styled(*label, label_style));
+            cursor = cursor.saturating_add(label.chars().count() as u16);
+        }
+        segments.push(CategoryShortcutSegment {
+            start: entry_start,
+            end: cursor,
+            action: *action,
+        });
+    }
+
+    if !entries.is_empty() {
+        spans.push(Span::styled(" | ", label_style));
+        cursor = cursor.saturating_add(3);
+    }
+
+    let previous_key = keymap
+        .key_label(KeyContext::CategoryForm, KeyAction::PreviousPalette)
+        .unwrap_or_default();
+    let next_key = keymap
+        .key_label(KeyContext::CategoryForm, KeyAction::NextPalette)
+        .unwrap_or_default();
+    let left_start = cursor;
+    cursor = cursor.saturating_add(previous_key.chars().count() as u16);
+    spans.push(Span::styled(previous_key, key_style));
+    segments.push(CategoryShortcutSegment {
+        start: left_start,
+        end: cursor,
+        action: CategoryShortcutAction::PreviousPalette,
+    });
+    spans.push(Span::styled("/", label_style));
+    cursor = cursor.saturating_add(1);
+    let right_start = cursor;
+    cursor = cursor.saturating_add(next_key.chars().count() as u16);
+    spans.push(Span::styled(next_key, key_style));
+    spans.push(Span::styled(" Select", label_style));
+    cursor = cursor.saturating_add(" Select".len() as u16);
+    segments.push(CategoryShortcutSegment {
+        start: right_start,
+        end: cursor,
+        action: CategoryShortcutAction::NextPalette,
+    });
+
+    let delete_key = keymap.key_label(KeyContext::CategoryForm, KeyAction::DeletePreset);
+    if let (true, Some(delete_key)) = (include_delete, delete_key) {
+        spans.push(Span::styled(" | ", label_style));
+        cursor = cursor.saturating_add(3);
+        let entry_start = cursor;
+        cursor = cursor.saturating_add(delete_key.chars().count() as u16);
+        spans.push(Span::styled(delete_key, key_style));
+        spans.push(Span::styled(" Delete Theme", label_style));
+        cursor = cursor.saturating_add(" Delete Theme".len() as u16);
+        segments.push(CategoryShortcutSegment {
+            start: entry_start,
+            end: cursor,
+            action: CategoryShortcutAction::DeletePreset,
+        });
+    }
+
+    (Line::from(spans), segments, cursor)
+}
+
+#[derive(Clone)]
+struct FormLine {
+    line: Line<'static>,
+    highlight: bool,
+}
+
+impl FormLine {
+    fn plain(line: Line<'static>) -> Self {
+        Self {
+            line,
+            highlight: false,
+        }
+    }
+    
+    fn highlighted(line: Line<'static>) -> Self {
+        Self {
+            line,
+            highlight: true,
+        }
+    }
+}
+
+fn materialize_form_lines(
+    lines: &[FormLine],
+    width: usize,
+    app: &AppState,
+) -> Vec<Line<'static>> {
+    lines
+        .iter()
+        .map(|form_line| {
+            if form_line.highlight {
+                highlight_line_with_width(form_line.line.clone(), width, app)
+            } else {
+                form_line.line.clone()
+            }
+        })
+        .collect()
+}
+
+fn highlight_line_with_width(
+    mut line: Line<'static>,
+    width: usize,
+    app: &AppState,
+) -> Line<'static> {
+    let mut text_width = 0usize;
+    let highlight_style = app.selection_style();
+    for span in &mut line.spans {
+        // Spans with their own background are color swatches; keep them.
+        if span.style.bg.is_none() {
+            span.style = highlight_style;
+        }
+        text_width += UnicodeWidthStr::width(span.content.as_ref());
+    }
+    if width > text_width {
+        line.spans
+            .push(Span::styled(" ".repeat(width - text_width), highlight_style));
+    }
+    line
+}
+
+fn plain_line(line: impl Into<Line<'static>>) -> FormLine {
+    FormLine::plain(line.into())
+}
+
+fn make_action_line(label: &str, selected: bool, app: &AppState) -> FormLine {
+    let style = Style::default()
+        .fg(app.theme.accent)
+        .add_modifier(Modifier::BOLD);
+    let line = Line::from(vec![Span::styled(label.to_string(), style)]);
+    if selected {
+        FormLine::highlighted(line)
+    } else {
+        FormLine::plain(line)
+    }
+}
+
+fn make_field_line(label: &str, value: &str, selected: bool, app: &AppState) -> FormLine {
+    let value_display = if value.trim().is_empty() {
+        "(empty)".to_string()
+    } else {
+        single_line_preview(value)
+    };
+    let label_style = Style::default()
+        .fg(app.theme.accent)
+        .add_modifier(Modifier::BOLD);
+    let value_style = Style::default().fg(app.theme.text);
+    let label_span = Span::styled(format!("{label}: "), label_style);
+    let value_span = Span::styled(value_display, value_style);
+    if selected {
+        FormLine::highlighted(Line::from(vec![label_span, value_span]))
+    } else {
+        FormLine::plain(Line::from(vec![label_span, value_span]))
+    }
+}
+
+fn single_line_preview(value: &str) -> String {
+    if value.contains('\n') {
+        value.lines().collect::<Vec<_>>().join(" ⏎ ")
+    } else {
+        value.to_string()
+    }
+}
+
+fn make_color_field_line(
+    label: &str,
+    value: &str,
+    selected: bool,
+    color: Option<Color>,
+    app: &AppState,
+) -> FormLine {
+    let value_display = if value.trim().is_empty() {
+        "(empty)".to_string()
+    } else {
+        value.to_string()
+    };
+    let label_style = Style::default()
+        .fg(app.theme.accent)
+        .add_modifier(Modifier::BOLD);
+    let value_style = Style::default()
+        .fg(color.unwrap_or(app.theme.text))
+        .add_modifier(Modifier::BOLD);
+    let mut spans = vec![Span::styled(format!("{label}: "), label_style)];
+    match color.filter(|_| sanitize_hex_color_input(value).is_some()) {
+        Some(swatch) => {
+            spans.push(Span::styled("    ", Style::default().bg(swatch)));
+            spans.push(Span::raw(" "));
+        }
+        None if !value.trim().is_empty() => {
+            spans.push(Span::styled(
+                "invalid ",
+                Style::default().bg(Color::Red).fg(Color::White),
+            ));
+            spans.push(Span::raw(" "));
+        }
+        None => {}
+    }
+    spans.push(Span::styled(value_display, value_style));
+    if selected {
+        FormLine::highlighted(Line::from(spans))
+    } else {
+        FormLine::plain(Line::from(spans))
+    }
+}
+
+fn make_toggle_line(label: &str, value: bool, selected: bool, app: &AppState) -> FormLine {
+    let status = if value { "Yes" } else { "No" };
+    let label_style = Style::default()
+        .fg(app.theme.accent)
+        .add_modifier(Modifier::BOLD);
+    let mut value_style = Style::default()
+        .fg(if value { Color::Green } else { Color::Red })
+        .add_modifier(Modifier::BOLD);
+    let label_span = Span::styled(format!("{label}: "), label_style);
+    let value_span = Span::styled(status, value_style);
+    if selected {
+        FormLine::highlighted(Line::from(vec![label_span, value_span]))
+    } else {
+        FormLine::plain(Line::from(vec![label_span, value_span]))
+    }
+}
+
+fn centered_rect(area: Rect, width_percent: u16, height_percent: u16) -> Rect {
+    let horizontal = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - width_percent) / 2),
+            Constraint::Percentage(width_percent),
+            Constraint::Percentage((100 - width_percent) / 2),
+        ])
+        .split(area);
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - height_percent) / 2),
+            Constraint::Percentage(height_percent),
+            Constraint::Percentage((100 - height_percent) / 2),
+        ])
+        .split(horizontal[1]);
+    vertical[1]
+}
+
+fn with_terminal_suspension<B, F, T>(terminal: &mut Terminal<B>, f: F) -> Result<T>
+where
+    B: ratatui::backend::Backend + Write,
+    F: FnOnce() -> Result<T>,
+{
+    terminal.show_cursor()?;
+    disable_raw_mode()?;
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture
+    )?;
+    let result = f();
+    enable_raw_mode()?;
+    execute!(
+        terminal.backend_mut(),
+        EnterAlternateScreen,
+        EnableMouseCapture
+    )?;
+    terminal.hide_cursor()?;
+    terminal.clear()?;
+    result
+}
+
+fn preferred_editor() -> String {
+    std::env::var("VISUAL")
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+        .or_else(|| {
+            std::env::var("EDITOR")
+                .ok()
+                .filter(|value| !value.trim().is_empty())
+        })
+        .unwrap_or_else(|| "vi".to_string())
+}
+
+fn open_in_editor(path: &Path) -> Result<()> {
+    let editor = preferred_editor();
+    // Run through the shell so editors configured with arguments ("code --wait") work.
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{editor} \"$1\""))
+        .arg("menu-maker")
+        .arg(path)
+        .status()
+        .with_context(|| format!("Unable to launch editor '{editor}'"))?;
+    if !status.success() {
+        anyhow::bail!("Editor '{editor}' exited with status {:?}", status.code());
+    }
+    Ok(())
+}
+
+/// A new directory under the temp dir that only the current user can
+/// enter. `mkdtemp` picks an unused name, so nobody can plant a file or
+/// symlink where the editor's file will go.
+fn private_temp_dir() -> Result<PathBuf> {
+    #[cfg(unix)]
+    {
+        use std::ffi::{CString, OsString};
+        use std::os::unix::ffi::{OsStrExt, OsStringExt};
+        let template = std::env::temp_dir().join("menu-maker-XXXXXX");
+        let mut bytes = CString::new(template.as_os_str().as_bytes())?.into_bytes_with_nul();
+        if unsafe { libc::mkdtemp(bytes.as_mut_ptr().cast()) }.is_null() {
+            return Err(io::Error::last_os_error())
+                .context("Unable to create a temporary directory");
+        }
+        bytes.pop();
+        Ok(PathBuf::from(OsString::from_vec(bytes)))
+    }
+    #[cfg(not(unix))]
+    {
+        let nanos = SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.subsec_nanos())
+            .unwrap_or_default();
+        let dir = std::env::temp_dir().join(format!("menu-maker-{}-{nanos}", std::process::id()));
+        fs::create_dir(&dir).with_context(|| format!("Unable to create {}", dir.display()))?;
+        Ok(dir)
+    }
+}
+
+fn edit_in_external_editor(initial: &str, extension: &str) -> Result<String> {
+    let dir = private_temp_dir()?;
+    let path = dir.join(format!("edit.{extension}"));
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let written = options
+        .open(&path)
+        .and_then(|mut file| file.write_all(initial.as_bytes()))
+        .with_context(|| format!("Unable to write {}", path.display()));
+    let status = written.and_then(|()| open_in_editor(&path));
+    let edited = fs::read_to_string(&path);
+    let _ = fs::remove_dir_all(&dir);
+    status?;
+    let mut edited = edited?;
+    if edited.ends_with('\n') {
+        edited.pop();
+        if edited.ends_with('\r') {
+            edited.pop();
+        }
+    }
+    Ok(edited)
+}
+
+fn run_command<B>(terminal: &mut Terminal<B>, pending: &PendingCommand) -> Result<Option<i32>>
+where
+    B: ratatui::backend::Backend + Write,
+{
+    with_terminal_suspension(terminal, || {
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(&pending.command)
+            .env(JOB_MARKER_VAR, std::process::id().to_string());
+        if let Some(dir) = &pending.dir {
+            command.current_dir(dir);
+        }
+        let status = command.status();
+
+        let exit_code = match status {
+            Ok(status) => {
+                if pending.pause {
+                    println!(
+                        "\nCommand exited with code {:?}. Press Enter to return...",
+                        status.code()
+                    );
+                    let _ = io::stdin().read_line(&mut String::new());
+                }
+                status.code()
+            }
+            Err(err) => {
+                println!("Failed to run command: {err}");
+                println!("Press Enter to continue...");
+                let _ = io::stdin().read_line(&mut String::new());
+                None
+            }
+        };
+        Ok(exit_code)
+    })
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+struct NamedColorPair {
+    name: Option<String>,
+    background: Option<String>,
+    text: Option<String>,
+}
+
+const DEFAULT_CATEGORY_COLOR_PRESETS: &[(&str, &str, &str)] = &[
+    ("Teal Glow", "#034e68", "#caf0f8"),
+    ("Amber Pop", "#6f1d1b", "#ffe5d9"),
+    ("Purple Mist", "#240046", "#f8f9fa"),
+    ("Forest Tones", "#283618", "#fefae0"),
+    ("Slate Shine", "#2b2d42", "#edf2f4"),
+];
+
+#[derive(Clone)]
+struct ColorPreset {
+    name: String,
+    background: String,
+    text: String,
+    custom_index: Option<usize>,
+}
+
+impl ColorPreset {
+    fn new(name: impl Into<String>, background: &str, text: &str) -> Self {
+        Self {
+            name: name.into(),
+            background: normalize_hex(background),
+            text: normalize_hex(text),
+            custom_index: None,
+        }
+    }
+
+    fn from_custom(name: impl Into<String>, background: &str, text: &str, index: usize) -> Self {
+        Self {
+            name: name.into(),
+            background: normalize_hex(background),
+            text: normalize_hex(text),
+            custom_index: Some(index),
+        }
+    }
+
+    fn matches(&self, background: &str, text: &str) -> bool {
+        let bg = normalize_hex(background);
+        let txt = normalize_hex(text);
+        self.background.eq_ignore_ascii_case(&bg) && self.text.eq_ignore_ascii_case(&txt)
+    }
+}
+
+#[derive(Clone)]
+struct ThemeOption {
+    key: String,
+    label: String,
+    primary_hex: String,
+    accent_hex: String,
+    background_hex: String,
+    surface_hex: String,
+    text_hex: String,
+    highlight_hex: String,
+    footer_background_hex: String,
+    footer_key_hex: String,
+    footer_label_hex: String,
+    status_bar_hex: String,
+    popup_border_hex: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+struct AppSettings {
+    title: Option<String>,
+    columns: Option<u16>,
+    #[serde(default)]
+    theme_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    color_mode: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    status_template: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    backup_count: Option<usize>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+struct SavedTheme {
+    name: String,
+    primary: String,
+    accent: String,
+    background: String,
+    surface: String,
+    text: String,
+    #[serde(default)]
+    highlight: Option<String>,
+    #[serde(flatten)]
+    chrome: ThemeChrome,
+}
+
+impl SavedTheme {
+    fn to_theme(&self) -> Theme {
+        let highlight = self.highlight.as_deref().unwrap_or(self.accent.as_str());
+        Theme::from_hexes(
+            self.name.clone(),
+            &self.primary,
+            &self.accent,
+            highlight,
+            &self.background,
+            &self.surface,
+            &self.text,
+        )
+        .with_chrome(&self.chrome)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+struct MenuFile {
+    #[serde(default)]
+    schema_version: u32,
+    #[serde(default)]
+    categories: BTreeMap<String, CategoryConfig>,
+    #[serde(default)]
+    app_settings: AppSettings,
+    #[serde(default)]
+    custom_colors: Vec<NamedColorPair>,
+    #[serde(default)]
+    saved_themes: Vec<SavedTheme>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct CategoryConfig {
+    #[serde(default = "default_true")]
+    expanded: bool,
+    column: Option<u16>,
+    #[serde(default)]
+    items: Vec<MenuItemConfig>,
+    #[serde(default)]
+    colors: Option<ColorConfig>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+struct ColorConfig {
+    background: Option<String>,
+    text: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+struct MenuItemConfig {
+    label: String,
+    cmd: String,
+    info: Option<String>,
+    pause: Option<bool>,
+    #[serde(default, skip_serializing_if = "ItemStyle::is_empty")]
+    style: ItemStyle,
+}
+
+/// Optional per-item look. Unset colors fall back to the category colors and
+/// then to the theme.
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq)]
+struct ItemStyle {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    background: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    icon: Option<String>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    bold: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    dim: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    italic: bool,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// Icon column width. Narrow icons are padded to it so labels line up with
+/// items that use emoji.
+const ITEM_ICON_WIDTH: usize = 2;
+
+impl ItemStyle {
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn apply(&self, mut style: Style) -> Style {
+        if let Some(text) = self.text.as_deref().and_then(color_from_hex) {
+            style = style.fg(text);
+        }
+        if let Some(bg) = self.background.as_deref().and_then(color_from_hex) {
+            style = style.bg(bg);
+        }
+        if self.bold {
+            style = style.add_modifier(Modifier::BOLD);
+        }
+        if self.dim {
+            style = style.add_modifier(Modifier::DIM);
+        }
+        if self.italic {
+            style = style.add_modifier(Modifier::ITALIC);
+        }
+        style
+    }
+
+    fn decorate(&self, label: &str) -> String {
+        match self.icon.as_deref().filter(|icon| !icon.is_empty()) {
+            Some(icon) => {
+                let padding = ITEM_ICON_WIDTH.saturating_sub(UnicodeWidthStr::width(icon));
+                format!("{icon}{} {label}", " ".repeat(padding))
+            }
+            None => label.to_string(),
+        }
+    }
+}
+
+struct ItemStylePreset {
+    name: &'static str,
+    text: Option<&'static str>,
+    background: Option<&'static str>,
+    icon: Option<&'static str>,
+    bold: bool,
+    dim: bool,
+    italic: bool,
+}
+
+impl ItemStylePreset {
+    const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            text: None,
+            background: None,
+            icon: None,
+            bold: false,
+            dim: false,
+            italic: false,
+        }
+    }
+
+    fn to_style(&self) -> ItemStyle {
+        ItemStyle {
+            text: self.text.map(normalize_hex),
+            background: self.background.map(normalize_hex),
+            icon: self.icon.map(str::to_string),
+            bold: self.bold,
+            dim: self.dim,
+            italic: self.italic,
+        }
+    }
+}
+
+const ITEM_STYLE_PRESETS: &[ItemStylePreset] = &[
+    ItemStylePreset::new("Plain"),
+    ItemStylePreset {
+        text: Some("#ff6b6b"),
+        icon: Some("⚠"),
+        bold: true,
+        ..ItemStylePreset::new("Danger")
+    },
+    ItemStylePreset {
+        text: Some("#8ce99a"),
+        icon: Some("✔"),
+        ..ItemStylePreset::new("Success")
+    },
+    ItemStylePreset {
+        text: Some("#ffd43b"),
+        icon: Some("★"),
+        bold: true,
+        ..ItemStylePreset::new("Favorite")
+    },
+    ItemStylePreset {
+        text: Some("#74c0fc"),
+        icon: Some("📖"),
+        italic: true,
+        ..ItemStylePreset::new("Docs")
+    },
+    ItemStylePreset {
+        icon: Some("🌐"),
+        ..ItemStylePreset::new("Network")
+    },
+    ItemStylePreset {
+        icon: Some("⚙"),
+        ..ItemStylePreset::new("Settings")
+    },
+    ItemStylePreset {
+        dim: true,
+        ..ItemStylePreset::new("Muted")
+    },
+    ItemStylePreset {
+        text: Some("#ffe5d9"),
+        background: Some("#6f1d1b"),
+        bold: true,
+        ..ItemStylePreset::new("Alert Banner")
+    },
+];
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_saved_theme() -> SavedTheme {
+    let base_theme = Theme::from_name("nord").unwrap_or_else(|| {
+        Theme::from_hexes(
+            "default".to_string(),
+            "#5E81AC",
+            "#D08770",
+            "#76B3C5",
+            "#3B4252",
+            "#4C566A",
+            "#ECEFF4",
+        )
+    });
+    SavedTheme {
+        name: "default".to_string(),
+        primary: base_theme.primary_hex.clone(),
+        accent: base_theme.accent_hex.clone(),
+        highlight: Some(base_theme.highlight_hex.clone()),
+        background: base_theme.background_hex.clone(),
+        surface: base_theme.surface_hex.clone(),
+        text: base_theme.text_hex.clone(),
+        chrome: base_theme.chrome(),
+    }
+}
+
+/// File formats a menu can be written in, picked by the file extension.
+/// TOML and YAML allow multi-line commands without escaping.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MenuFormat {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl MenuFormat {
+    /// Looked for in this order, so a hand-written menus.toml or menus.yaml
+    /// wins over the menus.json created on first start.
+    const SEARCH_ORDER: [MenuFormat; 3] = [MenuFormat::Toml, MenuFormat::Yaml, MenuFormat::Json];
+
+    fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => MenuFormat::Toml,
+            Some("yaml" | "yml") => MenuFormat::Yaml,
+            _ => MenuFormat::Json,
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "json" => Some(MenuFormat::Json),
+            "toml" => Some(MenuFormat::Toml),
+            "yaml" | "yml" => Some(MenuFormat::Yaml),
+            _ => None,
+        }
+    }
+
+    /// Also the file extension.
+    fn name(self) -> &'static str {
+        match self {
+            MenuFormat::Json => "json",
+            MenuFormat::Toml => "toml",
+            MenuFormat::Yaml => "yaml",
+        }
+    }
+
+    fn parse<T: serde::de::DeserializeOwned>(self, data: &str) -> Result<T> {
+        Ok(match self {
+            MenuFormat::Json => serde_json::from_str(data)?,
+            MenuFormat::Toml => toml::from_str(data)?,
+            MenuFormat::Yaml => serde_yaml::from_str(data)?,
+        })
+    }
+
+    fn render<T: Serialize>(self, value: &T) -> Result<String> {
+        Ok(match self {
+            MenuFormat::Json => serde_json::to_string_pretty(value)?,
+            MenuFormat::Toml => toml::to_string_pretty(value)?,
+            MenuFormat::Yaml => serde_yaml::to_string(value)?,
+        })
+    }
+}
+
+/// `<stem>.toml`, `.yaml`, `.yml` or `.json` in `dir`, whichever exists
+/// first; JSON when there is none yet.
+fn menu_file_in(dir: &Path, stem: &str) -> PathBuf {
+    MenuFormat::SEARCH_ORDER
+        .iter()
+        .flat_map(|format| match format {
+            MenuFormat::Yaml => vec!["yaml", "yml"],
+            other => vec![other.name()],
+        })
+        .map(|ext| dir.join(format!("{stem}.{ext}")))
+        .find(|path| path.is_file())
+        .unwrap_or_else(|| dir.join(format!("{stem}.json")))
+}
+
+/// The file `menu-maker convert` replaced `path` with, once `path` is gone.
+fn converted_menu_file(path: &Path) -> Option<PathBuf> {
+    if path.exists() {
+        return None;
+    }
+    shadowed_menu_files(path).into_iter().next()
+}
+
+/// Menu files next to `path` with the same stem in another format; they
+/// are ignored while `path` exists.
+fn shadowed_menu_files(path: &Path) -> Vec<PathBuf> {
+    ["json", "toml", "yaml", "yml"]
+        .into_iter()
+        .map(|ext| path.with_extension(ext))
+        .filter(|other| other != path && other.is_file())
+        .collect()
+}
+
+impl MenuFile {
+    /// Load `path`, upgrading files written by older releases. The original
+    /// is copied into `backups_dir` before the upgraded file replaces it.
+    fn load(path: &Path, backups_dir: &Path) -> Result<Self> {
+        let format = MenuFormat::from_path(path);
+        if path.exists() {
+            let data = fs::read_to_string(path)?;
+            let mut value: serde_json::Value = format.parse(&data)?;
+            let version = menu_schema_version(&value);
+            if version > MENU_SCHEMA_VERSION {
+                anyhow::bail!(
+                    "Schema version {version} is newer than this release supports ({MENU_SCHEMA_VERSION})"
+                );
+            }
+            if version == MENU_SCHEMA_VERSION {
+                // Parse the text directly so errors keep their line and column.
+                return format.parse(&data);
+            }
+            backup_before_migration(path, backups_dir, version)?;
+            migrate_menu_value(&mut value, version)?;
+            let migrated: MenuFile = serde_json::from_value(value)?;
+            migrated.save(path)?;
+            Ok(migrated)
+        } else {
+            let default = Self::default_data();
+            default.save(path)?;
+            Ok(default)
+        }
+    }
+
+    /// Parse a menu file this user does not own, upgrading it in memory
+    /// only.
+    fn read(path: &Path) -> Result<Self> {
+        let data = fs::read_to_string(path)
+            .with_context(|| format!("Unable to read {}", path.display()))?;
+        let mut value: serde_json::Value = MenuFormat::from_path(path).parse(&data)?;
+        let version = menu_schema_version(&value);
+        if version > MENU_SCHEMA_VERSION {
+            anyhow::bail!(
+                "Schema version {version} is newer than this release supports ({MENU_SCHEMA_VERSION})"
+            );
+        }
+        migrate_menu_value(&mut value, version)?;
+        Ok(serde_json::from_value(value)?)
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        let data = MenuFormat::from_path(path).render(self)?;
+        write_atomic(path, data.as_bytes())
+    }
+
+    fn default_data() -> Self {
+        let mut categories = BTreeMap::new();
+        categories.insert(
+            "System Tools".to_string(),
+            CategoryConfig {
+                expanded: true,
+                column: Some(1),
+                items: vec![MenuItemConfig {
+                    label: "System Monitor".into(),
+                    cmd: "htop".into(),
+                    info: Some("Interactive process viewer".into()),
+                    pause: Some(false),
+                    style: ItemStyle::default(),
+                }],
+                colors: None,
+            },
+        );
+        let saved_themes = vec![default_saved_theme()];
+
+        MenuFile {
+            schema_version: MENU_SCHEMA_VERSION,
+            categories,
+            app_settings: AppSettings {
+                title: Some("Menu Maker — Enhanced Categorized Menu System".into()),
+                columns: Some(1),
+                theme_key: Some(saved_theme_key(0)),
+                color_mode: None,
+                status_template: None,
+                backup_count: None,
+            },
+            custom_colors: Vec::new(),
+            saved_themes,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct AppPaths {
+    config_dir: PathBuf,
+    /// Executables imported by the bin scan.
+    data_dir: PathBuf,
+    menu_file: PathBuf,
+    theme_file: PathBuf,
+    keymap_file: PathBuf,
+    themes_dir: PathBuf,
+    backups_dir: PathBuf,
+    profiles_dir: PathBuf,
+    /// Active profile; `None` when a menu file was given with `--config`.
+    profile: Option<String>,
+    /// Set when this run moved the old `~/.local/menu-maker` tree.
+    migrated_from: Option<PathBuf>,
+    /// Read-only menu maintained by the administrator.
+    system_menu_file: PathBuf,
+    /// Read-only menu of the project the working directory belongs to.
+    project_menu_file: Option<PathBuf>,
+}
+
+impl AppPaths {
+    fn new(profile: Option<&str>, config: Option<PathBuf>) -> Result<Self> {
+        let home = dirs::home_dir().context("Unable to determine home directory")?;
+        let (config_dir, state_dir, data_dir) = match env_dir("MENU_MAKER_HOME") {
+            // One directory holds everything, laid out like the old tree.
+            Some(root) => (root.clone(), root.clone(), root),
+            None => (
+                xdg_dir("XDG_CONFIG_HOME", &home, ".config"),
+                xdg_dir("XDG_STATE_HOME", &home, ".local/state"),
+                xdg_dir("XDG_DATA_HOME", &home, ".local/share"),
+            ),
+        };
+        let migrated_from = if env_dir("MENU_MAKER_HOME").is_none() {
+            migrate_legacy_tree(
+                &home.join(".local/menu-maker"),
+                &config_dir,
+                &state_dir,
+                &data_dir,
+            )?
+        } else {
+            None
+        };
+        fs::create_dir_all(&config_dir)?;
+        let mut paths = Self {
+            menu_file: menu_file_in(&config_dir, "menus"),
+            theme_file: config_dir.join("theme.json"),
+            keymap_file: config_dir.join("keymap.json"),
+            themes_dir: config_dir.join("themes"),
+            backups_dir: state_dir.join("backups"),
+            profiles_dir: config_dir.join("profiles"),
+            profile: Some(DEFAULT_PROFILE.to_string()),
+            config_dir,
+            data_dir,
+            migrated_from,
+            system_menu_file: PathBuf::from(SYSTEM_MENU_FILE),
+            project_menu_file: std::env::current_dir()
+                .ok()
+                .and_then(|dir| find_project_menu(&dir)),
+        };
+        match (profile, config) {
+            (Some(_), Some(_)) => anyhow::bail!("Use either --profile or --config, not both"),
+            (Some(name), None) => {
+                validate_profile_name(name).map_err(anyhow::Error::msg)?;
+                paths.select_profile(name);
+            }
+            (None, Some(file)) => {
+                paths.menu_file = expand_home(&file.to_string_lossy());
+                paths.profile = None;
+            }
+            (None, None) => {}
+        }
+        Ok(paths)
+    }
+
+    /// Backups of the default menu go straight into `backups_dir`, those of
+    /// profiles into `profiles/` below it and those of a `--config` file into
+    /// a directory named after its full path, so files with the same stem
+    /// never share backups.
+    fn backups_for(&self, path: &Path) -> PathBuf {
+        let parent = path.parent();
+        if parent == Some(self.config_dir.as_path()) {
+            return self.backups_dir.clone();
+        }
+        if parent == Some(self.profiles_dir.as_path()) {
+            return self.backups_dir.join("profiles");
+        }
+        let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        let hash = fnv1a(&absolute.to_string_lossy());
+        let (stem, _) = backup_name_parts(path);
+        self.backups_dir
+            .join("config")
+            .join(format!("{stem}-{hash:016x}"))
+    }
+
+    fn profile_menu_file(&self, name: &str) -> PathBuf {
+        if name == DEFAULT_PROFILE {
+            menu_file_in(&self.config_dir, "menus")
+        } else {
+            menu_file_in(&self.profiles_dir, name)
+        }
+    }
+
+    fn select_profile(&mut self, name: &str) {
+        self.menu_file = self.profile_menu_file(name);
+        self.profile = Some(name.to_string());
+    }
+
+    /// The default profile first, then the files in `profiles/` by name.
+    fn list_profiles(&self) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(&self.profiles_dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|entry| entry.path())
+                    .filter(|path| {
+                        path.extension()
+                            .and_then(|ext| ext.to_str())
+                            .and_then(MenuFormat::from_name)
+                            .is_some()
+                    })
+                    .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+                    .filter(|name| validate_profile_name(name).is_ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+        names.sort();
+        names.dedup();
+        names.insert(0, DEFAULT_PROFILE.to_string());
+        names
+    }
+
+    /// Named profiles keep their saved themes and custom colors in the
+    /// default menus.json so every profile offers the same ones.
+    fn shares_themes(&self) -> bool {
+        self.profile
+            .as_deref()
+            .is_some_and(|name| name != DEFAULT_PROFILE)
+    }
+
+    /// Read-only layers that exist, in the order they are merged.
+    fn layer_files(&self) -> Vec<(ItemSource, PathBuf)> {
+        let mut layers = Vec::new();
+        if self.system_menu_file.is_file() {
+            layers.push((ItemSource::System, self.system_menu_file.clone()));
+        }
+        if let Some(project) = &self.project_menu_file {
+            layers.push((ItemSource::Project, project.clone()));
+        }
+        layers
+    }
+
+    /// Directory project commands run in.
+    fn project_root(&self) -> Option<&Path> {
+        self.project_menu_file.as_deref().and_then(Path::parent)
+    }
+
+    fn source_path(&self, source: ItemSource) -> PathBuf {
+        match source {
+            ItemSource::User => self.menu_file.clone(),
+            ItemSource::System => self.system_menu_file.clone(),
+            ItemSource::Project => self
+                .project_menu_file
+                .clone()
+                .unwrap_or_else(|| PathBuf::from(PROJECT_MENU_FILE)),
+        }
+    }
+
+    fn load_menu(&self) -> Result<MenuFile> {
+        let mut menu_file = MenuFile::load(&self.menu_file, &self.backups_for(&self.menu_file))?;
+        if self.shares_themes() {
+            let shared_path = self.profile_menu_file(DEFAULT_PROFILE);
+            if let Ok(shared) = MenuFile::load(&shared_path, &self.backups_for(&shared_path)) {
+                menu_file.saved_themes = shared.saved_themes;
+                menu_file.custom_colors = shared.custom_colors;
+            }
+        }
+        Ok(menu_file)
+    }
+}
+
+const DEFAULT_PROFILE: &str = "default";
+const SYSTEM_MENU_FILE: &str = "/etc/menu-maker/menus.json";
+const PROJECT_MENU_FILE: &str = ".menu-maker.json";
+
+/// Nearest `.menu-maker.json` in `start` or one of its parents, the way
+/// make looks for a Makefile.
+fn find_project_menu(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_MENU_FILE))
+        .find(|path| path.is_file())
+}
+
+/// Which layer a menu item was loaded from. Only user items are editable
+/// and saved; the others are merged in on every load.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+enum ItemSource {
+    #[default]
+    User,
+    System,
+    Project,
+}
+
+impl ItemSource {
+    fn name(self) -> &'static str {
+        match self {
+            ItemSource::User => "user",
+            ItemSource::System => "system",
+            ItemSource::Project => "project",
+        }
+    }
+
+    fn marker(self) -> String {
+        match self {
+            ItemSource::User => String::new(),
+            other => format!("  [{}]", other.name()),
+        }
+    }
+}
+
+/// Merge the read-only layers into `categories`. System items come before the
+/// user's own in a shared category; project categories are kept as a section
+/// of their own. Returns a warning for every layer that could not be read.
+fn merge_menu_layers(paths: &AppPaths, categories: &mut Vec<CategoryState>) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for (source, path) in paths.layer_files() {
+        let layer = match MenuFile::read(&path) {
+            Ok(layer) => layer,
+            Err(err) => {
+                warnings.push(format!("{}: {err}", display_path(&path)));
+                continue;
+            }
+        };
+        for (name, config) in &layer.categories {
+            let mut incoming = CategoryState::from_config(name, config);
+            incoming.user_layer = false;
+            for item in &mut incoming.items {
+                item.source = source;
+            }
+            if source == ItemSource::Project {
+                incoming.source = ItemSource::Project;
+                categories.push(incoming);
+                continue;
+            }
+            match categories
+                .iter_mut()
+                .find(|category| category.name == *name && category.source == ItemSource::User)
+            {
+                Some(existing) => {
+                    existing.items.splice(0..0, incoming.items);
+                }
+                None => categories.push(incoming),
+            }
+        }
+    }
+    warnings
+}
+
+fn env_dir(name: &str) -> Option<PathBuf> {
+    std::env::var(name)
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+        .map(|value| expand_home(value.trim()))
+}
+
+/// `$<var>/menu-maker`, or `~/<fallback>/menu-maker` when the variable is
+/// unset or not absolute, as the XDG base directory spec asks.
+fn xdg_dir(var: &str, home: &Path, fallback: &str) -> PathBuf {
+    env_dir(var)
+        .filter(|dir| dir.is_absolute())
+        .unwrap_or_else(|| home.join(fallback))
+        .join("menu-maker")
+}
+
+/// Move the pre-XDG `~/.local/menu-maker` tree into the new directories,
+/// once. Commands pointing into the old bin directory are rewritten.
+fn migrate_legacy_tree(
+    legacy: &Path,
+    config_dir: &Path,
+    state_dir: &Path,
+    data_dir: &Path,
+) -> Result<Option<PathBuf>> {
+    if !legacy.is_dir() || legacy == config_dir || menu_file_in(config_dir, "menus").exists() {
+        return Ok(None);
+    }
+    // menus.json is written last: until it arrives the migration counts as
+    // unfinished, so a run that stopped halfway is picked up on the next start.
+    let moves = [
+        ("theme.json", config_dir),
+        ("keymap.json", config_dir),
+        ("themes", config_dir),
+        ("profiles", config_dir),
+        ("backups", state_dir),
+        ("bin", data_dir),
+    ];
+    for (name, target_dir) in moves {
+        let source = legacy.join(name);
+        let target = target_dir.join(name);
+        if source.exists() && !target.exists() {
+            fs::create_dir_all(target_dir)?;
+            move_path(&source, &target).with_context(|| {
+                format!(
+                    "Unable to move {} to {}",
+                    source.display(),
+                    target.display()
+                )
+            })?;
+        }
+    }
+    let old_bin = legacy.join("bin");
+    let new_bin = data_dir.join("bin");
+    let rewrite = |data: &str| {
+        data.replace(
+            &format!("{}/", display_path(&old_bin)),
+            &format!("{}/", display_path(&new_bin)),
+        )
+        .replace(
+            &format!("{}/", old_bin.display()),
+            &format!("{}/", new_bin.display()),
+        )
+    };
+    if let Ok(entries) = fs::read_dir(config_dir.join("profiles")) {
+        for file in entries.flatten().map(|entry| entry.path()) {
+            let Ok(data) = fs::read_to_string(&file) else {
+                continue;
+            };
+            let rewritten = rewrite(&data);
+            if rewritten != data {
+                write_atomic(&file, rewritten.as_bytes())?;
+            }
+        }
+    }
+    let legacy_menu = legacy.join("menus.json");
+    if let Ok(data) = fs::read_to_string(&legacy_menu) {
+        fs::create_dir_all(config_dir)?;
+        let target = config_dir.join("menus.json");
+        write_atomic(&target, rewrite(&data).as_bytes())?;
+        if let Ok(metadata) = fs::metadata(&legacy_menu) {
+            let _ = fs::set_permissions(&target, metadata.permissions());
+        }
+        fs::remove_file(&legacy_menu)
+            .with_context(|| format!("Unable to remove {}", legacy_menu.display()))?;
+    }
+    // Only removes the old directory if nothing unexpected was left in it.
+    let _ = fs::remove_dir(legacy);
+    Ok(Some(legacy.to_path_buf()))
+}
+
+/// Rename, falling back to copy and delete across filesystems.
+fn move_path(source: &Path, target: &Path) -> Result<()> {
+    if fs::rename(source, target).is_ok() {
+        return Ok(());
+    }
+    copy_recursive(source, target)?;
+    if source.is_dir() {
+        fs::remove_dir_all(source)?;
+    } else {
+        fs::remove_file(source)?;
+    }
+    Ok(())
+}
+
+fn copy_recursive(source: &Path, target: &Path) -> Result<()> {
+    if source.is_dir() {
+        fs::create_dir_all(target)?;
+        for entry in fs::read_dir(source)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &target.join(entry.file_name()))?;
+        }
+    } else {
+        fs::copy(source, target)?;
+    }
+    Ok(())
+}
+
+fn validate_profile_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("Profile name is empty".into());
+    }
+    if !name
+        .chars()
+        .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_')
+    {
+        return Err(format!(
+            "Profile name '{name}' may only contain letters, digits, '-' and '_'"
+        ));
+    }
+    Ok(())
+}
+
+struct AppState {
+    categories: Vec<CategoryState>,
+    custom_colors: Vec<NamedColorPair>,
+    saved_themes: Vec<SavedTheme>,
+    theme_files: Vec<ThemeFileEntry>,
+    column_count: u16,
+    current_index: usize,
+    display_entries: Vec<DisplayEntry>,
+    column_map: Vec<Vec<usize>>,
+    should_quit: bool,
+    pending_command: Option<PendingCommand>,
+    pending_action: Option<DeferredAction>,
+    status_message: Option<String>,
+    paths: AppPaths,
+    theme: Theme,
+    theme_key: String,
+    title: String,
+    active_popup: Option<PopupState>,
+    keymap: Keymap,
+    keymap_warnings: Vec<String>,
+    help_overlay: Option<HelpOverlay>,
+    theme_before_preview: Option<Theme>,
+    color_picker: Option<ColorPickerState>,
+    color_mode: ColorMode,
+    color_mode_setting: Option<String>,
+    status_template: Option<String>,
+    segments: LiveSegments,
+    last_exit_code: Option<i32>,
+    backup_count: Option<usize>,
+    recovery: Option<MenuRecovery>,
+    menu_save_blocked: bool,
+    profiles: Vec<String>,
+    config_watch: ConfigWatch,
+    external_change: Option<ExternalChange>,
+}
+
+impl AppState {
+    fn resolve_theme_key(
+        stored: Option<String>,
+        theme: &Theme,
+        saved_themes: &[SavedTheme],
+        theme_files: &[ThemeFileEntry],
+    ) -> String {
+        if let Some(key) = stored {
+            if key == CUSTOM_THEME_KEY || is_preset_theme_key(&key) {
+                return key;
+            }
+            if let Some(stem) = parse_file_theme_key(&key) {
+                if theme_files.iter().any(|entry| entry.stem == stem) {
+                    return key;
+                }
+            }
+            if let Some(idx) = parse_saved_theme_key(&key) {
+                if idx < saved_themes.len() {
+                    return key;
+                }
+            }
+        }
+        if let Some(idx) = saved_themes
+            .iter()
+            .position(|saved| saved.name == theme.name)
+        {
+            return saved_theme_key(idx);
+        }
+        if is_preset_theme_key(&theme.name) {
+            return theme.name.clone();
+        }
+        CUSTOM_THEME_KEY.to_string()
+    }
+    fn new(paths: AppPaths) -> Result<Self> {
+        let (mut menu_file, recovery) = match paths.load_menu() {
+            Ok(menu_file) => (menu_file, None),
+            Err(err) => (
+                MenuFile::default_data(),
+                Some(MenuRecovery::from_error(&paths.menu_file, &err, true)),
+            ),
+        };
+        if !menu_file
+            .saved_themes
+            .iter()
+            .any(|saved| saved.name.eq_ignore_ascii_case("default"))
+        {
+            menu_file.saved_themes.push(default_saved_theme());
+            if recovery.is_none() {
+                let _ =
+                    lock_file(&paths.menu_file).and_then(|_lock| menu_file.save(&paths.menu_file));
+            }
+        }
+        let menu_save_blocked = recovery.is_some();
+        let profiles = paths.list_profiles();
+        let theme = Theme::load(&paths.theme_file)?;
+        let (keymap, keymap_warnings) = Keymap::load(&paths.keymap_file);
+        let saved_themes = menu_file.saved_themes.clone();
+        let (theme_files, theme_file_warnings) = load_theme_directory(&paths.themes_dir);
+
+        let mut categories: Vec<CategoryState> = menu_file
+            .categories
+            .iter()
+            .map(|(name, cfg)| CategoryState::from_config(name, cfg))
+            .collect();
+        let layer_warnings = merge_menu_layers(&paths, &mut categories);
+        categories.sort_by_key(CategoryState::sort_key);
+
+        let mut column_count = menu_file
+            .app_settings
+            .columns
+            .unwrap_or(1)
+            .clamp(1, MAX_COLUMNS);
+        if column_count == 0 {
+            column_count = 1;
+        }
+
+        let stored_theme_key = AppState::resolve_theme_key(
+            menu_file.app_settings.theme_key.clone(),
+            &theme,
+            &saved_themes,
+            &theme_files,
+        );
+        let resolved_theme = if stored_theme_key == CUSTOM_THEME_KEY {
+            theme.clone()
+        } else if let Some(idx) = parse_saved_theme_key(&stored_theme_key) {
+            saved_themes
+                .get(idx)
+                .map(SavedTheme::to_theme)
+                .unwrap_or_else(|| theme.clone())
+        } else if let Some(stem) = parse_file_theme_key(&stored_theme_key) {
+            theme_files
+                .iter()
+                .find(|entry| entry.stem == stem)
+                .map(|entry| entry.theme.to_theme())
+                .unwrap_or_else(|| theme.clone())
+        } else if is_preset_theme_key(&stored_theme_key) {
+            Theme::from_name(&stored_theme_key).unwrap_or_else(|| theme.clone())
+        } else {
+            theme.clone()
+        };
+
+        let mut app = AppState {
+            categories,
+            custom_colors: menu_file.custom_colors,
+            saved_themes,
+            theme_files,
+            column_count,
+            current_index: 0,
+            display_entries: Vec::new(),
+            column_map: Vec::new(),
+            should_quit: false,
+            pending_command: None,
+            pending_action: None,
+            status_message: None,
+            paths,
+            theme_key: stored_theme_key,
+            theme: resolved_theme,
+            title: menu_file
+                .app_settings
+                .title
+                .unwrap_or_else(|| "Menu Maker".into()),
+            active_popup: None,
+            keymap,
+            keymap_warnings,
+            help_overlay: None,
+            theme_before_preview: None,
+            color_picker: None,
+            color_mode: ColorMode::detect(menu_file.app_settings.color_mode.as_deref()),
+            color_mode_setting: menu_file.app_settings.color_mode,
+            status_template: menu_file.app_settings.status_template,
+            segments: LiveSegments::default(),
+            last_exit_code: None,
+            backup_count: menu_file.app_settings.backup_count,
+            recovery,
+            menu_save_blocked,
+            profiles,
+            config_watch: ConfigWatch::default(),
+            external_change: None,
+        };
+        app.config_watch.mark(&app.paths);
+        app.refresh_segments();
+        app.rebuild_display();
+        app.report_theme_file_warnings(&theme_file_warnings);
+        app.report_keymap_warnings();
+        app.report_layer_warnings(&layer_warnings);
+        if let Some(ignored) = shadowed_menu_files(&app.paths.menu_file).first() {
+            app.set_status(Some(format!(
+                "Using {}; {} is ignored",
+                display_path(&app.paths.menu_file),
+                display_path(ignored)
+            )));
+        }
+        if let Some(legacy) = app.paths.migrated_from.clone() {
+            app.set_status(Some(format!(
+                "Moved {} to {}",
+                display_path(&legacy),
+                display_path(&app.paths.config_dir)
+            )));
+        }
+        Ok(app)
+    }
+
+    fn rebuild_display(&mut self) {
+        self.sort_categories();
+        self.display_entries.clear();
+        let columns = self.column_count.max(1);
+        self.column_map = vec![Vec::new(); columns as usize];
+        for (idx, category) in self.categories.iter().enumerate() {
+            let column_index = ((category.column.saturating_sub(1)) as usize)
+                .min(self.column_map.len().saturating_sub(1));
+            let entry_index = self.display_entries.len();
+            self.display_entries.push(DisplayEntry::Category {
+                category_index: idx,
+            });
+            self.column_map[column_index].push(entry_index);
+            if category.expanded {
+                for item_index in 0..category.items.len() {
+                    let entry_index = self.display_entries.len();
+                    self.display_entries.push(DisplayEntry::Item {
+                        category_index: idx,
+                        item_index,
+                    });
+                    self.column_map[column_index].push(entry_index);
+                }
+            }
+        }
+        if self.current_index >= self.display_entries.len() {
+            self.current_index = self.current_index.saturating_sub(1);
+            if self.display_entries.is_empty() {
+                self.current_index = 0;
+            }
+        }
+    }
+
+    fn entry_line(&self, entry_index: usize) -> (Line<'_>, Style) {
+        match &self.display_entries[entry_index] {
+            DisplayEntry::Category { category_index } => {
+                let category = &self.categories[*category_index];
+                let marker = if category.expanded { "▼" } else { "▶" };
+                let mut style = Style::default()
+                    .fg(self.theme.text)
+                    .bg(self.theme.surface)
+                    .add_modifier(Modifier::BOLD);
+                if let Some(colors) = &category.colors {
+                    if let Some(bg) = colors
+                        .background
+                        .as_ref()
+                        .and_then(|hex| color_from_hex(hex))
+                    {
+                        style = style.bg(bg);
+                    }
+                    if let Some(text) = colors.text.as_ref().and_then(|hex| color_from_hex(hex)) {
+                        style = style.fg(text);
+                    }
+                }
+                let section = match (category.source, self.paths.project_root()) {
+                    (ItemSource::Project, Some(root)) => format!(
+                        "  [project: {}]",
+                        root.file_name()
+                            .map(|name| name.to_string_lossy().into_owned())
+                            .unwrap_or_else(|| display_path(root))
+                    ),
+                    _ => String::new(),
+                };
+                (
+                    Line::from(format!("{marker} {}{section}", category.name)),
+                    style,
+                )
+            }
+            DisplayEntry::Item {
+                category_index,
+                item_index,
+            } => {
+                let item = &self.categories[*category_index].items[*item_index];
+                let mut style = Style::default().fg(self.theme.text).bg(self.theme.surface);
+                if let Some(colors) = self.categories[*category_index].colors.as_ref() {
+                    if let Some(bg) = colors
+                        .background
+                        .as_ref()
+                        .and_then(|hex| color_from_hex(hex))
+                    {
+                        style = style.bg(bg);
+                    }
+                    if let Some(text) = colors.text.as_ref().and_then(|hex| color_from_hex(hex)) {
+                        style = style.fg(text);
+                    }
+                }
+                let style = item.style.apply(style);
+                (
+                    Line::from(format!(
+                        "    {}{}",
+                        item.style.decorate(&item.label),
+                        // The project section is marked on its category header.
+                        if item.source == ItemSource::Project {
+                            String::new()
+                        } else {
+                            item.source.marker()
+                        }
+                    )),
+                    style,
+                )
+            }
+        }
+    }
+
+    fn selection_style(&self) -> Style {
+        let style = Style::default()
+            .fg(self.theme.background)
+            .bg(self.theme.highlight)
+            .add_modifier(Modifier::BOLD);
+        if self.color_mode == ColorMode::Monochrome {
+            style.add_modifier(Modifier::REVERSED)
+        } else {
+            style
+        }
+    }
+
+    fn highlight_entry_line(&self, line: Line<'_>) -> Line<'static> {
+        let mut spans = Vec::new();
+        for span in line.spans {
+            let mut owned = Span::styled(span.content.to_string(), span.style);
+            owned.style = owned.style.patch(self.selection_style());
+            spans.push(owned);
+        }
+        Line::from(spans)
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        if self.recovery.is_some() {
+            self.handle_recovery_key(key);
+            return;
+        }
+        if self.external_change.is_some() {
+            self.handle_external_change_key(key);
+            return;
+        }
+        if self.help_overlay.is_some() {
+            self.handle_help_key(key);
+            return;
+        }
+        if let Some(context) = self.key_context() {
+            if self.keymap.action_for(context, &key) == Some(KeyAction::Help) {
+                self.open_help(context);
+                return;
+            }
+        }
+        if let Some(picker) = self.color_picker.as_mut() {
+            match picker.handle_key(key, &self.keymap) {
+                Some(true) => self.close_color_picker(true),
+                Some(false) => self.close_color_picker(false),
+                None => self.write_picker_color(),
+            }
+            return;
+        }
+        if self.active_popup.is_some() {
+            let result = {
+                let popup = self.active_popup.as_mut().unwrap();
+                match popup {
+                    PopupState::Info(_) | PopupState::Message(_) => match key.code {
+                        KeyCode::Esc | KeyCode::Enter => PopupResult::Close(None),
+                        _ => PopupResult::None,
+                    },
+                    PopupState::ItemForm(form) => match form.handle_key(key, &self.keymap) {
+                        ItemFormKeyResult::Continue => PopupResult::None,
+                        ItemFormKeyResult::Cancel => {
+                            PopupResult::Close(Some("Item edit cancelled".into()))
+                        }
+                        ItemFormKeyResult::Submit(data) => PopupResult::ItemSubmit(data),
+                        ItemFormKeyResult::OpenEditor(field) => PopupResult::ItemOpenEditor(field),
+                        ItemFormKeyResult::PickColor => PopupResult::OpenColorPicker,
                     },
-                    PopupState::CategoryForm(form) => match form.handle_key(key) {
+                    PopupState::CategoryForm(form) => match form.handle_key(key, &self.keymap) {
                         FormKeyResult::Continue => PopupResult::None,
                         FormKeyResult::Cancel => {
                             PopupResult::Close(Some("Category edit cancelled".into()))
                         }
-                        FormKeyResult::Submit(data) => PopupResult::CategorySubmit(data),
-                        FormKeyResult::DeletePreset(index) => {
-                            PopupResult::CategoryDeletePreset(index)
+                        FormKeyResult::Submit(data) => PopupResult::CategorySubmit(data),
+                        FormKeyResult::DeletePreset(index) => {
+                            PopupResult::CategoryDeletePreset(index)
+                        }
+                        FormKeyResult::PickColor => PopupResult::OpenColorPicker,
+                    },
+                    PopupState::SettingsForm(form) => match form.handle_key(key, &self.keymap) {
+                        SettingsFormKeyResult::Continue => PopupResult::None,
+                        SettingsFormKeyResult::Cancel => {
+                            PopupResult::Close(Some("Settings update cancelled".into()))
+                        }
+                        SettingsFormKeyResult::Submit(data) => PopupResult::SettingsSubmit(data),
+                        SettingsFormKeyResult::DeleteSavedTheme(index) => {
+                            PopupResult::SettingsDeleteSavedTheme(index)
+                        }
+                        SettingsFormKeyResult::ImportTheme(path) => {
+                            PopupResult::SettingsImportTheme(path)
+                        }
+                        SettingsFormKeyResult::ExportTheme(option, path) => {
+                            PopupResult::SettingsExportTheme(option, path)
+                        }
+                        SettingsFormKeyResult::PickColor => PopupResult::OpenColorPicker,
+                        SettingsFormKeyResult::RestoreBackup(path) => {
+                            PopupResult::SettingsRestoreBackup(path)
+                        }
+                        SettingsFormKeyResult::ExportMenu(path) => {
+                            PopupResult::SettingsExportMenu(path)
+                        }
+                    },
+                    PopupState::ImportList(list) => match list.handle_key(key, &self.keymap) {
+                        ImportListKeyResult::Continue => PopupResult::None,
+                        ImportListKeyResult::Cancel => {
+                            PopupResult::Close(Some("Import cancelled".into()))
+                        }
+                        ImportListKeyResult::Scan(dir) => PopupResult::ImportScan(dir),
+                        ImportListKeyResult::Import(candidates) => {
+                            PopupResult::ImportSubmit(candidates)
+                        }
+                    },
+                }
+            };
+            match result {
+                PopupResult::None => {}
+                PopupResult::Close(status) => {
+                    self.active_popup = None;
+                    if let Some(msg) = status {
+                        self.set_status(Some(msg));
+                    }
+                }
+                PopupResult::ItemSubmit(data) => match self.apply_item_form_input(data) {
+                    Ok(msg) => {
+                        self.active_popup = None;
+                        self.set_status(Some(msg));
+                    }
+                    Err(err_msg) => {
+                        if let Some(PopupState::ItemForm(form)) = self.active_popup.as_mut() {
+                            form.error = Some(err_msg);
+                        }
+                    }
+                },
+                PopupResult::ItemOpenEditor(field) => {
+                    self.pending_action = Some(DeferredAction::ExternalEditor(field));
+                }
+                PopupResult::CategorySubmit(data) => match self.process_category_submission(data) {
+                    Ok(msg) => {
+                        self.active_popup = None;
+                        self.set_status(Some(msg));
+                    }
+                    Err(err_msg) => {
+                        if let Some(PopupState::CategoryForm(form)) = self.active_popup.as_mut() {
+                            form.error = Some(err_msg);
+                        }
+                    }
+                },
+                PopupResult::CategoryDeletePreset(index) => {
+                    let result = self.delete_custom_category_preset(index);
+                    self.handle_category_preset_delete_result(result);
+                }
+                PopupResult::SettingsSubmit(data) => match self.apply_settings_form_input(data) {
+                    Ok(msg) => {
+                        self.active_popup = None;
+                        self.set_status(Some(msg));
+                    }
+                    Err(err_msg) => {
+                        if let Some(PopupState::SettingsForm(form)) = self.active_popup.as_mut() {
+                            form.error = Some(err_msg);
+                        }
+                    }
+                },
+                PopupResult::SettingsDeleteSavedTheme(index) => {
+                    self.handle_saved_theme_deletion(index);
+                }
+                PopupResult::SettingsImportTheme(path) => self.import_theme_file(&path),
+                PopupResult::SettingsExportTheme(option, path) => {
+                    self.export_theme_option(&option, &path)
+                }
+                PopupResult::SettingsRestoreBackup(path) => self.handle_backup_restore(path),
+                PopupResult::SettingsExportMenu(path) => self.export_menu_to(&path),
+                PopupResult::ImportScan(dir) => self.scan_task_directory(&dir),
+                PopupResult::ImportSubmit(candidates) => {
+                    self.active_popup = None;
+                    let message = self.import_candidates(candidates);
+                    self.set_status(Some(message));
+                }
+                PopupResult::OpenColorPicker => self.open_color_picker(),
+            }
+            return;
+        }
+        if let Some(action) = self.keymap.action_for(KeyContext::Main, &key) {
+            self.execute_main_action(action);
+        }
+    }
+
+    fn handle_mouse(&mut self, mouse: MouseEvent, terminal_area: Rect) {
+        if self.recovery.is_some() {
+            self.handle_recovery_mouse(mouse, terminal_area);
+            return;
+        }
+        if self.external_change.is_some() {
+            return;
+        }
+        if let Some(context) = self.help_overlay.as_ref().map(|overlay| overlay.context) {
+            let line_count = self.help_lines(context).len();
+            let max_scroll = help_max_scroll(line_count, help_overlay_area(terminal_area));
+            let Some(overlay) = self.help_overlay.as_mut() else {
+                return;
+            };
+            match mouse.kind {
+                MouseEventKind::ScrollUp => overlay.scroll = overlay.scroll.saturating_sub(1),
+                MouseEventKind::ScrollDown => {
+                    overlay.scroll = overlay.scroll.saturating_add(1).min(max_scroll)
+                }
+                MouseEventKind::Down(MouseButton::Left) => self.help_overlay = None,
+                _ => {}
+            }
+            return;
+        }
+        if let Some(picker) = self.color_picker.as_mut() {
+            let layout = color_picker_layout(terminal_area);
+            picker.handle_mouse(mouse, &layout);
+            self.write_picker_color();
+            return;
+        }
+        if self.active_popup.is_some() {
+            if let Some(action) = self.detect_popup_click(mouse, terminal_area) {
+                self.apply_popup_click(action);
+            }
+            return;
+        }
+        if !matches!(mouse.kind, MouseEventKind::Down(MouseButton::Left)) {
+            return;
+        }
+        if let Some(entry_index) = self.entry_at_position(mouse.column, mouse.row, terminal_area) {
+            self.current_index = entry_index;
+            match self.display_entries[entry_index] {
+                DisplayEntry::Category { .. } => self.toggle_category(),
+                DisplayEntry::Item { .. } => self.prepare_command(),
+            }
+            return;
+        }
+
+        let sections = main_sections(terminal_area, self.show_profile_tabs());
+        if let Some(tabs_area) = sections.tabs {
+            if mouse.row == tabs_area.y {
+                let column = mouse.column.saturating_sub(tabs_area.x);
+                let clicked = self
+                    .profile_tabs()
+                    .segments
+                    .into_iter()
+                    .find(|(start, end, _)| column >= *start && column < *end);
+                if let Some((_, _, name)) = clicked {
+                    self.switch_profile(&name);
+                }
+                return;
+            }
+        }
+        let footer_area = sections.shortcuts;
+        if mouse.row >= footer_area.y
+            && mouse.row < footer_area.y + footer_area.height
+            && mouse.column >= footer_area.x
+            && mouse.column < footer_area.x + footer_area.width
+        {
+            if self.handle_footer_click(mouse.column, footer_area) {
+                return;
+            }
+        }
+    }
+
+    fn entry_at_position(&self, column: u16, row: u16, terminal_area: Rect) -> Option<usize> {
+        let content_area = main_sections(terminal_area, self.show_profile_tabs())
+            .content
+            .inner(&Margin {
+                vertical: 1,
+                horizontal: 1,
+            });
+        if content_area.width == 0 || content_area.height == 0 {
+            return None;
+        }
+        if column < content_area.x
+            || column >= content_area.x + content_area.width
+            || row < content_area.y
+            || row >= content_area.y + content_area.height
+        {
+            return None;
+        }
+
+        let column_count = self.column_count.max(1);
+        let constraints = (0..column_count)
+            .map(|_| Constraint::Ratio(1, column_count as u32))
+            .collect::<Vec<_>>();
+        let column_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(constraints)
+            .split(content_area);
+        for (idx, chunk) in column_chunks.iter().enumerate() {
+            if column < chunk.x
+                || column >= chunk.x + chunk.width
+                || row < chunk.y
+                || row >= chunk.y + chunk.height
+            {
+                continue;
+            }
+            let entries = self.column_map.get(idx)?;
+            if entries.is_empty() {
+                return None;
+            }
+            let line = row.saturating_sub(chunk.y);
+            let line_idx = usize::from(line);
+            if line_idx >= entries.len() {
+                return None;
+            }
+            return Some(entries[line_idx]);
+        }
+        None
+    }
+
+    fn handle_footer_click(&mut self, column: u16, footer_area: Rect) -> bool {
+        let line_data = self.footer_line_data();
+        if line_data.segments.is_empty() || line_data.total_width == 0 {
+            return false;
+        }
+        if footer_area.width == 0 || footer_area.height == 0 {
+            return false;
+        }
+        let text_width = line_data.total_width.min(footer_area.width);
+        let mut start_x = footer_area.x;
+        if footer_area.width > text_width {
+            start_x += (footer_area.width - text_width) / 2;
+        }
+        if column < start_x || column >= start_x + text_width {
+            return false;
+        }
+        let relative = column - start_x;
+        for segment in line_data.segments {
+            if relative >= segment.start && relative < segment.end {
+                self.execute_main_action(segment.action);
+                return true;
+            }
+        }
+        false
+    }
+
+    fn detect_popup_click(
+        &self,
+        mouse: MouseEvent,
+        terminal_area: Rect,
+    ) -> Option<PopupClickAction> {
+        if !matches!(mouse.kind, MouseEventKind::Down(MouseButton::Left)) {
+            return None;
+        }
+        let popup = self.active_popup.as_ref()?;
+        match popup {
+            PopupState::CategoryForm(form) => {
+                let (_lines, layout) = form.render_lines(self);
+                let Some([_, shortcut_area, content_area, _]) = popup_sections(terminal_area)
+                else {
+                    return None;
+                };
+                if mouse.column >= shortcut_area.x
+                    && mouse.column < shortcut_area.x + shortcut_area.width
+                    && mouse.row >= shortcut_area.y
+                    && mouse.row < shortcut_area.y + shortcut_area.height
+                {
+                    if layout.shortcut_total_width == 0
+                        || layout.shortcut_segments.is_empty()
+                        || shortcut_area.width == 0
+                    {
+                        return None;
+                    }
+                    let text_width = layout.shortcut_total_width.min(shortcut_area.width);
+                    if text_width == 0 {
+                        return None;
+                    }
+                    let mut start_x = shortcut_area.x;
+                    if shortcut_area.width > text_width {
+                        start_x += (shortcut_area.width - text_width) / 2;
+                    }
+                    if mouse.column < start_x || mouse.column >= start_x + text_width {
+                        return None;
+                    }
+                    let relative = mouse.column - start_x;
+                    for segment in &layout.shortcut_segments {
+                        if relative >= segment.start && relative < segment.end {
+                            return Some(PopupClickAction::Category(CategoryFormClick::Shortcut(
+                                segment.action,
+                            )));
+                        }
+                    }
+                    return None;
+                }
+                let inner = content_area.inner(&popup_content_margin());
+                if inner.width == 0
+                    || inner.height == 0
+                    || mouse.column < inner.x
+                    || mouse.column >= inner.x + inner.width
+                    || mouse.row < inner.y
+                    || mouse.row >= inner.y + inner.height
+                {
+                    return None;
+                }
+                let line_idx = usize::from(mouse.row.saturating_sub(inner.y));
+                if line_idx >= layout.line_count {
+                    return None;
+                }
+                if layout.name_line == Some(line_idx) {
+                    return Some(PopupClickAction::Category(CategoryFormClick::SelectField(
+                        CategoryField::Name,
+                    )));
+                }
+                if layout.column_line == Some(line_idx) {
+                    return Some(PopupClickAction::Category(CategoryFormClick::SelectField(
+                        CategoryField::Column,
+                    )));
+                }
+                if layout.custom_name_line == Some(line_idx) {
+                    return Some(PopupClickAction::Category(CategoryFormClick::SelectField(
+                        CategoryField::CustomPresetName,
+                    )));
+                }
+                if layout.custom_background_line == Some(line_idx) {
+                    return Some(PopupClickAction::Category(CategoryFormClick::SelectField(
+                        CategoryField::CustomPresetBackground,
+                    )));
+                }
+                if layout.custom_text_line == Some(line_idx) {
+                    return Some(PopupClickAction::Category(CategoryFormClick::SelectField(
+                        CategoryField::CustomPresetText,
+                    )));
+                }
+                if layout.presets_heading_line == Some(line_idx) {
+                    return Some(PopupClickAction::Category(CategoryFormClick::SelectField(
+                        CategoryField::Palette,
+                    )));
+                }
+                if let Some(start) = layout.presets_start_line {
+                    if line_idx >= start && line_idx < start + layout.presets_count {
+                        let palette_idx = line_idx - start;
+                        return Some(PopupClickAction::Category(
+                            CategoryFormClick::SelectPalette(palette_idx),
+                        ));
+                    }
+                }
+                None
+            }
+            PopupState::SettingsForm(form) => {
+                let (_lines, layout) = form.render_lines(self);
+                let Some([_, shortcut_area, content_area, _]) = popup_sections(terminal_area)
+                else {
+                    return None;
+                };
+                if mouse.column >= shortcut_area.x
+                    && mouse.column < shortcut_area.x + shortcut_area.width
+                    && mouse.row >= shortcut_area.y
+                    && mouse.row < shortcut_area.y + shortcut_area.height
+                {
+                    if layout.shortcut_total_width == 0
+                        || layout.shortcut_segments.is_empty()
+                        || shortcut_area.width == 0
+                    {
+                        return None;
+                    }
+                    let text_width = layout.shortcut_total_width.min(shortcut_area.width);
+                    if text_width == 0 {
+                        return None;
+                    }
+                    let mut start_x = shortcut_area.x;
+                    if shortcut_area.width > text_width {
+                        start_x += (shortcut_area.width - text_width) / 2;
+                    }
+                    if mouse.column < start_x || mouse.column >= start_x + text_width {
+                        return None;
+                    }
+                    let relative = mouse.column - start_x;
+                    for segment in &layout.shortcut_segments {
+                        if relative >= segment.start && relative < segment.end {
+                            return Some(PopupClickAction::Settings(SettingsFormClick::Shortcut(
+                                segment.action,
+                            )));
+                        }
+                    }
+                    return None;
+                }
+                let inner = content_area.inner(&popup_content_margin());
+                if inner.width == 0
+                    || inner.height == 0
+                    || mouse.column < inner.x
+                    || mouse.column >= inner.x + inner.width
+                    || mouse.row < inner.y
+                    || mouse.row >= inner.y + inner.height
+                {
+                    return None;
+                }
+                let line_idx = usize::from(mouse.row.saturating_sub(inner.y));
+                if line_idx >= layout.line_count {
+                    return None;
+                }
+                if layout.title_line == Some(line_idx) {
+                    return Some(PopupClickAction::Settings(SettingsFormClick::SelectField(
+                        SettingsField::Title,
+                    )));
+                }
+                if layout.status_template_line == Some(line_idx) {
+                    return Some(PopupClickAction::Settings(SettingsFormClick::SelectField(
+                        SettingsField::StatusTemplate,
+                    )));
+                }
+                if layout.columns_line == Some(line_idx) {
+                    return Some(PopupClickAction::Settings(SettingsFormClick::SelectField(
+                        SettingsField::Columns,
+                    )));
+                }
+                if layout.theme_heading_line == Some(line_idx) {
+                    return Some(PopupClickAction::Settings(SettingsFormClick::SelectField(
+                        SettingsField::Theme,
+                    )));
+                }
+                if let Some(start) = layout.theme_list_start {
+                    if line_idx >= start && line_idx < start + layout.theme_count {
+                        let theme_idx = line_idx - start;
+                        return Some(PopupClickAction::Settings(SettingsFormClick::SelectTheme(
+                            theme_idx,
+                        )));
+                    }
+                }
+                if layout.delete_saved_theme_line == Some(line_idx) {
+                    if let Some(idx) = layout.delete_saved_theme_index {
+                        return Some(PopupClickAction::Settings(
+                            SettingsFormClick::DeleteSavedTheme(idx),
+                        ));
+                    }
+                }
+                if layout.theme_file_path_line == Some(line_idx) {
+                    return Some(PopupClickAction::Settings(SettingsFormClick::SelectField(
+                        SettingsField::ThemeFilePath,
+                    )));
+                }
+                if layout.custom_heading_line == Some(line_idx) {
+                    return Some(PopupClickAction::Settings(SettingsFormClick::SelectField(
+                        SettingsField::CustomName,
+                    )));
+                }
+                if layout.custom_name_line == Some(line_idx) {
+                    return Some(PopupClickAction::Settings(SettingsFormClick::SelectField(
+                        SettingsField::CustomName,
+                    )));
+                }
+                if layout.derive_seed_line == Some(line_idx) {
+                    return Some(PopupClickAction::Settings(SettingsFormClick::SelectField(
+                        SettingsField::DeriveSeed,
+                    )));
+                }
+                if layout.derive_mode_line == Some(line_idx) {
+                    return Some(PopupClickAction::Settings(
+                        SettingsFormClick::ToggleDeriveMode,
+                    ));
+                }
+                if layout.custom_primary_line == Some(line_idx) {
+                    return Some(PopupClickAction::Settings(SettingsFormClick::SelectField(
+                        SettingsField::CustomPrimary,
+                    )));
+                }
+                if layout.custom_accent_line == Some(line_idx) {
+                    return Some(PopupClickAction::Settings(SettingsFormClick::SelectField(
+                        SettingsField::CustomAccent,
+                    )));
+                }
+                if layout.custom_background_line == Some(line_idx) {
+                    return Some(PopupClickAction::Settings(SettingsFormClick::SelectField(
+                        SettingsField::CustomBackground,
+                    )));
+                }
+                if layout.custom_surface_line == Some(line_idx) {
+                    return Some(PopupClickAction::Settings(SettingsFormClick::SelectField(
+                        SettingsField::CustomSurface,
+                    )));
+                }
+                if layout.custom_text_line == Some(line_idx) {
+                    return Some(PopupClickAction::Settings(SettingsFormClick::SelectField(
+                        SettingsField::CustomText,
+                    )));
+                }
+                if layout.custom_highlight_line == Some(line_idx) {
+                    return Some(PopupClickAction::Settings(SettingsFormClick::SelectField(
+                        SettingsField::CustomHighlight,
+                    )));
+                }
+                if layout.custom_footer_background_line == Some(line_idx) {
+                    return Some(PopupClickAction::Settings(SettingsFormClick::SelectField(
+                        SettingsField::CustomFooterBackground,
+                    )));
+                }
+                if layout.custom_footer_key_line == Some(line_idx) {
+                    return Some(PopupClickAction::Settings(SettingsFormClick::SelectField(
+                        SettingsField::CustomFooterKey,
+                    )));
+                }
+                if layout.custom_footer_label_line == Some(line_idx) {
+                    return Some(PopupClickAction::Settings(SettingsFormClick::SelectField(
+                        SettingsField::CustomFooterLabel,
+                    )));
+                }
+                if layout.custom_status_bar_line == Some(line_idx) {
+                    return Some(PopupClickAction::Settings(SettingsFormClick::SelectField(
+                        SettingsField::CustomStatusBar,
+                    )));
+                }
+                if layout.backup_line == Some(line_idx) {
+                    return Some(PopupClickAction::Settings(SettingsFormClick::SelectField(
+                        SettingsField::Backup,
+                    )));
+                }
+                if layout.export_line == Some(line_idx) {
+                    return Some(PopupClickAction::Settings(SettingsFormClick::SelectField(
+                        SettingsField::ExportPath,
+                    )));
+                }
+                if layout.custom_popup_border_line == Some(line_idx) {
+                    return Some(PopupClickAction::Settings(SettingsFormClick::SelectField(
+                        SettingsField::CustomPopupBorder,
+                    )));
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn apply_popup_click(&mut self, action: PopupClickAction) {
+        match action {
+            PopupClickAction::Category(category_click) => {
+                let mut pending_submit: Option<CategorySubmitPayload> = None;
+                let mut pending_delete: Option<usize> = None;
+                let mut pending_cancel = false;
+                let mut pending_help = false;
+                let mut pending_pick_color = false;
+                if let Some(PopupState::CategoryForm(form)) = self.active_popup.as_mut() {
+                    match category_click {
+                        CategoryFormClick::SelectField(field) => {
+                            form.selected_field = field;
+                        }
+                        CategoryFormClick::SelectPalette(index) => {
+                            if index < form.color_presets.len() {
+                                form.selected_field = CategoryField::Palette;
+                                form.palette_index = index;
+                                form.apply_selected_palette();
+                            }
+                        }
+                        CategoryFormClick::Shortcut(action) => match action {
+                            CategoryShortcutAction::NextField => {
+                                form.error = None;
+                                form.next_field();
+                            }
+                            CategoryShortcutAction::PreviousField => {
+                                form.error = None;
+                                form.previous_field();
+                            }
+                            CategoryShortcutAction::Submit => {
+                                match form.build_submission() {
+                                    Ok(input) => pending_submit = Some(input),
+                                    Err(err) => form.error = Some(err),
+                                }
+                            }
+                            CategoryShortcutAction::Cancel => {
+                                pending_cancel = true;
+                            }
+                            CategoryShortcutAction::PreviousPalette => {
+                                form.error = None;
+                                form.previous_palette();
+                            }
+                            CategoryShortcutAction::NextPalette => {
+                                form.error = None;
+                                form.next_palette();
+                            }
+                            CategoryShortcutAction::DeletePreset => {
+                                if let Some(index) = form.current_custom_preset_index() {
+                                    pending_delete = Some(index);
+                                } else {
+                                    form.error = Some("Select a custom theme to delete".into());
+                                }
+                            }
+                            CategoryShortcutAction::PickColor => {
+                                pending_pick_color = true;
+                            }
+                            CategoryShortcutAction::Help => {
+                                pending_help = true;
+                            }
+                        },
+                    }
+                }
+                if let Some(index) = pending_delete {
+                    let result = self.delete_custom_category_preset(index);
+                    self.handle_category_preset_delete_result(result);
+                }
+                if let Some(input) = pending_submit {
+                    let submit_result = self.process_category_submission(input);
+                    match submit_result {
+                        Ok(msg) => {
+                            self.active_popup = None;
+                            self.set_status(Some(msg));
+                        }
+                        Err(err) => {
+                            if let Some(PopupState::CategoryForm(form)) = self.active_popup.as_mut()
+                            {
+                                form.error = Some(err);
+                            }
+                        }
+                    }
+                }
+                if pending_cancel {
+                    self.active_popup = None;
+                    self.set_status(Some("Category edit cancelled".into()));
+                }
+                if pending_pick_color {
+                    self.open_color_picker();
+                }
+                if pending_help {
+                    self.open_help(KeyContext::CategoryForm);
+                }
+            }
+            PopupClickAction::Settings(settings_click) => {
+                let mut pending_delete_theme: Option<usize> = None;
+                let mut pending_restore: Option<PathBuf> = None;
+                let mut pending_export: Option<String> = None;
+                if let Some(PopupState::SettingsForm(form)) = self.active_popup.as_mut() {
+                    match settings_click {
+                        SettingsFormClick::SelectField(field) => {
+                            form.selected_field = field;
+                        }
+                        SettingsFormClick::SelectTheme(index) => {
+                            if index < form.theme_options.len() {
+                                form.selected_field = SettingsField::Theme;
+                                if form.theme_index != index {
+                                    form.theme_index = index;
+                                    form.populate_custom_fields_from_selection();
+                                }
+                            }
                         }
-                    },
-                    PopupState::SettingsForm(form) => match form.handle_key(key) {
-                        SettingsFormKeyResult::Continue => PopupResult::None,
-                        SettingsFormKeyResult::Cancel => {
-                            PopupResult::Close(Some("Settings update cancelled".into()))
+                        SettingsFormClick::DeleteSavedTheme(index) => {
+                            pending_delete_theme = Some(index);
                         }
-                        SettingsFormKeyResult::Submit(data) => PopupResult::SettingsSubmit(data),
-                        SettingsFormKeyResult::DeleteSavedTheme(index) => {
-                            PopupResult::SettingsDeleteSavedTheme(index)
+                        SettingsFormClick::ToggleDeriveMode => {
+                            form.selected_field = SettingsField::DeriveMode;
+                            form.derive_light = !form.derive_light;
                         }
-                    },
+                        SettingsFormClick::Shortcut(action) => match action {
+                            SettingsShortcutAction::NextField => {
+                                if let Some(PopupState::SettingsForm(form)) =
+                                    self.active_popup.as_mut()
+                                {
+                                    form.error = None;
+                                    form.next_field();
+                                }
+                            }
+                            SettingsShortcutAction::Submit => {
+                                let input = if let Some(PopupState::SettingsForm(form)) =
+                                    self.active_popup.as_mut()
+                                {
+                                    form.error = None;
+                                    form.to_input()
+                                } else {
+                                    return;
+                                };
+                                match self.apply_settings_form_input(input) {
+                                    Ok(msg) => {
+                                        self.active_popup = None;
+                                        self.set_status(Some(msg));
+                                    }
+                                    Err(err) => {
+                                        if let Some(PopupState::SettingsForm(form)) =
+                                            self.active_popup.as_mut()
+                                        {
+                                            form.error = Some(err);
+                                        }
+                                    }
+                                }
+                            }
+                            SettingsShortcutAction::Cancel => {
+                                self.active_popup = None;
+                                self.set_status(Some("Settings update cancelled".into()));
+                            }
+                            SettingsShortcutAction::PreviousTheme => {
+                                if let Some(PopupState::SettingsForm(form)) =
+                                    self.active_popup.as_mut()
+                                {
+                                    form.error = None;
+                                    form.previous_theme();
+                                }
+                            }
+                            SettingsShortcutAction::NextTheme => {
+                                if let Some(PopupState::SettingsForm(form)) =
+                                    self.active_popup.as_mut()
+                                {
+                                    form.error = None;
+                                    form.next_theme();
+                                }
+                            }
+                            SettingsShortcutAction::DeleteTheme => {
+                                if let Some(PopupState::SettingsForm(form)) =
+                                    self.active_popup.as_mut()
+                                {
+                                    if let Some(index) = form.current_deletable_theme_index() {
+                                        pending_delete_theme = Some(index);
+                                    } else {
+                                        form.error =
+                                            Some("Select a custom theme to delete".into());
+                                    }
+                                }
+                            }
+                            SettingsShortcutAction::PickColor => self.open_color_picker(),
+                            SettingsShortcutAction::DeriveTheme => form.derive_theme(),
+                            SettingsShortcutAction::RestoreBackup => {
+                                pending_restore = form.request_restore();
+                            }
+                            SettingsShortcutAction::ExportMenu => {
+                                if form.export_path.trim().is_empty() {
+                                    form.selected_field = SettingsField::ExportPath;
+                                    form.error =
+                                        Some("Enter a file or directory to export to".into());
+                                } else {
+                                    pending_export = Some(form.export_path.clone());
+                                }
+                            }
+                            SettingsShortcutAction::ImportTheme => {
+                                let path = form.theme_file_path.clone();
+                                self.import_theme_file(&path);
+                            }
+                            SettingsShortcutAction::ExportTheme => {
+                                let path = form.theme_file_path.clone();
+                                if let Some(option) =
+                                    form.theme_options.get(form.theme_index).cloned()
+                                {
+                                    self.export_theme_option(&option, &path);
+                                }
+                            }
+                            SettingsShortcutAction::Help => {
+                                self.open_help(KeyContext::SettingsForm);
+                            }
+                        },
+                    }
+                }
+                if let Some(index) = pending_delete_theme {
+                    self.handle_saved_theme_deletion(index);
+                }
+                if let Some(path) = pending_restore {
+                    self.handle_backup_restore(path);
+                }
+                if let Some(path) = pending_export {
+                    self.export_menu_to(&path);
+                }
+            }
+        }
+    }
+
+    fn reload_from_disk(&mut self) -> Result<()> {
+        let menu_file = self.paths.load_menu()?;
+        self.apply_loaded_menu(menu_file)?;
+        self.menu_save_blocked = false;
+        Ok(())
+    }
+
+    /// Reload everything, switching to the recovery screen if menus.json
+    /// itself is broken.
+    fn reload_or_recover(&mut self) {
+        let menu_file = match self.paths.load_menu() {
+            Ok(menu_file) => menu_file,
+            Err(err) => {
+                self.recovery = Some(MenuRecovery::from_error(&self.paths.menu_file, &err, false));
+                return;
+            }
+        };
+        if let Err(err) = self.apply_loaded_menu(menu_file) {
+            self.set_status(Some(format!("Reload failed: {err}")));
+        } else {
+            self.menu_save_blocked = false;
+            self.set_status(Some("Configuration reloaded".into()));
+            self.report_keymap_warnings();
+        }
+    }
+
+    fn apply_loaded_menu(&mut self, menu_file: MenuFile) -> Result<()> {
+        self.theme = Theme::load(&self.paths.theme_file)?;
+        let (keymap, keymap_warnings) = Keymap::load(&self.paths.keymap_file);
+        self.keymap = keymap;
+        self.keymap_warnings = keymap_warnings;
+        self.saved_themes = menu_file.saved_themes;
+        let (theme_files, theme_file_warnings) = load_theme_directory(&self.paths.themes_dir);
+        self.theme_files = theme_files;
+        self.report_theme_file_warnings(&theme_file_warnings);
+        self.theme_key = AppState::resolve_theme_key(
+            menu_file.app_settings.theme_key.clone(),
+            &self.theme,
+            &self.saved_themes,
+            &self.theme_files,
+        );
+        self.categories = menu_file
+            .categories
+            .iter()
+            .map(|(name, cfg)| CategoryState::from_config(name, cfg))
+            .collect();
+        let layer_warnings = merge_menu_layers(&self.paths, &mut self.categories);
+        self.report_layer_warnings(&layer_warnings);
+        self.categories.sort_by_key(CategoryState::sort_key);
+        self.custom_colors = menu_file.custom_colors;
+        self.column_count = menu_file
+            .app_settings
+            .columns
+            .unwrap_or(self.column_count)
+            .clamp(1, MAX_COLUMNS);
+        self.color_mode = ColorMode::detect(menu_file.app_settings.color_mode.as_deref());
+        self.color_mode_setting = menu_file.app_settings.color_mode;
+        self.status_template = menu_file.app_settings.status_template;
+        self.backup_count = menu_file.app_settings.backup_count;
+        if let Some(title) = menu_file.app_settings.title {
+            self.title = title;
+        }
+        self.rebuild_display();
+        self.config_watch.mark(&self.paths);
+        Ok(())
+    }
+
+    /// Pick up edits other programs made to menus.json or theme.json. The
+    /// reload waits for a decision when a form has unsaved edits.
+    fn check_config_files(&mut self) {
+        if let Some(converted) = converted_menu_file(&self.paths.menu_file) {
+            let previous = std::mem::replace(&mut self.paths.menu_file, converted);
+            // Same menu in another format, so it becomes the merge base.
+            self.config_watch.mark(&self.paths);
+            self.set_status(Some(format!(
+                "{} was converted; now using {}",
+                display_path(&previous),
+                display_path(&self.paths.menu_file)
+            )));
+        }
+        if self.external_change.is_some() {
+            return;
+        }
+        let files = self.config_watch.changed(&self.paths);
+        if files.is_empty() {
+            return;
+        }
+        if self.recovery.is_some() || self.menu_save_blocked {
+            // The broken file may have been fixed in another editor.
+            self.config_watch.mark(&self.paths);
+            if self.reload_from_disk().is_ok() {
+                self.recovery = None;
+                self.set_status(Some(format!(
+                    "Loaded {} after it changed on disk",
+                    display_path(&self.paths.menu_file)
+                )));
+            }
+            return;
+        }
+        if self.has_unsaved_changes() {
+            self.external_change = Some(ExternalChange { files });
+            return;
+        }
+        self.reload_changed_files(&files);
+    }
+
+    fn has_unsaved_changes(&self) -> bool {
+        matches!(
+            self.active_popup,
+            Some(
+                PopupState::ItemForm(_) | PopupState::CategoryForm(_) | PopupState::SettingsForm(_)
+            )
+        )
+    }
+
+    fn reload_changed_files(&mut self, files: &[PathBuf]) {
+        let anchor = self.selection_anchor();
+        match self.paths.load_menu() {
+            Ok(menu_file) => {
+                if let Err(err) = self.apply_loaded_menu(menu_file) {
+                    self.config_watch.mark(&self.paths);
+                    self.set_status(Some(format!("Reload failed: {err}")));
+                    return;
+                }
+            }
+            Err(err) => {
+                self.config_watch.mark(&self.paths);
+                self.recovery = Some(MenuRecovery::from_error(&self.paths.menu_file, &err, false));
+                return;
+            }
+        }
+        self.restore_selection(anchor);
+        if let Some(notice) = self.config_watch.merge_notice.take() {
+            self.set_status(Some(notice));
+            return;
+        }
+        let names = files
+            .iter()
+            .filter_map(|path| path.file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .collect::<Vec<_>>()
+            .join(" and ");
+        self.set_status(Some(format!("Reloaded {names} after it changed on disk")));
+    }
+
+    fn handle_external_change_key(&mut self, key: KeyEvent) {
+        let Some(change) = self.external_change.as_ref() else {
+            return;
+        };
+        match key.code {
+            KeyCode::Char('r') | KeyCode::Char('R') => {
+                let files = change.files.clone();
+                self.external_change = None;
+                self.active_popup = None;
+                self.color_picker = None;
+                self.theme_before_preview = None;
+                self.reload_changed_files(&files);
+            }
+            KeyCode::Char('k') | KeyCode::Char('K') | KeyCode::Esc => {
+                self.external_change = None;
+                self.config_watch.acknowledge(&self.paths);
+                self.set_status(Some(
+                    "Kept your edits; saving will merge them with the outside change".into(),
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    /// Category and item under the cursor, to find them again after the
+    /// menu is rebuilt from disk.
+    fn selection_anchor(&self) -> Option<(String, ItemSource, Option<String>)> {
+        match self.display_entries.get(self.current_index)? {
+            DisplayEntry::Category { category_index } => {
+                let category = &self.categories[*category_index];
+                Some((category.name.clone(), category.source, None))
+            }
+            DisplayEntry::Item {
+                category_index,
+                item_index,
+            } => {
+                let category = &self.categories[*category_index];
+                let item = &category.items[*item_index];
+                Some((
+                    category.name.clone(),
+                    category.source,
+                    Some(item.label.clone()),
+                ))
+            }
+        }
+    }
+
+    fn restore_selection(&mut self, anchor: Option<(String, ItemSource, Option<String>)>) {
+        let Some((name, source, label)) = anchor else {
+            return;
+        };
+        let found = self.display_entries.iter().position(|entry| match entry {
+            DisplayEntry::Category { category_index } => {
+                let category = &self.categories[*category_index];
+                label.is_none() && category.name == name && category.source == source
+            }
+            DisplayEntry::Item {
+                category_index,
+                item_index,
+            } => {
+                let category = &self.categories[*category_index];
+                category.name == name
+                    && category.source == source
+                    && label.as_deref() == Some(category.items[*item_index].label.as_str())
+            }
+        });
+        if let Some(index) = found {
+            self.current_index = index;
+        }
+    }
+
+    fn handle_recovery_key(&mut self, key: KeyEvent) {
+        let Some(recovery) = self.recovery.as_mut() else {
+            return;
+        };
+        let count = RecoveryOption::ALL.len();
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => {
+                recovery.selected = (recovery.selected + count - 1) % count
+            }
+            KeyCode::Down | KeyCode::Char('j') | KeyCode::Tab => {
+                recovery.selected = (recovery.selected + 1) % count
+            }
+            KeyCode::Enter => {
+                let option = RecoveryOption::ALL[recovery.selected];
+                self.choose_recovery_option(option);
+            }
+            code => {
+                if let Some(option) = RecoveryOption::from_key(code) {
+                    self.choose_recovery_option(option);
+                }
+            }
+        }
+    }
+
+    fn handle_recovery_mouse(&mut self, mouse: MouseEvent, terminal_area: Rect) {
+        let MouseEventKind::Down(MouseButton::Left) = mouse.kind else {
+            return;
+        };
+        let Some(recovery) = self.recovery.as_ref() else {
+            return;
+        };
+        let area = recovery_area(terminal_area);
+        let (_, first_option) = recovery_lines(recovery, self);
+        let Some(row) = mouse.row.checked_sub(area.y + 1) else {
+            return;
+        };
+        let Some(index) = usize::from(row).checked_sub(first_option) else {
+            return;
+        };
+        if let Some(option) = RecoveryOption::ALL.get(index).copied() {
+            if let Some(recovery) = self.recovery.as_mut() {
+                recovery.selected = index;
+            }
+            self.choose_recovery_option(option);
+        }
+    }
+
+    fn choose_recovery_option(&mut self, option: RecoveryOption) {
+        match option {
+            RecoveryOption::OpenEditor => self.pending_action = Some(DeferredAction::EditMenuFile),
+            RecoveryOption::LoadBackup => self.recover_from_backup(),
+            RecoveryOption::UseDefaults => {
+                let mut menu_file = MenuFile::default_data();
+                menu_file.saved_themes.push(default_saved_theme());
+                if let Err(err) = self.apply_loaded_menu(menu_file) {
+                    self.set_recovery_notice(format!("Could not load defaults: {err}"));
+                    return;
+                }
+                self.recovery = None;
+                self.menu_save_blocked = true;
+                self.set_status(Some(format!(
+                    "Using the default menu; {} is untouched and changes are not saved until it loads",
+                    display_path(&self.paths.menu_file)
+                )));
+            }
+            RecoveryOption::Dismiss => {
+                let at_startup = self.recovery.as_ref().is_some_and(|r| r.at_startup);
+                self.recovery = None;
+                if at_startup {
+                    self.should_quit = true;
+                } else {
+                    self.menu_save_blocked = true;
+                    self.set_status(Some(
+                        "Kept the current menu; changes are not saved until the file loads".into(),
+                    ));
+                }
+            }
+        }
+    }
+
+    fn set_recovery_notice(&mut self, notice: String) {
+        if let Some(recovery) = self.recovery.as_mut() {
+            recovery.notice = Some(notice);
+        }
+    }
+
+    /// Load the newest backup that parses. The broken file is kept next to
+    /// menus.json with a `.broken` suffix rather than rotated into backups.
+    fn recover_from_backup(&mut self) {
+        let backup = list_backups(
+            &self.paths.backups_for(&self.paths.menu_file),
+            &self.paths.menu_file,
+        )
+        .into_iter()
+        .find_map(|path| {
+            let data = backup_contents(&path, &self.paths.menu_file).ok()?;
+            Some((path, data))
+        });
+        let Some((backup, data)) = backup else {
+            self.set_recovery_notice("No readable backup was found".into());
+            return;
+        };
+        let mut broken_name = self.paths.menu_file.as_os_str().to_owned();
+        broken_name.push(".broken");
+        let broken_path = PathBuf::from(broken_name);
+        let written = lock_file(&self.paths.menu_file).and_then(|_lock| {
+            fs::copy(&self.paths.menu_file, &broken_path)
+                .context("Could not keep a copy of the broken file")?;
+            write_atomic(&self.paths.menu_file, data.as_bytes())
+                .context("Could not restore the backup")
+        });
+        if let Err(err) = written {
+            self.set_recovery_notice(format!("{err:#}"));
+            return;
+        }
+        match self.reload_from_disk() {
+            Ok(()) => {
+                self.recovery = None;
+                self.set_status(Some(format!(
+                    "Loaded backup from {}; the broken file was saved as {}",
+                    backup_label(&backup),
+                    display_path(&broken_path)
+                )));
+            }
+            Err(err) => self.set_recovery_notice(format!("Reload failed: {err}")),
+        }
+    }
+
+    fn edit_menu_file_externally<B>(&mut self, terminal: &mut Terminal<B>) -> Result<()>
+    where
+        B: ratatui::backend::Backend + Write,
+    {
+        let path = self.paths.menu_file.clone();
+        if let Err(err) = with_terminal_suspension(terminal, || open_in_editor(&path)) {
+            self.set_recovery_notice(format!("External editor failed: {err}"));
+            return Ok(());
+        }
+        let at_startup = self.recovery.as_ref().is_none_or(|r| r.at_startup);
+        match self.paths.load_menu() {
+            Ok(menu_file) => {
+                self.apply_loaded_menu(menu_file)?;
+                self.recovery = None;
+                self.menu_save_blocked = false;
+                self.set_status(Some(format!("Loaded {}", display_path(&path))));
+            }
+            Err(err) => {
+                let mut recovery = MenuRecovery::from_error(&path, &err, at_startup);
+                recovery.notice = Some("The file still has errors".into());
+                self.recovery = Some(recovery);
+            }
+        }
+        Ok(())
+    }
+
+    fn report_theme_file_warnings(&mut self, warnings: &[String]) {
+        if let Some(first) = warnings.first() {
+            let mut message = format!("Theme file skipped: {first}");
+            if warnings.len() > 1 {
+                message.push_str(&format!(" (+{} more)", warnings.len() - 1));
+            }
+            self.set_status(Some(message));
+        }
+    }
+
+    fn report_layer_warnings(&mut self, warnings: &[String]) {
+        if let Some(first) = warnings.first() {
+            let mut message = format!("Menu layer skipped: {first}");
+            if warnings.len() > 1 {
+                message.push_str(&format!(" (+{} more)", warnings.len() - 1));
+            }
+            self.set_status(Some(message));
+        }
+    }
+
+    fn report_keymap_warnings(&mut self) {
+        if let Some(first) = self.keymap_warnings.first() {
+            let mut message = format!("Keymap: {first}");
+            if self.keymap_warnings.len() > 1 {
+                message.push_str(&format!(" (+{} more)", self.keymap_warnings.len() - 1));
+            }
+            self.set_status(Some(message));
+        }
+    }
+
+    fn toggle_category(&mut self) {
+        if let Some(DisplayEntry::Category { category_index }) =
+            self.display_entries.get(self.current_index)
+        {
+            if let Some(category) = self.categories.get_mut(*category_index) {
+                category.expanded = !category.expanded;
+                self.rebuild_display();
+                let _ = self.save_menu();
+            }
+        }
+    }
+
+    fn move_selection_up(&mut self) {
+        if self.display_entries.is_empty() {
+            return;
+        }
+        if self.current_index == 0 {
+            self.current_index = self.display_entries.len().saturating_sub(1);
+        } else {
+            self.current_index -= 1;
+        }
+    }
+
+    fn move_selection_down(&mut self) {
+        if self.display_entries.is_empty() {
+            return;
+        }
+        self.current_index = (self.current_index + 1) % self.display_entries.len();
+    }
+
+    fn activate_current_entry(&mut self) {
+        if let Some(entry) = self.display_entries.get(self.current_index) {
+            match entry {
+                DisplayEntry::Category { .. } => {
+                    self.toggle_category();
+                }
+                DisplayEntry::Item { .. } => self.prepare_command(),
+            }
+        }
+    }
+
+    fn prepare_command(&mut self) {
+        if let Some(DisplayEntry::Item {
+            category_index,
+            item_index,
+        }) = self.display_entries.get(self.current_index)
+        {
+            let item = &self.categories[*category_index].items[*item_index];
+            if item.cmd.trim().is_empty() {
+                return;
+            }
+            self.status_message = Some(format!("Running {}", item.label));
+            self.pending_command = Some(PendingCommand {
+                command: item.cmd.clone(),
+                pause: item.pause,
+                dir: match item.source {
+                    ItemSource::Project => self.paths.project_root().map(Path::to_path_buf),
+                    _ => None,
+                },
+            });
+        }
+    }
+
+    fn save_menu(&self) -> Result<()> {
+        if self.menu_save_blocked {
+            anyhow::bail!(
+                "{} could not be loaded; not overwriting it",
+                display_path(&self.paths.menu_file)
+            );
+        }
+        let mut categories_map = BTreeMap::new();
+        for category in &self.categories {
+            // Categories that only come from the system or project menu are
+            // not the user's to save.
+            if category.source == ItemSource::Project
+                || !(category.user_layer || category.has_user_items())
+            {
+                continue;
+            }
+            categories_map.insert(category.name.clone(), category.to_config());
+        }
+        if self.paths.shares_themes() {
+            self.save_shared_themes()?;
+        }
+        let menu_file = MenuFile {
+            schema_version: MENU_SCHEMA_VERSION,
+            categories: categories_map,
+            app_settings: AppSettings {
+                title: Some(self.title.clone()),
+                columns: Some(self.column_count),
+                theme_key: Some(self.theme_key.clone()),
+                color_mode: self.color_mode_setting.clone(),
+                status_template: self.status_template.clone(),
+                backup_count: self.backup_count,
+            },
+            custom_colors: if self.paths.shares_themes() {
+                Vec::new()
+            } else {
+                self.custom_colors.clone()
+            },
+            saved_themes: if self.paths.shares_themes() {
+                Vec::new()
+            } else {
+                self.saved_themes.clone()
+            },
+        };
+        let _lock = lock_file(&self.paths.menu_file)?;
+        if let Some(converted) = converted_menu_file(&self.paths.menu_file) {
+            anyhow::bail!(
+                "{} was converted to {}; this change was not saved",
+                display_path(&self.paths.menu_file),
+                display_path(&converted)
+            );
+        }
+        // Another instance may have saved since this one last read the file.
+        let on_disk = read_menu_value(&self.paths.menu_file);
+        let base = self.config_watch.menu_base.borrow().clone();
+        let mut conflicts = Vec::new();
+        let merged = on_disk.is_some() && on_disk != base;
+        let menu_file = if merged {
+            let ours = serde_json::to_value(&menu_file)?;
+            let value = merge_json(
+                base.as_ref(),
+                Some(&ours),
+                on_disk.as_ref(),
+                "",
+                &mut conflicts,
+            )
+            .unwrap_or(ours);
+            serde_json::from_value(value)?
+        } else {
+            menu_file
+        };
+        // Expanding or collapsing a category saves too; backing that up
+        // would soon rotate out every copy worth restoring.
+        let view_only = on_disk.clone().map(without_view_state)
+            == Some(without_view_state(serde_json::to_value(&menu_file)?));
+        let backup = if view_only {
+            None
+        } else {
+            backup_file(
+                &self.paths.menu_file,
+                &self.paths.backups_for(&self.paths.menu_file),
+                self.backups_to_keep(),
+            )?
+        };
+        menu_file.save(&self.paths.menu_file)?;
+        // Theme changes are written just before the menu, so both files
+        // now hold what this app wrote.
+        self.config_watch.mark(&self.paths);
+        if merged {
+            // Forget the stamp so the watcher loads the merged result.
+            self.config_watch.menu.set(None);
+            *self.config_watch.merge_notice.borrow_mut() =
+                Some(merge_notice(&conflicts, backup.is_some()));
+        }
+        Ok(())
+    }
+
+    fn take_pending_command(&mut self) -> Option<PendingCommand> {
+        self.pending_command.take()
+    }
+
+    fn take_pending_action(&mut self) -> Option<DeferredAction> {
+        self.pending_action.take()
+    }
+
+    fn set_status(&mut self, message: Option<String>) {
+        self.status_message = message;
+    }
+
+    fn status_text(&self) -> String {
+        let template = self
+            .status_template
+            .as_deref()
+            .unwrap_or(DEFAULT_STATUS_TEMPLATE);
+        let mut text = self.expand_template(template);
+        if !template.contains("{message}") {
+            if let Some(msg) = &self.status_message {
+                text.push_str(" | ");
+                text.push_str(msg);
+            }
+        }
+        text
+    }
+
+    fn header_text(&self) -> String {
+        self.expand_template(&self.title)
+    }
+
+    fn expand_template(&self, template: &str) -> String {
+        expand_template(template, |segment| {
+            let value = match segment {
+                "item" => {
+                    let total = self.display_entries.len();
+                    let current = if total == 0 {
+                        0
+                    } else {
+                        self.current_index + 1
+                    };
+                    format!("{current}/{total}")
                 }
+                "theme" => self.theme.name.clone(),
+                "message" => self.status_message.clone().unwrap_or_default(),
+                "command" => self
+                    .selected_item_indices()
+                    .map(|(category, item)| self.categories[category].items[item].cmd.clone())
+                    .unwrap_or_default(),
+                "exit_code" => self
+                    .last_exit_code
+                    .map(|code| code.to_string())
+                    .unwrap_or_else(|| "-".into()),
+                "hostname" => self.segments.hostname.clone(),
+                "user" => self.segments.user.clone(),
+                "cwd" => self.segments.cwd.clone(),
+                "git_branch" => self.segments.git_branch.clone(),
+                "clock" => self.segments.clock.clone(),
+                "date" => self.segments.date.clone(),
+                "jobs" => self.segments.jobs.to_string(),
+                _ => return None,
             };
-            match result {
-                PopupResult::None => {}
-                PopupResult::Close(status) => {
-                    self.active_popup = None;
-                    if let Some(msg) = status {
-                        self.set_status(Some(msg));
-                    }
+            Some(value)
+        })
+    }
+
+    /// Recompute the live segments that the header or status template uses.
+    /// Runs on every event-loop tick, so unused segments are skipped.
+    fn refresh_segments(&mut self) {
+        let status_template = self
+            .status_template
+            .as_deref()
+            .unwrap_or(DEFAULT_STATUS_TEMPLATE);
+        let uses = |segment: &str| {
+            let placeholder = format!("{{{segment}}}");
+            self.title.contains(&placeholder) || status_template.contains(&placeholder)
+        };
+        let (hostname, user, cwd, git_branch, clock, jobs) = (
+            uses("hostname"),
+            uses("user"),
+            uses("cwd"),
+            uses("git_branch"),
+            uses("clock") || uses("date"),
+            uses("jobs"),
+        );
+        let segments = &mut self.segments;
+        if hostname && segments.hostname.is_empty() {
+            segments.hostname = current_hostname();
+        }
+        if user && segments.user.is_empty() {
+            segments.user = std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .unwrap_or_default();
+        }
+        if cwd || git_branch {
+            let dir = std::env::current_dir().unwrap_or_default();
+            if cwd {
+                segments.cwd = display_path(&dir);
+            }
+            if git_branch {
+                segments.git_branch = git_branch_name(&dir).unwrap_or_default();
+            }
+        }
+        if clock {
+            let (date, time) = local_date_time();
+            segments.date = date;
+            segments.clock = time;
+        }
+        if jobs {
+            segments.jobs = running_job_count();
+        }
+    }
+
+    fn available_color_presets(&self) -> Vec<ColorPreset> {
+        let mut presets: Vec<ColorPreset> = DEFAULT_CATEGORY_COLOR_PRESETS
+            .iter()
+            .map(|(name, bg, text)| ColorPreset::new(*name, *bg, *text))
+            .collect();
+        for (idx, pair) in self.custom_colors.iter().enumerate() {
+            if let (Some(bg), Some(text)) = (pair.background.as_deref(), pair.text.as_deref()) {
+                let name = pair
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| format!("Custom Theme {}", idx + 1));
+                presets.push(ColorPreset::from_custom(name, bg, text, idx));
+            }
+        }
+        if presets.is_empty() {
+            presets.push(ColorPreset::new("Default", "#034e68", "#caf0f8"));
+        }
+        presets
+    }
+
+    fn theme_options(&self) -> Vec<ThemeOption> {
+        let mut options: Vec<ThemeOption> = THEME_PRESETS
+            .iter()
+            .map(|(key, def)| ThemeOption::from_definition(key, def))
+            .collect();
+        for entry in &self.theme_files {
+            options.push(ThemeOption::from_theme(
+                &file_theme_key(&entry.stem),
+                &entry.theme.name,
+                &entry.theme.to_theme(),
+            ));
+        }
+        for (idx, saved) in self.saved_themes.iter().enumerate() {
+            options.push(ThemeOption::from_theme(
+                &saved_theme_key(idx),
+                &saved.name,
+                &saved.to_theme(),
+            ));
+        }
+        if self.theme_key == CUSTOM_THEME_KEY {
+            options.push(ThemeOption::from_theme(
+                CUSTOM_THEME_KEY,
+                "Custom Theme",
+                self.committed_theme(),
+            ));
+        }
+        options
+    }
+
+    /// The theme saved in settings, ignoring any preview shown while the
+    /// settings form is open.
+    fn committed_theme(&self) -> &Theme {
+        self.theme_before_preview.as_ref().unwrap_or(&self.theme)
+    }
+
+    /// Render the whole UI with the theme highlighted or typed into the
+    /// settings form, and restore the saved theme once the form closes.
+    fn sync_theme_preview(&mut self) {
+        let preview = match &self.active_popup {
+            Some(PopupState::SettingsForm(form)) => form.preview_theme(),
+            _ => None,
+        };
+        match preview {
+            Some(theme) => {
+                if self.theme_before_preview.is_none() {
+                    self.theme_before_preview = Some(self.theme.clone());
                 }
-                PopupResult::ItemSubmit(data) => match self.apply_item_form_input(data) {
-                    Ok(msg) => {
-                        self.active_popup = None;
-                        self.set_status(Some(msg));
-                    }
-                    Err(err_msg) => {
-                        if let Some(PopupState::ItemForm(form)) = self.active_popup.as_mut() {
-                            form.error = Some(err_msg);
-                        }
-                    }
-                },
-                PopupResult::CategorySubmit(data) => match self.process_category_submission(data) {
-                    Ok(msg) => {
-                        self.active_popup = None;
-                        self.set_status(Some(msg));
-                    }
-                    Err(err_msg) => {
-                        if let Some(PopupState::CategoryForm(form)) = self.active_popup.as_mut() {
-                            form.error = Some(err_msg);
-                        }
-                    }
-                },
-                PopupResult::CategoryDeletePreset(index) => {
-                    let result = self.delete_custom_category_preset(index);
-                    self.handle_category_preset_delete_result(result);
+                self.theme = theme;
+            }
+            None => self.end_theme_preview(),
+        }
+    }
+
+    fn end_theme_preview(&mut self) {
+        if let Some(original) = self.theme_before_preview.take() {
+            self.theme = original;
+        }
+    }
+
+    fn theme_from_saved_index(&self, index: usize) -> Option<Theme> {
+        self.saved_themes.get(index).map(SavedTheme::to_theme)
+    }
+
+    fn show_profile_tabs(&self) -> bool {
+        self.profiles.len() > 1
+    }
+
+    fn profile_tabs(&self) -> ProfileTabs {
+        let active_style = self.selection_style();
+        let inactive_style = Style::default()
+            .fg(self.theme.footer_label)
+            .bg(self.theme.footer_background);
+        let mut spans = Vec::new();
+        let mut segments = Vec::new();
+        let mut cursor: u16 = 0;
+        for name in &self.profiles {
+            let label = format!(" {name} ");
+            let width = UnicodeWidthStr::width(label.as_str()) as u16;
+            let style = if self.paths.profile.as_deref() == Some(name.as_str()) {
+                active_style
+            } else {
+                inactive_style
+            };
+            segments.push((cursor, cursor.saturating_add(width), name.clone()));
+            spans.push(Span::styled(label, style));
+            spans.push(Span::styled(" ", inactive_style));
+            cursor = cursor.saturating_add(width + 1);
+        }
+        if let Some(key) = self
+            .keymap
+            .key_label(KeyContext::Main, KeyAction::NextProfile)
+        {
+            spans.push(Span::styled(
+                format!(" {key} switch profile"),
+                inactive_style.add_modifier(Modifier::DIM),
+            ));
+        }
+        ProfileTabs {
+            line: Line::from(spans),
+            segments,
+        }
+    }
+
+    fn footer_line(&self) -> Line<'static> {
+        self.footer_line_data().line
+    }
+
+    fn footer_line_data(&self) -> FooterLineData {
+        let base_bg = self.theme.footer_background;
+        let shortcut_style = Style::default()
+            .fg(self.theme.footer_key)
+            .bg(base_bg)
+            .add_modifier(Modifier::BOLD);
+        let label_style = Style::default().fg(self.theme.footer_label).bg(base_bg);
+        let mut spans: Vec<Span<'static>> = Vec::new();
+        let mut segments = Vec::new();
+        let mut cursor: u16 = 0;
+        let shortcuts = FOOTER_SHORTCUTS.iter().filter_map(|shortcut| {
+            self.keymap
+                .key_label(KeyContext::Main, shortcut.action)
+                .map(|key| (key, shortcut))
+        });
+        for (index, (key, shortcut)) in shortcuts.enumerate() {
+            if index > 0 {
+                spans.push(Span::styled(" | ", label_style));
+                cursor = cursor.saturating_add(3);
+            }
+            let entry_start = cursor;
+            let key_len = key.chars().count() as u16;
+            spans.push(Span::styled(key, shortcut_style));
+            spans.push(Span::styled(shortcut.label, label_style));
+            let label_len = shortcut.label.chars().count() as u16;
+            let entry_end = entry_start
+                .saturating_add(key_len)
+                .saturating_add(label_len);
+            segments.push(FooterSegment {
+                start: entry_start,
+                end: entry_end,
+                action: shortcut.action,
+            });
+            cursor = entry_end;
+        }
+        FooterLineData {
+            line: Line::from(spans),
+            segments,
+            total_width: cursor,
+        }
+    }
+
+    fn key_context(&self) -> Option<KeyContext> {
+        if self.color_picker.is_some() {
+            return Some(KeyContext::ColorPicker);
+        }
+        match &self.active_popup {
+            None => Some(KeyContext::Main),
+            Some(PopupState::ItemForm(_)) => Some(KeyContext::ItemForm),
+            Some(PopupState::CategoryForm(_)) => Some(KeyContext::CategoryForm),
+            Some(PopupState::SettingsForm(_)) => Some(KeyContext::SettingsForm),
+            Some(PopupState::ImportList(_)) => Some(KeyContext::ImportList),
+            Some(PopupState::Info(_)) | Some(PopupState::Message(_)) => None,
+        }
+    }
+
+    fn open_color_picker(&mut self) {
+        let picker = match self.active_popup.as_ref() {
+            Some(PopupState::ItemForm(form)) => form.color_picker(&self.theme),
+            Some(PopupState::CategoryForm(form)) => form.color_picker(),
+            Some(PopupState::SettingsForm(form)) => form.color_picker(),
+            _ => None,
+        };
+        match picker {
+            Some(picker) => self.color_picker = Some(picker),
+            None => match self.active_popup.as_mut() {
+                Some(PopupState::ItemForm(form)) => {
+                    form.error = Some("Select a color field to pick a color".into())
                 }
-                PopupResult::SettingsSubmit(data) => match self.apply_settings_form_input(data) {
-                    Ok(msg) => {
-                        self.active_popup = None;
-                        self.set_status(Some(msg));
-                    }
-                    Err(err_msg) => {
-                        if let Some(PopupState::SettingsForm(form)) = self.active_popup.as_mut() {
-                            form.error = Some(err_msg);
-                        }
-                    }
-                },
-                PopupResult::SettingsDeleteSavedTheme(index) => {
-                    self.handle_saved_theme_deletion(index);
+                Some(PopupState::CategoryForm(form)) => {
+                    form.error = Some("Select a color field to pick a color".into())
                 }
-            }
-            return;
+                Some(PopupState::SettingsForm(form)) => {
+                    form.error = Some("Select a color field to pick a color".into())
+                }
+                _ => {}
+            },
         }
-        match key.code {
-            KeyCode::Char('q') | KeyCode::Esc => self.should_quit = true,
-            KeyCode::Up | KeyCode::Char('k') => self.move_selection_up(),
-            KeyCode::Down | KeyCode::Char('j') => self.move_selection_down(),
-            KeyCode::Enter => self.activate_current_entry(),
-            KeyCode::Char(' ') => {
-                self.toggle_category();
-            }
-            KeyCode::Char('r') => {
-                if let Err(err) = self.reload_from_disk() {
-                    self.set_status(Some(format!("Reload failed: {err}")));
-                } else {
-                    self.set_status(Some("Configuration reloaded".into()));
+    }
+
+    fn write_picker_color(&mut self) {
+        if let Some(picker) = self.color_picker.as_ref() {
+            let hex = picker.hex();
+            self.set_form_color(picker.target, hex);
+        }
+    }
+
+    fn close_color_picker(&mut self, apply: bool) {
+        if let Some(picker) = self.color_picker.take() {
+            let value = if apply {
+                picker.hex()
+            } else {
+                picker.original.clone()
+            };
+            self.set_form_color(picker.target, value);
+        }
+    }
+
+    fn set_form_color(&mut self, target: ColorPickerTarget, value: String) {
+        match (target, self.active_popup.as_mut()) {
+            (ColorPickerTarget::Item(field), Some(PopupState::ItemForm(form))) => {
+                form.selected_field = field;
+                if let Some(slot) = form.active_value_mut() {
+                    *slot = value;
                 }
             }
-            KeyCode::Char('i') => self.show_info_popup(),
-            KeyCode::Char('n') => self.queue_new_item(),
-            KeyCode::Char('e') => self.queue_edit_current(),
-            KeyCode::Char('d') => self.delete_selected_item(),
-            KeyCode::Char('s') => self.queue_settings(),
-            KeyCode::Char('t') => {
-                if key.modifiers.contains(KeyModifiers::CONTROL) {
-                    self.queue_settings_with_focus(SettingsField::Title);
-                } else {
-                    self.queue_settings_with_focus(SettingsField::Theme);
+            (ColorPickerTarget::Category(field), Some(PopupState::CategoryForm(form))) => {
+                form.selected_field = field;
+                if let Some(slot) = form.active_value_mut() {
+                    *slot = value;
                 }
             }
-            KeyCode::Char('b') => {
-                if key.modifiers.contains(KeyModifiers::CONTROL) {
-                    self.run_bin_scan();
+            (ColorPickerTarget::Settings(field), Some(PopupState::SettingsForm(form))) => {
+                form.selected_field = field;
+                if let Some(slot) = form.active_value_mut() {
+                    *slot = value;
                 }
             }
             _ => {}
         }
     }
 
-    fn handle_mouse(&mut self, mouse: MouseEvent, terminal_area: Rect) {
-        if self.active_popup.is_some() {
-            if let Some(action) = self.detect_popup_click(mouse, terminal_area) {
-                self.apply_popup_click(action);
-            }
+    fn open_help(&mut self, context: KeyContext) {
+        self.help_overlay = Some(HelpOverlay { context, scroll: 0 });
+    }
+
+    fn handle_help_key(&mut self, key: KeyEvent) {
+        let Some(context) = self.help_overlay.as_ref().map(|overlay| overlay.context) else {
             return;
-        }
-        if !matches!(mouse.kind, MouseEventKind::Down(MouseButton::Left)) {
+        };
+        let toggles_help = self.keymap.action_for(context, &key) == Some(KeyAction::Help);
+        let (width, height) = crossterm::terminal::size().unwrap_or((80, 24));
+        let area = help_overlay_area(Rect::new(0, 0, width, height));
+        let max_scroll = help_max_scroll(self.help_lines(context).len(), area);
+        let page = area.height.saturating_sub(3).max(1);
+        let Some(overlay) = self.help_overlay.as_mut() else {
             return;
+        };
+        match key.code {
+            _ if toggles_help => self.help_overlay = None,
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => self.help_overlay = None,
+            KeyCode::Up | KeyCode::Char('k') => overlay.scroll = overlay.scroll.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                overlay.scroll = overlay.scroll.saturating_add(1).min(max_scroll)
+            }
+            KeyCode::PageUp => overlay.scroll = overlay.scroll.saturating_sub(page),
+            KeyCode::PageDown => {
+                overlay.scroll = overlay.scroll.saturating_add(page).min(max_scroll)
+            }
+            KeyCode::Home => overlay.scroll = 0,
+            KeyCode::End => overlay.scroll = max_scroll,
+            _ => {}
         }
-        if let Some(entry_index) = self.entry_at_position(mouse.column, mouse.row, terminal_area) {
-            self.current_index = entry_index;
-            match self.display_entries[entry_index] {
-                DisplayEntry::Category { .. } => self.toggle_category(),
-                DisplayEntry::Item { .. } => self.prepare_command(),
+    }
+
+    fn help_lines(&self, context: KeyContext) -> Vec<Line<'static>> {
+        let heading_style = Style::default()
+            .fg(self.theme.accent)
+            .add_modifier(Modifier::BOLD);
+        let key_style = Style::default()
+            .fg(self.theme.highlight)
+            .add_modifier(Modifier::BOLD);
+        let text_style = Style::default().fg(self.theme.text);
+
+        let mut rows: Vec<(&str, String, &str)> = Vec::new();
+        for spec in key_specs_for(context) {
+            let keys = self
+                .keymap
+                .chords_for(context, spec.action)
+                .iter()
+                .map(|chord| chord.label())
+                .collect::<Vec<_>>();
+            let keys = if keys.is_empty() {
+                "(unbound)".to_string()
+            } else {
+                keys.join(", ")
+            };
+            rows.push((spec.group, keys, spec.description));
+        }
+        if !matches!(context, KeyContext::Main | KeyContext::ColorPicker) {
+            for (keys, description) in TEXT_EDITING_HELP {
+                rows.push(("Text Editing", keys.to_string(), description));
+            }
+        }
+        for (mouse_context, gesture, description) in MOUSE_BINDINGS {
+            if *mouse_context == context {
+                rows.push(("Mouse", gesture.to_string(), description));
             }
-            return;
         }
 
-        let layout = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Length(1),
-                Constraint::Length(1),
-                Constraint::Min(1),
-                Constraint::Length(1),
-            ])
-            .split(terminal_area);
-        if layout.len() < 4 {
-            return;
+        let key_width = rows
+            .iter()
+            .map(|(_, keys, _)| UnicodeWidthStr::width(keys.as_str()))
+            .max()
+            .unwrap_or(0);
+        let mut groups: Vec<&str> = Vec::new();
+        for (group, _, _) in &rows {
+            if !groups.contains(group) {
+                groups.push(group);
+            }
         }
-        let footer_area = layout[1];
-        if mouse.row >= footer_area.y
-            && mouse.row < footer_area.y + footer_area.height
-            && mouse.column >= footer_area.x
-            && mouse.column < footer_area.x + footer_area.width
-        {
-            if self.handle_footer_click(mouse.column, footer_area) {
-                return;
+        let mut lines: Vec<Line<'static>> = Vec::new();
+        for group in groups {
+            if !lines.is_empty() {
+                lines.push(Line::from(""));
+            }
+            lines.push(Line::from(Span::styled(group.to_string(), heading_style)));
+            for (_, keys, description) in
+                rows.iter().filter(|(row_group, _, _)| *row_group == group)
+            {
+                let padding = key_width.saturating_sub(UnicodeWidthStr::width(keys.as_str()));
+                lines.push(Line::from(vec![
+                    Span::raw("  "),
+                    Span::styled(format!("{keys}{}", " ".repeat(padding)), key_style),
+                    Span::raw("  "),
+                    Span::styled(description.to_string(), text_style),
+                ]));
+            }
+        }
+        if !self.keymap_warnings.is_empty() {
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled("Keymap Problems", heading_style)));
+            for warning in &self.keymap_warnings {
+                lines.push(Line::from(Span::styled(
+                    format!("  {warning}"),
+                    Style::default().fg(Color::Red),
+                )));
             }
         }
+        lines
     }
 
-    fn entry_at_position(&self, column: u16, row: u16, terminal_area: Rect) -> Option<usize> {
-        let layout = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Length(1),
-                Constraint::Length(1),
-                Constraint::Min(1),
-                Constraint::Length(1),
-            ])
-            .split(terminal_area);
-        if layout.len() < 3 {
-            return None;
+    fn execute_main_action(&mut self, action: KeyAction) {
+        match action {
+            KeyAction::Quit => self.should_quit = true,
+            KeyAction::MoveUp => self.move_selection_up(),
+            KeyAction::MoveDown => self.move_selection_down(),
+            KeyAction::Activate => self.activate_current_entry(),
+            KeyAction::ToggleCategory => self.toggle_category(),
+            KeyAction::Reload => self.reload_or_recover(),
+            KeyAction::ShowInfo => self.show_info_popup(),
+            KeyAction::NewItem => self.queue_new_item(),
+            KeyAction::Edit => self.queue_edit_current(),
+            KeyAction::Delete => self.delete_selected_item(),
+            KeyAction::Settings => self.queue_settings(),
+            KeyAction::ThemeSettings => self.queue_settings_with_focus(SettingsField::Theme),
+            KeyAction::TitleSettings => self.queue_settings_with_focus(SettingsField::Title),
+            KeyAction::ScanBin => self.run_bin_scan(),
+            KeyAction::ImportTasks => self.open_task_import(),
+            KeyAction::ImportAliases => self.open_alias_import(),
+            KeyAction::NextProfile => self.cycle_profile(true),
+            KeyAction::PreviousProfile => self.cycle_profile(false),
+            KeyAction::Help => self.open_help(KeyContext::Main),
+            _ => {}
         }
-        let content_area = layout[2].inner(&Margin {
-            vertical: 1,
-            horizontal: 1,
-        });
-        if content_area.width == 0 || content_area.height == 0 {
-            return None;
+    }
+
+    fn queue_new_item(&mut self) {
+        self.pending_action = Some(DeferredAction::NewItem);
+    }
+
+    fn queue_edit_current(&mut self) {
+        if let Some(entry) = self.display_entries.get(self.current_index) {
+            match entry {
+                DisplayEntry::Item {
+                    category_index,
+                    item_index,
+                } => {
+                    self.pending_action = Some(DeferredAction::EditItem {
+                        category_index: *category_index,
+                        item_index: *item_index,
+                    });
+                }
+                DisplayEntry::Category { category_index } => {
+                    self.pending_action = Some(DeferredAction::EditCategory {
+                        category_index: *category_index,
+                    });
+                }
+            }
         }
-        if column < content_area.x
-            || column >= content_area.x + content_area.width
-            || row < content_area.y
-            || row >= content_area.y + content_area.height
+    }
+
+    fn queue_settings(&mut self) {
+        self.queue_settings_with_focus(SettingsField::Title);
+    }
+
+    fn queue_settings_with_focus(&mut self, focus: SettingsField) {
+        self.pending_action = Some(DeferredAction::Settings(focus));
+    }
+
+    fn show_info_popup(&mut self) {
+        if let Some((category_index, item_index)) = self.selected_item_indices() {
+            let item = &self.categories[category_index].items[item_index];
+            self.active_popup = Some(PopupState::Info(InfoPopup {
+                label: item.label.clone(),
+                command: item.cmd.clone(),
+                category: self.categories[category_index].name.clone(),
+                description: item.info.clone(),
+                source: format!(
+                    "{} ({})",
+                    item.source.name(),
+                    display_path(&self.paths.source_path(item.source))
+                ),
+            }));
+        }
+    }
+
+    fn selected_item_indices(&self) -> Option<(usize, usize)> {
+        if let Some(DisplayEntry::Item {
+            category_index,
+            item_index,
+        }) = self.display_entries.get(self.current_index)
         {
-            return None;
+            Some((*category_index, *item_index))
+        } else {
+            None
         }
+    }
 
-        let column_count = self.column_count.max(1);
-        let constraints = (0..column_count)
-            .map(|_| Constraint::Ratio(1, column_count as u32))
-            .collect::<Vec<_>>();
-        let column_chunks = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints(constraints)
-            .split(content_area);
-        for (idx, chunk) in column_chunks.iter().enumerate() {
-            if column < chunk.x
-                || column >= chunk.x + chunk.width
-                || row < chunk.y
-                || row >= chunk.y + chunk.height
-            {
-                continue;
+    fn delete_selected_item(&mut self) {
+        if let Some((category_index, item_index)) = self.selected_item_indices() {
+            let source = self.categories[category_index].items[item_index].source;
+            if source != ItemSource::User {
+                self.set_status(Some(format!(
+                    "This item comes from {} and cannot be deleted here",
+                    display_path(&self.paths.source_path(source))
+                )));
+                return;
             }
-            let entries = self.column_map.get(idx)?;
-            if entries.is_empty() {
-                return None;
+            self.categories[category_index].items.remove(item_index);
+            if self.categories[category_index].items.is_empty() {
+                self.categories.remove(category_index);
             }
-            let line = row.saturating_sub(chunk.y);
-            let line_idx = usize::from(line);
-            if line_idx >= entries.len() {
-                return None;
+            if self.current_index >= self.display_entries.len().saturating_sub(1) {
+                self.current_index = self.current_index.saturating_sub(1);
+            }
+            let _ = self.save_menu();
+            self.sort_categories();
+            self.rebuild_display();
+            self.set_status(Some("Item deleted".into()));
+        }
+    }
+
+    fn sort_categories(&mut self) {
+        self.categories.sort_by_key(CategoryState::sort_key);
+    }
+
+    fn ensure_category(&mut self, name: &str) -> usize {
+        if let Some(idx) = self
+            .categories
+            .iter()
+            .position(|c| c.name == name && c.source == ItemSource::User)
+        {
+            return idx;
+        }
+        self.categories.push(CategoryState {
+            name: name.to_string(),
+            expanded: true,
+            column: 1,
+            colors: None,
+            items: Vec::new(),
+            source: ItemSource::User,
+            user_layer: true,
+        });
+        let idx = self.categories.len() - 1;
+        idx
+    }
+
+    fn upsert_saved_theme(&mut self, saved: SavedTheme) -> usize {
+        if let Some(idx) = self
+            .saved_themes
+            .iter()
+            .position(|theme| theme.name == saved.name)
+        {
+            self.saved_themes[idx] = saved;
+            idx
+        } else {
+            self.saved_themes.push(saved);
+            self.saved_themes.len() - 1
+        }
+    }
+
+    fn delete_saved_theme(&mut self, index: usize) {
+        if index >= self.saved_themes.len() {
+            return;
+        }
+        self.saved_themes.remove(index);
+        if let Some(old_index) = parse_saved_theme_key(&self.theme_key) {
+            if old_index == index {
+                if let Some(fallback) = Theme::from_name("nord") {
+                    let _ = fallback.save(&self.paths.theme_file);
+                    match self.theme_before_preview.as_mut() {
+                        Some(original) => *original = fallback,
+                        None => self.theme = fallback,
+                    }
+                    self.theme_key = "nord".into();
+                }
+            } else if old_index > index {
+                self.theme_key = saved_theme_key(old_index - 1);
             }
-            return Some(entries[line_idx]);
         }
-        None
     }
 
-    fn handle_footer_click(&mut self, column: u16, footer_area: Rect) -> bool {
-        let line_data = self.footer_line_data();
-        if line_data.segments.is_empty() || line_data.total_width == 0 {
-            return false;
+    /// Write saved themes and custom colors back to the default menus.json
+    /// when a named profile changed them.
+    fn save_shared_themes(&self) -> Result<()> {
+        let shared_path = self.paths.profile_menu_file(DEFAULT_PROFILE);
+        let _lock = lock_file(&shared_path)?;
+        let mut shared = MenuFile::load(&shared_path, &self.paths.backups_for(&shared_path))?;
+        let unchanged = serde_json::to_value(&shared.saved_themes)?
+            == serde_json::to_value(&self.saved_themes)?
+            && serde_json::to_value(&shared.custom_colors)?
+                == serde_json::to_value(&self.custom_colors)?;
+        if unchanged {
+            return Ok(());
         }
-        if footer_area.width == 0 || footer_area.height == 0 {
-            return false;
+        shared.saved_themes = self.saved_themes.clone();
+        shared.custom_colors = self.custom_colors.clone();
+        backup_file(
+            &shared_path,
+            &self.paths.backups_for(&shared_path),
+            self.backups_to_keep(),
+        )?;
+        shared.save(&shared_path)
+    }
+
+    fn switch_profile(&mut self, name: &str) {
+        if self.paths.profile.as_deref() == Some(name) {
+            return;
         }
-        let text_width = line_data.total_width.min(footer_area.width);
-        let mut start_x = footer_area.x;
-        if footer_area.width > text_width {
-            start_x += (footer_area.width - text_width) / 2;
+        self.paths.select_profile(name);
+        self.current_index = 0;
+        self.reload_or_recover();
+        self.profiles = self.paths.list_profiles();
+        if self.recovery.is_none() {
+            self.set_status(Some(format!("Profile: {name}")));
         }
-        if column < start_x || column >= start_x + text_width {
-            return false;
+    }
+
+    fn cycle_profile(&mut self, forward: bool) {
+        if self.profiles.len() < 2 {
+            self.set_status(Some(
+                "No other profiles; start with --profile <name> to create one".into(),
+            ));
+            return;
         }
-        let relative = column - start_x;
-        for segment in line_data.segments {
-            if relative >= segment.start && relative < segment.end {
-                self.execute_footer_action(segment.action);
-                return true;
-            }
+        let count = self.profiles.len();
+        let next = match self
+            .paths
+            .profile
+            .as_ref()
+            .and_then(|name| self.profiles.iter().position(|p| p == name))
+        {
+            Some(index) if forward => (index + 1) % count,
+            Some(index) => (index + count - 1) % count,
+            None => 0,
+        };
+        let name = self.profiles[next].clone();
+        self.switch_profile(&name);
+    }
+
+    /// Append an item to another profile's menu file, creating the category
+    /// there if needed.
+    fn add_item_to_profile(&self, profile: &str, category: &str, item: &MenuItem) -> Result<()> {
+        let path = self.paths.profile_menu_file(profile);
+        let _lock = lock_file(&path)?;
+        let mut menu_file = MenuFile::load(&path, &self.paths.backups_for(&path))?;
+        let config = MenuItemConfig {
+            label: item.label.clone(),
+            cmd: item.cmd.clone(),
+            info: Some(item.info.clone()),
+            pause: Some(item.pause),
+            style: item.style.clone(),
+        };
+        let entry = menu_file
+            .categories
+            .entry(category.to_string())
+            .or_insert_with(|| CategoryConfig {
+                expanded: true,
+                column: Some(1),
+                items: Vec::new(),
+                colors: None,
+            });
+        if entry
+            .items
+            .iter()
+            .any(|existing| existing.label == item.label)
+        {
+            anyhow::bail!("{category}/{} already exists", item.label);
         }
-        false
+        entry.items.push(config);
+        backup_file(
+            &path,
+            &self.paths.backups_for(&path),
+            self.backups_to_keep(),
+        )?;
+        menu_file.save(&path)
     }
 
-    fn detect_popup_click(
-        &self,
-        mouse: MouseEvent,
-        terminal_area: Rect,
-    ) -> Option<PopupClickAction> {
-        if !matches!(mouse.kind, MouseEventKind::Down(MouseButton::Left)) {
-            return None;
+    fn backups_to_keep(&self) -> usize {
+        self.backup_count.unwrap_or(DEFAULT_BACKUP_COUNT)
+    }
+
+    /// Replace menus.json with a backup. The current file is backed up first
+    /// so a restore can itself be undone.
+    fn restore_backup(&mut self, backup: &Path) -> Result<String, String> {
+        let data = backup_contents(backup, &self.paths.menu_file)
+            .map_err(|err| format!("Backup is not a valid menu file: {err:#}"))?;
+        {
+            let _lock = lock_file(&self.paths.menu_file).map_err(|err| format!("{err:#}"))?;
+            backup_file(
+                &self.paths.menu_file,
+                &self.paths.backups_for(&self.paths.menu_file),
+                self.backups_to_keep(),
+            )
+            .map_err(|err| format!("Could not back up the current menu: {err}"))?;
+            write_atomic(&self.paths.menu_file, data.as_bytes())
+                .map_err(|err| format!("Restore failed: {err}"))?;
         }
-        let popup = self.active_popup.as_ref()?;
-        match popup {
-            PopupState::CategoryForm(form) => {
-                let (_lines, layout) = form.render_lines(self);
-                let Some([_, shortcut_area, content_area, _]) = popup_sections(terminal_area)
-                else {
-                    return None;
-                };
-                if mouse.column >= shortcut_area.x
-                    && mouse.column < shortcut_area.x + shortcut_area.width
-                    && mouse.row >= shortcut_area.y
-                    && mouse.row < shortcut_area.y + shortcut_area.height
-                {
-                    if layout.shortcut_total_width == 0
-                        || layout.shortcut_segments.is_empty()
-                        || shortcut_area.width == 0
-                    {
-                        return None;
-                    }
-                    let text_width = layout.shortcut_total_width.min(shortcut_area.width);
-                    if text_width == 0 {
-                        return None;
-                    }
-                    let mut start_x = shortcut_area.x;
-                    if shortcut_area.width > text_width {
-                        start_x += (shortcut_area.width - text_width) / 2;
-                    }
-                    if mouse.column < start_x || mouse.column >= start_x + text_width {
-                        return None;
-                    }
-                    let relative = mouse.column - start_x;
-                    for segment in &layout.shortcut_segments {
-                        if relative >= segment.start && relative < segment.end {
-                            return Some(PopupClickAction::Category(CategoryFormClick::Shortcut(
-                                segment.action,
-                            )));
-                        }
-                    }
-                    return None;
-                }
-                let inner = content_area.inner(&popup_content_margin());
-                if inner.width == 0
-                    || inner.height == 0
-                    || mouse.column < inner.x
-                    || mouse.column >= inner.x + inner.width
-                    || mouse.row < inner.y
-                    || mouse.row >= inner.y + inner.height
-                {
-                    return None;
-                }
-                let line_idx = usize::from(mouse.row.saturating_sub(inner.y));
-                if line_idx >= layout.line_count {
-                    return None;
-                }
-                if layout.name_line == Some(line_idx) {
-                    return Some(PopupClickAction::Category(CategoryFormClick::SelectField(
-                        CategoryField::Name,
-                    )));
-                }
-                if layout.column_line == Some(line_idx) {
-                    return Some(PopupClickAction::Category(CategoryFormClick::SelectField(
-                        CategoryField::Column,
-                    )));
-                }
-                if layout.custom_name_line == Some(line_idx) {
-                    return Some(PopupClickAction::Category(CategoryFormClick::SelectField(
-                        CategoryField::CustomPresetName,
-                    )));
-                }
-                if layout.custom_background_line == Some(line_idx) {
-                    return Some(PopupClickAction::Category(CategoryFormClick::SelectField(
-                        CategoryField::CustomPresetBackground,
-                    )));
-                }
-                if layout.custom_text_line == Some(line_idx) {
-                    return Some(PopupClickAction::Category(CategoryFormClick::SelectField(
-                        CategoryField::CustomPresetText,
-                    )));
-                }
-                if layout.presets_heading_line == Some(line_idx) {
-                    return Some(PopupClickAction::Category(CategoryFormClick::SelectField(
-                        CategoryField::Palette,
-                    )));
-                }
-                if let Some(start) = layout.presets_start_line {
-                    if line_idx >= start && line_idx < start + layout.presets_count {
-                        let palette_idx = line_idx - start;
-                        return Some(PopupClickAction::Category(
-                            CategoryFormClick::SelectPalette(palette_idx),
-                        ));
-                    }
-                }
-                None
-            }
-            PopupState::SettingsForm(form) => {
-                let (_lines, layout) = form.render_lines(self);
-                let Some([_, shortcut_area, content_area, _]) = popup_sections(terminal_area)
-                else {
-                    return None;
-                };
-                if mouse.column >= shortcut_area.x
-                    && mouse.column < shortcut_area.x + shortcut_area.width
-                    && mouse.row >= shortcut_area.y
-                    && mouse.row < shortcut_area.y + shortcut_area.height
-                {
-                    if layout.shortcut_total_width == 0
-                        || layout.shortcut_segments.is_empty()
-                        || shortcut_area.width == 0
-                    {
-                        return None;
-                    }
-                    let text_width = layout.shortcut_total_width.min(shortcut_area.width);
-                    if text_width == 0 {
-                        return None;
-                    }
-                    let mut start_x = shortcut_area.x;
-                    if shortcut_area.width > text_width {
-                        start_x += (shortcut_area.width - text_width) / 2;
-                    }
-                    if mouse.column < start_x || mouse.column >= start_x + text_width {
-                        return None;
-                    }
-                    let relative = mouse.column - start_x;
-                    for segment in &layout.shortcut_segments {
-                        if relative >= segment.start && relative < segment.end {
-                            return Some(PopupClickAction::Settings(SettingsFormClick::Shortcut(
-                                segment.action,
-                            )));
-                        }
-                    }
-                    return None;
-                }
-                let inner = content_area.inner(&popup_content_margin());
-                if inner.width == 0
-                    || inner.height == 0
-                    || mouse.column < inner.x
-                    || mouse.column >= inner.x + inner.width
-                    || mouse.row < inner.y
-                    || mouse.row >= inner.y + inner.height
-                {
-                    return None;
-                }
-                let line_idx = usize::from(mouse.row.saturating_sub(inner.y));
-                if line_idx >= layout.line_count {
-                    return None;
-                }
-                if layout.title_line == Some(line_idx) {
-                    return Some(PopupClickAction::Settings(SettingsFormClick::SelectField(
-                        SettingsField::Title,
-                    )));
-                }
-                if layout.columns_line == Some(line_idx) {
-                    return Some(PopupClickAction::Settings(SettingsFormClick::SelectField(
-                        SettingsField::Columns,
-                    )));
-                }
-                if layout.theme_heading_line == Some(line_idx) {
-                    return Some(PopupClickAction::Settings(SettingsFormClick::SelectField(
-                        SettingsField::Theme,
-                    )));
-                }
-                if let Some(start) = layout.theme_list_start {
-                    if line_idx >= start && line_idx < start + layout.theme_count {
-                        let theme_idx = line_idx - start;
-                        return Some(PopupClickAction::Settings(SettingsFormClick::SelectTheme(
-                            theme_idx,
-                        )));
-                    }
-                }
-                if layout.delete_saved_theme_line == Some(line_idx) {
-                    if let Some(idx) = layout.delete_saved_theme_index {
-                        return Some(PopupClickAction::Settings(
-                            SettingsFormClick::DeleteSavedTheme(idx),
-                        ));
-                    }
-                }
-                if layout.custom_heading_line == Some(line_idx) {
-                    return Some(PopupClickAction::Settings(SettingsFormClick::SelectField(
-                        SettingsField::CustomName,
-                    )));
-                }
-                if layout.custom_name_line == Some(line_idx) {
-                    return Some(PopupClickAction::Settings(SettingsFormClick::SelectField(
-                        SettingsField::CustomName,
-                    )));
-                }
-                if layout.custom_primary_line == Some(line_idx) {
-                    return Some(PopupClickAction::Settings(SettingsFormClick::SelectField(
-                        SettingsField::CustomPrimary,
-                    )));
-                }
-                if layout.custom_accent_line == Some(line_idx) {
-                    return Some(PopupClickAction::Settings(SettingsFormClick::SelectField(
-                        SettingsField::CustomAccent,
-                    )));
-                }
-                if layout.custom_background_line == Some(line_idx) {
-                    return Some(PopupClickAction::Settings(SettingsFormClick::SelectField(
-                        SettingsField::CustomBackground,
-                    )));
-                }
-                if layout.custom_surface_line == Some(line_idx) {
-                    return Some(PopupClickAction::Settings(SettingsFormClick::SelectField(
-                        SettingsField::CustomSurface,
-                    )));
+        self.end_theme_preview();
+        self.reload_from_disk()
+            .map_err(|err| format!("Reload failed: {err}"))?;
+        Ok(format!("Restored {}", backup_label(backup)))
+    }
+
+    fn handle_backup_restore(&mut self, backup: PathBuf) {
+        match self.restore_backup(&backup) {
+            Ok(message) => {
+                self.active_popup = None;
+                self.set_status(Some(message));
+            }
+            Err(err) => {
+                if let Some(PopupState::SettingsForm(form)) = self.active_popup.as_mut() {
+                    form.error = Some(err);
                 }
-                if layout.custom_text_line == Some(line_idx) {
-                    return Some(PopupClickAction::Settings(SettingsFormClick::SelectField(
-                        SettingsField::CustomText,
-                    )));
+            }
+        }
+    }
+
+    fn handle_saved_theme_deletion(&mut self, index: usize) {
+        self.delete_saved_theme(index);
+        let new_options = self.theme_options();
+        let new_index = new_options
+            .iter()
+            .position(|opt| opt.key == self.theme_key)
+            .unwrap_or(0);
+        if let Some(PopupState::SettingsForm(form)) = self.active_popup.as_mut() {
+            form.theme_options = new_options;
+            form.theme_index = new_index;
+            form.selected_field = SettingsField::Theme;
+            form.populate_custom_fields_from_selection();
+        }
+        self.set_status(Some("Custom theme deleted".into()));
+    }
+
+    fn refresh_settings_theme_options(&mut self, select_key: &str) {
+        let new_options = self.theme_options();
+        if let Some(PopupState::SettingsForm(form)) = self.active_popup.as_mut() {
+            form.theme_index = new_options
+                .iter()
+                .position(|opt| opt.key == select_key)
+                .unwrap_or(form.theme_index.min(new_options.len().saturating_sub(1)));
+            form.theme_options = new_options;
+            form.selected_field = SettingsField::Theme;
+            form.populate_custom_fields_from_selection();
+        }
+    }
+
+    fn import_theme_file(&mut self, path: &str) {
+        let result = if path.trim().is_empty() {
+            Err(anyhow::anyhow!("Enter the path of a theme to import"))
+        } else {
+            import_color_scheme(&expand_home(path.trim())).and_then(|(themes, skipped)| {
+                let stems = themes
+                    .iter()
+                    .map(|theme| write_theme_file(&self.paths.themes_dir, theme))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok((stems, skipped))
+            })
+        };
+        match result {
+            Ok((stems, skipped)) => {
+                let (theme_files, _) = load_theme_directory(&self.paths.themes_dir);
+                self.theme_files = theme_files;
+                let select_key = stems.first().map(|stem| file_theme_key(stem));
+                self.refresh_settings_theme_options(select_key.as_deref().unwrap_or_default());
+                let mut message = if stems.len() == 1 {
+                    format!("Imported theme to themes/{}.json", stems[0])
+                } else {
+                    format!("Imported {} themes into themes/", stems.len())
+                };
+                if !skipped.is_empty() {
+                    message.push_str(&format!("; {}", skipped.join("; ")));
                 }
-                if layout.custom_highlight_line == Some(line_idx) {
-                    return Some(PopupClickAction::Settings(SettingsFormClick::SelectField(
-                        SettingsField::CustomHighlight,
-                    )));
+                self.set_status(Some(message));
+            }
+            Err(err) => {
+                if let Some(PopupState::SettingsForm(form)) = self.active_popup.as_mut() {
+                    form.error = Some(format!("Import failed: {err}"));
                 }
-                None
             }
-            _ => None,
         }
     }
 
-    fn apply_popup_click(&mut self, action: PopupClickAction) {
-        match action {
-            PopupClickAction::Category(category_click) => {
-                let mut pending_submit: Option<CategorySubmitPayload> = None;
-                let mut pending_delete: Option<usize> = None;
-                let mut pending_cancel = false;
-                if let Some(PopupState::CategoryForm(form)) = self.active_popup.as_mut() {
-                    match category_click {
-                        CategoryFormClick::SelectField(field) => {
-                            form.selected_field = field;
-                        }
-                        CategoryFormClick::SelectPalette(index) => {
-                            if index < form.color_presets.len() {
-                                form.selected_field = CategoryField::Palette;
-                                form.palette_index = index;
-                                form.apply_selected_palette();
-                            }
-                        }
-                        CategoryFormClick::Shortcut(action) => match action {
-                            CategoryShortcutAction::NextField => {
-                                form.error = None;
-                                form.next_field();
-                            }
-                            CategoryShortcutAction::PreviousField => {
-                                form.error = None;
-                                form.previous_field();
-                            }
-                            CategoryShortcutAction::Submit => {
-                                match form.build_submission() {
-                                    Ok(input) => pending_submit = Some(input),
-                                    Err(err) => form.error = Some(err),
-                                }
-                            }
-                            CategoryShortcutAction::Cancel => {
-                                pending_cancel = true;
-                            }
-                            CategoryShortcutAction::PreviousPalette => {
-                                form.error = None;
-                                form.previous_palette();
-                            }
-                            CategoryShortcutAction::NextPalette => {
-                                form.error = None;
-                                form.next_palette();
-                            }
-                            CategoryShortcutAction::DeletePreset => {
-                                if let Some(index) = form.current_custom_preset_index() {
-                                    pending_delete = Some(index);
-                                } else {
-                                    form.error = Some("Select a custom theme to delete".into());
-                                }
-                            }
-                        },
-                    }
+    fn export_menu_to(&mut self, path: &str) {
+        let target = expand_home(path.trim());
+        let result = ExportFormat::from_path(&target).and_then(|format| {
+            let menu_file = self.paths.load_menu()?;
+            export_menu(&menu_file, format, &target)
+        });
+        match result {
+            Ok(message) => self.set_status(Some(message)),
+            Err(err) => {
+                if let Some(PopupState::SettingsForm(form)) = self.active_popup.as_mut() {
+                    form.error = Some(format!("Export failed: {err}"));
                 }
-                if let Some(index) = pending_delete {
-                    let result = self.delete_custom_category_preset(index);
-                    self.handle_category_preset_delete_result(result);
+            }
+        }
+    }
+
+    fn export_theme_option(&mut self, option: &ThemeOption, path: &str) {
+        let theme = option.to_saved_theme();
+        let target = if path.trim().is_empty() {
+            self.paths
+                .themes_dir
+                .join(format!("{}.json", theme_file_stem(&theme.name)))
+        } else {
+            expand_home(path.trim())
+        };
+        match export_theme(&theme, &target) {
+            Ok(()) => {
+                if target.starts_with(&self.paths.themes_dir) {
+                    let (theme_files, _) = load_theme_directory(&self.paths.themes_dir);
+                    self.theme_files = theme_files;
+                    self.refresh_settings_theme_options(&option.key);
                 }
-                if let Some(input) = pending_submit {
-                    let submit_result = self.process_category_submission(input);
-                    match submit_result {
-                        Ok(msg) => {
-                            self.active_popup = None;
-                            self.set_status(Some(msg));
-                        }
-                        Err(err) => {
-                            if let Some(PopupState::CategoryForm(form)) = self.active_popup.as_mut()
-                            {
-                                form.error = Some(err);
-                            }
-                        }
-                    }
+                self.set_status(Some(format!("Exported theme to {}", target.display())));
+            }
+            Err(err) => {
+                if let Some(PopupState::SettingsForm(form)) = self.active_popup.as_mut() {
+                    form.error = Some(format!("Export failed: {err}"));
                 }
-                if pending_cancel {
-                    self.active_popup = None;
-                    self.set_status(Some("Category edit cancelled".into()));
+            }
+        }
+    }
+
+    fn execute_deferred_action<B>(
+        &mut self,
+        terminal: &mut Terminal<B>,
+        action: DeferredAction,
+    ) -> Result<()>
+    where
+        B: ratatui::backend::Backend + Write,
+    {
+        match action {
+            DeferredAction::NewItem => self.open_item_form(None),
+            DeferredAction::EditItem {
+                category_index,
+                item_index,
+            } => self.open_item_form(Some((category_index, item_index))),
+            DeferredAction::EditCategory { category_index } => {
+                if let Some(category) = self.categories.get(category_index) {
+                    let presets = self.available_color_presets();
+                    self.active_popup = Some(PopupState::CategoryForm(CategoryFormState::new(
+                        category_index,
+                        category,
+                        presets,
+                    )));
                 }
             }
-            PopupClickAction::Settings(settings_click) => {
-                let mut pending_delete_theme: Option<usize> = None;
-                if let Some(PopupState::SettingsForm(form)) = self.active_popup.as_mut() {
-                    match settings_click {
-                        SettingsFormClick::SelectField(field) => {
-                            form.selected_field = field;
-                        }
-                        SettingsFormClick::SelectTheme(index) => {
-                            if index < form.theme_options.len() {
-                                form.selected_field = SettingsField::Theme;
-                                if form.theme_index != index {
-                                    form.theme_index = index;
-                                    form.populate_custom_fields_from_selection();
-                                }
-                            }
-                        }
-                        SettingsFormClick::DeleteSavedTheme(index) => {
-                            pending_delete_theme = Some(index);
-                        }
-                        SettingsFormClick::Shortcut(action) => match action {
-                            SettingsShortcutAction::NextField => {
-                                if let Some(PopupState::SettingsForm(form)) =
-                                    self.active_popup.as_mut()
-                                {
-                                    form.error = None;
-                                    form.next_field();
-                                }
-                            }
-                            SettingsShortcutAction::Submit => {
-                                let input = if let Some(PopupState::SettingsForm(form)) =
-                                    self.active_popup.as_mut()
-                                {
-                                    form.error = None;
-                                    form.to_input()
-                                } else {
-                                    return;
-                                };
-                                match self.apply_settings_form_input(input) {
-                                    Ok(msg) => {
-                                        self.active_popup = None;
-                                        self.set_status(Some(msg));
-                                    }
-                                    Err(err) => {
-                                        if let Some(PopupState::SettingsForm(form)) =
-                                            self.active_popup.as_mut()
-                                        {
-                                            form.error = Some(err);
-                                        }
-                                    }
-                                }
-                            }
-                            SettingsShortcutAction::Cancel => {
-                                self.active_popup = None;
-                                self.set_status(Some("Settings update cancelled".into()));
-                            }
-                            SettingsShortcutAction::PreviousTheme => {
-                                if let Some(PopupState::SettingsForm(form)) =
-                                    self.active_popup.as_mut()
-                                {
-                                    form.error = None;
-                                    form.previous_theme();
-                                }
-                            }
-                            SettingsShortcutAction::NextTheme => {
-                                if let Some(PopupState::SettingsForm(form)) =
-                                    self.active_popup.as_mut()
-                                {
-                                    form.error = None;
-                                    form.next_theme();
-                                }
-                            }
-                            SettingsShortcutAction::DeleteTheme => {
-                                if let Some(PopupState::SettingsForm(form)) =
-                                    self.active_popup.as_mut()
-                                {
-                                    if let Some(index) = form.current_deletable_theme_index() {
-                                        pending_delete_theme = Some(index);
-                                    } else {
-                                        form.error =
-                                            Some("Select a custom theme to delete".into());
-                                    }
-                                }
-                            }
-                        },
+            DeferredAction::Settings(focus) => self.prompt_settings(focus)?,
+            DeferredAction::ExternalEditor(field) => {
+                self.edit_item_field_externally(terminal, field)?
+            }
+            DeferredAction::EditMenuFile => self.edit_menu_file_externally(terminal)?,
+        }
+        Ok(())
+    }
+
+    fn edit_item_field_externally<B>(
+        &mut self,
+        terminal: &mut Terminal<B>,
+        field: ItemField,
+    ) -> Result<()>
+    where
+        B: ratatui::backend::Backend + Write,
+    {
+        let Some(PopupState::ItemForm(form)) = self.active_popup.as_ref() else {
+            return Ok(());
+        };
+        let (initial, extension) = match field {
+            ItemField::Description => (form.info.clone(), "txt"),
+            _ => (form.command.clone(), "sh"),
+        };
+        let result =
+            with_terminal_suspension(terminal, || edit_in_external_editor(&initial, extension));
+        if let Some(PopupState::ItemForm(form)) = self.active_popup.as_mut() {
+            match result {
+                Ok(edited) => {
+                    match field {
+                        ItemField::Description => form.info = edited,
+                        _ => form.command = edited,
                     }
+                    form.selected_field = field;
+                    self.set_status(Some("Field updated from editor".into()));
                 }
-                if let Some(index) = pending_delete_theme {
-                    self.handle_saved_theme_deletion(index);
-                }
+                Err(err) => form.error = Some(format!("External editor failed: {err}")),
             }
         }
+        Ok(())
     }
 
-    fn reload_from_disk(&mut self) -> Result<()> {
-        let menu_file = MenuFile::load(&self.paths.menu_file)?;
-        self.theme = Theme::load(&self.paths.theme_file)?;
-        self.saved_themes = menu_file.saved_themes;
-        self.theme_key = AppState::resolve_theme_key(
-            menu_file.app_settings.theme_key.clone(),
+    fn open_item_form(&mut self, target: Option<(usize, usize)>) {
+        // Project categories are read-only, so new items cannot go there.
+        let mut default_categories: Vec<String> = self
+            .categories
+            .iter()
+            .filter(|c| c.source == ItemSource::User)
+            .map(|c| c.name.clone())
+            .collect();
+        if default_categories.is_empty() {
+            default_categories.push("General".to_string());
+        }
+
+        let (default_label, default_cmd, default_info, default_category, default_pause) =
+            if let Some((cat_idx, item_idx)) = target {
+                let category_name = self.categories[cat_idx].name.clone();
+                let item = &self.categories[cat_idx].items[item_idx];
+                (
+                    item.label.clone(),
+                    item.cmd.clone(),
+                    item.info.clone(),
+                    category_name,
+                    item.pause,
+                )
+            } else {
+                (
+                    String::new(),
+                    String::new(),
+                    String::new(),
+                    default_categories
+                        .get(0)
+                        .cloned()
+                        .unwrap_or_else(|| "General".into()),
+                    false,
+                )
+            };
+        let default_style = target
+            .map(|(cat_idx, item_idx)| self.categories[cat_idx].items[item_idx].style.clone())
+            .unwrap_or_default();
+
+        let fallback_category = default_categories
+            .get(0)
+            .cloned()
+            .unwrap_or_else(|| "General".into());
+        let initial_category = if default_category.trim().is_empty() {
+            fallback_category.clone()
+        } else {
+            default_category.clone()
+        };
+
+        let mut form = ItemFormState::new(
+            target,
+            default_label,
+            default_cmd,
+            default_info,
+            initial_category,
+            fallback_category,
+            default_pause,
+            default_style,
+            default_categories,
+        );
+        form.profiles = self.profiles.clone();
+        form.current_profile = self
+            .paths
+            .profile
+            .as_ref()
+            .and_then(|name| form.profiles.iter().position(|p| p == name));
+        form.profile_index = form.current_profile.unwrap_or(0);
+        if let Some((cat_idx, item_idx)) = target {
+            let source = self.categories[cat_idx].items[item_idx].source;
+            if source != ItemSource::User {
+                form.read_only = Some(display_path(&self.paths.source_path(source)));
+            }
+        }
+        if form.current_profile.is_none() {
+            // A --config file is not a profile; offer it as the first entry.
+            form.profiles.insert(0, "this file".into());
+            form.current_profile = Some(0);
+        }
+        self.active_popup = Some(PopupState::ItemForm(form));
+    }
+
+    fn prompt_settings(&mut self, focus: SettingsField) -> Result<()> {
+        let options = self.theme_options();
+        let is_custom = self.theme_key == CUSTOM_THEME_KEY;
+        let mut form = SettingsFormState::new(
+            self.title.clone(),
+            self.column_count,
+            self.theme_key.clone(),
+            options,
+            focus,
             &self.theme,
-            &self.saved_themes,
+            is_custom,
         );
-        self.categories = menu_file
+        form.status_template = self
+            .status_template
+            .clone()
+            .unwrap_or_else(|| DEFAULT_STATUS_TEMPLATE.to_string());
+        form.backups = list_backups(
+            &self.paths.backups_for(&self.paths.menu_file),
+            &self.paths.menu_file,
+        );
+        self.active_popup = Some(PopupState::SettingsForm(Box::new(form)));
+        Ok(())
+    }
+
+    fn run_bin_scan(&mut self) {
+        if let Err(err) = self.scan_bin_directory() {
+            self.set_status(Some(format!("Bin scan failed: {err}")));
+        } else {
+            self.set_status(Some("Bin directory scanned".into()));
+        }
+    }
+
+    fn existing_commands(&self) -> HashSet<String> {
+        self.categories
+            .iter()
+            .flat_map(|category| category.items.iter())
+            .map(|item| item.cmd.clone())
+            .collect()
+    }
+
+    fn open_task_import(&mut self) {
+        let dir = std::env::current_dir()
+            .map(|dir| display_path(&dir))
+            .unwrap_or_default();
+        self.active_popup = Some(PopupState::ImportList(ImportListState::for_directory(
+            "Import Tasks",
+            dir,
+        )));
+    }
+
+    fn open_alias_import(&mut self) {
+        // Labels are matched across the whole menu, so an alias does not
+        // turn up next to an item of the same name in another category.
+        let existing_labels: Vec<String> = self
             .categories
             .iter()
-            .map(|(name, cfg)| CategoryState::from_config(name, cfg))
+            .flat_map(|category| category.items.iter())
+            .filter(|item| item.source == ItemSource::User)
+            .map(|item| item.label.clone())
             .collect();
-        self.categories
-            .sort_by_key(|category| (category.column, category.name.clone()));
-        self.custom_colors = menu_file.custom_colors;
-        self.column_count = menu_file
-            .app_settings
-            .columns
-            .unwrap_or(self.column_count)
-            .clamp(1, MAX_COLUMNS);
-        if let Some(title) = menu_file.app_settings.title {
-            self.title = title;
+        let existing_commands = self.existing_commands();
+        match detect_shell_aliases() {
+            Ok(candidates) => {
+                let candidates: Vec<ImportCandidate> = candidates
+                    .into_iter()
+                    .filter(|candidate| {
+                        !existing_commands.contains(&candidate.cmd)
+                            && !existing_labels.contains(&candidate.label)
+                    })
+                    .collect();
+                if candidates.is_empty() {
+                    self.set_status(Some(
+                        "No new aliases or functions in ~/.bashrc, ~/.bash_aliases or ~/.zshrc"
+                            .into(),
+                    ));
+                    return;
+                }
+                self.active_popup = Some(PopupState::ImportList(ImportListState::with_candidates(
+                    "Import Aliases",
+                    candidates,
+                )));
+            }
+            Err(err) => self.set_status(Some(format!("Alias scan failed: {err}"))),
+        }
+    }
+
+    fn scan_task_directory(&mut self, dir: &str) {
+        let existing_commands = self.existing_commands();
+        let result = detect_tasks(&expand_home(dir.trim())).map(|candidates| {
+            candidates
+                .into_iter()
+                .filter(|candidate| !existing_commands.contains(&candidate.cmd))
+                .collect::<Vec<_>>()
+        });
+        let Some(PopupState::ImportList(list)) = self.active_popup.as_mut() else {
+            return;
+        };
+        match result {
+            Ok(candidates) if candidates.is_empty() => {
+                list.error = Some(format!("No new tasks found in {}", dir.trim()));
+            }
+            Ok(candidates) => list.show_candidates(candidates),
+            Err(err) => list.error = Some(format!("Scan failed: {err}")),
+        }
+    }
+
+    /// Add the checked candidates as items of their categories.
+    fn import_candidates(&mut self, candidates: Vec<ImportCandidate>) -> String {
+        let count = candidates.len();
+        for candidate in candidates {
+            let idx = self.ensure_category(&candidate.category);
+            self.categories[idx].expanded = true;
+            self.categories[idx].items.push(MenuItem {
+                label: candidate.label,
+                cmd: candidate.cmd,
+                info: candidate.info,
+                // Imported commands mostly print something worth reading.
+                pause: true,
+                style: ItemStyle::default(),
+                source: ItemSource::User,
+            });
+        }
+        self.rebuild_display();
+        match self.save_menu() {
+            Ok(()) => format!("Imported {count} item(s)"),
+            Err(err) => format!("Imported {count} item(s) but saving failed: {err}"),
         }
+    }
+
+    fn scan_bin_directory(&mut self) -> Result<()> {
+        let source = Path::new("./import");
+        if !source.exists() || !source.is_dir() {
+            return Ok(());
+        }
+        let dest = self.paths.data_dir.join("bin");
+        fs::create_dir_all(&dest)?;
+
+        let mut existing_commands = self.existing_commands();
+
+        let mut new_items = Vec::new();
+        for entry in fs::read_dir(source)? {
+            let entry = entry?;
+            let path = entry.path();
+            if !path.is_file() || !is_executable_file(&entry) {
+                continue;
+            }
+            let filename = match path.file_name().and_then(|n| n.to_str()) {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+            let dest_path = dest.join(&filename);
+            if dest_path.exists() {
+                continue;
+            }
+            fs::rename(&path, &dest_path)?;
+            #[cfg(unix)]
+            {
+                let mut perms = fs::metadata(&dest_path)?.permissions();
+                perms.set_mode(0o755);
+                fs::set_permissions(&dest_path, perms)?;
+            }
+            let cmd_path = if dest_path.to_string_lossy().contains(char::is_whitespace) {
+                format!("'{}'", dest_path.display())
+            } else {
+                display_path(&dest_path)
+            };
+            if existing_commands.contains(&cmd_path) {
+                continue;
+            }
+            existing_commands.insert(cmd_path.clone());
+            let label = filename_to_label(&filename);
+            new_items.push(MenuItem {
+                label,
+                cmd: cmd_path,
+                info: format!("Executable: {filename}"),
+                pause: false,
+                style: ItemStyle::default(),
+                source: ItemSource::User,
+            });
+        }
+
+        if new_items.is_empty() {
+            return Ok(());
+        }
+
+        let idx = self.ensure_category("Bin Executables");
+        self.categories[idx].expanded = true;
+        self.categories[idx].items.extend(new_items);
         self.rebuild_display();
+        let _ = self.save_menu();
         Ok(())
     }
 
-    fn toggle_category(&mut self) {
-        if let Some(DisplayEntry::Category { category_index }) =
-            self.display_entries.get(self.current_index)
+    fn apply_item_form_input(&mut self, input: ItemFormInput) -> Result<String, String> {
+        let label = input.label.trim();
+        if label.is_empty() {
+            return Err("Label is required".into());
+        }
+        let command = input.command.trim();
+        if command.is_empty() {
+            return Err("Command is required".into());
+        }
+        let info = input.info.trim().to_string();
+        let mut category_name = input.category.trim().to_string();
+        if category_name.is_empty() {
+            category_name = input.fallback_category.trim().to_string();
+        }
+        if category_name.is_empty() {
+            category_name = "General".into();
+        }
+
+        let style = ItemStyle {
+            text: parse_color_field(&input.text_color)?,
+            background: parse_color_field(&input.background)?,
+            icon: Some(input.icon.trim().to_string()).filter(|icon| !icon.is_empty()),
+            bold: input.bold,
+            dim: input.dim,
+            italic: input.italic,
+        };
+        if style
+            .icon
+            .as_deref()
+            .is_some_and(|icon| UnicodeWidthStr::width(icon) > ITEM_ICON_WIDTH)
         {
-            if let Some(category) = self.categories.get_mut(*category_index) {
-                category.expanded = !category.expanded;
+            return Err(format!(
+                "Icon must fit in {ITEM_ICON_WIDTH} columns (one symbol or emoji)"
+            ));
+        }
+
+        let new_item = MenuItem {
+            label: label.to_string(),
+            cmd: command.to_string(),
+            info,
+            pause: input.pause,
+            style,
+            source: ItemSource::User,
+        };
+
+        if let Some((category_index, item_index)) = input.target {
+            if self
+                .categories
+                .get(category_index)
+                .and_then(|category| category.items.get(item_index))
+                .is_some_and(|item| item.source != ItemSource::User)
+            {
+                return Err("System and project items are read-only".into());
+            }
+        }
+
+        if let Some(profile) = input.profile.as_deref() {
+            if let Some((category_index, item_index)) = input.target {
+                if self
+                    .categories
+                    .get(category_index)
+                    .is_none_or(|category| item_index >= category.items.len())
+                {
+                    return Err("Item no longer exists".into());
+                }
+                if self.menu_save_blocked {
+                    return Err("This menu could not be loaded, so the item cannot be moved".into());
+                }
+            }
+            self.add_item_to_profile(profile, &category_name, &new_item)
+                .map_err(|err| format!("Could not update profile '{profile}': {err}"))?;
+            if let Some((category_index, item_index)) = input.target {
+                let previous = self.categories.clone();
+                self.categories[category_index].items.remove(item_index);
+                if self.categories[category_index].items.is_empty() {
+                    self.categories.remove(category_index);
+                }
+                if let Err(err) = self.save_menu() {
+                    // The copy in the other profile stays; keep showing the
+                    // original too, since it is still in this file.
+                    self.categories = previous;
+                    self.rebuild_display();
+                    return Ok(format!(
+                        "Item copied to profile {profile}, but it could not be removed here: {err}"
+                    ));
+                }
+                self.rebuild_display();
+                return Ok(format!("Item moved to profile {profile}"));
+            }
+            return Ok(format!("Item added to profile {profile}"));
+        }
+
+        match input.target {
+            Some((category_index, item_index)) => {
+                if category_index >= self.categories.len() {
+                    return Err("Item no longer exists".into());
+                }
+                if item_index >= self.categories[category_index].items.len() {
+                    return Err("Item no longer exists".into());
+                }
+                let same_category = self.categories[category_index].name == category_name;
+                if same_category {
+                    self.categories[category_index].items[item_index] = new_item;
+                } else {
+                    self.categories[category_index].items.remove(item_index);
+                    if self.categories[category_index].items.is_empty() {
+                        self.categories.remove(category_index);
+                    }
+                    let dest_idx = self.ensure_category(&category_name);
+                    self.categories[dest_idx].expanded = true;
+                    self.categories[dest_idx].items.push(new_item);
+                }
+                self.rebuild_display();
+                let _ = self.save_menu();
+                Ok("Item updated".into())
+            }
+            None => {
+                let idx = self.ensure_category(&category_name);
+                self.categories[idx].expanded = true;
+                self.categories[idx].items.push(new_item);
                 self.rebuild_display();
                 let _ = self.save_menu();
+                Ok("Item added".into())
             }
         }
     }
 
-    fn move_selection_up(&mut self) {
-        if self.display_entries.is_empty() {
-            return;
+    fn apply_category_form_input(&mut self, input: CategoryFormInput) -> Result<String, String> {
+        if input.category_index >= self.categories.len() {
+            return Err("Category no longer exists".into());
         }
-        if self.current_index == 0 {
-            self.current_index = self.display_entries.len().saturating_sub(1);
+        if self.categories[input.category_index].source == ItemSource::Project {
+            return Err("Project categories are read-only; edit .menu-maker.json instead".into());
+        }
+        let current_name = self.categories[input.category_index].name.clone();
+        let new_name = if input.name.trim().is_empty() {
+            current_name.clone()
         } else {
-            self.current_index -= 1;
+            input.name.trim().to_string()
+        };
+        if new_name != current_name
+            && self
+                .categories
+                .iter()
+                .enumerate()
+                .any(|(idx, cat)| idx != input.category_index && cat.name == new_name)
+        {
+            return Err("Category name already exists".into());
         }
-    }
-
-    fn move_selection_down(&mut self) {
-        if self.display_entries.is_empty() {
-            return;
+        if new_name != current_name
+            && self.categories[input.category_index]
+                .items
+                .iter()
+                .any(|item| item.source != ItemSource::User)
+        {
+            return Err("Categories with system items cannot be renamed".into());
         }
-        self.current_index = (self.current_index + 1) % self.display_entries.len();
+        let column_value = if input.column_value.trim().is_empty() {
+            self.categories[input.category_index].column
+        } else {
+            input
+                .column_value
+                .trim()
+                .parse::<u16>()
+                .map_err(|_| "Column must be a number".to_string())?
+        }
+        .clamp(1, MAX_COLUMNS);
+
+        let background = parse_color_field(&input.background)?;
+        let text = parse_color_field(&input.text_color)?;
+
+        let category = &mut self.categories[input.category_index];
+        category.name = new_name;
+        category.user_layer = true;
+        category.column = column_value;
+        category.colors = match (background, text) {
+            (None, None) => None,
+            (bg, txt) => Some(ColorConfig {
+                background: bg,
+                text: txt,
+            }),
+        };
+
+        self.rebuild_display();
+        let _ = self.save_menu();
+        Ok("Category updated".into())
     }
 
-    fn activate_current_entry(&mut self) {
-        if let Some(entry) = self.display_entries.get(self.current_index) {
-            match entry {
-                DisplayEntry::Category { .. } => {
-                    self.toggle_category();
+    fn process_category_submission(
+        &mut self,
+        payload: CategorySubmitPayload,
+    ) -> Result<String, String> {
+        let mut messages: Vec<String> = Vec::new();
+        if let Some(preset_input) = payload.new_preset {
+            match self.add_custom_category_preset(preset_input) {
+                Ok(msg) => {
+                    self.handle_category_preset_add_result(Ok(msg.clone()));
+                    messages.push(msg);
+                }
+                Err(err_msg) => {
+                    self.handle_category_preset_add_result(Err(err_msg.clone()));
+                    return Err(err_msg);
                 }
-                DisplayEntry::Item { .. } => self.prepare_command(),
             }
         }
-    }
-
-    fn prepare_command(&mut self) {
-        if let Some(DisplayEntry::Item {
-            category_index,
-            item_index,
-        }) = self.display_entries.get(self.current_index)
-        {
-            let item = &self.categories[*category_index].items[*item_index];
-            if item.cmd.trim().is_empty() {
-                return;
+        match self.apply_category_form_input(payload.form) {
+            Ok(msg) => {
+                messages.push(msg.clone());
+                Ok(messages.join(" | "))
             }
-            self.status_message = Some(format!("Running {}", item.label));
-            self.pending_command = Some(PendingCommand {
-                command: item.cmd.clone(),
-                pause: item.pause,
-            });
+            Err(err_msg) => Err(err_msg),
         }
     }
 
-    fn save_menu(&self) -> Result<()> {
-        let mut categories_map = BTreeMap::new();
-        for category in &self.categories {
-            categories_map.insert(category.name.clone(), category.to_config());
+    fn add_custom_category_preset(&mut self, input: CustomPresetInput) -> Result<String, String> {
+        let mut name = input.name.trim().to_string();
+        if name.is_empty() {
+            name = format!("Custom Theme {}", self.custom_colors.len() + 1);
         }
-        let menu_file = MenuFile {
-            categories: categories_map,
-            app_settings: AppSettings {
-                title: Some(self.title.clone()),
-                columns: Some(self.column_count),
-                theme_key: Some(self.theme_key.clone()),
-            },
-            custom_colors: self.custom_colors.clone(),
-            saved_themes: self.saved_themes.clone(),
-        };
-        menu_file.save(&self.paths.menu_file)
+        self.custom_colors.push(NamedColorPair {
+            name: Some(name.clone()),
+            background: Some(input.background.clone()),
+            text: Some(input.text.clone()),
+        });
+        let _ = self.save_menu();
+        Ok(format!("Theme '{name}' added"))
     }
 
-    fn take_pending_command(&mut self) -> Option<PendingCommand> {
-        self.pending_command.take()
+    fn delete_custom_category_preset(&mut self, index: usize) -> Result<String, String> {
+        if index >= self.custom_colors.len() {
+            return Err("Custom theme not found".into());
+        }
+        let removed = self.custom_colors.remove(index);
+        let name = removed
+            .name
+            .clone()
+            .unwrap_or_else(|| format!("Custom Theme {}", index + 1));
+        let _ = self.save_menu();
+        Ok(format!("Theme '{name}' deleted"))
     }
 
-    fn take_pending_action(&mut self) -> Option<DeferredAction> {
-        self.pending_action.take()
+    fn handle_category_preset_add_result(
+        &mut self,
+        result: Result<String, String>,
+    ) {
+        match result {
+            Ok(msg) => {
+                let presets = self.available_color_presets();
+                if let Some(PopupState::CategoryForm(form)) = self.active_popup.as_mut() {
+                    form.refresh_presets(presets);
+                    if !form.color_presets.is_empty() {
+                        form.focus_palette_index(form.color_presets.len() - 1);
+                    }
+                }
+                self.set_status(Some(msg));
+            }
+            Err(err_msg) => {
+                if let Some(PopupState::CategoryForm(form)) = self.active_popup.as_mut() {
+                    form.error = Some(err_msg);
+                }
+            }
+        }
     }
 
-    fn set_status(&mut self, message: Option<String>) {
-        self.status_message = message;
+    fn handle_category_preset_delete_result(
+        &mut self,
+        result: Result<String, String>,
+    ) {
+        match result {
+            Ok(msg) => {
+                let presets = self.available_color_presets();
+                if let Some(PopupState::CategoryForm(form)) = self.active_popup.as_mut() {
+                    let target_index = form
+                        .palette_index
+                        .min(presets.len().saturating_sub(1));
+                    form.refresh_presets(presets);
+                    if !form.color_presets.is_empty() {
+                        form.focus_palette_index(target_index);
+                    } else {
+                        form.selected_field = CategoryField::Name;
+                    }
+                }
+                self.set_status(Some(msg));
+            }
+            Err(err_msg) => {
+                if let Some(PopupState::CategoryForm(form)) = self.active_popup.as_mut() {
+                    form.error = Some(err_msg);
+                }
+            }
+        }
     }
 
-    fn status_text(&self) -> String {
-        let total = self.display_entries.len();
-        let current = if total == 0 {
-            0
+    fn apply_settings_form_input(&mut self, input: SettingsFormInput) -> Result<String, String> {
+        self.end_theme_preview();
+        let mut title = input.title.trim().to_string();
+        if title.is_empty() {
+            title = self.title.clone();
+        }
+        let columns = if input.columns.trim().is_empty() {
+            self.column_count
+        } else {
+            input
+                .columns
+                .trim()
+                .parse::<u16>()
+                .map_err(|_| "Columns must be a number".to_string())?
+        }
+        .clamp(1, MAX_COLUMNS);
+        let mut theme_key = input.theme_key.trim().to_string();
+        if theme_key.is_empty() {
+            theme_key = self.theme_key.clone();
+        }
+        let custom_primary = input.custom_primary.trim();
+        let custom_accent = input.custom_accent.trim();
+        let custom_background = input.custom_background.trim();
+        let custom_surface = input.custom_surface.trim();
+        let custom_text = input.custom_text.trim();
+        let custom_highlight = input.custom_highlight.trim();
+        let custom_footer_background = input.custom_footer_background.trim();
+        let custom_footer_key = input.custom_footer_key.trim();
+        let custom_footer_label = input.custom_footer_label.trim();
+        let custom_status_bar = input.custom_status_bar.trim();
+        let custom_popup_border = input.custom_popup_border.trim();
+        let custom_theme_name = input.custom_theme_name.trim();
+        let theme_options_snapshot = self.theme_options();
+        let selected_option = theme_options_snapshot
+            .iter()
+            .find(|opt| opt.key == theme_key);
+        let has_any_color_input = [
+            custom_primary,
+            custom_accent,
+            custom_highlight,
+            custom_background,
+            custom_surface,
+            custom_text,
+            custom_footer_background,
+            custom_footer_key,
+            custom_footer_label,
+            custom_status_bar,
+            custom_popup_border,
+        ]
+        .iter()
+        .any(|value| !value.is_empty());
+        let colors_match_selected = selected_option
+            .map(|option| {
+                hex_strings_equal(custom_primary, &option.primary_hex)
+                    && hex_strings_equal(custom_accent, &option.accent_hex)
+                    && hex_strings_equal(custom_highlight, &option.highlight_hex)
+                    && hex_strings_equal(custom_background, &option.background_hex)
+                    && hex_strings_equal(custom_surface, &option.surface_hex)
+                    && hex_strings_equal(custom_text, &option.text_hex)
+                    && hex_strings_equal(custom_footer_background, &option.footer_background_hex)
+                    && hex_strings_equal(custom_footer_key, &option.footer_key_hex)
+                    && hex_strings_equal(custom_footer_label, &option.footer_label_hex)
+                    && hex_strings_equal(custom_status_bar, &option.status_bar_hex)
+                    && hex_strings_equal(custom_popup_border, &option.popup_border_hex)
+            })
+            .unwrap_or(false);
+        let name_matches_selected = if custom_theme_name.is_empty() {
+            true
+        } else if let Some(option) = selected_option {
+            if parse_saved_theme_key(&option.key).is_some() {
+                custom_theme_name.eq_ignore_ascii_case(&option.label)
+            } else if option.key == CUSTOM_THEME_KEY {
+                custom_theme_name.eq_ignore_ascii_case(&self.theme.name)
+            } else {
+                false
+            }
         } else {
-            self.current_index + 1
+            false
+        };
+        let use_custom_colors = if has_any_color_input {
+            selected_option
+                .map(|_| !colors_match_selected || !name_matches_selected)
+                .unwrap_or(true)
+        } else if custom_theme_name.is_empty() {
+            false
+        } else {
+            !name_matches_selected || selected_option.is_none()
         };
-        let mut text = format!("Item {}/{} | Theme: {}", current, total, self.theme.name);
-        if let Some(msg) = &self.status_message {
-            text.push_str(" | ");
-            text.push_str(msg);
-        }
-        text
-    }
 
-    fn available_color_presets(&self) -> Vec<ColorPreset> {
-        let mut presets: Vec<ColorPreset> = DEFAULT_CATEGORY_COLOR_PRESETS
-            .iter()
-            .map(|(name, bg, text)| ColorPreset::new(*name, *bg, *text))
-            .collect();
-        for (idx, pair) in self.custom_colors.iter().enumerate() {
-            if let (Some(bg), Some(text)) = (pair.background.as_deref(), pair.text.as_deref()) {
-                let name = pair
-                    .name
-                    .clone()
-                    .unwrap_or_else(|| format!("Custom Theme {}", idx + 1));
-                presets.push(ColorPreset::from_custom(name, bg, text, idx));
-            }
-        }
-        if presets.is_empty() {
-            presets.push(ColorPreset::new("Default", "#034e68", "#caf0f8"));
-        }
-        presets
-    }
+        let status_template = Some(input.status_template.trim())
+            .filter(|template| !template.is_empty() && *template != DEFAULT_STATUS_TEMPLATE)
+            .map(str::to_string);
 
-    fn theme_options(&self) -> Vec<ThemeOption> {
-        let mut options: Vec<ThemeOption> = THEME_PRESETS
-            .iter()
-            .map(|(key, def)| ThemeOption::from_definition(key, def))
-            .collect();
-        for (idx, saved) in self.saved_themes.iter().enumerate() {
-            options.push(ThemeOption {
-                key: saved_theme_key(idx),
-                label: saved.name.clone(),
-                primary_hex: saved.primary.clone(),
-                accent_hex: saved.accent.clone(),
-                highlight_hex: saved
-                    .highlight
-                    .clone()
-                    .unwrap_or_else(|| saved.accent.clone()),
-                background_hex: saved.background.clone(),
-                surface_hex: saved.surface.clone(),
-                text_hex: saved.text.clone(),
-            });
+        let mut changed = false;
+        if title != self.title {
+            self.title = title;
+            changed = true;
         }
-        if self.theme_key == CUSTOM_THEME_KEY {
-            options.push(ThemeOption {
-                key: CUSTOM_THEME_KEY.to_string(),
-                label: "Custom Theme".to_string(),
-                primary_hex: self.theme.primary_hex.clone(),
-                accent_hex: self.theme.accent_hex.clone(),
-                highlight_hex: self.theme.highlight_hex.clone(),
-                background_hex: self.theme.background_hex.clone(),
-                surface_hex: self.theme.surface_hex.clone(),
-                text_hex: self.theme.text_hex.clone(),
-            });
+        if status_template != self.status_template {
+            self.status_template = status_template;
+            changed = true;
         }
-        options
-    }
-
-    fn theme_from_saved_index(&self, index: usize) -> Option<Theme> {
-        self.saved_themes.get(index).map(|saved| {
-            let highlight = saved
-                .highlight
-                .as_deref()
-                .unwrap_or_else(|| saved.accent.as_str());
-            Theme::from_hexes(
-                saved.name.clone(),
-                &saved.primary,
-                &saved.accent,
-                highlight,
-                &saved.background,
-                &saved.surface,
-                &saved.text,
+        if columns != self.column_count {
+            self.column_count = columns;
+            self.rebuild_display();
+            changed = true;
+        }
+        if use_custom_colors {
+            let primary = require_color_field(custom_primary, "Primary")?;
+            let accent = require_color_field(custom_accent, "Accent")?;
+            let highlight = require_color_field(custom_highlight, "Highlight")?;
+            let background = require_color_field(custom_background, "Background")?;
+            let surface = require_color_field(custom_surface, "Surface")?;
+            let text_color = require_color_field(custom_text, "Text")?;
+            let chrome = ThemeChrome {
+                footer_background: parse_color_field(custom_footer_background)?,
+                footer_key: parse_color_field(custom_footer_key)?,
+                footer_label: parse_color_field(custom_footer_label)?,
+                status_bar: parse_color_field(custom_status_bar)?,
+                popup_border: parse_color_field(custom_popup_border)?,
+            };
+            let theme_name = if custom_theme_name.is_empty() {
+                "Custom Theme".to_string()
+            } else {
+                custom_theme_name.to_string()
+            };
+            let theme = Theme::from_hexes(
+                theme_name.clone(),
+                &primary,
+                &accent,
+                &highlight,
+                &background,
+                &surface,
+                &text_color,
             )
-        })
-    }
-
-    fn footer_line(&self) -> Line<'static> {
-        self.footer_line_data().line
-    }
-
-    fn footer_line_data(&self) -> FooterLineData {
-        let base_bg =
-            color_from_hex("#76B3C5").unwrap_or_else(|| self.theme.highlight);
-        let shortcut_fg =
-            color_from_hex("#FDA009").unwrap_or_else(|| self.theme.accent);
-        let label_fg =
-            color_from_hex("#2E3544").unwrap_or_else(|| self.theme.surface);
-        let shortcut_style = Style::default()
-            .fg(shortcut_fg)
-            .bg(base_bg)
-            .add_modifier(Modifier::BOLD);
-        let label_style = Style::default().fg(label_fg).bg(base_bg);
-        let mut spans: Vec<Span<'static>> = Vec::new();
-        let mut segments = Vec::new();
-        let mut cursor: u16 = 0;
-        for (index, shortcut) in FOOTER_SHORTCUTS.iter().enumerate() {
-            if index > 0 {
-                spans.push(Span::styled(" | ", label_style));
-                cursor = cursor.saturating_add(3);
+            .with_chrome(&chrome);
+            let mut new_theme_key = CUSTOM_THEME_KEY.to_string();
+            if !custom_theme_name.is_empty() {
+                let saved = SavedTheme {
+                    name: theme_name.clone(),
+                    primary,
+                    accent,
+                    highlight: Some(highlight.clone()),
+                    background,
+                    surface,
+                    text: text_color,
+                    chrome,
+                };
+                let index = self.upsert_saved_theme(saved);
+                new_theme_key = saved_theme_key(index);
             }
-            let entry_start = cursor;
-            spans.push(Span::styled(shortcut.key, shortcut_style));
-            spans.push(Span::styled(shortcut.label, label_style));
-            let key_len = shortcut.key.chars().count() as u16;
-            let label_len = shortcut.label.chars().count() as u16;
-            let entry_end = entry_start
-                .saturating_add(key_len)
-                .saturating_add(label_len);
-            segments.push(FooterSegment {
-                start: entry_start,
-                end: entry_end,
-                action: shortcut.action,
-            });
-            cursor = entry_end;
-        }
-        FooterLineData {
-            line: Line::from(spans),
-            segments,
-            total_width: cursor,
-        }
-    }
-
-    fn execute_footer_action(&mut self, action: FooterAction) {
-        match action {
-            FooterAction::Quit => self.should_quit = true,
-            FooterAction::Edit => self.queue_edit_current(),
-            FooterAction::Execute => self.prepare_command(),
-            FooterAction::NewItem => self.queue_new_item(),
-            FooterAction::Delete => self.delete_selected_item(),
-            FooterAction::Settings => self.queue_settings(),
-            FooterAction::ScanBin => self.run_bin_scan(),
-        }
-    }
-
-    fn queue_new_item(&mut self) {
-        self.pending_action = Some(DeferredAction::NewItem);
-    }
-
-    fn queue_edit_current(&mut self) {
-        if let Some(entry) = self.display_entries.get(self.current_index) {
-            match entry {
-                DisplayEntry::Item {
-                    category_index,
-                    item_index,
-                } => {
-                    self.pending_action = Some(DeferredAction::EditItem {
-                        category_index: *category_index,
-                        item_index: *item_index,
-                    });
+            self.theme = theme.clone();
+            self.theme_key = new_theme_key;
+            let _ = self.theme.save(&self.paths.theme_file);
+            changed = true;
+        } else {
+            if let Some(index) = parse_saved_theme_key(&theme_key) {
+                if parse_saved_theme_key(&self.theme_key) != Some(index) {
+                    if let Some(theme) = self.theme_from_saved_index(index) {
+                        self.theme = theme.clone();
+                        self.theme_key = saved_theme_key(index);
+                        let _ = self.theme.save(&self.paths.theme_file);
+                        changed = true;
+                    } else {
+                        return Err("Saved theme not found".into());
+                    }
                 }
-                DisplayEntry::Category { category_index } => {
-                    self.pending_action = Some(DeferredAction::EditCategory {
-                        category_index: *category_index,
-                    });
+            } else if let Some(stem) = parse_file_theme_key(&theme_key) {
+                if theme_key != self.theme_key {
+                    let entry = self
+                        .theme_files
+                        .iter()
+                        .find(|entry| entry.stem == stem)
+                        .ok_or_else(|| "Theme file not found".to_string())?;
+                    self.theme = entry.theme.to_theme();
+                    self.theme_key = theme_key.clone();
+                    let _ = self.theme.save(&self.paths.theme_file);
+                    changed = true;
+                }
+            } else if theme_key == CUSTOM_THEME_KEY {
+                if self.theme_key != CUSTOM_THEME_KEY {
+                    return Err("Enter custom colors to create a custom theme".into());
                 }
+            } else if theme_key != self.theme_key {
+                let theme = Theme::from_name(&theme_key)
+                    .ok_or_else(|| "Unknown theme selected".to_string())?;
+                self.theme = theme.clone();
+                self.theme_key = theme_key.clone();
+                let _ = self.theme.save(&self.paths.theme_file);
+                changed = true;
             }
         }
-    }
-
-    fn queue_settings(&mut self) {
-        self.queue_settings_with_focus(SettingsField::Title);
-    }
-
-    fn queue_settings_with_focus(&mut self, focus: SettingsField) {
-        self.pending_action = Some(DeferredAction::Settings(focus));
-    }
-
-    fn show_info_popup(&mut self) {
-        if let Some((category_index, item_index)) = self.selected_item_indices() {
-            let item = &self.categories[category_index].items[item_index];
-            self.active_popup = Some(PopupState::Info(InfoPopup {
-                label: item.label.clone(),
-                command: item.cmd.clone(),
-                category: self.categories[category_index].name.clone(),
-                description: item.info.clone(),
-            }));
-        }
-    }
 
-    fn selected_item_indices(&self) -> Option<(usize, usize)> {
-        if let Some(DisplayEntry::Item {
-            category_index,
-            item_index,
-        }) = self.display_entries.get(self.current_index)
-        {
-            Some((*category_index, *item_index))
+        let _ = self.save_menu();
+        if changed {
+            Ok("Settings updated".into())
         } else {
-            None
+            Ok("No settings changed".into())
         }
     }
+}
 
-    fn delete_selected_item(&mut self) {
-        if let Some((category_index, item_index)) = self.selected_item_indices() {
-            self.categories[category_index].items.remove(item_index);
-            if self.categories[category_index].items.is_empty() {
-                self.categories.remove(category_index);
-            }
-            if self.current_index >= self.display_entries.len().saturating_sub(1) {
-                self.current_index = self.current_index.saturating_sub(1);
-            }
-            let _ = self.save_menu();
-            self.sort_categories();
-            self.rebuild_display();
-            self.set_status(Some("Item deleted".into()));
-        }
-    }
+#[derive(Clone)]
+struct PendingCommand {
+    command: String,
+    pause: bool,
+    /// Working directory; set for project commands.
+    dir: Option<PathBuf>,
+}
 
-    fn sort_categories(&mut self) {
-        self.categories
-            .sort_by_key(|category| (category.column, category.name.clone()));
-    }
+#[derive(Clone)]
+struct MenuItem {
+    label: String,
+    cmd: String,
+    info: String,
+    pause: bool,
+    style: ItemStyle,
+    source: ItemSource,
+}
 
-    fn ensure_category(&mut self, name: &str) -> usize {
-        if let Some(idx) = self.categories.iter().position(|c| c.name == name) {
-            return idx;
+impl MenuItem {
+    fn from_config(category: &str, cfg: &MenuItemConfig) -> Self {
+        MenuItem {
+            label: cfg.label.clone(),
+            cmd: cfg.cmd.clone(),
+            info: cfg
+                .info
+                .clone()
+                .unwrap_or_else(|| format!("Item in {category}")),
+            pause: cfg.pause.unwrap_or(false),
+            style: cfg.style.clone(),
+            source: ItemSource::User,
         }
-        self.categories.push(CategoryState {
-            name: name.to_string(),
-            expanded: true,
-            column: 1,
-            colors: None,
-            items: Vec::new(),
-        });
-        let idx = self.categories.len() - 1;
-        idx
     }
+}
 
-    fn upsert_saved_theme(&mut self, saved: SavedTheme) -> usize {
-        if let Some(idx) = self
-            .saved_themes
+#[derive(Clone)]
+struct CategoryState {
+    name: String,
+    expanded: bool,
+    column: u16,
+    colors: Option<ColorConfig>,
+    items: Vec<MenuItem>,
+    /// `Project` for categories of the project menu, `User` otherwise.
+    source: ItemSource,
+    /// Set when the user's menu file has this category, so its settings are
+    /// saved even when every item comes from another layer.
+    user_layer: bool,
+}
+
+impl CategoryState {
+    fn from_config(name: &str, cfg: &CategoryConfig) -> Self {
+        let column = cfg.column.unwrap_or(1).clamp(1, MAX_COLUMNS);
+        let items = cfg
+            .items
             .iter()
-            .position(|theme| theme.name == saved.name)
-        {
-            self.saved_themes[idx] = saved;
-            idx
-        } else {
-            self.saved_themes.push(saved);
-            self.saved_themes.len() - 1
+            .map(|item| MenuItem::from_config(name, item))
+            .collect();
+        CategoryState {
+            name: name.to_string(),
+            expanded: cfg.expanded,
+            column,
+            colors: cfg.colors.clone(),
+            items,
+            source: ItemSource::User,
+            user_layer: true,
         }
     }
 
-    fn delete_saved_theme(&mut self, index: usize) {
-        if index >= self.saved_themes.len() {
-            return;
-        }
-        self.saved_themes.remove(index);
-        if let Some(old_index) = parse_saved_theme_key(&self.theme_key) {
-            if old_index == index {
-                if let Some(fallback) = Theme::from_name("nord") {
-                    self.theme = fallback.clone();
-                    self.theme_key = "nord".into();
-                    let _ = self.theme.save(&self.paths.theme_file);
-                }
-            } else if old_index > index {
-                self.theme_key = saved_theme_key(old_index - 1);
-            }
-        }
+    /// Project categories lead their column, the rest sort by name.
+    fn sort_key(&self) -> (bool, u16, String) {
+        (
+            self.source != ItemSource::Project,
+            self.column,
+            self.name.clone(),
+        )
     }
 
-    fn handle_saved_theme_deletion(&mut self, index: usize) {
-        self.delete_saved_theme(index);
-        let new_options = self.theme_options();
-        let new_index = new_options
+    fn has_user_items(&self) -> bool {
+        self.items
             .iter()
-            .position(|opt| opt.key == self.theme_key)
-            .unwrap_or(0);
-        if let Some(PopupState::SettingsForm(form)) = self.active_popup.as_mut() {
-            form.theme_options = new_options;
-            form.theme_index = new_index;
-            form.selected_field = SettingsField::Theme;
-            form.populate_custom_fields_from_selection();
-        }
-        self.set_status(Some("Custom theme deleted".into()));
+            .any(|item| item.source == ItemSource::User)
     }
 
-    fn execute_deferred_action<B>(
-        &mut self,
-        _terminal: &mut Terminal<B>,
-        action: DeferredAction,
-    ) -> Result<()>
-    where
-        B: ratatui::backend::Backend + Write,
-    {
-        match action {
-            DeferredAction::NewItem => self.open_item_form(None),
-            DeferredAction::EditItem {
-                category_index,
-                item_index,
-            } => self.open_item_form(Some((category_index, item_index))),
-            DeferredAction::EditCategory { category_index } => {
-                if let Some(category) = self.categories.get(category_index) {
-                    let presets = self.available_color_presets();
-                    self.active_popup = Some(PopupState::CategoryForm(CategoryFormState::new(
-                        category_index,
-                        category,
-                        presets,
-                    )));
-                }
-            }
-            DeferredAction::Settings(focus) => self.prompt_settings(focus)?,
+    fn to_config(&self) -> CategoryConfig {
+        CategoryConfig {
+            expanded: self.expanded,
+            column: Some(self.column),
+            items: self
+                .items
+                .iter()
+                .filter(|item| item.source == ItemSource::User)
+                .map(|item| MenuItemConfig {
+                    label: item.label.clone(),
+                    cmd: item.cmd.clone(),
+                    info: Some(item.info.clone()),
+                    pause: Some(item.pause),
+                    style: item.style.clone(),
+                })
+                .collect(),
+            colors: self.colors.clone(),
         }
-        Ok(())
     }
+}
 
-    fn open_item_form(&mut self, target: Option<(usize, usize)>) {
-        let default_categories = if self.categories.is_empty() {
-            vec!["General".to_string()]
-        } else {
-            self.categories.iter().map(|c| c.name.clone()).collect()
-        };
+enum DisplayEntry {
+    Category {
+        category_index: usize,
+    },
+    Item {
+        category_index: usize,
+        item_index: usize,
+    },
+}
 
-        let (default_label, default_cmd, default_info, default_category, default_pause) =
-            if let Some((cat_idx, item_idx)) = target {
-                let category_name = self.categories[cat_idx].name.clone();
-                let item = &self.categories[cat_idx].items[item_idx];
-                (
-                    item.label.clone(),
-                    item.cmd.clone(),
-                    item.info.clone(),
-                    category_name,
-                    item.pause,
-                )
-            } else {
-                (
-                    String::new(),
-                    String::new(),
-                    String::new(),
-                    default_categories
-                        .get(0)
-                        .cloned()
-                        .unwrap_or_else(|| "General".into()),
-                    false,
-                )
-            };
+#[derive(Clone)]
+struct InfoPopup {
+    label: String,
+    command: String,
+    category: String,
+    description: String,
+    source: String,
+}
 
-        let fallback_category = default_categories
-            .get(0)
-            .cloned()
-            .unwrap_or_else(|| "General".into());
-        let initial_category = if default_category.trim().is_empty() {
-            fallback_category.clone()
-        } else {
-            default_category.clone()
-        };
+struct ItemFormState {
+    target: Option<(usize, usize)>,
+    label: String,
+    command: String,
+    info: String,
+    category: String,
+    fallback_category: String,
+    pause: bool,
+    icon: String,
+    text_color: String,
+    background: String,
+    bold: bool,
+    dim: bool,
+    italic: bool,
+    preset_index: usize,
+    available_categories: Vec<String>,
+    profiles: Vec<String>,
+    profile_index: usize,
+    current_profile: Option<usize>,
+    /// Where a system or project item comes from; such items cannot be saved.
+    read_only: Option<String>,
+    selected_field: ItemField,
+    error: Option<String>,
+    mode_label: &'static str,
+}
 
-        let form = ItemFormState::new(
-            target,
-            default_label,
-            default_cmd,
-            default_info,
-            initial_category,
-            fallback_category,
-            default_pause,
-            default_categories,
-        );
-        self.active_popup = Some(PopupState::ItemForm(form));
-    }
+#[derive(Clone)]
+struct ItemFormInput {
+    target: Option<(usize, usize)>,
+    label: String,
+    command: String,
+    info: String,
+    category: String,
+    fallback_category: String,
+    pause: bool,
+    icon: String,
+    text_color: String,
+    background: String,
+    bold: bool,
+    dim: bool,
+    italic: bool,
+    /// Another profile the item should be moved to.
+    profile: Option<String>,
+}
 
-    fn prompt_settings(&mut self, focus: SettingsField) -> Result<()> {
-        let options = self.theme_options();
-        let is_custom = self.theme_key == CUSTOM_THEME_KEY;
-        self.active_popup = Some(PopupState::SettingsForm(SettingsFormState::new(
-            self.title.clone(),
-            self.column_count,
-            self.theme_key.clone(),
-            options,
-            focus,
-            &self.theme,
-            is_custom,
-        )));
-        Ok(())
-    }
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ItemField {
+    Label,
+    Command,
+    Description,
+    Category,
+    Profile,
+    Pause,
+    Icon,
+    TextColor,
+    Background,
+    Bold,
+    Dim,
+    Italic,
+    StylePreset,
+}
 
-    fn run_bin_scan(&mut self) {
-        if let Err(err) = self.scan_bin_directory() {
-            self.set_status(Some(format!("Bin scan failed: {err}")));
-        } else {
-            self.set_status(Some("Bin directory scanned".into()));
+enum PopupState {
+    Info(InfoPopup),
+    Message(String),
+    ItemForm(ItemFormState),
+    CategoryForm(CategoryFormState),
+    SettingsForm(Box<SettingsFormState>),
+    ImportList(ImportListState),
+}
+
+/// A command an importer found, shown as a checklist entry before it
+/// becomes a menu item.
+#[derive(Clone)]
+struct ImportCandidate {
+    category: String,
+    label: String,
+    cmd: String,
+    info: String,
+    checked: bool,
+}
+
+/// Checklist preview of commands to import. Task imports start on a
+/// directory field; submitting it scans the directory and shows the list.
+struct ImportListState {
+    title: &'static str,
+    dir: Option<String>,
+    editing_dir: bool,
+    candidates: Vec<ImportCandidate>,
+    selected: usize,
+    error: Option<String>,
+}
+
+enum ImportListKeyResult {
+    Continue,
+    Cancel,
+    Scan(String),
+    Import(Vec<ImportCandidate>),
+}
+
+impl ImportListState {
+    fn for_directory(title: &'static str, dir: String) -> Self {
+        ImportListState {
+            title,
+            dir: Some(dir),
+            editing_dir: true,
+            candidates: Vec::new(),
+            selected: 0,
+            error: None,
         }
     }
 
-    fn scan_bin_directory(&mut self) -> Result<()> {
-        let source = Path::new("./import");
-        if !source.exists() || !source.is_dir() {
-            return Ok(());
+    fn with_candidates(title: &'static str, candidates: Vec<ImportCandidate>) -> Self {
+        ImportListState {
+            title,
+            dir: None,
+            editing_dir: false,
+            candidates,
+            selected: 0,
+            error: None,
         }
-        let dest = self.paths.config_dir.join("bin");
-        fs::create_dir_all(&dest)?;
+    }
 
-        let mut existing_commands = HashSet::new();
-        for category in &self.categories {
-            for item in &category.items {
-                existing_commands.insert(item.cmd.clone());
-            }
-        }
+    fn show_candidates(&mut self, candidates: Vec<ImportCandidate>) {
+        self.candidates = candidates;
+        self.selected = 0;
+        self.editing_dir = false;
+    }
 
-        let mut new_items = Vec::new();
-        for entry in fs::read_dir(source)? {
-            let entry = entry?;
-            let path = entry.path();
-            if !path.is_file() || !is_executable_file(&entry) {
-                continue;
+    fn handle_key(&mut self, key: KeyEvent, keymap: &Keymap) -> ImportListKeyResult {
+        self.error = None;
+        match keymap.action_for(KeyContext::ImportList, &key) {
+            Some(KeyAction::Cancel) => {
+                if self.editing_dir || self.dir.is_none() {
+                    return ImportListKeyResult::Cancel;
+                }
+                // Back to the directory field to scan another one.
+                self.editing_dir = true;
+                return ImportListKeyResult::Continue;
             }
-            let filename = match path.file_name().and_then(|n| n.to_str()) {
-                Some(name) => name.to_string(),
-                None => continue,
-            };
-            let dest_path = dest.join(&filename);
-            if dest_path.exists() {
-                continue;
+            Some(KeyAction::Submit) => {
+                if self.editing_dir {
+                    return ImportListKeyResult::Scan(self.dir.clone().unwrap_or_default());
+                }
+                let chosen: Vec<ImportCandidate> = self
+                    .candidates
+                    .iter()
+                    .filter(|candidate| candidate.checked)
+                    .cloned()
+                    .collect();
+                if chosen.is_empty() {
+                    self.error = Some("Nothing is checked".into());
+                    return ImportListKeyResult::Continue;
+                }
+                return ImportListKeyResult::Import(chosen);
             }
-            fs::rename(&path, &dest_path)?;
-            #[cfg(unix)]
-            {
-                let mut perms = fs::metadata(&dest_path)?.permissions();
-                perms.set_mode(0o755);
-                fs::set_permissions(&dest_path, perms)?;
+            Some(KeyAction::ClearField) if self.editing_dir => {
+                if let Some(dir) = self.dir.as_mut() {
+                    dir.clear();
+                }
+                return ImportListKeyResult::Continue;
             }
-            let cmd_path = format!("~/.local/menu-maker/bin/{filename}");
-            if existing_commands.contains(&cmd_path) {
-                continue;
+            Some(KeyAction::MoveUp) if !self.editing_dir => {
+                self.selected = self.selected.saturating_sub(1);
+                return ImportListKeyResult::Continue;
             }
-            existing_commands.insert(cmd_path.clone());
-            let label = filename_to_label(&filename);
-            new_items.push(MenuItem {
-                label,
-                cmd: cmd_path,
-                info: format!("Executable: {filename}"),
-                pause: false,
-            });
+            Some(KeyAction::MoveDown) if !self.editing_dir => {
+                if self.selected + 1 < self.candidates.len() {
+                    self.selected += 1;
+                }
+                return ImportListKeyResult::Continue;
+            }
+            Some(KeyAction::ToggleSelection) if !self.editing_dir => {
+                if let Some(candidate) = self.candidates.get_mut(self.selected) {
+                    candidate.checked = !candidate.checked;
+                }
+                return ImportListKeyResult::Continue;
+            }
+            Some(KeyAction::ToggleAll) if !self.editing_dir => {
+                let check = !self.candidates.iter().all(|candidate| candidate.checked);
+                for candidate in &mut self.candidates {
+                    candidate.checked = check;
+                }
+                return ImportListKeyResult::Continue;
+            }
+            _ => {}
         }
-
-        if new_items.is_empty() {
-            return Ok(());
+        if let (true, Some(dir)) = (self.editing_dir, self.dir.as_mut()) {
+            match key.code {
+                KeyCode::Backspace => {
+                    dir.pop();
+                }
+                KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => dir.push(c),
+                _ => {}
+            }
         }
+        ImportListKeyResult::Continue
+    }
+}
 
-        let idx = self.ensure_category("Bin Executables");
-        self.categories[idx].expanded = true;
-        self.categories[idx].items.extend(new_items);
-        self.rebuild_display();
-        let _ = self.save_menu();
-        Ok(())
+/// Size and modification time of a config file when this app last read or
+/// wrote it.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        Some(FileStamp {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        })
     }
+}
 
-    fn apply_item_form_input(&mut self, input: ItemFormInput) -> Result<String, String> {
-        let label = input.label.trim();
-        if label.is_empty() {
-            return Err("Label is required".into());
-        }
-        let command = input.command.trim();
-        if command.is_empty() {
-            return Err("Command is required".into());
-        }
-        let info = input.info.trim().to_string();
-        let mut category_name = input.category.trim().to_string();
-        if category_name.is_empty() {
-            category_name = input.fallback_category.trim().to_string();
-        }
-        if category_name.is_empty() {
-            category_name = "General".into();
-        }
+/// Polls menus.json and theme.json for changes made by other programs.
+/// Cells let `save_menu` record its own writes through `&self`.
+#[derive(Default)]
+struct ConfigWatch {
+    menu: Cell<Option<FileStamp>>,
+    theme: Cell<Option<FileStamp>>,
+    /// menus.json as last read or written, the common ancestor when another
+    /// instance saved in between.
+    menu_base: RefCell<Option<serde_json::Value>>,
+    /// Result of the last merge, shown once the merged file is reloaded.
+    merge_notice: RefCell<Option<String>>,
+}
 
-        let new_item = MenuItem {
-            label: label.to_string(),
-            cmd: command.to_string(),
-            info,
-            pause: input.pause,
-        };
+impl ConfigWatch {
+    fn mark(&self, paths: &AppPaths) {
+        self.menu.set(FileStamp::of(&paths.menu_file));
+        self.theme.set(FileStamp::of(&paths.theme_file));
+        *self.menu_base.borrow_mut() = read_menu_value(&paths.menu_file);
+    }
 
-        match input.target {
-            Some((category_index, item_index)) => {
-                if category_index >= self.categories.len() {
-                    return Err("Item no longer exists".into());
-                }
-                if item_index >= self.categories[category_index].items.len() {
-                    return Err("Item no longer exists".into());
-                }
-                let same_category = self.categories[category_index].name == category_name;
-                if same_category {
-                    self.categories[category_index].items[item_index] = new_item;
-                } else {
-                    self.categories[category_index].items.remove(item_index);
-                    if self.categories[category_index].items.is_empty() {
-                        self.categories.remove(category_index);
-                    }
-                    let dest_idx = self.ensure_category(&category_name);
-                    self.categories[dest_idx].expanded = true;
-                    self.categories[dest_idx].items.push(new_item);
-                }
-                self.rebuild_display();
-                let _ = self.save_menu();
-                Ok("Item updated".into())
-            }
-            None => {
-                let idx = self.ensure_category(&category_name);
-                self.categories[idx].expanded = true;
-                self.categories[idx].items.push(new_item);
-                self.rebuild_display();
-                let _ = self.save_menu();
-                Ok("Item added".into())
-            }
-        }
+    /// Stop reporting the current files as changed but keep the merge base,
+    /// so the next save still merges in what the other instance wrote.
+    fn acknowledge(&self, paths: &AppPaths) {
+        self.menu.set(FileStamp::of(&paths.menu_file));
+        self.theme.set(FileStamp::of(&paths.theme_file));
+    }
+
+    /// Files that changed since the last `mark`. A deleted file is not a
+    /// change; it would only be recreated with defaults.
+    fn changed(&self, paths: &AppPaths) -> Vec<PathBuf> {
+        [
+            (&paths.menu_file, &self.menu),
+            (&paths.theme_file, &self.theme),
+        ]
+        .into_iter()
+        .filter(|(path, seen)| {
+            let current = FileStamp::of(path);
+            current.is_some() && current != seen.get()
+        })
+        .map(|(path, _)| path.clone())
+        .collect()
     }
+}
+
+/// Config files that changed on disk while a form had unsaved edits.
+struct ExternalChange {
+    files: Vec<PathBuf>,
+}
+
+enum DeferredAction {
+    NewItem,
+    EditItem {
+        category_index: usize,
+        item_index: usize,
+    },
+    EditCategory {
+        category_index: usize,
+    },
+    Settings(SettingsField),
+    ExternalEditor(ItemField),
+    EditMenuFile,
+}
+
+enum PopupResult {
+    None,
+    Close(Option<String>),
+    ItemSubmit(ItemFormInput),
+    ItemOpenEditor(ItemField),
+    CategorySubmit(CategorySubmitPayload),
+    CategoryDeletePreset(usize),
+    SettingsSubmit(SettingsFormInput),
+    SettingsDeleteSavedTheme(usize),
+    SettingsImportTheme(String),
+    SettingsExportTheme(ThemeOption, String),
+    SettingsRestoreBackup(PathBuf),
+    SettingsExportMenu(String),
+    ImportScan(String),
+    ImportSubmit(Vec<ImportCandidate>),
+    OpenColorPicker,
+}
+
+enum PopupClickAction {
+    Category(CategoryFormClick),
+    Settings(SettingsFormClick),
+}
+
+enum CategoryFormClick {
+    SelectField(CategoryField),
+    SelectPalette(usize),
+    Shortcut(CategoryShortcutAction),
+}
+
+enum SettingsFormClick {
+    SelectField(SettingsField),
+    SelectTheme(usize),
+    DeleteSavedTheme(usize),
+    ToggleDeriveMode,
+    Shortcut(SettingsShortcutAction),
+}
+
+#[derive(Clone, Copy)]
+struct SettingsShortcutSegment {
+    start: u16,
+    end: u16,
+    action: SettingsShortcutAction,
+}
+
+#[derive(Clone, Copy)]
+struct CategoryShortcutSegment {
+    start: u16,
+    end: u16,
+    action: CategoryShortcutAction,
+}
+
+#[derive(Clone, Copy)]
+enum SettingsShortcutAction {
+    NextField,
+    Submit,
+    Cancel,
+    PreviousTheme,
+    NextTheme,
+    DeleteTheme,
+    ImportTheme,
+    ExportTheme,
+    PickColor,
+    DeriveTheme,
+    RestoreBackup,
+    ExportMenu,
+    Help,
+}
+
+#[derive(Clone, Copy)]
+enum CategoryShortcutAction {
+    NextField,
+    PreviousField,
+    Submit,
+    Cancel,
+    PreviousPalette,
+    NextPalette,
+    DeletePreset,
+    PickColor,
+    Help,
+}
+
+struct CategoryFormState {
+    category_index: usize,
+    name: String,
+    column_value: String,
+    selected_field: CategoryField,
+    error: Option<String>,
+    color_presets: Vec<ColorPreset>,
+    palette_index: usize,
+    custom_preset_name: String,
+    custom_preset_background: String,
+    custom_preset_text: String,
+}
+
+#[derive(Default)]
+struct CategoryFormLayout {
+    line_count: usize,
+    name_line: Option<usize>,
+    column_line: Option<usize>,
+    custom_heading_line: Option<usize>,
+    custom_name_line: Option<usize>,
+    custom_background_line: Option<usize>,
+    custom_text_line: Option<usize>,
+    shortcut_segments: Vec<CategoryShortcutSegment>,
+    shortcut_total_width: u16,
+    shortcut_line: Option<Line<'static>>,
+    presets_heading_line: Option<usize>,
+    presets_start_line: Option<usize>,
+    presets_count: usize,
+}
+
+#[derive(Clone)]
+struct CategoryFormInput {
+    category_index: usize,
+    name: String,
+    column_value: String,
+    background: String,
+    text_color: String,
+}
+
+struct CategorySubmitPayload {
+    form: CategoryFormInput,
+    new_preset: Option<CustomPresetInput>,
+}
+
+#[derive(Clone)]
+struct CustomPresetInput {
+    name: String,
+    background: String,
+    text: String,
+}
 
-    fn apply_category_form_input(&mut self, input: CategoryFormInput) -> Result<String, String> {
-        if input.category_index >= self.categories.len() {
-            return Err("Category no longer exists".into());
-        }
-        let current_name = self.categories[input.category_index].name.clone();
-        let new_name = if input.name.trim().is_empty() {
-            current_name.clone()
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CategoryField {
+    Name,
+    Column,
+    CustomPresetName,
+    CustomPresetBackground,
+    CustomPresetText,
+    Palette,
+}
+
+enum FormKeyResult {
+    Continue,
+    Cancel,
+    Submit(CategorySubmitPayload),
+    DeletePreset(usize),
+    PickColor,
+}
+
+enum ItemFormKeyResult {
+    Continue,
+    Cancel,
+    Submit(ItemFormInput),
+    OpenEditor(ItemField),
+    PickColor,
+}
+
+impl CategoryFormState {
+    fn new(index: usize, category: &CategoryState, presets: Vec<ColorPreset>) -> Self {
+        let background = category
+            .colors
+            .as_ref()
+            .and_then(|c| c.background.clone())
+            .map(|value| normalize_hex(&value))
+            .unwrap_or_default();
+        let text = category
+            .colors
+            .as_ref()
+            .and_then(|c| c.text.clone())
+            .map(|value| normalize_hex(&value))
+            .unwrap_or_default();
+        let color_presets = if presets.is_empty() {
+            vec![ColorPreset::new("Default", "#034e68", "#caf0f8")]
         } else {
-            input.name.trim().to_string()
+            presets
         };
-        if new_name != current_name
-            && self
-                .categories
+        let palette_index = if !background.is_empty() && !text.is_empty() {
+            color_presets
                 .iter()
-                .enumerate()
-                .any(|(idx, cat)| idx != input.category_index && cat.name == new_name)
-        {
-            return Err("Category name already exists".into());
-        }
-        let column_value = if input.column_value.trim().is_empty() {
-            self.categories[input.category_index].column
+                .position(|preset| preset.matches(&background, &text))
+                .unwrap_or(0)
         } else {
-            input
-                .column_value
-                .trim()
-                .parse::<u16>()
-                .map_err(|_| "Column must be a number".to_string())?
+            0
+        };        let mut custom_name = String::new();
+        if let Some(preset) = color_presets.get(palette_index) {
+            custom_name = preset.name.clone();
+        } else if !category.name.is_empty() {
+            custom_name = format!("{} Colors", category.name);
         }
-        .clamp(1, MAX_COLUMNS);
 
-        let background = parse_color_field(&input.background)?;
-        let text = parse_color_field(&input.text_color)?;
+        Self {
+            category_index: index,
+            name: category.name.clone(),
+            column_value: category.column.to_string(),
+            selected_field: CategoryField::Name,
+            error: None,
+            color_presets,
+            palette_index,
+            custom_preset_name: custom_name,
+            custom_preset_background: background,
+            custom_preset_text: text,
+        }
+    }
 
-        let category = &mut self.categories[input.category_index];
-        category.name = new_name;
-        category.column = column_value;
-        category.colors = match (background, text) {
-            (None, None) => None,
-            (bg, txt) => Some(ColorConfig {
-                background: bg,
-                text: txt,
-            }),
-        };
+    fn render_lines(&self, app: &AppState) -> (Vec<FormLine>, CategoryFormLayout) {
+        let mut layout = CategoryFormLayout::default();
+        let mut lines: Vec<FormLine> = Vec::new();
+        lines.push(plain_line(Line::from("Update the category fields below.")));
 
-        self.rebuild_display();
-        let _ = self.save_menu();
-        Ok("Category updated".into())
-    }
+        layout.name_line = Some(lines.len());
+        lines.push(make_field_line(
+            "Name",
+            &self.name,
+            self.selected_field == CategoryField::Name,
+            app,
+        ));
 
-    fn process_category_submission(
-        &mut self,
-        payload: CategorySubmitPayload,
-    ) -> Result<String, String> {
-        let mut messages: Vec<String> = Vec::new();
-        if let Some(preset_input) = payload.new_preset {
-            match self.add_custom_category_preset(preset_input) {
-                Ok(msg) => {
-                    self.handle_category_preset_add_result(Ok(msg.clone()));
-                    messages.push(msg);
+        layout.column_line = Some(lines.len());
+        lines.push(make_field_line(
+            "Column",
+            &self.column_value,
+            self.selected_field == CategoryField::Column,
+            app,
+        ));
+
+        if !self.color_presets.is_empty() {
+            lines.push(plain_line(Line::from("")));
+            layout.presets_heading_line = Some(lines.len());
+            lines.push(plain_line(Line::from(vec![Span::styled(
+                "Color Theme (Tab to focus, ←/→ select)",
+                Style::default()
+                    .fg(app.theme.accent)
+                    .add_modifier(Modifier::BOLD),
+            )])));
+            layout.presets_start_line = Some(lines.len());
+            for (idx, preset) in self.color_presets.iter().enumerate() {
+                let is_selected = self.palette_index == idx;
+                let highlight_palette = is_selected && self.selected_field == CategoryField::Palette;
+                let mut label_style = Style::default().fg(app.theme.text);
+                if is_selected {
+                    label_style = label_style.add_modifier(Modifier::BOLD);
                 }
-                Err(err_msg) => {
-                    self.handle_category_preset_add_result(Err(err_msg.clone()));
-                    return Err(err_msg);
+                let preview_bg = color_from_hex(&preset.background);
+                let preview_text = color_from_hex(&preset.text).unwrap_or(app.theme.text);
+                let mut spans = vec![Span::styled(
+                    format!("{:>2}. {}", idx + 1, preset.name),
+                    label_style,
+                )];
+                if let Some(bg) = preview_bg {
+                    spans.push(Span::raw("  "));
+                    spans.push(Span::styled(
+                        "     ",
+                        Style::default().bg(bg).fg(preview_text),
+                    ));
                 }
+                spans.push(Span::raw("  "));
+                let mut background_hex_style = Style::default().fg(app.theme.text);
+                let mut divider_style = Style::default().fg(app.theme.text);
+                let mut text_hex_style = Style::default().fg(app.theme.text);
+                if is_selected {
+                    background_hex_style = background_hex_style.add_modifier(Modifier::BOLD);
+                    divider_style = divider_style.add_modifier(Modifier::BOLD);
+                    text_hex_style = text_hex_style.add_modifier(Modifier::BOLD);
+                }
+                spans.push(Span::styled(preset.background.clone(), background_hex_style));
+                spans.push(Span::styled(" / ", divider_style));
+                spans.push(Span::styled(preset.text.clone(), text_hex_style));
+                let line = if highlight_palette {
+                    FormLine::highlighted(Line::from(spans))
+                } else {
+                    FormLine::plain(Line::from(spans))
+                };
+                lines.push(line);
             }
+            layout.presets_count = self.color_presets.len();
         }
-        match self.apply_category_form_input(payload.form) {
-            Ok(msg) => {
-                messages.push(msg.clone());
-                Ok(messages.join(" | "))
-            }
-            Err(err_msg) => Err(err_msg),
-        }
-    }
 
-    fn add_custom_category_preset(&mut self, input: CustomPresetInput) -> Result<String, String> {
-        let mut name = input.name.trim().to_string();
-        if name.is_empty() {
-            name = format!("Custom Theme {}", self.custom_colors.len() + 1);
+        lines.push(plain_line(Line::from("")));
+        layout.custom_heading_line = Some(lines.len());
+        lines.push(plain_line(Line::from(vec![Span::styled(
+            "Custom Theme (#RRGGBB)",
+            Style::default()
+                .fg(app.theme.accent)
+                .add_modifier(Modifier::BOLD),
+        )])));
+        layout.custom_name_line = Some(lines.len());
+        lines.push(make_field_line(
+            "Name",
+            &self.custom_preset_name,
+            self.selected_field == CategoryField::CustomPresetName,
+            app,
+        ));
+        layout.custom_background_line = Some(lines.len());
+        lines.push(make_color_field_line(
+            "Background",
+            &self.custom_preset_background,
+            self.selected_field == CategoryField::CustomPresetBackground,
+            color_from_hex(&self.custom_preset_background),
+            app,
+        ));
+        layout.custom_text_line = Some(lines.len());
+        lines.push(make_color_field_line(
+            "Text",
+            &self.custom_preset_text,
+            self.selected_field == CategoryField::CustomPresetText,
+            color_from_hex(&self.custom_preset_text),
+            app,
+        ));
+        if let (Some(text), Some(background)) = (
+            hex_to_rgb(&self.custom_preset_text),
+            hex_to_rgb(&self.custom_preset_background),
+        ) {
+            lines.push(plain_line(contrast_line(
+                "Text on Background contrast",
+                text,
+                background,
+                app,
+            )));
         }
-        self.custom_colors.push(NamedColorPair {
-            name: Some(name.clone()),
-            background: Some(input.background.clone()),
-            text: Some(input.text.clone()),
-        });
-        let _ = self.save_menu();
-        Ok(format!("Theme '{name}' added"))
-    }
 
-    fn delete_custom_category_preset(&mut self, index: usize) -> Result<String, String> {
-        if index >= self.custom_colors.len() {
-            return Err("Custom theme not found".into());
+        lines.push(plain_line(Line::from("")));
+        let (shortcut_line, shortcut_segments, shortcut_width) =
+            build_category_shortcut_line(app, self.has_deletable_preset());
+        layout.shortcut_line = Some(shortcut_line);
+        layout.shortcut_segments = shortcut_segments;
+        layout.shortcut_total_width = shortcut_width;
+        if let Some(error) = &self.error {
+            lines.push(plain_line(Line::from(vec![Span::styled(
+                error.clone(),
+                Style::default().fg(Color::Red),
+            )])));
         }
-        let removed = self.custom_colors.remove(index);
-        let name = removed
-            .name
-            .clone()
-            .unwrap_or_else(|| format!("Custom Theme {}", index + 1));
-        let _ = self.save_menu();
-        Ok(format!("Theme '{name}' deleted"))
+        layout.line_count = lines.len();
+        (lines, layout)
     }
-
-    fn handle_category_preset_add_result(
-        &mut self,
-        result: Result<String, String>,
-    ) {
-        match result {
-            Ok(msg) => {
-                let presets = self.available_color_presets();
-                if let Some(PopupState::CategoryForm(form)) = self.active_popup.as_mut() {
-                    form.refresh_presets(presets);
-                    if !form.color_presets.is_empty() {
-                        form.focus_palette_index(form.color_presets.len() - 1);
+    fn handle_key(&mut self, key: KeyEvent, keymap: &Keymap) -> FormKeyResult {
+        self.error = None;
+        if let Some(action) = keymap.action_for(KeyContext::CategoryForm, &key) {
+            match action {
+                KeyAction::Cancel => return FormKeyResult::Cancel,
+                KeyAction::Submit => {
+                    if self.selected_field == CategoryField::Palette {
+                        if let Some(index) = self.current_custom_preset_index() {
+                            return FormKeyResult::DeletePreset(index);
+                        }
                     }
+                    return match self.build_submission() {
+                        Ok(input) => FormKeyResult::Submit(input),
+                        Err(err) => {
+                            self.error = Some(err);
+                            FormKeyResult::Continue
+                        }
+                    };
                 }
-                self.set_status(Some(msg));
-            }
-            Err(err_msg) => {
-                if let Some(PopupState::CategoryForm(form)) = self.active_popup.as_mut() {
-                    form.error = Some(err_msg);
+                KeyAction::NextField => {
+                    self.next_field();
+                    return FormKeyResult::Continue;
+                }
+                KeyAction::PreviousField => {
+                    self.previous_field();
+                    return FormKeyResult::Continue;
+                }
+                KeyAction::PreviousPalette if self.selected_field == CategoryField::Palette => {
+                    self.previous_palette();
+                    return FormKeyResult::Continue;
+                }
+                KeyAction::NextPalette if self.selected_field == CategoryField::Palette => {
+                    self.next_palette();
+                    return FormKeyResult::Continue;
+                }
+                KeyAction::PickColor if self.color_picker().is_some() => {
+                    return FormKeyResult::PickColor;
                 }
+                KeyAction::DeletePreset if self.selected_field == CategoryField::Palette => {
+                    return match self.current_custom_preset_index() {
+                        Some(index) => FormKeyResult::DeletePreset(index),
+                        None => FormKeyResult::Continue,
+                    };
+                }
+                KeyAction::ClearField => {
+                    if let Some(value) = self.active_value_mut() {
+                        value.clear();
+                    } else if self.selected_field == CategoryField::Palette {
+                        if let Some(index) = self.current_custom_preset_index() {
+                            return FormKeyResult::DeletePreset(index);
+                        }
+                    }
+                    return FormKeyResult::Continue;
+                }
+                _ => {}
             }
         }
-    }
-
-    fn handle_category_preset_delete_result(
-        &mut self,
-        result: Result<String, String>,
-    ) {
-        match result {
-            Ok(msg) => {
-                let presets = self.available_color_presets();
-                if let Some(PopupState::CategoryForm(form)) = self.active_popup.as_mut() {
-                    let target_index = form
-                        .palette_index
-                        .min(presets.len().saturating_sub(1));
-                    form.refresh_presets(presets);
-                    if !form.color_presets.is_empty() {
-                        form.focus_palette_index(target_index);
-                    } else {
-                        form.selected_field = CategoryField::Name;
-                    }
+        match key.code {
+            KeyCode::Backspace => {
+                if let Some(value) = self.active_value_mut() {
+                    value.pop();
                 }
-                self.set_status(Some(msg));
+                FormKeyResult::Continue
             }
-            Err(err_msg) => {
-                if let Some(PopupState::CategoryForm(form)) = self.active_popup.as_mut() {
-                    form.error = Some(err_msg);
+            KeyCode::Char(c) => {
+                if self.selected_field != CategoryField::Palette
+                    && !key.modifiers.contains(KeyModifiers::CONTROL)
+                {
+                    if let Some(value) = self.active_value_mut() {
+                        value.push(c);
+                    }
                 }
+                FormKeyResult::Continue
             }
+            _ => FormKeyResult::Continue,
         }
     }
 
-    fn apply_settings_form_input(&mut self, input: SettingsFormInput) -> Result<String, String> {
-        let mut title = input.title.trim().to_string();
-        if title.is_empty() {
-            title = self.title.clone();
-        }
-        let columns = if input.columns.trim().is_empty() {
-            self.column_count
-        } else {
-            input
-                .columns
-                .trim()
-                .parse::<u16>()
-                .map_err(|_| "Columns must be a number".to_string())?
-        }
-        .clamp(1, MAX_COLUMNS);
-        let mut theme_key = input.theme_key.trim().to_string();
-        if theme_key.is_empty() {
-            theme_key = self.theme_key.clone();
-        }
-        let custom_primary = input.custom_primary.trim();
-        let custom_accent = input.custom_accent.trim();
-        let custom_background = input.custom_background.trim();
-        let custom_surface = input.custom_surface.trim();
-        let custom_text = input.custom_text.trim();
-        let custom_highlight = input.custom_highlight.trim();
-        let custom_theme_name = input.custom_theme_name.trim();
-        let theme_options_snapshot = self.theme_options();
-        let selected_option = theme_options_snapshot
-            .iter()
-            .find(|opt| opt.key == theme_key);
-        let has_any_color_input = [
-            custom_primary,
-            custom_accent,
-            custom_highlight,
-            custom_background,
-            custom_surface,
-            custom_text,
-        ]
-        .iter()
-        .any(|value| !value.is_empty());
-        let colors_match_selected = selected_option
-            .map(|option| {
-                hex_strings_equal(custom_primary, &option.primary_hex)
-                    && hex_strings_equal(custom_accent, &option.accent_hex)
-                    && hex_strings_equal(custom_highlight, &option.highlight_hex)
-                    && hex_strings_equal(custom_background, &option.background_hex)
-                    && hex_strings_equal(custom_surface, &option.surface_hex)
-                    && hex_strings_equal(custom_text, &option.text_hex)
-            })
-            .unwrap_or(false);
-        let name_matches_selected = if custom_theme_name.is_empty() {
-            true
-        } else if let Some(option) = selected_option {
-            if parse_saved_theme_key(&option.key).is_some() {
-                custom_theme_name.eq_ignore_ascii_case(&option.label)
-            } else if option.key == CUSTOM_THEME_KEY {
-                custom_theme_name.eq_ignore_ascii_case(&self.theme.name)
-            } else {
-                false
-            }
-        } else {
-            false
-        };
-        let use_custom_colors = if has_any_color_input {
-            selected_option
-                .map(|_| !colors_match_selected || !name_matches_selected)
-                .unwrap_or(true)
-        } else if custom_theme_name.is_empty() {
-            false
-        } else {
-            !name_matches_selected || selected_option.is_none()
-        };
+    fn build_submission(&self) -> Result<CategorySubmitPayload, String> {
+        let background_result = parse_color_field(&self.custom_preset_background)?;
+        let text_result = parse_color_field(&self.custom_preset_text)?;
 
-        let mut changed = false;
-        if title != self.title {
-            self.title = title;
-            changed = true;
-        }
-        if columns != self.column_count {
-            self.column_count = columns;
-            self.rebuild_display();
-            changed = true;
-        }
-        if use_custom_colors {
-            let primary = require_color_field(custom_primary, "Primary")?;
-            let accent = require_color_field(custom_accent, "Accent")?;
-            let highlight = require_color_field(custom_highlight, "Highlight")?;
-            let background = require_color_field(custom_background, "Background")?;
-            let surface = require_color_field(custom_surface, "Surface")?;
-            let text_color = require_color_field(custom_text, "Text")?;
-            let theme_name = if custom_theme_name.is_empty() {
-                "Custom Theme".to_string()
-            } else {
-                custom_theme_name.to_string()
-            };
-            let theme = Theme::from_hexes(
-                theme_name.clone(),
-                &primary,
-                &accent,
-                &highlight,
-                &background,
-                &surface,
-                &text_color,
-            );
-            let mut new_theme_key = CUSTOM_THEME_KEY.to_string();
-            if !custom_theme_name.is_empty() {
-                let saved = SavedTheme {
-                    name: theme_name.clone(),
-                    primary,
-                    accent,
-                    highlight: Some(highlight.clone()),
-                    background,
-                    surface,
-                    text: text_color,
+        let background_value = background_result.clone().unwrap_or_default();
+        let text_value = text_result.clone().unwrap_or_default();
+
+        let mut new_preset: Option<CustomPresetInput> = None;
+        if let (Some(bg), Some(txt)) = (background_result, text_result) {
+            let exists = self
+                .color_presets
+                .iter()
+                .any(|preset| hex_strings_equal(&preset.background, &bg) && hex_strings_equal(&preset.text, &txt));
+            if !exists {
+                let name = if self.custom_preset_name.trim().is_empty() {
+                    "Custom Theme".to_string()
+                } else {
+                    self.custom_preset_name.trim().to_string()
                 };
-                let index = self.upsert_saved_theme(saved);
-                new_theme_key = saved_theme_key(index);
+                new_preset = Some(CustomPresetInput {
+                    name,
+                    background: bg.clone(),
+                    text: txt.clone(),
+                });
             }
-            self.theme = theme.clone();
-            self.theme_key = new_theme_key;
-            let _ = self.theme.save(&self.paths.theme_file);
-            changed = true;
-        } else {
-            if let Some(index) = parse_saved_theme_key(&theme_key) {
-                if parse_saved_theme_key(&self.theme_key) != Some(index) {
-                    if let Some(theme) = self.theme_from_saved_index(index) {
-                        self.theme = theme.clone();
-                        self.theme_key = saved_theme_key(index);
-                        let _ = self.theme.save(&self.paths.theme_file);
-                        changed = true;
-                    } else {
-                        return Err("Saved theme not found".into());
-                    }
+        }
+
+        Ok(CategorySubmitPayload {
+            form: CategoryFormInput {
+                category_index: self.category_index,
+                name: self.name.clone(),
+                column_value: self.column_value.clone(),
+                background: background_value,
+                text_color: text_value,
+            },
+            new_preset,
+        })
+    }
+
+    fn next_field(&mut self) {
+        let has_palette = !self.color_presets.is_empty();
+        self.selected_field = match self.selected_field {
+            CategoryField::Name => CategoryField::Column,
+            CategoryField::Column => {
+                if has_palette {
+                    CategoryField::Palette
+                } else {
+                    CategoryField::CustomPresetName
                 }
-            } else if theme_key == CUSTOM_THEME_KEY {
-                if self.theme_key != CUSTOM_THEME_KEY {
-                    return Err("Enter custom colors to create a custom theme".into());
+            }
+            CategoryField::Palette => CategoryField::CustomPresetName,
+            CategoryField::CustomPresetName => CategoryField::CustomPresetBackground,
+            CategoryField::CustomPresetBackground => CategoryField::CustomPresetText,
+            CategoryField::CustomPresetText => CategoryField::Name,
+        };
+    }
+    fn previous_field(&mut self) {
+        let has_palette = !self.color_presets.is_empty();
+        self.selected_field = match self.selected_field {
+            CategoryField::Name => CategoryField::CustomPresetText,
+            CategoryField::Column => CategoryField::Name,
+            CategoryField::Palette => CategoryField::Column,
+            CategoryField::CustomPresetName => {
+                if has_palette {
+                    CategoryField::Palette
+                } else {
+                    CategoryField::Column
                 }
-            } else if theme_key != self.theme_key {
-                let theme = Theme::from_name(&theme_key)
-                    .ok_or_else(|| "Unknown theme selected".to_string())?;
-                self.theme = theme.clone();
-                self.theme_key = theme_key.clone();
-                let _ = self.theme.save(&self.paths.theme_file);
-                changed = true;
             }
-        }
-
-        let _ = self.save_menu();
-        if changed {
-            Ok("Settings updated".into())
-        } else {
-            Ok("No settings changed".into())
+            CategoryField::CustomPresetBackground => CategoryField::CustomPresetName,
+            CategoryField::CustomPresetText => CategoryField::CustomPresetBackground,
+        };
+    }
+    fn active_value_mut(&mut self) -> Option<&mut String> {
+        match self.selected_field {
+            CategoryField::Name => Some(&mut self.name),
+            CategoryField::Column => Some(&mut self.column_value),
+            CategoryField::CustomPresetName => Some(&mut self.custom_preset_name),
+            CategoryField::CustomPresetBackground => Some(&mut self.custom_preset_background),
+            CategoryField::CustomPresetText => Some(&mut self.custom_preset_text),
+            CategoryField::Palette => None,
         }
     }
-}
 
-#[derive(Clone)]
-struct PendingCommand {
-    command: String,
-    pause: bool,
-}
+    fn color_picker(&self) -> Option<ColorPickerState> {
+        let (label, value, partner_label, partner) = match self.selected_field {
+            CategoryField::CustomPresetBackground => (
+                "Background",
+                &self.custom_preset_background,
+                "Text",
+                &self.custom_preset_text,
+            ),
+            CategoryField::CustomPresetText => (
+                "Text",
+                &self.custom_preset_text,
+                "Background",
+                &self.custom_preset_background,
+            ),
+            _ => return None,
+        };
+        Some(ColorPickerState::new(
+            ColorPickerTarget::Category(self.selected_field),
+            label,
+            value,
+            hex_to_rgb(partner).map(|rgb| (partner_label.to_string(), rgb)),
+        ))
+    }
 
-#[derive(Clone)]
-struct MenuItem {
-    label: String,
-    cmd: String,
-    info: String,
-    pause: bool,
-}
+    fn has_deletable_preset(&self) -> bool {
+        self.current_custom_preset_index().is_some()
+    }
 
-impl MenuItem {
-    fn from_config(category: &str, cfg: &MenuItemConfig) -> Self {
-        MenuItem {
-            label: cfg.label.clone(),
-            cmd: cfg.cmd.clone(),
-            info: cfg
-                .info
-                .clone()
-                .unwrap_or_else(|| format!("Item in {category}")),
-            pause: cfg.pause.unwrap_or(false),
+    fn current_custom_preset_index(&self) -> Option<usize> {
+        self.color_presets
+            .get(self.palette_index)
+            .and_then(|preset| preset.custom_index)
+    }
+    fn refresh_presets(&mut self, presets: Vec<ColorPreset>) {
+        self.color_presets = presets;
+        if self.color_presets.is_empty() {
+            self.palette_index = 0;
+        } else {
+            if self.palette_index >= self.color_presets.len() {
+                self.palette_index = self.color_presets.len() - 1;
+            }
+            self.apply_selected_palette();
         }
     }
-}
-
-#[derive(Clone)]
-struct CategoryState {
-    name: String,
-    expanded: bool,
-    column: u16,
-    colors: Option<ColorConfig>,
-    items: Vec<MenuItem>,
-}
 
-impl CategoryState {
-    fn from_config(name: &str, cfg: &CategoryConfig) -> Self {
-        let column = cfg.column.unwrap_or(1).clamp(1, MAX_COLUMNS);
-        let items = cfg
-            .items
-            .iter()
-            .map(|item| MenuItem::from_config(name, item))
-            .collect();
-        CategoryState {
-            name: name.to_string(),
-            expanded: cfg.expanded,
-            column,
-            colors: cfg.colors.clone(),
-            items,
+    fn focus_palette_index(&mut self, index: usize) {
+        if self.color_presets.is_empty() {
+            return;
         }
+        self.palette_index = index.min(self.color_presets.len() - 1);
+        self.selected_field = CategoryField::Palette;
+        self.apply_selected_palette();
     }
 
-    fn to_config(&self) -> CategoryConfig {
-        CategoryConfig {
-            expanded: self.expanded,
-            column: Some(self.column),
-            items: self
-                .items
-                .iter()
-                .map(|item| MenuItemConfig {
-                    label: item.label.clone(),
-                    cmd: item.cmd.clone(),
-                    info: Some(item.info.clone()),
-                    category: Some(self.name.clone()),
-                    pause: Some(item.pause),
-                })
-                .collect(),
-            colors: self.colors.clone(),
+    fn next_palette(&mut self) {
+        if self.color_presets.is_empty() {
+            return;
         }
+        self.palette_index = (self.palette_index + 1) % self.color_presets.len();
+        self.apply_selected_palette();
     }
-}
-
-enum DisplayEntry {
-    Category {
-        category_index: usize,
-    },
-    Item {
-        category_index: usize,
-        item_index: usize,
-    },
-}
-
-#[derive(Clone)]
-struct InfoPopup {
-    label: String,
-    command: String,
-    category: String,
-    description: String,
-}
-
-struct ItemFormState {
-    target: Option<(usize, usize)>,
-    label: String,
-    command: String,
-    info: String,
-    category: String,
-    fallback_category: String,
-    pause: bool,
-    available_categories: Vec<String>,
-    selected_field: ItemField,
-    error: Option<String>,
-    mode_label: &'static str,
-}
-
-#[derive(Clone)]
-struct ItemFormInput {
-    target: Option<(usize, usize)>,
-    label: String,
-    command: String,
-    info: String,
-    category: String,
-    fallback_category: String,
-    pause: bool,
-}
-
-#[derive(Clone, Copy, PartialEq, Eq)]
-enum ItemField {
-    Label,
-    Command,
-    Description,
-    Category,
-    Pause,
-}
 
-enum PopupState {
-    Info(InfoPopup),
-    Message(String),
-    ItemForm(ItemFormState),
-    CategoryForm(CategoryFormState),
-    SettingsForm(SettingsFormState),
-}
+    fn previous_palette(&mut self) {
+        if self.color_presets.is_empty() {
+            return;
+        }
+        if self.palette_index == 0 {
+            self.palette_index = self.color_presets.len() - 1;
+        } else {
+            self.palette_index -= 1;
+        }
+        self.apply_selected_palette();
+    }
 
-enum DeferredAction {
-    NewItem,
-    EditItem {
-        category_index: usize,
-        item_index: usize,
-    },
-    EditCategory {
-        category_index: usize,
-    },
-    Settings(SettingsField),
+    fn apply_selected_palette(&mut self) {
+        if let Some(preset) = self.color_presets.get(self.palette_index) {
+            self.custom_preset_background = preset.background.clone();
+            self.custom_preset_text = preset.text.clone();
+            self.custom_preset_name = preset.name.clone();
+        }
+    }
 }
 
-enum PopupResult {
-    None,
-    Close(Option<String>),
-    ItemSubmit(ItemFormInput),
-    CategorySubmit(CategorySubmitPayload),
-    CategoryDeletePreset(usize),
-    SettingsSubmit(SettingsFormInput),
-    SettingsDeleteSavedTheme(usize),
-}
+impl ItemFormState {
+    fn new(
+        target: Option<(usize, usize)>,
+        label: String,
+        command: String,
+        info: String,
+        category: String,
+        fallback_category: String,
+        pause: bool,
+        style: ItemStyle,
+        available_categories: Vec<String>,
+    ) -> Self {
+        Self {
+            target,
+            label,
+            command,
+            info,
+            category,
+            fallback_category,
+            pause,
+            icon: style.icon.unwrap_or_default(),
+            text_color: style.text.unwrap_or_default(),
+            background: style.background.unwrap_or_default(),
+            bold: style.bold,
+            dim: style.dim,
+            italic: style.italic,
+            preset_index: 0,
+            available_categories,
+            profiles: Vec::new(),
+            profile_index: 0,
+            current_profile: None,
+            read_only: None,
+            selected_field: ItemField::Label,
+            error: None,
+            mode_label: if target.is_some() {
+                "Edit Menu Item"
+            } else {
+                "New Menu Item"
+            },
+        }
+    }
 
-enum PopupClickAction {
-    Category(CategoryFormClick),
-    Settings(SettingsFormClick),
-}
+    fn handle_key(&mut self, key: KeyEvent, keymap: &Keymap) -> ItemFormKeyResult {
+        self.error = None;
+        if let Some(source) = &self.read_only {
+            match keymap.action_for(KeyContext::ItemForm, &key) {
+                Some(KeyAction::Cancel) => return ItemFormKeyResult::Cancel,
+                Some(KeyAction::NextField) => self.next_field(),
+                Some(KeyAction::PreviousField) => self.previous_field(),
+                Some(KeyAction::Submit) => {
+                    self.error = Some(format!(
+                        "Read-only item from {source}; add your own item instead"
+                    ))
+                }
+                _ => {}
+            }
+            return ItemFormKeyResult::Continue;
+        }
+        for action in keymap.actions_for(KeyContext::ItemForm, &key) {
+            match action {
+                KeyAction::Cancel => return ItemFormKeyResult::Cancel,
+                KeyAction::Submit => return ItemFormKeyResult::Submit(self.to_input()),
+                KeyAction::NextField => {
+                    self.next_field();
+                    return ItemFormKeyResult::Continue;
+                }
+                KeyAction::PreviousField => {
+                    self.previous_field();
+                    return ItemFormKeyResult::Continue;
+                }
+                KeyAction::TogglePause if self.selected_field == ItemField::Pause => {
+                    self.pause = !self.pause;
+                    return ItemFormKeyResult::Continue;
+                }
+                KeyAction::ToggleEmphasis if self.toggle_emphasis() => {
+                    return ItemFormKeyResult::Continue;
+                }
+                KeyAction::PreviousPalette if self.selected_field == ItemField::StylePreset => {
+                    self.previous_preset();
+                    return ItemFormKeyResult::Continue;
+                }
+                KeyAction::NextPalette if self.selected_field == ItemField::StylePreset => {
+                    self.next_preset();
+                    return ItemFormKeyResult::Continue;
+                }
+                KeyAction::PreviousPalette if self.selected_field == ItemField::Profile => {
+                    self.profile_index = self
+                        .profile_index
+                        .checked_sub(1)
+                        .unwrap_or(self.profiles.len().saturating_sub(1));
+                    return ItemFormKeyResult::Continue;
+                }
+                KeyAction::NextPalette if self.selected_field == ItemField::Profile => {
+                    self.profile_index = (self.profile_index + 1) % self.profiles.len().max(1);
+                    return ItemFormKeyResult::Continue;
+                }
+                KeyAction::PickColor
+                    if matches!(
+                        self.selected_field,
+                        ItemField::TextColor | ItemField::Background
+                    ) =>
+                {
+                    return ItemFormKeyResult::PickColor;
+                }
+                KeyAction::OpenEditor => {
+                    let field = if self.selected_field == ItemField::Description {
+                        ItemField::Description
+                    } else {
+                        ItemField::Command
+                    };
+                    return ItemFormKeyResult::OpenEditor(field);
+                }
+                KeyAction::ClearField => {
+                    if let Some(value) = self.active_value_mut() {
+                        value.clear();
+                    }
+                    return ItemFormKeyResult::Continue;
+                }
+                _ => {}
+            }
+        }
+        match key.code {
+            KeyCode::Backspace => {
+                if let Some(value) = self.active_value_mut() {
+                    value.pop();
+                }
+                ItemFormKeyResult::Continue
+            }
+            KeyCode::Char(c) => {
+                if !matches!(self.selected_field, ItemField::Pause | ItemField::Profile)
+                    && !key.modifiers.contains(KeyModifiers::CONTROL)
+                {
+                    if let Some(value) = self.active_value_mut() {
+                        value.push(c);
+                    }
+                }
+                ItemFormKeyResult::Continue
+            }
+            _ => ItemFormKeyResult::Continue,
+        }
+    }
 
-enum CategoryFormClick {
-    SelectField(CategoryField),
-    SelectPalette(usize),
-    Shortcut(CategoryShortcutAction),
-}
+    fn to_input(&self) -> ItemFormInput {
+        ItemFormInput {
+            target: self.target,
+            label: self.label.clone(),
+            command: self.command.clone(),
+            info: self.info.clone(),
+            category: self.category.clone(),
+            fallback_category: self.fallback_category.clone(),
+            pause: self.pause,
+            icon: self.icon.clone(),
+            text_color: self.text_color.clone(),
+            background: self.background.clone(),
+            bold: self.bold,
+            dim: self.dim,
+            italic: self.italic,
+            profile: self.target_profile().map(str::to_string),
+        }
+    }
 
-enum SettingsFormClick {
-    SelectField(SettingsField),
-    SelectTheme(usize),
-    DeleteSavedTheme(usize),
-    Shortcut(SettingsShortcutAction),
-}
+    /// The chosen profile when it differs from the one being edited.
+    fn target_profile(&self) -> Option<&str> {
+        if Some(self.profile_index) == self.current_profile {
+            return None;
+        }
+        self.profiles.get(self.profile_index).map(String::as_str)
+    }
 
-#[derive(Clone, Copy)]
-struct SettingsShortcutSegment {
-    start: u16,
-    end: u16,
-    action: SettingsShortcutAction,
-}
+    /// The style as currently entered, ignoring colors that do not parse yet.
+    fn preview_style(&self) -> ItemStyle {
+        ItemStyle {
+            text: sanitize_hex_color_input(&self.text_color),
+            background: sanitize_hex_color_input(&self.background),
+            icon: Some(self.icon.trim().to_string()).filter(|icon| !icon.is_empty()),
+            bold: self.bold,
+            dim: self.dim,
+            italic: self.italic,
+        }
+    }
 
-#[derive(Clone, Copy)]
-struct CategoryShortcutSegment {
-    start: u16,
-    end: u16,
-    action: CategoryShortcutAction,
-}
+    fn toggle_emphasis(&mut self) -> bool {
+        match self.selected_field {
+            ItemField::Bold => self.bold = !self.bold,
+            ItemField::Dim => self.dim = !self.dim,
+            ItemField::Italic => self.italic = !self.italic,
+            _ => return false,
+        }
+        true
+    }
+
+    fn next_preset(&mut self) {
+        self.preset_index = (self.preset_index + 1) % ITEM_STYLE_PRESETS.len();
+        self.apply_selected_preset();
+    }
+
+    fn previous_preset(&mut self) {
+        self.preset_index = self
+            .preset_index
+            .checked_sub(1)
+            .unwrap_or(ITEM_STYLE_PRESETS.len() - 1);
+        self.apply_selected_preset();
+    }
+
+    fn apply_selected_preset(&mut self) {
+        if let Some(preset) = ITEM_STYLE_PRESETS.get(self.preset_index) {
+            let style = preset.to_style();
+            self.icon = style.icon.unwrap_or_default();
+            self.text_color = style.text.unwrap_or_default();
+            self.background = style.background.unwrap_or_default();
+            self.bold = style.bold;
+            self.dim = style.dim;
+            self.italic = style.italic;
+        }
+    }
+
+    /// Picker for the focused item color. Blank partners are compared with
+    /// the theme colors the item would otherwise use.
+    fn color_picker(&self, theme: &Theme) -> Option<ColorPickerState> {
+        let (label, value, partner_label, partner, fallback) = match self.selected_field {
+            ItemField::TextColor => (
+                "Item Text",
+                &self.text_color,
+                "Background",
+                &self.background,
+                &theme.surface_hex,
+            ),
+            ItemField::Background => (
+                "Item Background",
+                &self.background,
+                "Text",
+                &self.text_color,
+                &theme.text_hex,
+            ),
+            _ => return None,
+        };
+        let partner = hex_to_rgb(partner).or_else(|| hex_to_rgb(fallback));
+        Some(ColorPickerState::new(
+            ColorPickerTarget::Item(self.selected_field),
+            label,
+            value,
+            partner.map(|rgb| (partner_label.to_string(), rgb)),
+        ))
+    }
 
-#[derive(Clone, Copy)]
-enum SettingsShortcutAction {
-    NextField,
-    Submit,
-    Cancel,
-    PreviousTheme,
-    NextTheme,
-    DeleteTheme,
-}
+    fn next_field(&mut self) {
+        self.selected_field = match self.selected_field {
+            ItemField::Label => ItemField::Command,
+            ItemField::Command => ItemField::Description,
+            ItemField::Description => ItemField::Category,
+            ItemField::Category => ItemField::Profile,
+            ItemField::Profile => ItemField::Pause,
+            ItemField::Pause => ItemField::Icon,
+            ItemField::Icon => ItemField::TextColor,
+            ItemField::TextColor => ItemField::Background,
+            ItemField::Background => ItemField::Bold,
+            ItemField::Bold => ItemField::Dim,
+            ItemField::Dim => ItemField::Italic,
+            ItemField::Italic => ItemField::StylePreset,
+            ItemField::StylePreset => ItemField::Label,
+        };
+    }
 
-#[derive(Clone, Copy)]
-enum CategoryShortcutAction {
-    NextField,
-    PreviousField,
-    Submit,
-    Cancel,
-    PreviousPalette,
-    NextPalette,
-    DeletePreset,
+    fn previous_field(&mut self) {
+        self.selected_field = match self.selected_field {
+            ItemField::Label => ItemField::StylePreset,
+            ItemField::Command => ItemField::Label,
+            ItemField::Description => ItemField::Command,
+            ItemField::Category => ItemField::Description,
+            ItemField::Profile => ItemField::Category,
+            ItemField::Pause => ItemField::Profile,
+            ItemField::Icon => ItemField::Pause,
+            ItemField::TextColor => ItemField::Icon,
+            ItemField::Background => ItemField::TextColor,
+            ItemField::Bold => ItemField::Background,
+            ItemField::Dim => ItemField::Bold,
+            ItemField::Italic => ItemField::Dim,
+            ItemField::StylePreset => ItemField::Italic,
+        };
+    }
+
+    fn active_value_mut(&mut self) -> Option<&mut String> {
+        match self.selected_field {
+            ItemField::Label => Some(&mut self.label),
+            ItemField::Command => Some(&mut self.command),
+            ItemField::Description => Some(&mut self.info),
+            ItemField::Category => Some(&mut self.category),
+            ItemField::Icon => Some(&mut self.icon),
+            ItemField::TextColor => Some(&mut self.text_color),
+            ItemField::Background => Some(&mut self.background),
+            ItemField::Pause
+            | ItemField::Profile
+            | ItemField::Bold
+            | ItemField::Dim
+            | ItemField::Italic
+            | ItemField::StylePreset => None,
+        }
+    }
 }
 
-struct CategoryFormState {
-    category_index: usize,
-    name: String,
-    column_value: String,
-    selected_field: CategoryField,
+struct SettingsFormState {
+    title: String,
+    status_template: String,
+    columns_value: String,
+    theme_options: Vec<ThemeOption>,
+    theme_index: usize,
+    selected_field: SettingsField,
     error: Option<String>,
-    color_presets: Vec<ColorPreset>,
-    palette_index: usize,
-    custom_preset_name: String,
-    custom_preset_background: String,
-    custom_preset_text: String,
+    custom_primary: String,
+    custom_accent: String,
+    custom_background: String,
+    custom_surface: String,
+    custom_text: String,
+    custom_highlight: String,
+    custom_footer_background: String,
+    custom_footer_key: String,
+    custom_footer_label: String,
+    custom_status_bar: String,
+    custom_popup_border: String,
+    custom_theme_name: String,
+    theme_file_path: String,
+    derive_seed: String,
+    derive_light: bool,
+    backups: Vec<PathBuf>,
+    backup_index: usize,
+    /// Backup the restore key was pressed on once; pressing it again restores.
+    restore_armed: Option<PathBuf>,
+    export_path: String,
 }
 
 #[derive(Default)]
-struct CategoryFormLayout {
+struct SettingsFormLayout {
     line_count: usize,
-    name_line: Option<usize>,
-    column_line: Option<usize>,
+    title_line: Option<usize>,
+    status_template_line: Option<usize>,
+    columns_line: Option<usize>,
+    theme_heading_line: Option<usize>,
+    theme_list_start: Option<usize>,
+    theme_count: usize,
     custom_heading_line: Option<usize>,
+    delete_saved_theme_line: Option<usize>,
+    delete_saved_theme_index: Option<usize>,
+    shortcut_line: Option<Line<'static>>,
+    shortcut_segments: Vec<SettingsShortcutSegment>,
+    shortcut_total_width: u16,
     custom_name_line: Option<usize>,
+    derive_seed_line: Option<usize>,
+    derive_mode_line: Option<usize>,
+    custom_primary_line: Option<usize>,
+    custom_accent_line: Option<usize>,
     custom_background_line: Option<usize>,
+    custom_surface_line: Option<usize>,
     custom_text_line: Option<usize>,
-    shortcut_segments: Vec<CategoryShortcutSegment>,
-    shortcut_total_width: u16,
-    shortcut_line: Option<Line<'static>>,
-    presets_heading_line: Option<usize>,
-    presets_start_line: Option<usize>,
-    presets_count: usize,
+    custom_highlight_line: Option<usize>,
+    custom_footer_background_line: Option<usize>,
+    custom_footer_key_line: Option<usize>,
+    custom_footer_label_line: Option<usize>,
+    custom_status_bar_line: Option<usize>,
+    custom_popup_border_line: Option<usize>,
+    theme_file_path_line: Option<usize>,
+    backup_line: Option<usize>,
+    export_line: Option<usize>,
 }
 
 #[derive(Clone)]
-struct CategoryFormInput {
-    category_index: usize,
-    name: String,
-    column_value: String,
-    background: String,
-    text_color: String,
+struct SettingsFormInput {
+    title: String,
+    status_template: String,
+    columns: String,
+    theme_key: String,
+    custom_primary: String,
+    custom_accent: String,
+    custom_background: String,
+    custom_surface: String,
+    custom_text: String,
+    custom_highlight: String,
+    custom_footer_background: String,
+    custom_footer_key: String,
+    custom_footer_label: String,
+    custom_status_bar: String,
+    custom_popup_border: String,
+    custom_theme_name: String,
 }
 
-struct CategorySubmitPayload {
-    form: CategoryFormInput,
-    new_preset: Option<CustomPresetInput>,
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SettingsField {
+    Title,
+    StatusTemplate,
+    Columns,
+    Theme,
+    ThemeFilePath,
+    CustomName,
+    DeriveSeed,
+    DeriveMode,
+    CustomPrimary,
+    CustomAccent,
+    CustomBackground,
+    CustomSurface,
+    CustomText,
+    CustomHighlight,
+    CustomFooterBackground,
+    CustomFooterKey,
+    CustomFooterLabel,
+    CustomStatusBar,
+    CustomPopupBorder,
+    Backup,
+    ExportPath,
 }
 
-#[derive(Clone)]
-struct CustomPresetInput {
-    name: String,
-    background: String,
-    text: String,
+enum SettingsFormKeyResult {
+    Continue,
+    Cancel,
+    Submit(SettingsFormInput),
+    DeleteSavedTheme(usize),
+    ImportTheme(String),
+    ExportTheme(ThemeOption, String),
+    RestoreBackup(PathBuf),
+    ExportMenu(String),
+    PickColor,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-enum CategoryField {
-    Name,
-    Column,
-    CustomPresetName,
-    CustomPresetBackground,
-    CustomPresetText,
-    Palette,
-}
+impl SettingsFormState {
+    fn new(
+        title: String,
+        columns: u16,
+        theme_key: String,
+        options: Vec<ThemeOption>,
+        initial_field: SettingsField,
+        current_theme: &Theme,
+        is_custom_theme: bool,
+    ) -> Self {
+        let columns_value = columns.to_string();
+        let theme_index = options
+            .iter()
+            .position(|opt| opt.key == theme_key)
+            .unwrap_or(0);
+        Self {
+            title,
+            status_template: String::new(),
+            columns_value,
+            theme_options: options,
+            theme_index,
+            selected_field: initial_field,
+            error: None,
+            custom_primary: String::new(),
+            custom_accent: String::new(),
+            custom_background: String::new(),
+            custom_surface: String::new(),
+            custom_text: String::new(),
+            custom_highlight: String::new(),
+            custom_footer_background: String::new(),
+            custom_footer_key: String::new(),
+            custom_footer_label: String::new(),
+            custom_status_bar: String::new(),
+            custom_popup_border: String::new(),
+            custom_theme_name: if is_custom_theme {
+                current_theme.name.clone()
+            } else {
+                String::new()
+            },
+            theme_file_path: String::new(),
+            derive_seed: String::new(),
+            derive_light: false,
+            backups: Vec::new(),
+            backup_index: 0,
+            restore_armed: None,
+            export_path: String::new(),
+        }
+        .with_selected_theme_colors()
+    }
 
-enum FormKeyResult {
-    Continue,
-    Cancel,
-    Submit(CategorySubmitPayload),
-    DeletePreset(usize),
-}
+    fn preview_theme(&self) -> Option<Theme> {
+        let option = self.theme_options.get(self.theme_index)?;
+        let base = [
+            &self.custom_primary,
+            &self.custom_accent,
+            &self.custom_highlight,
+            &self.custom_background,
+            &self.custom_surface,
+            &self.custom_text,
+        ]
+        .map(|value| sanitize_hex_color_input(value));
+        let [Some(primary), Some(accent), Some(highlight), Some(background), Some(surface), Some(text)] =
+            base
+        else {
+            return Some(option.to_saved_theme().to_theme());
+        };
+        let chrome = ThemeChrome {
+            footer_background: sanitize_hex_color_input(&self.custom_footer_background),
+            footer_key: sanitize_hex_color_input(&self.custom_footer_key),
+            footer_label: sanitize_hex_color_input(&self.custom_footer_label),
+            status_bar: sanitize_hex_color_input(&self.custom_status_bar),
+            popup_border: sanitize_hex_color_input(&self.custom_popup_border),
+        };
+        let name = if self.custom_theme_name.trim().is_empty() {
+            option.label.clone()
+        } else {
+            self.custom_theme_name.trim().to_string()
+        };
+        Some(
+            Theme::from_hexes(
+                name,
+                &primary,
+                &accent,
+                &highlight,
+                &background,
+                &surface,
+                &text,
+            )
+            .with_chrome(&chrome),
+        )
+    }
 
-enum ItemFormKeyResult {
-    Continue,
-    Cancel,
-    Submit(ItemFormInput),
-}
+    /// Open the picker on the focused color field. Each field is compared
+    /// against the color it is drawn on or with for the contrast readout.
+    fn color_picker(&self) -> Option<ColorPickerState> {
+        let (label, value, partner_label, partner) = match self.selected_field {
+            SettingsField::DeriveSeed => {
+                return Some(ColorPickerState::new(
+                    ColorPickerTarget::Settings(SettingsField::DeriveSeed),
+                    "Seed Color",
+                    &self.derive_seed,
+                    None,
+                ));
+            }
+            SettingsField::CustomPrimary => {
+                ("Primary", &self.custom_primary, "Text", &self.custom_text)
+            }
+            SettingsField::CustomAccent => (
+                "Accent",
+                &self.custom_accent,
+                "Surface",
+                &self.custom_surface,
+            ),
+            SettingsField::CustomHighlight => (
+                "Highlight",
+                &self.custom_highlight,
+                "Background",
+                &self.custom_background,
+            ),
+            SettingsField::CustomBackground => (
+                "Background",
+                &self.custom_background,
+                "Text",
+                &self.custom_text,
+            ),
+            SettingsField::CustomSurface => {
+                ("Surface", &self.custom_surface, "Text", &self.custom_text)
+            }
+            SettingsField::CustomText => (
+                "Text",
+                &self.custom_text,
+                "Background",
+                &self.custom_background,
+            ),
+            SettingsField::CustomFooterBackground => (
+                "Shortcut Bar Background",
+                &self.custom_footer_background,
+                "Shortcut Labels",
+                &self.custom_footer_label,
+            ),
+            SettingsField::CustomFooterKey => (
+                "Shortcut Keys",
+                &self.custom_footer_key,
+                "Shortcut Bar Background",
+                &self.custom_footer_background,
+            ),
+            SettingsField::CustomFooterLabel => (
+                "Shortcut Labels",
+                &self.custom_footer_label,
+                "Shortcut Bar Background",
+                &self.custom_footer_background,
+            ),
+            SettingsField::CustomStatusBar => (
+                "Status Bar",
+                &self.custom_status_bar,
+                "Text",
+                &self.custom_text,
+            ),
+            SettingsField::CustomPopupBorder => (
+                "Popup Borders",
+                &self.custom_popup_border,
+                "Surface",
+                &self.custom_surface,
+            ),
+            _ => return None,
+        };
+        Some(ColorPickerState::new(
+            ColorPickerTarget::Settings(self.selected_field),
+            label,
+            value,
+            hex_to_rgb(partner).map(|rgb| (partner_label.to_string(), rgb)),
+        ))
+    }
 
-impl CategoryFormState {
-    fn new(index: usize, category: &CategoryState, presets: Vec<ColorPreset>) -> Self {
-        let background = category
-            .colors
-            .as_ref()
-            .and_then(|c| c.background.clone())
-            .map(|value| normalize_hex(&value))
-            .unwrap_or_default();
-        let text = category
-            .colors
-            .as_ref()
-            .and_then(|c| c.text.clone())
-            .map(|value| normalize_hex(&value))
-            .unwrap_or_default();
-        let color_presets = if presets.is_empty() {
-            vec![ColorPreset::new("Default", "#034e68", "#caf0f8")]
-        } else {
-            presets
+    /// Fill the six base colors from the seed (or Primary when no seed is
+    /// given). Bar and border colors are cleared so they derive as well.
+    fn derive_theme(&mut self) {
+        let seed = sanitize_hex_color_input(&self.derive_seed)
+            .or_else(|| sanitize_hex_color_input(&self.custom_primary))
+            .and_then(|hex| hex_to_rgb(&hex));
+        let Some(seed) = seed else {
+            self.error = Some("Enter a seed color (#RRGGBB) to derive a theme".into());
+            return;
         };
-        let palette_index = if !background.is_empty() && !text.is_empty() {
-            color_presets
-                .iter()
-                .position(|preset| preset.matches(&background, &text))
-                .unwrap_or(0)
-        } else {
-            0
-        };        let mut custom_name = String::new();
-        if let Some(preset) = color_presets.get(palette_index) {
-            custom_name = preset.name.clone();
-        } else if !category.name.is_empty() {
-            custom_name = format!("{} Colors", category.name);
+        if self.custom_theme_name.trim().is_empty() {
+            let mode = if self.derive_light { "Light" } else { "Dark" };
+            self.custom_theme_name = format!("{} {mode}", rgb_to_hex(seed));
+        }
+        let theme = derive_theme(self.custom_theme_name.trim(), seed, self.derive_light);
+        self.custom_primary = theme.primary;
+        self.custom_accent = theme.accent;
+        self.custom_highlight = theme.highlight.unwrap_or_default();
+        self.custom_background = theme.background;
+        self.custom_surface = theme.surface;
+        self.custom_text = theme.text;
+        self.custom_footer_key = theme.chrome.footer_key.unwrap_or_default();
+        for value in [
+            &mut self.custom_footer_background,
+            &mut self.custom_footer_label,
+            &mut self.custom_status_bar,
+            &mut self.custom_popup_border,
+        ] {
+            value.clear();
+        }
+    }
+
+    /// The first request for the selected backup only arms the restore; a
+    /// second one for the same backup returns it.
+    fn request_restore(&mut self) -> Option<PathBuf> {
+        let Some(path) = self.backups.get(self.backup_index).cloned() else {
+            self.error = Some("No backups to restore".into());
+            return None;
+        };
+        if self.restore_armed.take().as_ref() == Some(&path) {
+            return Some(path);
         }
+        self.restore_armed = Some(path);
+        None
+    }
 
-        Self {
-            category_index: index,
-            name: category.name.clone(),
-            column_value: category.column.to_string(),
-            selected_field: CategoryField::Name,
-            error: None,
-            color_presets,
-            palette_index,
-            custom_preset_name: custom_name,
-            custom_preset_background: background,
-            custom_preset_text: text,
-        }
+    fn current_deletable_theme_index(&self) -> Option<usize> {
+        self.theme_options
+            .get(self.theme_index)
+            .and_then(|opt| parse_saved_theme_key(&opt.key))
     }
 
-    fn render_lines(&self, app: &AppState) -> (Vec<FormLine>, CategoryFormLayout) {
-        let mut layout = CategoryFormLayout::default();
+    fn render_lines(&self, app: &AppState) -> (Vec<FormLine>, SettingsFormLayout) {
+        let mut layout = SettingsFormLayout::default();
         let mut lines: Vec<FormLine> = Vec::new();
-        lines.push(plain_line(Line::from("Update the category fields below.")));
+        let deletable_index = self.current_deletable_theme_index();
+        if let Some(saved_idx) = deletable_index {
+            layout.delete_saved_theme_index = Some(saved_idx);
+        }
+        lines.push(plain_line(Line::from("Adjust application settings below.")));
 
-        layout.name_line = Some(lines.len());
+        layout.title_line = Some(lines.len());
         lines.push(make_field_line(
-            "Name",
-            &self.name,
-            self.selected_field == CategoryField::Name,
+            "Title (header template)",
+            &self.title,
+            self.selected_field == SettingsField::Title,
+            app,
+        ));
+        layout.status_template_line = Some(lines.len());
+        lines.push(make_field_line(
+            "Status Bar Template",
+            &self.status_template,
+            self.selected_field == SettingsField::StatusTemplate,
             app,
         ));
+        if matches!(
+            self.selected_field,
+            SettingsField::Title | SettingsField::StatusTemplate
+        ) {
+            let segments = TEMPLATE_SEGMENTS
+                .iter()
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>()
+                .join(" ");
+            lines.push(plain_line(Line::from(vec![Span::styled(
+                format!("Segments: {segments}"),
+                Style::default().fg(app.theme.accent),
+            )])));
+        }
 
-        layout.column_line = Some(lines.len());
+        layout.columns_line = Some(lines.len());
         lines.push(make_field_line(
-            "Column",
-            &self.column_value,
-            self.selected_field == CategoryField::Column,
+            "Columns (1-6)",
+            &self.columns_value,
+            self.selected_field == SettingsField::Columns,
             app,
         ));
 
-        if !self.color_presets.is_empty() {
+        lines.push(plain_line(Line::from("")));
+        let (shortcut_line, shortcut_segments, shortcut_width) =
+            build_settings_shortcut_line(app, deletable_index.is_some());
+        layout.shortcut_line = Some(shortcut_line);
+        layout.shortcut_segments = shortcut_segments;
+        layout.shortcut_total_width = shortcut_width;
+        if let Some(error) = &self.error {
+            lines.push(plain_line(Line::from(vec![Span::styled(
+                error.clone(),
+                Style::default().fg(Color::Red),
+            )])));
+        }
+        if !self.theme_options.is_empty() {
             lines.push(plain_line(Line::from("")));
-            layout.presets_heading_line = Some(lines.len());
+            layout.theme_heading_line = Some(lines.len());
             lines.push(plain_line(Line::from(vec![Span::styled(
-                "Color Theme (Tab to focus, ←/→ select)",
+                "Theme Presets (Tab to focus, ←/→ select)",
                 Style::default()
                     .fg(app.theme.accent)
                     .add_modifier(Modifier::BOLD),
             )])));
-            layout.presets_start_line = Some(lines.len());
-            for (idx, preset) in self.color_presets.iter().enumerate() {
-                let is_selected = self.palette_index == idx;
-                let highlight_palette = is_selected && self.selected_field == CategoryField::Palette;
+            layout.theme_list_start = Some(lines.len());
+            for (idx, option) in self.theme_options.iter().enumerate() {
+                let is_active = self.theme_index == idx;
                 let mut label_style = Style::default().fg(app.theme.text);
-                if is_selected {
+                if is_active {
                     label_style = label_style.add_modifier(Modifier::BOLD);
                 }
-                let preview_bg = color_from_hex(&preset.background);
-                let preview_text = color_from_hex(&preset.text).unwrap_or(app.theme.text);
                 let mut spans = vec![Span::styled(
-                    format!("{:>2}. {}", idx + 1, preset.name),
+                    format!("{:>2}. {}", idx + 1, option.label),
                     label_style,
                 )];
-                if let Some(bg) = preview_bg {
+                if let Some(surface) = color_from_hex(&option.surface_hex) {
                     spans.push(Span::raw("  "));
                     spans.push(Span::styled(
                         "     ",
-                        Style::default().bg(bg).fg(preview_text),
+                        Style::default().bg(surface).fg(app.theme.text),
                     ));
                 }
-                spans.push(Span::raw("  "));
-                let mut background_hex_style = Style::default().fg(app.theme.text);
-                let mut divider_style = Style::default().fg(app.theme.text);
-                let mut text_hex_style = Style::default().fg(app.theme.text);
-                if is_selected {
-                    background_hex_style = background_hex_style.add_modifier(Modifier::BOLD);
-                    divider_style = divider_style.add_modifier(Modifier::BOLD);
-                    text_hex_style = text_hex_style.add_modifier(Modifier::BOLD);
+                if let Some(accent) = color_from_hex(&option.accent_hex) {
+                    spans.push(Span::raw(" "));
+                    spans.push(Span::styled(
+                        "     ",
+                        Style::default().bg(accent).fg(app.theme.background),
+                    ));
                 }
-                spans.push(Span::styled(preset.background.clone(), background_hex_style));
-                spans.push(Span::styled(" / ", divider_style));
-                spans.push(Span::styled(preset.text.clone(), text_hex_style));
-                let line = if highlight_palette {
-                    FormLine::highlighted(Line::from(spans))
+                if let Some(highlight) = color_from_hex(&option.highlight_hex) {
+                    spans.push(Span::raw(" "));
+                    spans.push(Span::styled(
+                        "     ",
+                        Style::default().bg(highlight).fg(app.theme.background),
+                    ));
+                }
+                for (label, hex) in [
+                    ("Primary", &option.primary_hex),
+                    ("Accent", &option.accent_hex),
+                    ("Highlight", &option.highlight_hex),
+                    ("Background", &option.background_hex),
+                    ("Surface", &option.surface_hex),
+                    ("Text", &option.text_hex),
+                ] {
+                    spans.push(Span::raw("  "));
+                    let mut color_style = Style::default().fg(app.theme.text);
+                    if is_active {
+                        color_style = color_style.add_modifier(Modifier::BOLD);
+                    }
+                    spans.push(Span::styled(
+                        format!("{} {}", label, hex.to_uppercase()),
+                        color_style,
+                    ));
+                }
+                let line = Line::from(spans);
+                if self.selected_field == SettingsField::Theme && is_active {
+                    lines.push(FormLine::highlighted(line));
                 } else {
-                    FormLine::plain(Line::from(spans))
-                };
-                lines.push(line);
+                    lines.push(FormLine::plain(line));
+                }
             }
-            layout.presets_count = self.color_presets.len();
+            layout.theme_count = self.theme_options.len();
+        }
+        layout.theme_file_path_line = Some(lines.len());
+        lines.push(make_field_line(
+            "Import/Export File (base16 .yaml, Alacritty .toml, Windows Terminal .json)",
+            &self.theme_file_path,
+            self.selected_field == SettingsField::ThemeFilePath,
+            app,
+        ));
+        lines.push(plain_line(Line::from("")));
+        layout.custom_heading_line = Some(lines.len());
+        lines.push(plain_line(Line::from(vec![Span::styled(
+            "Custom Theme Colors (#RRGGBB, leave blank to keep preset)",
+            Style::default()
+                .fg(app.theme.accent)
+                .add_modifier(Modifier::BOLD),
+        )])));
+        layout.custom_name_line = Some(lines.len());
+        lines.push(make_field_line(
+            "Custom Theme Name",
+            &self.custom_theme_name,
+            self.selected_field == SettingsField::CustomName,
+            app,
+        ));
+        layout.derive_seed_line = Some(lines.len());
+        lines.push(make_color_field_line(
+            "Seed Color (derive all colors from it)",
+            &self.derive_seed,
+            self.selected_field == SettingsField::DeriveSeed,
+            color_from_hex(&self.derive_seed),
+            app,
+        ));
+        layout.derive_mode_line = Some(lines.len());
+        let derive_key = app
+            .keymap
+            .key_label(KeyContext::SettingsForm, KeyAction::DeriveTheme)
+            .unwrap_or_default();
+        let toggle_key = app
+            .keymap
+            .key_label(KeyContext::SettingsForm, KeyAction::ToggleDeriveMode)
+            .unwrap_or_default();
+        lines.push(make_field_line(
+            &format!("Derive Mode ({toggle_key} toggles, {derive_key} derives)"),
+            if self.derive_light { "Light" } else { "Dark" },
+            self.selected_field == SettingsField::DeriveMode,
+            app,
+        ));
+        layout.custom_primary_line = Some(lines.len());
+        lines.push(make_color_field_line(
+            "Primary",
+            &self.custom_primary,
+            self.selected_field == SettingsField::CustomPrimary,
+            color_from_hex(&self.custom_primary),
+            app,
+        ));
+        layout.custom_accent_line = Some(lines.len());
+        lines.push(make_color_field_line(
+            "Accent",
+            &self.custom_accent,
+            self.selected_field == SettingsField::CustomAccent,
+            color_from_hex(&self.custom_accent),
+            app,
+        ));
+        layout.custom_highlight_line = Some(lines.len());
+        lines.push(make_color_field_line(
+            "Highlight",
+            &self.custom_highlight,
+            self.selected_field == SettingsField::CustomHighlight,
+            color_from_hex(&self.custom_highlight),
+            app,
+        ));
+        layout.custom_background_line = Some(lines.len());
+        lines.push(make_color_field_line(
+            "Background",
+            &self.custom_background,
+            self.selected_field == SettingsField::CustomBackground,
+            color_from_hex(&self.custom_background),
+            app,
+        ));
+        layout.custom_surface_line = Some(lines.len());
+        lines.push(make_color_field_line(
+            "Surface",
+            &self.custom_surface,
+            self.selected_field == SettingsField::CustomSurface,
+            color_from_hex(&self.custom_surface),
+            app,
+        ));
+        layout.custom_text_line = Some(lines.len());
+        lines.push(make_color_field_line(
+            "Text",
+            &self.custom_text,
+            self.selected_field == SettingsField::CustomText,
+            color_from_hex(&self.custom_text),
+            app,
+        ));
+        if let (Some(text), Some(background)) = (
+            hex_to_rgb(&self.custom_text),
+            hex_to_rgb(&self.custom_background),
+        ) {
+            lines.push(plain_line(contrast_line(
+                "Text on Background contrast",
+                text,
+                background,
+                app,
+            )));
         }
-
         lines.push(plain_line(Line::from("")));
-        layout.custom_heading_line = Some(lines.len());
         lines.push(plain_line(Line::from(vec![Span::styled(
-            "Custom Theme (#RRGGBB)",
+            "Bars and Borders (leave blank to derive from the colors above)",
             Style::default()
                 .fg(app.theme.accent)
                 .add_modifier(Modifier::BOLD),
         )])));
-        layout.custom_name_line = Some(lines.len());
-        lines.push(make_field_line(
-            "Name",
-            &self.custom_preset_name,
-            self.selected_field == CategoryField::CustomPresetName,
+        layout.custom_footer_background_line = Some(lines.len());
+        lines.push(make_color_field_line(
+            "Shortcut Bar Background",
+            &self.custom_footer_background,
+            self.selected_field == SettingsField::CustomFooterBackground,
+            color_from_hex(&self.custom_footer_background),
             app,
         ));
-        layout.custom_background_line = Some(lines.len());
+        layout.custom_footer_key_line = Some(lines.len());
         lines.push(make_color_field_line(
-            "Background",
-            &self.custom_preset_background,
-            self.selected_field == CategoryField::CustomPresetBackground,
-            color_from_hex(&self.custom_preset_background),
+            "Shortcut Keys",
+            &self.custom_footer_key,
+            self.selected_field == SettingsField::CustomFooterKey,
+            color_from_hex(&self.custom_footer_key),
             app,
         ));
-        layout.custom_text_line = Some(lines.len());
+        layout.custom_footer_label_line = Some(lines.len());
         lines.push(make_color_field_line(
-            "Text",
-            &self.custom_preset_text,
-            self.selected_field == CategoryField::CustomPresetText,
-            color_from_hex(&self.custom_preset_text),
+            "Shortcut Labels",
+            &self.custom_footer_label,
+            self.selected_field == SettingsField::CustomFooterLabel,
+            color_from_hex(&self.custom_footer_label),
+            app,
+        ));
+        layout.custom_status_bar_line = Some(lines.len());
+        lines.push(make_color_field_line(
+            "Status Bar",
+            &self.custom_status_bar,
+            self.selected_field == SettingsField::CustomStatusBar,
+            color_from_hex(&self.custom_status_bar),
+            app,
+        ));
+        layout.custom_popup_border_line = Some(lines.len());
+        lines.push(make_color_field_line(
+            "Popup Borders",
+            &self.custom_popup_border,
+            self.selected_field == SettingsField::CustomPopupBorder,
+            color_from_hex(&self.custom_popup_border),
             app,
         ));
-
         lines.push(plain_line(Line::from("")));
-        let (shortcut_line, shortcut_segments, shortcut_width) =
-            build_category_shortcut_line(app, self.has_deletable_preset());
-        layout.shortcut_line = Some(shortcut_line);
-        layout.shortcut_segments = shortcut_segments;
-        layout.shortcut_total_width = shortcut_width;
-        if let Some(error) = &self.error {
-            lines.push(plain_line(Line::from(vec![Span::styled(
-                error.clone(),
-                Style::default().fg(Color::Red),
-            )])));
+        let restore_key = app
+            .keymap
+            .key_label(KeyContext::SettingsForm, KeyAction::RestoreBackup)
+            .unwrap_or_default();
+        lines.push(plain_line(Line::from(vec![Span::styled(
+            format!("Menu Backups (←/→ select, {restore_key} restore)"),
+            Style::default()
+                .fg(app.theme.accent)
+                .add_modifier(Modifier::BOLD),
+        )])));
+        layout.backup_line = Some(lines.len());
+        let backup_value = match self.backups.get(self.backup_index) {
+            Some(path) if self.restore_armed.as_ref() == Some(path) => format!(
+                "{} will replace the current menu; press {restore_key} again to confirm",
+                backup_label(path)
+            ),
+            Some(path) => format!(
+                "{} ({}/{})",
+                backup_label(path),
+                self.backup_index + 1,
+                self.backups.len()
+            ),
+            None => "No backups yet".to_string(),
+        };
+        lines.push(make_field_line(
+            "Restore Backup",
+            &backup_value,
+            self.selected_field == SettingsField::Backup,
+            app,
+        ));
+        lines.push(plain_line(Line::from("")));
+        let export_key = app
+            .keymap
+            .key_label(KeyContext::SettingsForm, KeyAction::ExportMenu)
+            .unwrap_or_default();
+        lines.push(plain_line(Line::from(vec![Span::styled(
+            format!(
+                "Export Menu ({export_key}: .md, .html, .sh or a directory for .desktop files)"
+            ),
+            Style::default()
+                .fg(app.theme.accent)
+                .add_modifier(Modifier::BOLD),
+        )])));
+        layout.export_line = Some(lines.len());
+        lines.push(make_field_line(
+            "Export To",
+            &self.export_path,
+            self.selected_field == SettingsField::ExportPath,
+            app,
+        ));
+        layout.line_count = lines.len();
+        (lines, layout)
+    }
+
+    fn handle_key(&mut self, key: KeyEvent, keymap: &Keymap) -> SettingsFormKeyResult {
+        self.error = None;
+        let armed = self.restore_armed.take();
+        if let Some(action) = keymap.action_for(KeyContext::SettingsForm, &key) {
+            match action {
+                KeyAction::Cancel => return SettingsFormKeyResult::Cancel,
+                KeyAction::Submit => return SettingsFormKeyResult::Submit(self.to_input()),
+                KeyAction::NextField => {
+                    self.next_field();
+                    return SettingsFormKeyResult::Continue;
+                }
+                KeyAction::PreviousField => {
+                    self.previous_field();
+                    return SettingsFormKeyResult::Continue;
+                }
+                KeyAction::PreviousTheme if self.selected_field == SettingsField::Theme => {
+                    self.previous_theme();
+                    return SettingsFormKeyResult::Continue;
+                }
+                KeyAction::NextTheme if self.selected_field == SettingsField::Theme => {
+                    self.next_theme();
+                    return SettingsFormKeyResult::Continue;
+                }
+                KeyAction::PreviousTheme if self.selected_field == SettingsField::Backup => {
+                    // Backups are listed newest first, so "previous" is newer.
+                    self.backup_index = self.backup_index.saturating_sub(1);
+                    return SettingsFormKeyResult::Continue;
+                }
+                KeyAction::NextTheme if self.selected_field == SettingsField::Backup => {
+                    if self.backup_index + 1 < self.backups.len() {
+                        self.backup_index += 1;
+                    }
+                    return SettingsFormKeyResult::Continue;
+                }
+                KeyAction::RestoreBackup => {
+                    self.restore_armed = armed;
+                    return match self.request_restore() {
+                        Some(path) => SettingsFormKeyResult::RestoreBackup(path),
+                        None => SettingsFormKeyResult::Continue,
+                    };
+                }
+                KeyAction::ExportMenu => {
+                    if self.export_path.trim().is_empty() {
+                        self.selected_field = SettingsField::ExportPath;
+                        self.error = Some("Enter a file or directory to export to".into());
+                        return SettingsFormKeyResult::Continue;
+                    }
+                    return SettingsFormKeyResult::ExportMenu(self.export_path.clone());
+                }
+                KeyAction::PickColor if self.color_picker().is_some() => {
+                    return SettingsFormKeyResult::PickColor;
+                }
+                KeyAction::ToggleDeriveMode if self.selected_field == SettingsField::DeriveMode => {
+                    self.derive_light = !self.derive_light;
+                    return SettingsFormKeyResult::Continue;
+                }
+                KeyAction::DeriveTheme => {
+                    self.derive_theme();
+                    return SettingsFormKeyResult::Continue;
+                }
+                KeyAction::ImportTheme => {
+                    return SettingsFormKeyResult::ImportTheme(self.theme_file_path.clone());
+                }
+                KeyAction::ExportTheme => {
+                    return match self.theme_options.get(self.theme_index) {
+                        Some(option) => SettingsFormKeyResult::ExportTheme(
+                            option.clone(),
+                            self.theme_file_path.clone(),
+                        ),
+                        None => SettingsFormKeyResult::Continue,
+                    };
+                }
+                KeyAction::DeleteTheme if self.selected_field == SettingsField::Theme => {
+                    return match self.current_deletable_theme_index() {
+                        Some(index) => SettingsFormKeyResult::DeleteSavedTheme(index),
+                        None => SettingsFormKeyResult::Continue,
+                    };
+                }
+                KeyAction::ClearField => {
+                    if let Some(value) = self.active_value_mut() {
+                        value.clear();
+                    }
+                    return SettingsFormKeyResult::Continue;
+                }
+                _ => {}
+            }
+        }
+        match key.code {
+            KeyCode::Backspace => {
+                if let Some(value) = self.active_value_mut() {
+                    value.pop();
+                }
+                SettingsFormKeyResult::Continue
+            }
+            KeyCode::Char(c) => {
+                if self.selected_field != SettingsField::Theme
+                    && !key.modifiers.contains(KeyModifiers::CONTROL)
+                {
+                    if let Some(value) = self.active_value_mut() {
+                        value.push(c);
+                    }
+                }
+                SettingsFormKeyResult::Continue
+            }
+            _ => SettingsFormKeyResult::Continue,
+        }
+    }
+
+    fn to_input(&self) -> SettingsFormInput {
+        SettingsFormInput {
+            title: self.title.clone(),
+            status_template: self.status_template.clone(),
+            columns: self.columns_value.clone(),
+            theme_key: self
+                .theme_options
+                .get(self.theme_index)
+                .map(|opt| opt.key.clone())
+                .unwrap_or_default(),
+            custom_primary: self.custom_primary.clone(),
+            custom_accent: self.custom_accent.clone(),
+            custom_background: self.custom_background.clone(),
+            custom_surface: self.custom_surface.clone(),
+            custom_text: self.custom_text.clone(),
+            custom_highlight: self.custom_highlight.clone(),
+            custom_footer_background: self.custom_footer_background.clone(),
+            custom_footer_key: self.custom_footer_key.clone(),
+            custom_footer_label: self.custom_footer_label.clone(),
+            custom_status_bar: self.custom_status_bar.clone(),
+            custom_popup_border: self.custom_popup_border.clone(),
+            custom_theme_name: self.custom_theme_name.clone(),
+        }
+    }
+
+    fn next_field(&mut self) {
+        self.selected_field = match self.selected_field {
+            SettingsField::Title => SettingsField::StatusTemplate,
+            SettingsField::StatusTemplate => SettingsField::Columns,
+            SettingsField::Columns => SettingsField::Theme,
+            SettingsField::Theme => SettingsField::ThemeFilePath,
+            SettingsField::ThemeFilePath => SettingsField::CustomName,
+            SettingsField::CustomName => SettingsField::DeriveSeed,
+            SettingsField::DeriveSeed => SettingsField::DeriveMode,
+            SettingsField::DeriveMode => SettingsField::CustomPrimary,
+            SettingsField::CustomPrimary => SettingsField::CustomAccent,
+            SettingsField::CustomAccent => SettingsField::CustomHighlight,
+            SettingsField::CustomHighlight => SettingsField::CustomBackground,
+            SettingsField::CustomBackground => SettingsField::CustomSurface,
+            SettingsField::CustomSurface => SettingsField::CustomText,
+            SettingsField::CustomText => SettingsField::CustomFooterBackground,
+            SettingsField::CustomFooterBackground => SettingsField::CustomFooterKey,
+            SettingsField::CustomFooterKey => SettingsField::CustomFooterLabel,
+            SettingsField::CustomFooterLabel => SettingsField::CustomStatusBar,
+            SettingsField::CustomStatusBar => SettingsField::CustomPopupBorder,
+            SettingsField::CustomPopupBorder => SettingsField::Backup,
+            SettingsField::Backup => SettingsField::ExportPath,
+            SettingsField::ExportPath => SettingsField::Title,
+        };
+    }
+
+    fn previous_field(&mut self) {
+        self.selected_field = match self.selected_field {
+            SettingsField::Title => SettingsField::ExportPath,
+            SettingsField::ExportPath => SettingsField::Backup,
+            SettingsField::Backup => SettingsField::CustomPopupBorder,
+            SettingsField::StatusTemplate => SettingsField::Title,
+            SettingsField::Columns => SettingsField::StatusTemplate,
+            SettingsField::Theme => SettingsField::Columns,
+            SettingsField::ThemeFilePath => SettingsField::Theme,
+            SettingsField::CustomName => SettingsField::ThemeFilePath,
+            SettingsField::DeriveSeed => SettingsField::CustomName,
+            SettingsField::DeriveMode => SettingsField::DeriveSeed,
+            SettingsField::CustomPrimary => SettingsField::DeriveMode,
+            SettingsField::CustomAccent => SettingsField::CustomPrimary,
+            SettingsField::CustomHighlight => SettingsField::CustomAccent,
+            SettingsField::CustomBackground => SettingsField::CustomHighlight,
+            SettingsField::CustomSurface => SettingsField::CustomBackground,
+            SettingsField::CustomText => SettingsField::CustomSurface,
+            SettingsField::CustomFooterBackground => SettingsField::CustomText,
+            SettingsField::CustomFooterKey => SettingsField::CustomFooterBackground,
+            SettingsField::CustomFooterLabel => SettingsField::CustomFooterKey,
+            SettingsField::CustomStatusBar => SettingsField::CustomFooterLabel,
+            SettingsField::CustomPopupBorder => SettingsField::CustomStatusBar,
+        };
+    }
+
+    fn active_value_mut(&mut self) -> Option<&mut String> {
+        match self.selected_field {
+            SettingsField::Title => Some(&mut self.title),
+            SettingsField::StatusTemplate => Some(&mut self.status_template),
+            SettingsField::Columns => Some(&mut self.columns_value),
+            SettingsField::Theme => None,
+            SettingsField::ThemeFilePath => Some(&mut self.theme_file_path),
+            SettingsField::CustomName => Some(&mut self.custom_theme_name),
+            SettingsField::DeriveSeed => Some(&mut self.derive_seed),
+            SettingsField::DeriveMode => None,
+            SettingsField::CustomPrimary => Some(&mut self.custom_primary),
+            SettingsField::CustomAccent => Some(&mut self.custom_accent),
+            SettingsField::CustomBackground => Some(&mut self.custom_background),
+            SettingsField::CustomSurface => Some(&mut self.custom_surface),
+            SettingsField::CustomText => Some(&mut self.custom_text),
+            SettingsField::CustomHighlight => Some(&mut self.custom_highlight),
+            SettingsField::CustomFooterBackground => Some(&mut self.custom_footer_background),
+            SettingsField::CustomFooterKey => Some(&mut self.custom_footer_key),
+            SettingsField::CustomFooterLabel => Some(&mut self.custom_footer_label),
+            SettingsField::CustomStatusBar => Some(&mut self.custom_status_bar),
+            SettingsField::CustomPopupBorder => Some(&mut self.custom_popup_border),
+            SettingsField::Backup => None,
+            SettingsField::ExportPath => Some(&mut self.export_path),
+        }
+    }
+
+    fn with_selected_theme_colors(mut self) -> Self {
+        self.populate_custom_fields_from_selection();
+        self
+    }
+
+    fn populate_custom_fields_from_selection(&mut self) {
+        if self.theme_options.is_empty() {
+            return;
+        }
+        if self.theme_index >= self.theme_options.len() {
+            self.theme_index = 0;
+        }
+        if let Some(option) = self.theme_options.get(self.theme_index) {
+            self.custom_primary = option.primary_hex.clone();
+            self.custom_accent = option.accent_hex.clone();
+            self.custom_highlight = option.highlight_hex.clone();
+            self.custom_background = option.background_hex.clone();
+            self.custom_surface = option.surface_hex.clone();
+            self.custom_text = option.text_hex.clone();
+            self.custom_footer_background = option.footer_background_hex.clone();
+            self.custom_footer_key = option.footer_key_hex.clone();
+            self.custom_footer_label = option.footer_label_hex.clone();
+            self.custom_status_bar = option.status_bar_hex.clone();
+            self.custom_popup_border = option.popup_border_hex.clone();
+            if parse_saved_theme_key(&option.key).is_some() {
+                self.custom_theme_name = option.label.clone();
+            } else if option.key != CUSTOM_THEME_KEY {
+                self.custom_theme_name.clear();
+            }
+        }
+    }
+
+    fn next_theme(&mut self) {
+        if self.theme_options.is_empty() {
+            return;
+        }
+        self.theme_index = (self.theme_index + 1) % self.theme_options.len();
+        self.populate_custom_fields_from_selection();
+    }
+
+    fn previous_theme(&mut self) {
+        if self.theme_options.is_empty() {
+            return;
+        }
+        if self.theme_index == 0 {
+            self.theme_index = self.theme_options.len() - 1;
+        } else {
+            self.theme_index -= 1;
+        }
+        self.populate_custom_fields_from_selection();
+    }
+}
+
+fn build_settings_shortcut_line(
+    app: &AppState,
+    include_delete: bool,
+) -> (Line<'static>, Vec<SettingsShortcutSegment>, u16) {
+    let mut spans: Vec<Span<'static>> = Vec::new();
+    let mut segments: Vec<SettingsShortcutSegment> = Vec::new();
+    let mut cursor: u16 = 0;
+    let key_style = Style::default()
+        .fg(app.theme.footer_key)
+        .add_modifier(Modifier::BOLD);
+    let label_style = Style::default().fg(app.theme.footer_label);
+    let keymap = &app.keymap;
+    let entries: Vec<(String, &str, SettingsShortcutAction)> = [
+        (
+            KeyAction::NextField,
+            " Move",
+            SettingsShortcutAction::NextField,
+        ),
+        (KeyAction::Submit, " Save", SettingsShortcutAction::Submit),
+        (
+            KeyAction::Cancel,
+            " Cancel/Exit",
+            SettingsShortcutAction::Cancel,
+        ),
+        (
+            KeyAction::PickColor,
+            " Pick Color",
+            SettingsShortcutAction::PickColor,
+        ),
+        (
+            KeyAction::DeriveTheme,
+            " Derive",
+            SettingsShortcutAction::DeriveTheme,
+        ),
+        (
+            KeyAction::RestoreBackup,
+            " Restore Backup",
+            SettingsShortcutAction::RestoreBackup,
+        ),
+        (
+            KeyAction::ExportMenu,
+            " Export Menu",
+            SettingsShortcutAction::ExportMenu,
+        ),
+        (
+            KeyAction::ImportTheme,
+            " Import",
+            SettingsShortcutAction::ImportTheme,
+        ),
+        (
+            KeyAction::ExportTheme,
+            " Export",
+            SettingsShortcutAction::ExportTheme,
+        ),
+        (KeyAction::Help, " Help", SettingsShortcutAction::Help),
+    ]
+    .into_iter()
+    .filter_map(|(key_action, label, action)| {
+        keymap
+            .key_label(KeyContext::SettingsForm, key_action)
+            .map(|key| (key, label, action))
+    })
+    .collect();
+
+    for (idx, (key, label, action)) in entries.iter().enumerate() {
+        if idx > 0 {
+            spans.push(Span::styled(" | ", label_style));
+            cursor = cursor.saturating_add(3);
         }
-        layout.line_count = lines.len();
-        (lines, layout)
+        let entry_start = cursor;
+        cursor = cursor.saturating_add(key.chars().count() as u16);
+        spans.push(Span::styled(key.clone(), key_style));
+        if !label.is_empty() {
+            spans.push(Span::styled(*label, label_style));
+            cursor = cursor.saturating_add(label.chars().count() as u16);
+        }
+        segments.push(SettingsShortcutSegment {
+            start: entry_start,
+            end: cursor,
+            action: *action,
+        });
     }
-    fn handle_key(&mut self, key: KeyEvent) -> FormKeyResult {
-        self.error = None;
-        match key.code {
-            KeyCode::Esc => FormKeyResult::Cancel,
-            KeyCode::Enter => {
-                if self.selected_field == CategoryField::Palette {
-                    if self.has_deletable_preset() {
-                        if let Some(index) = self.current_custom_preset_index() {
-                            return FormKeyResult::DeletePreset(index);
-                        }
-                    }
-                    match self.build_submission() {
-                        Ok(input) => FormKeyResult::Submit(input),
-                        Err(err) => {
-                            self.error = Some(err);
-                            FormKeyResult::Continue
+
+    if !entries.is_empty() {
+        spans.push(Span::styled(" | ", label_style));
+        cursor = cursor.saturating_add(3);
+    }
+
+    let previous_key = keymap
+        .key_label(KeyContext::SettingsForm, KeyAction::PreviousTheme)
+        .unwrap_or_default();
+    let next_key = keymap
+        .key_label(KeyContext::SettingsForm, KeyAction::NextTheme)
+        .unwrap_or_default();
+    let left_start = cursor;
+    cursor = cursor.saturating_add(previous_key.chars().count() as u16);
+    spans.push(Span::styled(previous_key, key_style));
+    segments.push(SettingsShortcutSegment {
+        start: left_start,
+        end: cursor,
+        action: SettingsShortcutAction::PreviousTheme,
+    });
+    spans.push(Span::styled("/", label_style));
+    cursor = cursor.saturating_add(1);
+    let right_start = cursor;
+    cursor = cursor.saturating_add(next_key.chars().count() as u16);
+    spans.push(Span::styled(next_key, key_style));
+    spans.push(Span::styled(" Select", label_style));
+    cursor = cursor.saturating_add(" Select".len() as u16);
+    segments.push(SettingsShortcutSegment {
+        start: right_start,
+        end: cursor,
+        action: SettingsShortcutAction::NextTheme,
+    });
+
+    let delete_key = keymap.key_label(KeyContext::SettingsForm, KeyAction::DeleteTheme);
+    if let (true, Some(delete_key)) = (include_delete, delete_key) {
+        spans.push(Span::styled(" | ", label_style));
+        cursor = cursor.saturating_add(3);
+        let entry_start = cursor;
+        cursor = cursor.saturating_add(delete_key.chars().count() as u16);
+        spans.push(Span::styled(delete_key, key_style));
+        spans.push(Span::styled(" Delete theme", label_style));
+        cursor = cursor.saturating_add(" Delete theme".len() as u16);
+        segments.push(SettingsShortcutSegment {
+            start: entry_start,
+            end: cursor,
+            action: SettingsShortcutAction::DeleteTheme,
+        });
+    }
+
+    (Line::from(spans), segments, cursor)
+}
+
+#[derive(Clone)]
+struct Theme {
+    name: String,
+    primary: Color,
+    accent: Color,
+    #[allow(dead_code)]
+    highlight: Color,
+    background: Color,
+    surface: Color,
+    text: Color,
+    footer_background: Color,
+    footer_key: Color,
+    footer_label: Color,
+    status_bar: Color,
+    popup_border: Color,
+    primary_hex: String,
+    accent_hex: String,
+    highlight_hex: String,
+    background_hex: String,
+    surface_hex: String,
+    text_hex: String,
+    footer_background_hex: String,
+    footer_key_hex: String,
+    footer_label_hex: String,
+    status_bar_hex: String,
+    popup_border_hex: String,
+}
+
+impl Theme {
+    fn load(path: &Path) -> Result<Self> {
+        if path.exists() {
+            let data = fs::read_to_string(path)?;
+            match serde_json::from_str::<ThemeFile>(&data) {
+                Ok(file) => {
+                    if let Some(skin) = file.skin {
+                        if let Some(theme) = Theme::from_name(&skin) {
+                            return Ok(theme);
                         }
                     }
-                } else {
-                    match self.build_submission() {
-                        Ok(input) => FormKeyResult::Submit(input),
-                        Err(err) => {
-                            self.error = Some(err);
-                            FormKeyResult::Continue
+                    if let Some(colors) = file.colors {
+                        if let Some(theme) = Theme::from_colors("Custom", &colors) {
+                            return Ok(theme);
                         }
                     }
                 }
+                Err(_) => {}
             }
-            KeyCode::Tab | KeyCode::Down => {
-                self.next_field();
-                FormKeyResult::Continue
-            }
-            KeyCode::BackTab | KeyCode::Up => {
-                self.previous_field();
-                FormKeyResult::Continue
-            }
-            KeyCode::Left if self.selected_field == CategoryField::Palette => {
-                self.previous_palette();
-                FormKeyResult::Continue
-            }
-            KeyCode::Right if self.selected_field == CategoryField::Palette => {
-                self.next_palette();
-                FormKeyResult::Continue
-            }
-            KeyCode::Backspace => {
-                if let Some(value) = self.active_value_mut() {
-                    value.pop();
-                }
-                FormKeyResult::Continue
-            }
-            KeyCode::Delete => {
-                if let Some(value) = self.active_value_mut() {
-                    value.clear();
-                    FormKeyResult::Continue
-                } else if self.selected_field == CategoryField::Palette
-                    && self.has_deletable_preset()
-                {
-                    if let Some(index) = self.current_custom_preset_index() {
-                        FormKeyResult::DeletePreset(index)
-                    } else {
-                        FormKeyResult::Continue
-                    }
-                } else {
-                    FormKeyResult::Continue
-                }
-            }
-            KeyCode::Char('d') | KeyCode::Char('D')
-                if self.has_deletable_preset()
-                    && self.selected_field == CategoryField::Palette =>
-            {
-                if let Some(index) = self.current_custom_preset_index() {
-                    FormKeyResult::DeletePreset(index)
-                } else {
-                    FormKeyResult::Continue
-                }
-            }
-            KeyCode::Char(c) => {
-                if self.selected_field != CategoryField::Palette
-                    && !key.modifiers.contains(KeyModifiers::CONTROL) {
-                    if let Some(value) = self.active_value_mut() {
-                        value.push(c);
-                    }
-                }
-                FormKeyResult::Continue
-            }
-            _ => FormKeyResult::Continue,
         }
+        let theme = Theme::from_name("nord").unwrap();
+        theme.save(path)?;
+        Ok(theme)
     }
 
-    fn build_submission(&self) -> Result<CategorySubmitPayload, String> {
-        let background_result = parse_color_field(&self.custom_preset_background)?;
-        let text_result = parse_color_field(&self.custom_preset_text)?;
-
-        let background_value = background_result.clone().unwrap_or_default();
-        let text_value = text_result.clone().unwrap_or_default();
+    fn save(&self, path: &Path) -> Result<()> {
+        let file = ThemeFile {
+            skin: Some(self.name.clone()),
+            colors: Some(ThemeColorOverrides {
+                primary: Some(self.primary_hex.clone()),
+                accent: Some(self.accent_hex.clone()),
+                highlight: Some(self.highlight_hex.clone()),
+                background: Some(self.background_hex.clone()),
+                surface: Some(self.surface_hex.clone()),
+                text: Some(self.text_hex.clone()),
+                chrome: self.chrome(),
+            }),
+        };
+        let _lock = lock_file(path)?;
+        write_atomic(path, serde_json::to_string_pretty(&file)?.as_bytes())
+    }
 
-        let mut new_preset: Option<CustomPresetInput> = None;
-        if let (Some(bg), Some(txt)) = (background_result, text_result) {
-            let exists = self
-                .color_presets
-                .iter()
-                .any(|preset| hex_strings_equal(&preset.background, &bg) && hex_strings_equal(&preset.text, &txt));
-            if !exists {
-                let name = if self.custom_preset_name.trim().is_empty() {
-                    "Custom Theme".to_string()
-                } else {
-                    self.custom_preset_name.trim().to_string()
-                };
-                new_preset = Some(CustomPresetInput {
-                    name,
-                    background: bg.clone(),
-                    text: txt.clone(),
-                });
-            }
-        }
+    fn from_name(name: &str) -> Option<Self> {
+        THEME_PRESETS
+            .iter()
+            .find(|preset| preset.0 == name)
+            .map(|(_, def)| Theme::from_definition(name.to_string(), def))
+    }
 
-        Ok(CategorySubmitPayload {
-            form: CategoryFormInput {
-                category_index: self.category_index,
-                name: self.name.clone(),
-                column_value: self.column_value.clone(),
-                background: background_value,
-                text_color: text_value,
-            },
-            new_preset,
-        })
+    fn from_definition(name: String, def: &ThemeDefinition) -> Theme {
+        Theme::from_hexes(
+            name,
+            def.primary,
+            def.accent,
+            def.highlight,
+            def.background,
+            def.surface,
+            def.text,
+        )
+        .with_chrome(&def.chrome())
     }
 
-    fn next_field(&mut self) {
-        let has_palette = !self.color_presets.is_empty();
-        self.selected_field = match self.selected_field {
-            CategoryField::Name => CategoryField::Column,
-            CategoryField::Column => {
-                if has_palette {
-                    CategoryField::Palette
-                } else {
-                    CategoryField::CustomPresetName
-                }
-            }
-            CategoryField::Palette => CategoryField::CustomPresetName,
-            CategoryField::CustomPresetName => CategoryField::CustomPresetBackground,
-            CategoryField::CustomPresetBackground => CategoryField::CustomPresetText,
-            CategoryField::CustomPresetText => CategoryField::Name,
-        };
+    fn from_colors(name: &str, overrides: &ThemeColorOverrides) -> Option<Self> {
+        Some(
+            Theme::from_hexes(
+                name.to_string(),
+                overrides.primary.as_deref().unwrap_or("#5E81AC"),
+                overrides.accent.as_deref().unwrap_or("#D08770"),
+                overrides
+                    .highlight
+                    .as_deref()
+                    .or(overrides.accent.as_deref())
+                    .unwrap_or("#76B3C5"),
+                overrides.background.as_deref().unwrap_or("#3B4252"),
+                overrides.surface.as_deref().unwrap_or("#4C566A"),
+                overrides.text.as_deref().unwrap_or("#ECEFF4"),
+            )
+            .with_chrome(&overrides.chrome),
+        )
     }
-    fn previous_field(&mut self) {
-        let has_palette = !self.color_presets.is_empty();
-        self.selected_field = match self.selected_field {
-            CategoryField::Name => CategoryField::CustomPresetText,
-            CategoryField::Column => CategoryField::Name,
-            CategoryField::Palette => CategoryField::Column,
-            CategoryField::CustomPresetName => {
-                if has_palette {
-                    CategoryField::Palette
-                } else {
-                    CategoryField::Column
+
+    fn from_hexes(
+        name: String,
+        primary: &str,
+        accent: &str,
+        highlight: &str,
+        background: &str,
+        surface: &str,
+        text: &str,
+    ) -> Theme {
+        let primary_color = color_from_hex(primary).unwrap_or(Color::Blue);
+        let accent_color = color_from_hex(accent).unwrap_or(Color::Cyan);
+        let highlight_color = color_from_hex(highlight).unwrap_or(Color::Cyan);
+        let background_color = color_from_hex(background).unwrap_or(Color::Black);
+        let text_color = color_from_hex(text).unwrap_or(Color::White);
+        // Themes without explicit chrome colors get a shortcut bar in the
+        // highlight color, a status bar in the primary color and borders in
+        // the text color.
+        Theme {
+            name,
+            primary: primary_color,
+            accent: accent_color,
+            highlight: highlight_color,
+            background: background_color,
+            surface: color_from_hex(surface).unwrap_or(Color::DarkGray),
+            text: text_color,
+            footer_background: highlight_color,
+            footer_key: accent_color,
+            footer_label: background_color,
+            status_bar: primary_color,
+            popup_border: text_color,
+            primary_hex: normalize_hex(primary),
+            accent_hex: normalize_hex(accent),
+            highlight_hex: normalize_hex(highlight),
+            background_hex: normalize_hex(background),
+            surface_hex: normalize_hex(surface),
+            text_hex: normalize_hex(text),
+            footer_background_hex: normalize_hex(highlight),
+            footer_key_hex: normalize_hex(accent),
+            footer_label_hex: normalize_hex(background),
+            status_bar_hex: normalize_hex(primary),
+            popup_border_hex: normalize_hex(text),
+        }
+    }
+
+    /// Replace the derived chrome colors with any slots set in `chrome`.
+    fn with_chrome(mut self, chrome: &ThemeChrome) -> Theme {
+        let slots = [
+            (
+                &chrome.footer_background,
+                &mut self.footer_background,
+                &mut self.footer_background_hex,
+            ),
+            (
+                &chrome.footer_key,
+                &mut self.footer_key,
+                &mut self.footer_key_hex,
+            ),
+            (
+                &chrome.footer_label,
+                &mut self.footer_label,
+                &mut self.footer_label_hex,
+            ),
+            (
+                &chrome.status_bar,
+                &mut self.status_bar,
+                &mut self.status_bar_hex,
+            ),
+            (
+                &chrome.popup_border,
+                &mut self.popup_border,
+                &mut self.popup_border_hex,
+            ),
+        ];
+        for (value, color, hex) in slots {
+            if let Some(valid) = value.as_deref().and_then(sanitize_hex_color_input) {
+                if let Some(parsed) = color_from_hex(&valid) {
+                    *color = parsed;
+                    *hex = valid;
                 }
             }
-            CategoryField::CustomPresetBackground => CategoryField::CustomPresetName,
-            CategoryField::CustomPresetText => CategoryField::CustomPresetBackground,
-        };
-    }
-    fn active_value_mut(&mut self) -> Option<&mut String> {
-        match self.selected_field {
-            CategoryField::Name => Some(&mut self.name),
-            CategoryField::Column => Some(&mut self.column_value),
-            CategoryField::CustomPresetName => Some(&mut self.custom_preset_name),
-            CategoryField::CustomPresetBackground => Some(&mut self.custom_preset_background),
-            CategoryField::CustomPresetText => Some(&mut self.custom_preset_text),
-            CategoryField::Palette => None,
         }
+        self
     }
 
-    fn has_deletable_preset(&self) -> bool {
-        self.current_custom_preset_index().is_some()
+    fn chrome(&self) -> ThemeChrome {
+        ThemeChrome {
+            footer_background: Some(self.footer_background_hex.clone()),
+            footer_key: Some(self.footer_key_hex.clone()),
+            footer_label: Some(self.footer_label_hex.clone()),
+            status_bar: Some(self.status_bar_hex.clone()),
+            popup_border: Some(self.popup_border_hex.clone()),
+        }
     }
+}
 
-    fn current_custom_preset_index(&self) -> Option<usize> {
-        self.color_presets
-            .get(self.palette_index)
-            .and_then(|preset| preset.custom_index)
-    }
-    fn refresh_presets(&mut self, presets: Vec<ColorPreset>) {
-        self.color_presets = presets;
-        if self.color_presets.is_empty() {
-            self.palette_index = 0;
-        } else {
-            if self.palette_index >= self.color_presets.len() {
-                self.palette_index = self.color_presets.len() - 1;
-            }
-            self.apply_selected_palette();
+#[derive(Serialize, Deserialize)]
+struct ThemeFile {
+    skin: Option<String>,
+    colors: Option<ThemeColorOverrides>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ThemeColorOverrides {
+    primary: Option<String>,
+    accent: Option<String>,
+    #[serde(default)]
+    highlight: Option<String>,
+    background: Option<String>,
+    surface: Option<String>,
+    text: Option<String>,
+    #[serde(flatten)]
+    chrome: ThemeChrome,
+}
+
+/// Colors for the shortcut bar, status bar and popup borders. Any slot left
+/// out is derived from the theme's base colors, so files written before these
+/// slots existed keep loading.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+struct ThemeChrome {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    footer_background: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    footer_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    footer_label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    status_bar: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    popup_border: Option<String>,
+}
+
+struct ThemeDefinition {
+    name: &'static str,
+    primary: &'static str,
+    accent: &'static str,
+    highlight: &'static str,
+    background: &'static str,
+    surface: &'static str,
+    text: &'static str,
+    footer_background: &'static str,
+    footer_key: &'static str,
+    footer_label: &'static str,
+    status_bar: &'static str,
+    popup_border: &'static str,
+}
+
+impl ThemeDefinition {
+    fn chrome(&self) -> ThemeChrome {
+        ThemeChrome {
+            footer_background: Some(self.footer_background.to_string()),
+            footer_key: Some(self.footer_key.to_string()),
+            footer_label: Some(self.footer_label.to_string()),
+            status_bar: Some(self.status_bar.to_string()),
+            popup_border: Some(self.popup_border.to_string()),
         }
     }
+}
 
-    fn focus_palette_index(&mut self, index: usize) {
-        if self.color_presets.is_empty() {
-            return;
+impl ThemeOption {
+    fn from_definition(key: &str, def: &ThemeDefinition) -> Self {
+        ThemeOption::from_theme(key, def.name, &Theme::from_definition(key.to_string(), def))
+    }
+
+    fn to_saved_theme(&self) -> SavedTheme {
+        SavedTheme {
+            name: self.label.clone(),
+            primary: self.primary_hex.clone(),
+            accent: self.accent_hex.clone(),
+            highlight: Some(self.highlight_hex.clone()),
+            background: self.background_hex.clone(),
+            surface: self.surface_hex.clone(),
+            text: self.text_hex.clone(),
+            chrome: ThemeChrome {
+                footer_background: Some(self.footer_background_hex.clone()),
+                footer_key: Some(self.footer_key_hex.clone()),
+                footer_label: Some(self.footer_label_hex.clone()),
+                status_bar: Some(self.status_bar_hex.clone()),
+                popup_border: Some(self.popup_border_hex.clone()),
+            },
         }
-        self.palette_index = index.min(self.color_presets.len() - 1);
-        self.selected_field = CategoryField::Palette;
-        self.apply_selected_palette();
     }
 
-    fn next_palette(&mut self) {
-        if self.color_presets.is_empty() {
-            return;
+    fn from_theme(key: &str, label: &str, theme: &Theme) -> Self {
+        Self {
+            key: key.to_string(),
+            label: label.to_string(),
+            primary_hex: theme.primary_hex.clone(),
+            accent_hex: theme.accent_hex.clone(),
+            highlight_hex: theme.highlight_hex.clone(),
+            background_hex: theme.background_hex.clone(),
+            surface_hex: theme.surface_hex.clone(),
+            text_hex: theme.text_hex.clone(),
+            footer_background_hex: theme.footer_background_hex.clone(),
+            footer_key_hex: theme.footer_key_hex.clone(),
+            footer_label_hex: theme.footer_label_hex.clone(),
+            status_bar_hex: theme.status_bar_hex.clone(),
+            popup_border_hex: theme.popup_border_hex.clone(),
         }
-        self.palette_index = (self.palette_index + 1) % self.color_presets.len();
-        self.apply_selected_palette();
     }
+}
 
-    fn previous_palette(&mut self) {
-        if self.color_presets.is_empty() {
-            return;
-        }
-        if self.palette_index == 0 {
-            self.palette_index = self.color_presets.len() - 1;
-        } else {
-            self.palette_index -= 1;
+const THEME_PRESETS: &[(&str, ThemeDefinition)] = &[
+    (
+        "classic",
+        ThemeDefinition {
+            name: "Midnight Classic",
+            primary: "#6FC6D4",
+            accent: "#0F1A2B",
+            highlight: "#9FE6EC",
+            background: "#314A63",
+            surface: "#416079",
+            text: "#F2F8FF",
+            footer_background: "#9FE6EC",
+            footer_key: "#A34700",
+            footer_label: "#1B2B3D",
+            status_bar: "#6FC6D4",
+            popup_border: "#9FE6EC",
+        },
+    ),
+    (
+        "nord",
+        ThemeDefinition {
+            name: "Nord",
+            primary: "#5E81AC",
+            accent: "#D08770",
+            highlight: "#76B3C5",
+            background: "#3B4252",
+            surface: "#4C566A",
+            text: "#ECEFF4",
+            footer_background: "#76B3C5",
+            footer_key: "#FDA009",
+            footer_label: "#2E3544",
+            status_bar: "#5E81AC",
+            popup_border: "#88C0D0",
+        },
+    ),
+    (
+        "gruvbox",
+        ThemeDefinition {
+            name: "Midnight Mist",
+            primary: "#66C3CF",
+            accent: "#0E1828",
+            highlight: "#96DFE8",
+            background: "#2C4156",
+            surface: "#3B5A72",
+            text: "#F4FBFF",
+            footer_background: "#96DFE8",
+            footer_key: "#9C4A00",
+            footer_label: "#1A2A3A",
+            status_bar: "#66C3CF",
+            popup_border: "#96DFE8",
+        },
+    ),
+    (
+        "dracula",
+        ThemeDefinition {
+            name: "Midnight Dusk",
+            primary: "#6BC6D7",
+            accent: "#142033",
+            highlight: "#A1E6EC",
+            background: "#2E475F",
+            surface: "#3E5D78",
+            text: "#F5FBFF",
+            footer_background: "#A1E6EC",
+            footer_key: "#9E3D6B",
+            footer_label: "#1C2C3E",
+            status_bar: "#6BC6D7",
+            popup_border: "#A1E6EC",
+        },
+    ),
+    (
+        "monokai",
+        ThemeDefinition {
+            name: "Midnight Deep",
+            primary: "#5FC0CD",
+            accent: "#0D1725",
+            highlight: "#92DDE7",
+            background: "#243A50",
+            surface: "#344F68",
+            text: "#F6FCFF",
+            footer_background: "#92DDE7",
+            footer_key: "#8F4A00",
+            footer_label: "#15253A",
+            status_bar: "#5FC0CD",
+            popup_border: "#92DDE7",
+        },
+    ),
+];
+
+fn is_preset_theme_key(key: &str) -> bool {
+    THEME_PRESETS
+        .iter()
+        .any(|(preset_key, _)| preset_key == &key)
+}
+
+struct ThemeFileEntry {
+    stem: String,
+    theme: SavedTheme,
+}
+
+fn file_theme_key(stem: &str) -> String {
+    format!("{FILE_THEME_PREFIX}{stem}")
+}
+
+fn parse_file_theme_key(key: &str) -> Option<&str> {
+    key.strip_prefix(FILE_THEME_PREFIX)
+}
+
+/// Read every `*.json` theme in the themes directory, sorted by file name.
+/// Files that fail to parse are skipped and reported back as warnings.
+fn load_theme_directory(dir: &Path) -> (Vec<ThemeFileEntry>, Vec<String>) {
+    let mut entries = Vec::new();
+    let mut warnings = Vec::new();
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return (entries, warnings);
+    };
+    let mut paths: Vec<PathBuf> = read_dir
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("json"))
+        .collect();
+    paths.sort();
+    for path in paths {
+        let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        let parsed = fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|data| Ok(serde_json::from_str::<SavedTheme>(&data)?));
+        match parsed {
+            Ok(theme) => entries.push(ThemeFileEntry {
+                stem: stem.to_string(),
+                theme,
+            }),
+            Err(err) => warnings.push(format!("{}: {err}", path.display())),
         }
-        self.apply_selected_palette();
     }
+    (entries, warnings)
+}
 
-    fn apply_selected_palette(&mut self) {
-        if let Some(preset) = self.color_presets.get(self.palette_index) {
-            self.custom_preset_background = preset.background.clone();
-            self.custom_preset_text = preset.text.clone();
-            self.custom_preset_name = preset.name.clone();
+fn theme_file_stem(name: &str) -> String {
+    let mut stem = String::new();
+    for ch in name.trim().chars() {
+        if ch.is_ascii_alphanumeric() {
+            stem.push(ch.to_ascii_lowercase());
+        } else if !stem.is_empty() && !stem.ends_with('-') {
+            stem.push('-');
         }
     }
+    let stem = stem.trim_end_matches('-');
+    if stem.is_empty() {
+        "theme".to_string()
+    } else {
+        stem.to_string()
+    }
 }
 
-impl ItemFormState {
-    fn new(
-        target: Option<(usize, usize)>,
-        label: String,
-        command: String,
-        info: String,
-        category: String,
-        fallback_category: String,
-        pause: bool,
-        available_categories: Vec<String>,
-    ) -> Self {
-        Self {
-            target,
-            label,
-            command,
-            info,
-            category,
-            fallback_category,
-            pause,
-            available_categories,
-            selected_field: ItemField::Label,
-            error: None,
-            mode_label: if target.is_some() {
-                "Edit Menu Item"
-            } else {
-                "New Menu Item"
-            },
-        }
+/// Write `theme` into `dir` under a name derived from the theme's. A different
+/// theme already using that name is kept; the new file gets a `-2`, `-3`, ...
+/// suffix instead. Re-importing an identical theme reuses its file.
+fn write_theme_file(dir: &Path, theme: &SavedTheme) -> Result<String> {
+    let base = theme_file_stem(&theme.name);
+    let data = serde_json::to_string_pretty(theme)?;
+    let mut stem = base.clone();
+    for suffix in 2.. {
+        let path = dir.join(format!("{stem}.json"));
+        match fs::read_to_string(&path) {
+            Ok(existing) if existing == data => return Ok(stem),
+            Ok(_) => stem = format!("{base}-{suffix}"),
+            Err(_) => break,
+        }
+    }
+    let path = dir.join(format!("{stem}.json"));
+    write_atomic(&path, data.as_bytes())
+        .with_context(|| format!("Unable to write {}", path.display()))?;
+    Ok(stem)
+}
+
+fn export_theme(theme: &SavedTheme, path: &Path) -> Result<()> {
+    write_atomic(path, serde_json::to_string_pretty(theme)?.as_bytes())
+        .with_context(|| format!("Unable to write {}", path.display()))
+}
+
+/// Convert a base16 YAML, Alacritty TOML or Windows Terminal JSON color
+/// scheme into themes. JSON files written by the theme export are accepted
+/// as well. Also returns a notice for every scheme that had to be skipped.
+fn import_color_scheme(path: &Path) -> Result<(Vec<SavedTheme>, Vec<String>)> {
+    let data =
+        fs::read_to_string(path).with_context(|| format!("Unable to read {}", path.display()))?;
+    let fallback_name = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .map(filename_to_label)
+        .unwrap_or_else(|| "Imported Theme".into());
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match extension.as_str() {
+        "yaml" | "yml" => Ok((
+            vec![import_base16_scheme(&data, &fallback_name)?],
+            Vec::new(),
+        )),
+        "toml" => Ok((
+            vec![import_alacritty_scheme(&data, &fallback_name)?],
+            Vec::new(),
+        )),
+        "json" => import_windows_terminal_schemes(&data, &fallback_name),
+        other => anyhow::bail!("Unsupported theme format '.{other}'"),
     }
+}
 
-    fn handle_key(&mut self, key: KeyEvent) -> ItemFormKeyResult {
-        self.error = None;
-        match key.code {
-            KeyCode::Esc => ItemFormKeyResult::Cancel,
-            KeyCode::Enter => ItemFormKeyResult::Submit(self.to_input()),
-            KeyCode::Tab | KeyCode::Down => {
-                self.next_field();
-                ItemFormKeyResult::Continue
-            }
-            KeyCode::BackTab | KeyCode::Up => {
-                self.previous_field();
-                ItemFormKeyResult::Continue
-            }
-            KeyCode::Char(' ') if self.selected_field == ItemField::Pause => {
-                self.pause = !self.pause;
-                ItemFormKeyResult::Continue
-            }
-            KeyCode::Backspace => {
-                if let Some(value) = self.active_value_mut() {
-                    value.pop();
-                }
-                ItemFormKeyResult::Continue
-            }
-            KeyCode::Delete => {
-                if let Some(value) = self.active_value_mut() {
-                    value.clear();
-                }
-                ItemFormKeyResult::Continue
-            }
-            KeyCode::Char(c) => {
-                if self.selected_field != ItemField::Pause
-                    && !key.modifiers.contains(KeyModifiers::CONTROL)
-                {
-                    if let Some(value) = self.active_value_mut() {
-                        value.push(c);
-                    }
-                }
-                ItemFormKeyResult::Continue
+fn scheme_hex(value: &str) -> Option<String> {
+    let value = value.trim();
+    let value = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .unwrap_or(value);
+    sanitize_hex_color_input(value)
+}
+
+fn import_base16_scheme(data: &str, fallback_name: &str) -> Result<SavedTheme> {
+    let scheme: serde_yaml::Value = serde_yaml::from_str(data).context("Invalid base16 YAML")?;
+    // Newer tinted-theming files nest the slots under `palette`.
+    let palette = scheme.get("palette").unwrap_or(&scheme);
+    let color = |slot: &str| {
+        palette
+            .get(slot)
+            .or_else(|| palette.get(slot.to_ascii_lowercase()))
+            .and_then(|value| value.as_str())
+            .and_then(scheme_hex)
+            .with_context(|| format!("base16 scheme is missing {slot}"))
+    };
+    let name = scheme
+        .get("scheme")
+        .or_else(|| scheme.get("name"))
+        .and_then(|value| value.as_str())
+        .unwrap_or(fallback_name);
+    Ok(SavedTheme {
+        name: name.to_string(),
+        primary: color("base0D")?,
+        accent: color("base09")?,
+        highlight: Some(color("base0C")?),
+        background: color("base00")?,
+        surface: color("base01")?,
+        text: color("base05")?,
+        chrome: ThemeChrome::default(),
+    })
+}
+
+fn import_alacritty_scheme(data: &str, fallback_name: &str) -> Result<SavedTheme> {
+    let root: toml::Value = toml::from_str(data).context("Invalid Alacritty TOML")?;
+    let colors = root
+        .get("colors")
+        .context("Alacritty theme has no [colors] table")?;
+    let lookup = |section: &str, slot: &str| {
+        colors
+            .get(section)
+            .and_then(|table| table.get(slot))
+            .and_then(|value| value.as_str())
+            .and_then(scheme_hex)
+    };
+    let color = |section: &str, slot: &str| {
+        lookup(section, slot)
+            .with_context(|| format!("Alacritty theme is missing colors.{section}.{slot}"))
+    };
+    let background = color("primary", "background")?;
+    let text = color("primary", "foreground")?;
+    let surface = lookup("bright", "black").unwrap_or_else(|| mix_hex(&background, &text, 0.12));
+    Ok(SavedTheme {
+        name: fallback_name.to_string(),
+        primary: color("normal", "blue")?,
+        accent: color("normal", "yellow")?,
+        highlight: Some(color("normal", "cyan")?),
+        background,
+        surface,
+        text,
+        chrome: ThemeChrome::default(),
+    })
+}
+
+/// Every usable scheme of a Windows Terminal settings file or scheme; a
+/// broken scheme is skipped with a notice unless none is usable.
+fn import_windows_terminal_schemes(
+    data: &str,
+    fallback_name: &str,
+) -> Result<(Vec<SavedTheme>, Vec<String>)> {
+    let root: serde_json::Value = serde_json::from_str(data).context("Invalid JSON")?;
+    if root.get("primary").is_some() {
+        return Ok((vec![serde_json::from_value(root)?], Vec::new()));
+    }
+    let schemes: Vec<&serde_json::Value> =
+        match root.get("schemes").and_then(|list| list.as_array()) {
+            Some(list) => list.iter().collect(),
+            None => vec![&root],
+        };
+    if schemes.is_empty() {
+        anyhow::bail!("No color schemes found");
+    }
+    let mut themes = Vec::new();
+    let mut skipped = Vec::new();
+    for (index, scheme) in schemes.into_iter().enumerate() {
+        let converted = (|| -> Result<SavedTheme> {
+            let lookup = |slot: &str| {
+                scheme
+                    .get(slot)
+                    .and_then(|value| value.as_str())
+                    .and_then(scheme_hex)
+            };
+            let color = |slot: &str| {
+                lookup(slot).with_context(|| format!("Windows Terminal scheme is missing {slot}"))
+            };
+            let background = color("background")?;
+            let text = color("foreground")?;
+            let surface =
+                lookup("brightBlack").unwrap_or_else(|| mix_hex(&background, &text, 0.12));
+            Ok(SavedTheme {
+                name: scheme
+                    .get("name")
+                    .and_then(|value| value.as_str())
+                    .unwrap_or(fallback_name)
+                    .to_string(),
+                primary: color("blue")?,
+                accent: color("yellow")?,
+                highlight: Some(color("cyan")?),
+                background,
+                surface,
+                text,
+                chrome: ThemeChrome::default(),
+            })
+        })();
+        match converted {
+            Ok(theme) => themes.push(theme),
+            Err(err) => {
+                let name = scheme
+                    .get("name")
+                    .and_then(|value| value.as_str())
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("scheme {}", index + 1));
+                skipped.push(format!("skipped {name}: {err}"));
             }
-            _ => ItemFormKeyResult::Continue,
         }
     }
-
-    fn to_input(&self) -> ItemFormInput {
-        ItemFormInput {
-            target: self.target,
-            label: self.label.clone(),
-            command: self.command.clone(),
-            info: self.info.clone(),
-            category: self.category.clone(),
-            fallback_category: self.fallback_category.clone(),
-            pause: self.pause,
-        }
+    if themes.is_empty() {
+        anyhow::bail!("No usable color schemes: {}", skipped.join("; "));
     }
+    Ok((themes, skipped))
+}
 
-    fn next_field(&mut self) {
-        self.selected_field = match self.selected_field {
-            ItemField::Label => ItemField::Command,
-            ItemField::Command => ItemField::Description,
-            ItemField::Description => ItemField::Category,
-            ItemField::Category => ItemField::Pause,
-            ItemField::Pause => ItemField::Label,
-        };
-    }
+fn saved_theme_key(index: usize) -> String {
+    format!("{SAVED_THEME_PREFIX}{index}")
+}
 
-    fn previous_field(&mut self) {
-        self.selected_field = match self.selected_field {
-            ItemField::Label => ItemField::Pause,
-            ItemField::Command => ItemField::Label,
-            ItemField::Description => ItemField::Command,
-            ItemField::Category => ItemField::Description,
-            ItemField::Pause => ItemField::Category,
-        };
+fn parse_saved_theme_key(key: &str) -> Option<usize> {
+    key.strip_prefix(SAVED_THEME_PREFIX)?.parse::<usize>().ok()
+}
+
+#[derive(Clone, Copy)]
+struct FooterShortcut {
+    label: &'static str,
+    action: KeyAction,
+}
+
+struct FooterSegment {
+    start: u16,
+    end: u16,
+    action: KeyAction,
+}
+
+struct FooterLineData {
+    line: Line<'static>,
+    segments: Vec<FooterSegment>,
+    total_width: u16,
+}
+
+const FOOTER_SHORTCUTS: &[FooterShortcut] = &[
+    FooterShortcut {
+        label: " Help",
+        action: KeyAction::Help,
+    },
+    FooterShortcut {
+        label: " Exit",
+        action: KeyAction::Quit,
+    },
+    FooterShortcut {
+        label: " Edit",
+        action: KeyAction::Edit,
+    },
+    FooterShortcut {
+        label: " Run/Toggle",
+        action: KeyAction::Activate,
+    },
+    FooterShortcut {
+        label: " New Item",
+        action: KeyAction::NewItem,
+    },
+    FooterShortcut {
+        label: " Delete",
+        action: KeyAction::Delete,
+    },
+    FooterShortcut {
+        label: " Settings",
+        action: KeyAction::Settings,
+    },
+    FooterShortcut {
+        label: " Scan ./import",
+        action: KeyAction::ScanBin,
+    },
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum KeyContext {
+    Main,
+    ItemForm,
+    CategoryForm,
+    SettingsForm,
+    ImportList,
+    ColorPicker,
+}
+
+impl KeyContext {
+    const ALL: [KeyContext; 6] = [
+        KeyContext::Main,
+        KeyContext::ItemForm,
+        KeyContext::CategoryForm,
+        KeyContext::SettingsForm,
+        KeyContext::ImportList,
+        KeyContext::ColorPicker,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            KeyContext::Main => "Main View",
+            KeyContext::ItemForm => "Item Form",
+            KeyContext::CategoryForm => "Category Form",
+            KeyContext::SettingsForm => "Settings",
+            KeyContext::ImportList => "Import",
+            KeyContext::ColorPicker => "Color Picker",
+        }
     }
 
-    fn active_value_mut(&mut self) -> Option<&mut String> {
-        match self.selected_field {
-            ItemField::Label => Some(&mut self.label),
-            ItemField::Command => Some(&mut self.command),
-            ItemField::Description => Some(&mut self.info),
-            ItemField::Category => Some(&mut self.category),
-            ItemField::Pause => None,
+    fn label(self) -> &'static str {
+        match self {
+            KeyContext::Main => "main view",
+            KeyContext::ItemForm => "item form",
+            KeyContext::CategoryForm => "category form",
+            KeyContext::SettingsForm => "settings form",
+            KeyContext::ImportList => "import list",
+            KeyContext::ColorPicker => "color picker",
         }
     }
 }
 
-struct SettingsFormState {
-    title: String,
-    columns_value: String,
-    theme_options: Vec<ThemeOption>,
-    theme_index: usize,
-    selected_field: SettingsField,
-    error: Option<String>,
-    custom_primary: String,
-    custom_accent: String,
-    custom_background: String,
-    custom_surface: String,
-    custom_text: String,
-    custom_highlight: String,
-    custom_theme_name: String,
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum KeyAction {
+    Quit,
+    MoveUp,
+    MoveDown,
+    Activate,
+    ToggleCategory,
+    Reload,
+    ShowInfo,
+    NewItem,
+    Edit,
+    Delete,
+    Settings,
+    ThemeSettings,
+    TitleSettings,
+    ScanBin,
+    ImportTasks,
+    ImportAliases,
+    ToggleSelection,
+    ToggleAll,
+    NextField,
+    PreviousField,
+    Submit,
+    Cancel,
+    ClearField,
+    TogglePause,
+    OpenEditor,
+    PreviousPalette,
+    NextPalette,
+    DeletePreset,
+    PreviousTheme,
+    NextTheme,
+    DeleteTheme,
+    ImportTheme,
+    ExportTheme,
+    PickColor,
+    DeriveTheme,
+    ToggleDeriveMode,
+    ToggleEmphasis,
+    RestoreBackup,
+    ExportMenu,
+    NextProfile,
+    PreviousProfile,
+    Decrease,
+    Increase,
+    DecreaseMore,
+    IncreaseMore,
+    SetMinimum,
+    SetMaximum,
+    ToggleColorModel,
+    Help,
 }
 
-#[derive(Default)]
-struct SettingsFormLayout {
-    line_count: usize,
-    title_line: Option<usize>,
-    columns_line: Option<usize>,
-    theme_heading_line: Option<usize>,
-    theme_list_start: Option<usize>,
-    theme_count: usize,
-    custom_heading_line: Option<usize>,
-    delete_saved_theme_line: Option<usize>,
-    delete_saved_theme_index: Option<usize>,
-    shortcut_line: Option<Line<'static>>,
-    shortcut_segments: Vec<SettingsShortcutSegment>,
-    shortcut_total_width: u16,
-    custom_name_line: Option<usize>,
-    custom_primary_line: Option<usize>,
-    custom_accent_line: Option<usize>,
-    custom_background_line: Option<usize>,
-    custom_surface_line: Option<usize>,
-    custom_text_line: Option<usize>,
-    custom_highlight_line: Option<usize>,
+struct KeyBindingSpec {
+    context: KeyContext,
+    action: KeyAction,
+    name: &'static str,
+    description: &'static str,
+    group: &'static str,
+    defaults: &'static [&'static str],
+    /// Only fires on a non-text field, so a plain character here does not block typing.
+    field_scoped: bool,
+}
+
+const KEY_BINDING_SPECS: &[KeyBindingSpec] = &[
+    KeyBindingSpec {
+        context: KeyContext::Main,
+        action: KeyAction::Quit,
+        name: "quit",
+        description: "Quit",
+        group: "Application",
+        defaults: &["q", "Esc"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::Main,
+        action: KeyAction::MoveUp,
+        name: "move_up",
+        description: "Move selection up",
+        group: "Navigation",
+        defaults: &["Up", "k"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::Main,
+        action: KeyAction::MoveDown,
+        name: "move_down",
+        description: "Move selection down",
+        group: "Navigation",
+        defaults: &["Down", "j"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::Main,
+        action: KeyAction::Activate,
+        name: "activate",
+        description: "Run item / toggle category",
+        group: "Navigation",
+        defaults: &["Enter"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::Main,
+        action: KeyAction::ToggleCategory,
+        name: "toggle_category",
+        description: "Expand or collapse category",
+        group: "Navigation",
+        defaults: &["Space"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::Main,
+        action: KeyAction::Reload,
+        name: "reload",
+        description: "Reload configuration from disk",
+        group: "Application",
+        defaults: &["r"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::Main,
+        action: KeyAction::ShowInfo,
+        name: "show_info",
+        description: "Show item details",
+        group: "Items",
+        defaults: &["i"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::Main,
+        action: KeyAction::NewItem,
+        name: "new_item",
+        description: "Add a new item",
+        group: "Items",
+        defaults: &["n"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::Main,
+        action: KeyAction::Edit,
+        name: "edit",
+        description: "Edit item or category",
+        group: "Items",
+        defaults: &["e"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::Main,
+        action: KeyAction::Delete,
+        name: "delete",
+        description: "Delete selected item",
+        group: "Items",
+        defaults: &["d"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::Main,
+        action: KeyAction::Settings,
+        name: "settings",
+        description: "Open settings",
+        group: "Application",
+        defaults: &["s"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::Main,
+        action: KeyAction::ThemeSettings,
+        name: "theme_settings",
+        description: "Open settings at the theme list",
+        group: "Application",
+        defaults: &["t"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::Main,
+        action: KeyAction::TitleSettings,
+        name: "title_settings",
+        description: "Open settings at the title",
+        group: "Application",
+        defaults: &["Ctrl+t"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::Main,
+        action: KeyAction::ScanBin,
+        name: "scan_bin",
+        description: "Import executables from ./import",
+        group: "Items",
+        defaults: &["Ctrl+b"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::Main,
+        action: KeyAction::ImportTasks,
+        name: "import_tasks",
+        description: "Import Makefile, npm, just or Cargo tasks from a directory",
+        group: "Items",
+        defaults: &["Ctrl+p"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::Main,
+        action: KeyAction::ImportAliases,
+        name: "import_aliases",
+        description: "Import aliases and functions from ~/.bashrc, ~/.bash_aliases and ~/.zshrc",
+        group: "Items",
+        defaults: &["Ctrl+a"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::Main,
+        action: KeyAction::NextProfile,
+        name: "next_profile",
+        description: "Switch to the next profile",
+        group: "Profiles",
+        defaults: &["Tab"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::Main,
+        action: KeyAction::PreviousProfile,
+        name: "previous_profile",
+        description: "Switch to the previous profile",
+        group: "Profiles",
+        defaults: &["Shift+Tab"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::Main,
+        action: KeyAction::Help,
+        name: "help",
+        description: "Show key and mouse bindings",
+        group: "Application",
+        defaults: &["?", "F1"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::ItemForm,
+        action: KeyAction::NextField,
+        name: "next_field",
+        description: "Next field",
+        group: "Fields",
+        defaults: &["Tab", "Down"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::ItemForm,
+        action: KeyAction::PreviousField,
+        name: "previous_field",
+        description: "Previous field",
+        group: "Fields",
+        defaults: &["Shift+Tab", "Up"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::ItemForm,
+        action: KeyAction::Submit,
+        name: "submit",
+        description: "Save item",
+        group: "Form",
+        defaults: &["Enter"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::ItemForm,
+        action: KeyAction::Cancel,
+        name: "cancel",
+        description: "Cancel",
+        group: "Form",
+        defaults: &["Esc"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::ItemForm,
+        action: KeyAction::ClearField,
+        name: "clear_field",
+        description: "Clear field",
+        group: "Fields",
+        defaults: &["Delete"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::ItemForm,
+        action: KeyAction::TogglePause,
+        name: "toggle_pause",
+        description: "Toggle pause after run",
+        group: "Fields",
+        defaults: &["Space"],
+        field_scoped: true,
+    },
+    KeyBindingSpec {
+        context: KeyContext::ItemForm,
+        action: KeyAction::ToggleEmphasis,
+        name: "toggle_emphasis",
+        description: "Toggle bold, dim or italic",
+        group: "Fields",
+        defaults: &["Space"],
+        field_scoped: true,
+    },
+    KeyBindingSpec {
+        context: KeyContext::ItemForm,
+        action: KeyAction::PreviousPalette,
+        name: "previous_style",
+        description: "Previous style preset or profile",
+        group: "Fields",
+        defaults: &["Left"],
+        field_scoped: true,
+    },
+    KeyBindingSpec {
+        context: KeyContext::ItemForm,
+        action: KeyAction::NextPalette,
+        name: "next_style",
+        description: "Next style preset or profile",
+        group: "Fields",
+        defaults: &["Right"],
+        field_scoped: true,
+    },
+    KeyBindingSpec {
+        context: KeyContext::ItemForm,
+        action: KeyAction::PickColor,
+        name: "pick_color",
+        description: "Open the color picker for the focused color",
+        group: "Fields",
+        defaults: &["F2"],
+        field_scoped: true,
+    },
+    KeyBindingSpec {
+        context: KeyContext::ItemForm,
+        action: KeyAction::OpenEditor,
+        name: "open_editor",
+        description: "Edit command or description in $EDITOR",
+        group: "Fields",
+        defaults: &["Ctrl+e"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::ItemForm,
+        action: KeyAction::Help,
+        name: "help",
+        description: "Show key and mouse bindings",
+        group: "Form",
+        defaults: &["F1"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::CategoryForm,
+        action: KeyAction::NextField,
+        name: "next_field",
+        description: "Next field",
+        group: "Fields",
+        defaults: &["Tab", "Down"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::CategoryForm,
+        action: KeyAction::PreviousField,
+        name: "previous_field",
+        description: "Previous field",
+        group: "Fields",
+        defaults: &["Shift+Tab", "Up"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::CategoryForm,
+        action: KeyAction::Submit,
+        name: "submit",
+        description: "Save category",
+        group: "Form",
+        defaults: &["Enter"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::CategoryForm,
+        action: KeyAction::Cancel,
+        name: "cancel",
+        description: "Cancel",
+        group: "Form",
+        defaults: &["Esc"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::CategoryForm,
+        action: KeyAction::ClearField,
+        name: "clear_field",
+        description: "Clear field",
+        group: "Fields",
+        defaults: &["Delete"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::CategoryForm,
+        action: KeyAction::PreviousPalette,
+        name: "previous_palette",
+        description: "Previous color palette",
+        group: "Palettes",
+        defaults: &["Left"],
+        field_scoped: true,
+    },
+    KeyBindingSpec {
+        context: KeyContext::CategoryForm,
+        action: KeyAction::NextPalette,
+        name: "next_palette",
+        description: "Next color palette",
+        group: "Palettes",
+        defaults: &["Right"],
+        field_scoped: true,
+    },
+    KeyBindingSpec {
+        context: KeyContext::CategoryForm,
+        action: KeyAction::DeletePreset,
+        name: "delete_preset",
+        description: "Delete custom palette",
+        group: "Palettes",
+        defaults: &["d", "D"],
+        field_scoped: true,
+    },
+    KeyBindingSpec {
+        context: KeyContext::CategoryForm,
+        action: KeyAction::PickColor,
+        name: "pick_color",
+        description: "Open the color picker for the focused color",
+        group: "Fields",
+        defaults: &["F2"],
+        field_scoped: true,
+    },
+    KeyBindingSpec {
+        context: KeyContext::CategoryForm,
+        action: KeyAction::Help,
+        name: "help",
+        description: "Show key and mouse bindings",
+        group: "Form",
+        defaults: &["F1"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::SettingsForm,
+        action: KeyAction::NextField,
+        name: "next_field",
+        description: "Next field",
+        group: "Fields",
+        defaults: &["Tab", "Down"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::SettingsForm,
+        action: KeyAction::PreviousField,
+        name: "previous_field",
+        description: "Previous field",
+        group: "Fields",
+        defaults: &["Shift+Tab", "Up"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::SettingsForm,
+        action: KeyAction::Submit,
+        name: "submit",
+        description: "Save settings",
+        group: "Form",
+        defaults: &["Enter"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::SettingsForm,
+        action: KeyAction::Cancel,
+        name: "cancel",
+        description: "Cancel",
+        group: "Form",
+        defaults: &["Esc"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::SettingsForm,
+        action: KeyAction::ClearField,
+        name: "clear_field",
+        description: "Clear field",
+        group: "Fields",
+        defaults: &["Delete"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::SettingsForm,
+        action: KeyAction::PreviousTheme,
+        name: "previous_theme",
+        description: "Previous theme or newer backup",
+        group: "Themes",
+        defaults: &["Left"],
+        field_scoped: true,
+    },
+    KeyBindingSpec {
+        context: KeyContext::SettingsForm,
+        action: KeyAction::NextTheme,
+        name: "next_theme",
+        description: "Next theme or older backup",
+        group: "Themes",
+        defaults: &["Right"],
+        field_scoped: true,
+    },
+    KeyBindingSpec {
+        context: KeyContext::SettingsForm,
+        action: KeyAction::DeleteTheme,
+        name: "delete_theme",
+        description: "Delete saved theme",
+        group: "Themes",
+        defaults: &["d", "D"],
+        field_scoped: true,
+    },
+    KeyBindingSpec {
+        context: KeyContext::SettingsForm,
+        action: KeyAction::PickColor,
+        name: "pick_color",
+        description: "Open the color picker for the focused color",
+        group: "Fields",
+        defaults: &["F2"],
+        field_scoped: true,
+    },
+    KeyBindingSpec {
+        context: KeyContext::SettingsForm,
+        action: KeyAction::DeriveTheme,
+        name: "derive_theme",
+        description: "Derive all custom colors from the seed color",
+        group: "Themes",
+        defaults: &["Ctrl+g"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::SettingsForm,
+        action: KeyAction::ToggleDeriveMode,
+        name: "toggle_derive_mode",
+        description: "Switch between a dark and a light derived theme",
+        group: "Themes",
+        defaults: &["Space"],
+        field_scoped: true,
+    },
+    KeyBindingSpec {
+        context: KeyContext::SettingsForm,
+        action: KeyAction::RestoreBackup,
+        name: "restore_backup",
+        description: "Restore the selected menu backup",
+        group: "Form",
+        defaults: &["Ctrl+r"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::SettingsForm,
+        action: KeyAction::ExportMenu,
+        name: "export_menu",
+        description: "Export the menu to the export path field",
+        group: "Form",
+        defaults: &["Ctrl+x"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::SettingsForm,
+        action: KeyAction::ImportTheme,
+        name: "import_theme",
+        description: "Import the theme file at the path field",
+        group: "Themes",
+        defaults: &["Ctrl+o"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::SettingsForm,
+        action: KeyAction::ExportTheme,
+        name: "export_theme",
+        description: "Export the selected theme to the path field",
+        group: "Themes",
+        defaults: &["Ctrl+e"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::SettingsForm,
+        action: KeyAction::Help,
+        name: "help",
+        description: "Show key and mouse bindings",
+        group: "Form",
+        defaults: &["F1"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::ImportList,
+        action: KeyAction::MoveUp,
+        name: "move_up",
+        description: "Previous entry",
+        group: "Checklist",
+        defaults: &["Up"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::ImportList,
+        action: KeyAction::MoveDown,
+        name: "move_down",
+        description: "Next entry",
+        group: "Checklist",
+        defaults: &["Down"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::ImportList,
+        action: KeyAction::ToggleSelection,
+        name: "toggle_selection",
+        description: "Check or uncheck the entry",
+        group: "Checklist",
+        defaults: &["Space"],
+        field_scoped: true,
+    },
+    KeyBindingSpec {
+        context: KeyContext::ImportList,
+        action: KeyAction::ToggleAll,
+        name: "toggle_all",
+        description: "Check or uncheck every entry",
+        group: "Checklist",
+        defaults: &["Ctrl+a"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::ImportList,
+        action: KeyAction::Submit,
+        name: "submit",
+        description: "Scan the directory or import the checked entries",
+        group: "Form",
+        defaults: &["Enter"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::ImportList,
+        action: KeyAction::Cancel,
+        name: "cancel",
+        description: "Back to the directory, or cancel",
+        group: "Form",
+        defaults: &["Esc"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::ImportList,
+        action: KeyAction::ClearField,
+        name: "clear_field",
+        description: "Clear the directory",
+        group: "Form",
+        defaults: &["Delete"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::ImportList,
+        action: KeyAction::Help,
+        name: "help",
+        description: "Show key and mouse bindings",
+        group: "Form",
+        defaults: &["F1"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::ColorPicker,
+        action: KeyAction::MoveUp,
+        name: "move_up",
+        description: "Previous slider",
+        group: "Picker",
+        defaults: &["Up"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::ColorPicker,
+        action: KeyAction::MoveDown,
+        name: "move_down",
+        description: "Next slider, then the palette",
+        group: "Picker",
+        defaults: &["Down"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::ColorPicker,
+        action: KeyAction::Decrease,
+        name: "decrease",
+        description: "Lower the slider or pick the previous swatch",
+        group: "Picker",
+        defaults: &["Left"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::ColorPicker,
+        action: KeyAction::Increase,
+        name: "increase",
+        description: "Raise the slider or pick the next swatch",
+        group: "Picker",
+        defaults: &["Right"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::ColorPicker,
+        action: KeyAction::DecreaseMore,
+        name: "decrease_more",
+        description: "Lower the slider by 10",
+        group: "Picker",
+        defaults: &["Shift+Left", "PageDown"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::ColorPicker,
+        action: KeyAction::IncreaseMore,
+        name: "increase_more",
+        description: "Raise the slider by 10",
+        group: "Picker",
+        defaults: &["Shift+Right", "PageUp"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::ColorPicker,
+        action: KeyAction::SetMinimum,
+        name: "set_minimum",
+        description: "Set the slider to its minimum",
+        group: "Picker",
+        defaults: &["Home"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::ColorPicker,
+        action: KeyAction::SetMaximum,
+        name: "set_maximum",
+        description: "Set the slider to its maximum",
+        group: "Picker",
+        defaults: &["End"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::ColorPicker,
+        action: KeyAction::ToggleColorModel,
+        name: "toggle_color_model",
+        description: "Switch the sliders between RGB and HSL",
+        group: "Picker",
+        defaults: &["Tab", "Shift+Tab", "m"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::ColorPicker,
+        action: KeyAction::Submit,
+        name: "submit",
+        description: "Apply the color",
+        group: "Form",
+        defaults: &["Enter"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::ColorPicker,
+        action: KeyAction::Cancel,
+        name: "cancel",
+        description: "Cancel",
+        group: "Form",
+        defaults: &["Esc"],
+        field_scoped: false,
+    },
+    KeyBindingSpec {
+        context: KeyContext::ColorPicker,
+        action: KeyAction::Help,
+        name: "help",
+        description: "Show key and mouse bindings",
+        group: "Form",
+        defaults: &["F1"],
+        field_scoped: false,
+    },
+];
+
+const TEXT_EDITING_HELP: &[(&str, &str)] = &[
+    ("Backspace", "Delete the last character"),
+    ("Any character", "Type into the focused field"),
+];
+
+const MOUSE_BINDINGS: &[(KeyContext, &str, &str)] = &[
+    (KeyContext::Main, "Click item", "Run the item"),
+    (
+        KeyContext::Main,
+        "Click category",
+        "Expand or collapse the category",
+    ),
+    (
+        KeyContext::Main,
+        "Click shortcut bar",
+        "Run the clicked shortcut",
+    ),
+    (KeyContext::CategoryForm, "Click field", "Focus the field"),
+    (
+        KeyContext::CategoryForm,
+        "Click palette",
+        "Select the palette",
+    ),
+    (
+        KeyContext::CategoryForm,
+        "Click shortcut bar",
+        "Run the clicked shortcut",
+    ),
+    (KeyContext::SettingsForm, "Click field", "Focus the field"),
+    (KeyContext::SettingsForm, "Click theme", "Select the theme"),
+    (
+        KeyContext::SettingsForm,
+        "Click shortcut bar",
+        "Run the clicked shortcut",
+    ),
+    (
+        KeyContext::ColorPicker,
+        "Click/drag slider",
+        "Set the slider to the pointer position",
+    ),
+    (
+        KeyContext::ColorPicker,
+        "Scroll slider",
+        "Adjust the slider",
+    ),
+    (
+        KeyContext::ColorPicker,
+        "Click swatch",
+        "Use the palette color",
+    ),
+];
+
+struct HelpOverlay {
+    context: KeyContext,
+    scroll: u16,
+}
+
+/// State of the screen shown when menus.json cannot be parsed, at startup or
+/// on reload.
+struct MenuRecovery {
+    path: PathBuf,
+    message: String,
+    line: usize,
+    column: usize,
+    source_line: Option<String>,
+    at_startup: bool,
+    selected: usize,
+    notice: Option<String>,
+}
+
+impl MenuRecovery {
+    fn from_error(path: &Path, err: &anyhow::Error, at_startup: bool) -> Self {
+        let (line, column, message) =
+            if let Some(parse_error) = err.downcast_ref::<serde_json::Error>() {
+                let mut message = parse_error.to_string();
+                // The location is shown on its own line.
+                if let Some(index) = message.rfind(" at line ") {
+                    message.truncate(index);
+                }
+                (parse_error.line(), parse_error.column(), message)
+            } else if let Some(parse_error) = err.downcast_ref::<toml::de::Error>() {
+                let (line, column) = parse_error
+                    .span()
+                    .and_then(|span| {
+                        let data = fs::read_to_string(path).ok()?;
+                        let before = data.get(..span.start)?;
+                        let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+                        Some((
+                            before.matches('\n').count() + 1,
+                            before[line_start..].chars().count() + 1,
+                        ))
+                    })
+                    .unwrap_or((0, 0));
+                (line, column, parse_error.message().to_string())
+            } else if let Some(parse_error) = err.downcast_ref::<serde_yaml::Error>() {
+                let mut message = parse_error.to_string();
+                if let Some(index) = message.rfind(" at line ") {
+                    message.truncate(index);
+                }
+                match parse_error.location() {
+                    Some(location) => (location.line(), location.column(), message),
+                    None => (0, 0, message),
+                }
+            } else {
+                (0, 0, format!("{err:#}"))
+            };
+        let source_line = (line > 0)
+            .then(|| fs::read_to_string(path).ok())
+            .flatten()
+            .and_then(|data| {
+                data.lines()
+                    .nth(line - 1)
+                    .map(|text| text.replace('\t', " "))
+            })
+            .map(|text| text.chars().take(200).collect());
+        Self {
+            path: path.to_path_buf(),
+            message,
+            line,
+            column,
+            source_line,
+            at_startup,
+            selected: 0,
+            notice: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum RecoveryOption {
+    OpenEditor,
+    LoadBackup,
+    UseDefaults,
+    Dismiss,
 }
 
-#[derive(Clone)]
-struct SettingsFormInput {
-    title: String,
-    columns: String,
-    theme_key: String,
-    custom_primary: String,
-    custom_accent: String,
-    custom_background: String,
-    custom_surface: String,
-    custom_text: String,
-    custom_highlight: String,
-    custom_theme_name: String,
+impl RecoveryOption {
+    const ALL: [RecoveryOption; 4] = [
+        RecoveryOption::OpenEditor,
+        RecoveryOption::LoadBackup,
+        RecoveryOption::UseDefaults,
+        RecoveryOption::Dismiss,
+    ];
+
+    fn key_and_label(self, recovery: &MenuRecovery) -> (char, String) {
+        match self {
+            RecoveryOption::OpenEditor => ('e', format!("Open the file in {}", preferred_editor())),
+            RecoveryOption::LoadBackup => ('b', "Load the latest backup".to_string()),
+            RecoveryOption::UseDefaults => (
+                'd',
+                "Start with the default menu (the file is left untouched)".to_string(),
+            ),
+            RecoveryOption::Dismiss if recovery.at_startup => ('q', "Quit".to_string()),
+            RecoveryOption::Dismiss => ('q', "Keep the current menu".to_string()),
+        }
+    }
+
+    fn from_key(code: KeyCode) -> Option<Self> {
+        match code {
+            KeyCode::Char('e') => Some(RecoveryOption::OpenEditor),
+            KeyCode::Char('b') => Some(RecoveryOption::LoadBackup),
+            KeyCode::Char('d') => Some(RecoveryOption::UseDefaults),
+            KeyCode::Char('q') | KeyCode::Esc => Some(RecoveryOption::Dismiss),
+            _ => None,
+        }
+    }
 }
 
+const PICKER_LABEL_WIDTH: u16 = 11;
+const PICKER_SLIDER_WIDTH: u16 = 36;
+const PICKER_PALETTE_COLUMNS: u16 = 32;
+const PICKER_PALETTE_ROWS: u16 = 8;
+const PICKER_SWATCH_WIDTH: u16 = 2;
+const PICKER_SLIDER_TOP: u16 = 2;
+const PICKER_PALETTE_TOP: u16 = 7;
+const PICKER_LINE_COUNT: u16 = 19;
+
 #[derive(Clone, Copy, PartialEq, Eq)]
-enum SettingsField {
-    Title,
-    Columns,
-    Theme,
-    CustomName,
-    CustomPrimary,
-    CustomAccent,
-    CustomBackground,
-    CustomSurface,
-    CustomText,
-    CustomHighlight,
+enum ColorPickerTarget {
+    Item(ItemField),
+    Category(CategoryField),
+    Settings(SettingsField),
 }
 
-enum SettingsFormKeyResult {
-    Continue,
-    Cancel,
-    Submit(SettingsFormInput),
-    DeleteSavedTheme(usize),
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ColorPickerFocus {
+    Slider(usize),
+    Palette,
 }
 
-impl SettingsFormState {
-    fn new(
-        title: String,
-        columns: u16,
-        theme_key: String,
-        options: Vec<ThemeOption>,
-        initial_field: SettingsField,
-        current_theme: &Theme,
-        is_custom_theme: bool,
-    ) -> Self {
-        let columns_value = columns.to_string();
-        let theme_index = options
-            .iter()
-            .position(|opt| opt.key == theme_key)
-            .unwrap_or(0);
-        Self {
-            title,
-            columns_value,
-            theme_options: options,
-            theme_index,
-            selected_field: initial_field,
-            error: None,
-            custom_primary: String::new(),
-            custom_accent: String::new(),
-            custom_background: String::new(),
-            custom_surface: String::new(),
-            custom_text: String::new(),
-            custom_highlight: String::new(),
-            custom_theme_name: if is_custom_theme {
-                current_theme.name.clone()
-            } else {
-                String::new()
-            },
-        }
-        .with_selected_theme_colors()
-    }
+/// Popup for choosing a form color with HSL/RGB sliders or the xterm palette.
+/// Every change is written straight into the form field so the swatch and
+/// theme preview follow along; cancelling writes `original` back.
+#[derive(Clone)]
+struct ColorPickerState {
+    target: ColorPickerTarget,
+    label: String,
+    original: String,
+    rgb: (u8, u8, u8),
+    hue: u16,
+    saturation: u8,
+    lightness: u8,
+    hsl_mode: bool,
+    focus: ColorPickerFocus,
+    palette_index: u8,
+    contrast_with: Option<(String, (u8, u8, u8))>,
+}
 
-    fn current_deletable_theme_index(&self) -> Option<usize> {
-        self.theme_options
-            .get(self.theme_index)
-            .and_then(|opt| parse_saved_theme_key(&opt.key))
-    }
+struct ColorPickerLayout {
+    area: Rect,
+    inner: Rect,
+}
 
-    fn render_lines(&self, app: &AppState) -> (Vec<FormLine>, SettingsFormLayout) {
-        let mut layout = SettingsFormLayout::default();
-        let mut lines: Vec<FormLine> = Vec::new();
-        let deletable_index = self.current_deletable_theme_index();
-        if let Some(saved_idx) = deletable_index {
-            layout.delete_saved_theme_index = Some(saved_idx);
-        }
-        lines.push(plain_line(Line::from("Adjust application settings below.")));
+fn color_picker_layout(frame_area: Rect) -> ColorPickerLayout {
+    let width = (PICKER_PALETTE_COLUMNS * PICKER_SWATCH_WIDTH + 4)
+        .max(PICKER_LABEL_WIDTH + PICKER_SLIDER_WIDTH + 8)
+        .min(frame_area.width);
+    let height = (PICKER_LINE_COUNT + 2).min(frame_area.height);
+    let area = Rect::new(
+        frame_area.x + (frame_area.width - width) / 2,
+        frame_area.y + (frame_area.height - height) / 2,
+        width,
+        height,
+    );
+    let inner = area.inner(&Margin {
+        horizontal: 1,
+        vertical: 1,
+    });
+    ColorPickerLayout { area, inner }
+}
 
-        layout.title_line = Some(lines.len());
-        lines.push(make_field_line(
-            "Title",
-            &self.title,
-            self.selected_field == SettingsField::Title,
-            app,
-        ));
+fn slider_position(value: u16, max: u16, width: u16) -> u16 {
+    if max == 0 || width < 2 {
+        return 0;
+    }
+    ((u32::from(value) * u32::from(width - 1) + u32::from(max) / 2) / u32::from(max)) as u16
+}
 
-        layout.columns_line = Some(lines.len());
-        lines.push(make_field_line(
-            "Columns (1-6)",
-            &self.columns_value,
-            self.selected_field == SettingsField::Columns,
-            app,
-        ));
+fn slider_value_at(cell: u16, max: u16, width: u16) -> u16 {
+    if width < 2 {
+        return 0;
+    }
+    ((u32::from(cell.min(width - 1)) * u32::from(max) + u32::from(width - 1) / 2)
+        / u32::from(width - 1)) as u16
+}
 
-        lines.push(plain_line(Line::from("")));
-        let (shortcut_line, shortcut_segments, shortcut_width) =
-            build_settings_shortcut_line(app, deletable_index.is_some());
-        layout.shortcut_line = Some(shortcut_line);
-        layout.shortcut_segments = shortcut_segments;
-        layout.shortcut_total_width = shortcut_width;
-        if let Some(error) = &self.error {
-            lines.push(plain_line(Line::from(vec![Span::styled(
-                error.clone(),
-                Style::default().fg(Color::Red),
-            )])));
-        }
-        if !self.theme_options.is_empty() {
-            lines.push(plain_line(Line::from("")));
-            layout.theme_heading_line = Some(lines.len());
-            lines.push(plain_line(Line::from(vec![Span::styled(
-                "Theme Presets (Tab to focus, ←/→ select)",
-                Style::default()
-                    .fg(app.theme.accent)
-                    .add_modifier(Modifier::BOLD),
-            )])));
-            layout.theme_list_start = Some(lines.len());
-            for (idx, option) in self.theme_options.iter().enumerate() {
-                let is_active = self.theme_index == idx;
-                let mut label_style = Style::default().fg(app.theme.text);
-                if is_active {
-                    label_style = label_style.add_modifier(Modifier::BOLD);
-                }
-                let mut spans = vec![Span::styled(
-                    format!("{:>2}. {}", idx + 1, option.label),
-                    label_style,
-                )];
-                if let Some(surface) = color_from_hex(&option.surface_hex) {
-                    spans.push(Span::raw("  "));
-                    spans.push(Span::styled(
-                        "     ",
-                        Style::default().bg(surface).fg(app.theme.text),
-                    ));
-                }
-                if let Some(accent) = color_from_hex(&option.accent_hex) {
-                    spans.push(Span::raw(" "));
-                    spans.push(Span::styled(
-                        "     ",
-                        Style::default().bg(accent).fg(app.theme.background),
-                    ));
-                }
-                if let Some(highlight) = color_from_hex(&option.highlight_hex) {
-                    spans.push(Span::raw(" "));
-                    spans.push(Span::styled(
-                        "     ",
-                        Style::default().bg(highlight).fg(app.theme.background),
-                    ));
-                }
-                for (label, hex) in [
-                    ("Primary", &option.primary_hex),
-                    ("Accent", &option.accent_hex),
-                    ("Highlight", &option.highlight_hex),
-                    ("Background", &option.background_hex),
-                    ("Surface", &option.surface_hex),
-                    ("Text", &option.text_hex),
-                ] {
-                    spans.push(Span::raw("  "));
-                    let mut color_style = Style::default().fg(app.theme.text);
-                    if is_active {
-                        color_style = color_style.add_modifier(Modifier::BOLD);
-                    }
-                    spans.push(Span::styled(
-                        format!("{} {}", label, hex.to_uppercase()),
-                        color_style,
-                    ));
-                }
-                let line = Line::from(spans);
-                if self.selected_field == SettingsField::Theme && is_active {
-                    lines.push(FormLine::highlighted(line));
-                } else {
-                    lines.push(FormLine::plain(line));
-                }
-            }
-            layout.theme_count = self.theme_options.len();
-        }
-        lines.push(plain_line(Line::from("")));
-        layout.custom_heading_line = Some(lines.len());
-        lines.push(plain_line(Line::from(vec![Span::styled(
-            "Custom Theme Colors (#RRGGBB, leave blank to keep preset)",
-            Style::default()
-                .fg(app.theme.accent)
-                .add_modifier(Modifier::BOLD),
-        )])));
-        layout.custom_name_line = Some(lines.len());
-        lines.push(make_field_line(
-            "Custom Theme Name",
-            &self.custom_theme_name,
-            self.selected_field == SettingsField::CustomName,
-            app,
-        ));
-        layout.custom_primary_line = Some(lines.len());
-        lines.push(make_color_field_line(
-            "Primary",
-            &self.custom_primary,
-            self.selected_field == SettingsField::CustomPrimary,
-            color_from_hex(&self.custom_primary),
-            app,
-        ));
-        layout.custom_accent_line = Some(lines.len());
-        lines.push(make_color_field_line(
-            "Accent",
-            &self.custom_accent,
-            self.selected_field == SettingsField::CustomAccent,
-            color_from_hex(&self.custom_accent),
-            app,
-        ));
-        layout.custom_highlight_line = Some(lines.len());
-        lines.push(make_color_field_line(
-            "Highlight",
-            &self.custom_highlight,
-            self.selected_field == SettingsField::CustomHighlight,
-            color_from_hex(&self.custom_highlight),
-            app,
-        ));
-        layout.custom_background_line = Some(lines.len());
-        lines.push(make_field_line(
-            "Background",
-            &self.custom_background,
-            self.selected_field == SettingsField::CustomBackground,
-            app,
-        ));
-        layout.custom_surface_line = Some(lines.len());
-        lines.push(make_color_field_line(
-            "Surface",
-            &self.custom_surface,
-            self.selected_field == SettingsField::CustomSurface,
-            color_from_hex(&self.custom_surface),
-            app,
-        ));
-        layout.custom_text_line = Some(lines.len());
-        lines.push(make_color_field_line(
-            "Text",
-            &self.custom_text,
-            self.selected_field == SettingsField::CustomText,
-            color_from_hex(&self.custom_text),
-            app,
-        ));
-        layout.line_count = lines.len();
-        (lines, layout)
+impl ColorPickerState {
+    fn new(
+        target: ColorPickerTarget,
+        label: &str,
+        value: &str,
+        contrast_with: Option<(String, (u8, u8, u8))>,
+    ) -> Self {
+        let rgb = hex_to_rgb(value).unwrap_or((128, 128, 128));
+        let mut picker = Self {
+            target,
+            label: label.to_string(),
+            original: value.to_string(),
+            rgb,
+            hue: 0,
+            saturation: 0,
+            lightness: 0,
+            hsl_mode: true,
+            focus: ColorPickerFocus::Slider(0),
+            palette_index: nearest_ansi256(rgb.0, rgb.1, rgb.2),
+            contrast_with,
+        };
+        picker.sync_hsl();
+        picker
     }
 
-    fn handle_key(&mut self, key: KeyEvent) -> SettingsFormKeyResult {
-        self.error = None;
-        match key.code {
-            KeyCode::Esc => SettingsFormKeyResult::Cancel,
-            KeyCode::Enter => SettingsFormKeyResult::Submit(self.to_input()),
-            KeyCode::Tab | KeyCode::Down => {
-                self.next_field();
-                SettingsFormKeyResult::Continue
-            }
-            KeyCode::BackTab | KeyCode::Up => {
-                self.previous_field();
-                SettingsFormKeyResult::Continue
+    fn color(&self) -> Color {
+        Color::Rgb(self.rgb.0, self.rgb.1, self.rgb.2)
+    }
+
+    fn hex(&self) -> String {
+        rgb_to_hex(self.rgb)
+    }
+
+    fn sync_hsl(&mut self) {
+        let (hue, saturation, lightness) = rgb_to_hsl(self.rgb);
+        // Greys have no hue; keep the slider where it was.
+        if saturation > 0.0 {
+            self.hue = hue.round() as u16 % 360;
+        }
+        self.saturation = (saturation * 100.0).round() as u8;
+        self.lightness = (lightness * 100.0).round() as u8;
+    }
+
+    fn set_rgb(&mut self, rgb: (u8, u8, u8)) {
+        self.rgb = rgb;
+        self.sync_hsl();
+    }
+
+    fn slider_spec(&self, slider: usize) -> (&'static str, u16) {
+        match (self.hsl_mode, slider) {
+            (true, 0) => ("Hue", 359),
+            (true, 1) => ("Saturation", 100),
+            (true, _) => ("Lightness", 100),
+            (false, 0) => ("Red", 255),
+            (false, 1) => ("Green", 255),
+            (false, _) => ("Blue", 255),
+        }
+    }
+
+    fn slider_value(&self, slider: usize) -> u16 {
+        match (self.hsl_mode, slider) {
+            (true, 0) => self.hue,
+            (true, 1) => u16::from(self.saturation),
+            (true, _) => u16::from(self.lightness),
+            (false, 0) => u16::from(self.rgb.0),
+            (false, 1) => u16::from(self.rgb.1),
+            (false, _) => u16::from(self.rgb.2),
+        }
+    }
+
+    fn set_slider_value(&mut self, slider: usize, value: u16) {
+        let (_, max) = self.slider_spec(slider);
+        let value = value.min(max);
+        if self.hsl_mode {
+            match slider {
+                0 => self.hue = value,
+                1 => self.saturation = value as u8,
+                _ => self.lightness = value as u8,
             }
-            KeyCode::Left if self.selected_field == SettingsField::Theme => {
-                self.previous_theme();
-                SettingsFormKeyResult::Continue
+            self.rgb = hsl_to_rgb(
+                f32::from(self.hue),
+                f32::from(self.saturation) / 100.0,
+                f32::from(self.lightness) / 100.0,
+            );
+        } else {
+            let channel = value as u8;
+            let mut rgb = self.rgb;
+            match slider {
+                0 => rgb.0 = channel,
+                1 => rgb.1 = channel,
+                _ => rgb.2 = channel,
             }
-            KeyCode::Right if self.selected_field == SettingsField::Theme => {
-                self.next_theme();
-                SettingsFormKeyResult::Continue
+            self.set_rgb(rgb);
+        }
+    }
+
+    fn adjust_slider(&mut self, slider: usize, delta: i32) {
+        let (_, max) = self.slider_spec(slider);
+        let current = i32::from(self.slider_value(slider));
+        let next = if self.hsl_mode && slider == 0 {
+            (current + delta).rem_euclid(i32::from(max) + 1)
+        } else {
+            (current + delta).clamp(0, i32::from(max))
+        };
+        self.set_slider_value(slider, next as u16);
+    }
+
+    fn select_palette(&mut self, index: u8) {
+        self.palette_index = index;
+        self.set_rgb(ansi256_to_rgb(index));
+    }
+
+    fn move_palette(&mut self, delta: i32) {
+        let next = (i32::from(self.palette_index) + delta).clamp(0, 255);
+        self.select_palette(next as u8);
+    }
+
+    /// Returns `Some(true)` to apply, `Some(false)` to cancel, `None` to keep
+    /// the picker open.
+    fn handle_key(&mut self, key: KeyEvent, keymap: &Keymap) -> Option<bool> {
+        let columns = i32::from(PICKER_PALETTE_COLUMNS);
+        let action = keymap.action_for(KeyContext::ColorPicker, &key)?;
+        match (action, self.focus) {
+            (KeyAction::Submit, _) => return Some(true),
+            (KeyAction::Cancel, _) => return Some(false),
+            (KeyAction::ToggleColorModel, _) => self.hsl_mode = !self.hsl_mode,
+            (KeyAction::MoveUp, ColorPickerFocus::Slider(slider)) => {
+                self.focus = ColorPickerFocus::Slider(slider.saturating_sub(1))
             }
-            KeyCode::Char('d') | KeyCode::Char('D')
-                if self.selected_field == SettingsField::Theme =>
-            {
-                if let Some(index) = self.current_deletable_theme_index() {
-                    SettingsFormKeyResult::DeleteSavedTheme(index)
+            (KeyAction::MoveDown, ColorPickerFocus::Slider(slider)) => {
+                self.focus = if slider >= 2 {
+                    ColorPickerFocus::Palette
                 } else {
-                    SettingsFormKeyResult::Continue
+                    ColorPickerFocus::Slider(slider + 1)
                 }
             }
-            KeyCode::Backspace => {
-                if let Some(value) = self.active_value_mut() {
-                    value.pop();
-                }
-                SettingsFormKeyResult::Continue
+            (KeyAction::Decrease, ColorPickerFocus::Slider(slider)) => {
+                self.adjust_slider(slider, -1)
             }
-            KeyCode::Delete => {
-                if let Some(value) = self.active_value_mut() {
-                    value.clear();
+            (KeyAction::Increase, ColorPickerFocus::Slider(slider)) => {
+                self.adjust_slider(slider, 1)
+            }
+            (KeyAction::DecreaseMore, ColorPickerFocus::Slider(slider)) => {
+                self.adjust_slider(slider, -10)
+            }
+            (KeyAction::IncreaseMore, ColorPickerFocus::Slider(slider)) => {
+                self.adjust_slider(slider, 10)
+            }
+            (KeyAction::SetMinimum, ColorPickerFocus::Slider(slider)) => {
+                self.set_slider_value(slider, 0)
+            }
+            (KeyAction::SetMaximum, ColorPickerFocus::Slider(slider)) => {
+                self.set_slider_value(slider, u16::MAX)
+            }
+            (KeyAction::MoveUp, ColorPickerFocus::Palette) => {
+                if i32::from(self.palette_index) < columns {
+                    self.focus = ColorPickerFocus::Slider(2);
+                } else {
+                    self.move_palette(-columns);
                 }
-                SettingsFormKeyResult::Continue
             }
-            KeyCode::Char(c) => {
-                if self.selected_field != SettingsField::Theme
-                    && !key.modifiers.contains(KeyModifiers::CONTROL)
-                {
-                    if let Some(value) = self.active_value_mut() {
-                        value.push(c);
+            (KeyAction::MoveDown, ColorPickerFocus::Palette)
+                if i32::from(self.palette_index) + columns <= 255 =>
+            {
+                self.move_palette(columns)
+            }
+            (KeyAction::Decrease | KeyAction::DecreaseMore, ColorPickerFocus::Palette) => {
+                self.move_palette(-1)
+            }
+            (KeyAction::Increase | KeyAction::IncreaseMore, ColorPickerFocus::Palette) => {
+                self.move_palette(1)
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn handle_mouse(&mut self, mouse: MouseEvent, layout: &ColorPickerLayout) {
+        let inner = layout.inner;
+        if mouse.row < inner.y || mouse.column < inner.x {
+            return;
+        }
+        let line = mouse.row - inner.y;
+        let column = mouse.column - inner.x;
+        if (PICKER_SLIDER_TOP..PICKER_SLIDER_TOP + 3).contains(&line) {
+            let slider = usize::from(line - PICKER_SLIDER_TOP);
+            match mouse.kind {
+                MouseEventKind::Down(MouseButton::Left)
+                | MouseEventKind::Drag(MouseButton::Left) => {
+                    self.focus = ColorPickerFocus::Slider(slider);
+                    if column >= PICKER_LABEL_WIDTH {
+                        let cell = column - PICKER_LABEL_WIDTH;
+                        let (_, max) = self.slider_spec(slider);
+                        self.set_slider_value(
+                            slider,
+                            slider_value_at(cell, max, PICKER_SLIDER_WIDTH),
+                        );
                     }
                 }
-                SettingsFormKeyResult::Continue
+                MouseEventKind::ScrollUp => self.adjust_slider(slider, 1),
+                MouseEventKind::ScrollDown => self.adjust_slider(slider, -1),
+                _ => {}
             }
-            _ => SettingsFormKeyResult::Continue,
+        } else if (PICKER_PALETTE_TOP..PICKER_PALETTE_TOP + PICKER_PALETTE_ROWS).contains(&line)
+            && column < PICKER_PALETTE_COLUMNS * PICKER_SWATCH_WIDTH
+            && matches!(mouse.kind, MouseEventKind::Down(MouseButton::Left))
+        {
+            let row = line - PICKER_PALETTE_TOP;
+            let index = row * PICKER_PALETTE_COLUMNS + column / PICKER_SWATCH_WIDTH;
+            self.focus = ColorPickerFocus::Palette;
+            self.select_palette(index as u8);
         }
     }
+}
 
-    fn to_input(&self) -> SettingsFormInput {
-        SettingsFormInput {
-            title: self.title.clone(),
-            columns: self.columns_value.clone(),
-            theme_key: self
-                .theme_options
-                .get(self.theme_index)
-                .map(|opt| opt.key.clone())
-                .unwrap_or_default(),
-            custom_primary: self.custom_primary.clone(),
-            custom_accent: self.custom_accent.clone(),
-            custom_background: self.custom_background.clone(),
-            custom_surface: self.custom_surface.clone(),
-            custom_text: self.custom_text.clone(),
-            custom_highlight: self.custom_highlight.clone(),
-            custom_theme_name: self.custom_theme_name.clone(),
+fn key_specs_for(context: KeyContext) -> impl Iterator<Item = &'static KeyBindingSpec> {
+    KEY_BINDING_SPECS
+        .iter()
+        .filter(move |spec| spec.context == context)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct KeyChord {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let mut modifiers =
+            modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        let mut code = code;
+        // Shift is already folded into the character (or into BackTab) by the terminal.
+        if matches!(code, KeyCode::Char(_) | KeyCode::BackTab) {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        if let KeyCode::Char(c) = code {
+            if modifiers.contains(KeyModifiers::CONTROL) {
+                code = KeyCode::Char(c.to_ascii_lowercase());
+            }
         }
+        Self { code, modifiers }
     }
 
-    fn next_field(&mut self) {
-        self.selected_field = match self.selected_field {
-            SettingsField::Title => SettingsField::Columns,
-            SettingsField::Columns => SettingsField::Theme,
-            SettingsField::Theme => SettingsField::CustomName,
-            SettingsField::CustomName => SettingsField::CustomPrimary,
-            SettingsField::CustomPrimary => SettingsField::CustomAccent,
-            SettingsField::CustomAccent => SettingsField::CustomHighlight,
-            SettingsField::CustomHighlight => SettingsField::CustomBackground,
-            SettingsField::CustomBackground => SettingsField::CustomSurface,
-            SettingsField::CustomSurface => SettingsField::CustomText,
-            SettingsField::CustomText => SettingsField::Title,
-        };
+    fn from_event(key: &KeyEvent) -> Self {
+        Self::new(key.code, key.modifiers)
     }
 
-    fn previous_field(&mut self) {
-        self.selected_field = match self.selected_field {
-            SettingsField::Title => SettingsField::CustomText,
-            SettingsField::Columns => SettingsField::Title,
-            SettingsField::Theme => SettingsField::Columns,
-            SettingsField::CustomName => SettingsField::Theme,
-            SettingsField::CustomPrimary => SettingsField::CustomName,
-            SettingsField::CustomAccent => SettingsField::CustomPrimary,
-            SettingsField::CustomHighlight => SettingsField::CustomAccent,
-            SettingsField::CustomBackground => SettingsField::CustomHighlight,
-            SettingsField::CustomSurface => SettingsField::CustomBackground,
-            SettingsField::CustomText => SettingsField::CustomSurface,
+    fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        if text.is_empty() {
+            return None;
+        }
+        if let Some(rest) = text.strip_prefix('^') {
+            let mut chars = rest.chars();
+            if let (Some(c), None) = (chars.next(), chars.next()) {
+                return Some(Self::new(KeyCode::Char(c), KeyModifiers::CONTROL));
+            }
+        }
+        let (modifier_part, key_part) = if text == "+" {
+            ("", "+")
+        } else if let Some(prefix) = text.strip_suffix("++") {
+            (prefix, "+")
+        } else {
+            text.rsplit_once('+').unwrap_or(("", text))
+        };
+        let mut modifiers = KeyModifiers::NONE;
+        for token in modifier_part.split('+').filter(|token| !token.is_empty()) {
+            match token.trim().to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => modifiers |= KeyModifiers::CONTROL,
+                "alt" | "meta" | "option" => modifiers |= KeyModifiers::ALT,
+                "shift" => modifiers |= KeyModifiers::SHIFT,
+                _ => return None,
+            }
+        }
+        let lower = key_part.to_ascii_lowercase();
+        let code = match lower.as_str() {
+            "enter" | "return" | "↵" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "space" => KeyCode::Char(' '),
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" | "ins" => KeyCode::Insert,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" | "pgup" => KeyCode::PageUp,
+            "pagedown" | "pgdn" => KeyCode::PageDown,
+            "up" | "↑" => KeyCode::Up,
+            "down" | "↓" => KeyCode::Down,
+            "left" | "←" => KeyCode::Left,
+            "right" | "→" => KeyCode::Right,
+            _ => {
+                let mut chars = key_part.chars();
+                let first = chars.next()?;
+                if chars.next().is_none() {
+                    if modifiers.contains(KeyModifiers::SHIFT) {
+                        KeyCode::Char(first.to_ascii_uppercase())
+                    } else {
+                        KeyCode::Char(first)
+                    }
+                } else {
+                    let number = lower.strip_prefix('f')?.parse::<u8>().ok()?;
+                    if !(1..=24).contains(&number) {
+                        return None;
+                    }
+                    KeyCode::F(number)
+                }
+            }
+        };
+        Some(Self::new(code, modifiers))
+    }
+
+    fn label(&self) -> String {
+        let key = match self.code {
+            KeyCode::Char(' ') => "Space".to_string(),
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::Enter => "↵".to_string(),
+            KeyCode::Esc => "Esc".to_string(),
+            KeyCode::Tab => "Tab".to_string(),
+            KeyCode::BackTab => "Shift+Tab".to_string(),
+            KeyCode::Backspace => "Backspace".to_string(),
+            KeyCode::Delete => "Del".to_string(),
+            KeyCode::Insert => "Ins".to_string(),
+            KeyCode::Home => "Home".to_string(),
+            KeyCode::End => "End".to_string(),
+            KeyCode::PageUp => "PgUp".to_string(),
+            KeyCode::PageDown => "PgDn".to_string(),
+            KeyCode::Up => "↑".to_string(),
+            KeyCode::Down => "↓".to_string(),
+            KeyCode::Left => "←".to_string(),
+            KeyCode::Right => "→".to_string(),
+            KeyCode::F(number) => format!("F{number}"),
+            _ => "?".to_string(),
         };
+        let ctrl = self.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = self.modifiers.contains(KeyModifiers::ALT);
+        let shift = self.modifiers.contains(KeyModifiers::SHIFT);
+        if ctrl && !alt && !shift && matches!(self.code, KeyCode::Char(c) if c != ' ') {
+            return format!("^{key}");
+        }
+        let mut label = String::new();
+        if ctrl {
+            label.push_str("Ctrl+");
+        }
+        if alt {
+            label.push_str("Alt+");
+        }
+        if shift {
+            label.push_str("Shift+");
+        }
+        label.push_str(&key);
+        label
     }
 
-    fn active_value_mut(&mut self) -> Option<&mut String> {
-        match self.selected_field {
-            SettingsField::Title => Some(&mut self.title),
-            SettingsField::Columns => Some(&mut self.columns_value),
-            SettingsField::Theme => None,
-            SettingsField::CustomName => Some(&mut self.custom_theme_name),
-            SettingsField::CustomPrimary => Some(&mut self.custom_primary),
-            SettingsField::CustomAccent => Some(&mut self.custom_accent),
-            SettingsField::CustomBackground => Some(&mut self.custom_background),
-            SettingsField::CustomSurface => Some(&mut self.custom_surface),
-            SettingsField::CustomText => Some(&mut self.custom_text),
-            SettingsField::CustomHighlight => Some(&mut self.custom_highlight),
+    fn is_plain_char(&self) -> bool {
+        matches!(self.code, KeyCode::Char(_))
+            && !self
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct KeymapFile {
+    #[serde(default)]
+    main: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    item_form: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    category_form: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    settings_form: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    import_list: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    color_picker: BTreeMap<String, Vec<String>>,
+}
+
+impl KeymapFile {
+    fn defaults() -> Self {
+        let mut file = KeymapFile::default();
+        for spec in KEY_BINDING_SPECS {
+            file.section_mut(spec.context).insert(
+                spec.name.to_string(),
+                spec.defaults.iter().map(|key| key.to_string()).collect(),
+            );
         }
+        file
     }
 
-    fn with_selected_theme_colors(mut self) -> Self {
-        self.populate_custom_fields_from_selection();
-        self
+    fn section(&self, context: KeyContext) -> &BTreeMap<String, Vec<String>> {
+        match context {
+            KeyContext::Main => &self.main,
+            KeyContext::ItemForm => &self.item_form,
+            KeyContext::CategoryForm => &self.category_form,
+            KeyContext::SettingsForm => &self.settings_form,
+            KeyContext::ImportList => &self.import_list,
+            KeyContext::ColorPicker => &self.color_picker,
+        }
     }
 
-    fn populate_custom_fields_from_selection(&mut self) {
-        if self.theme_options.is_empty() {
+    fn section_mut(&mut self, context: KeyContext) -> &mut BTreeMap<String, Vec<String>> {
+        match context {
+            KeyContext::Main => &mut self.main,
+            KeyContext::ItemForm => &mut self.item_form,
+            KeyContext::CategoryForm => &mut self.category_form,
+            KeyContext::SettingsForm => &mut self.settings_form,
+            KeyContext::ImportList => &mut self.import_list,
+            KeyContext::ColorPicker => &mut self.color_picker,
+        }
+    }
+}
+
+struct Keymap {
+    bindings: Vec<(KeyContext, KeyChord, KeyAction)>,
+}
+
+impl Keymap {
+    /// Loads `keymap.json`, falling back to the built-in bindings. The returned
+    /// warnings describe unknown actions, unparseable keys and conflicting bindings,
+    /// or why the file could not be used at all; a bad keymap never stops startup.
+    fn load(path: &Path) -> (Self, Vec<String>) {
+        if !path.exists() {
+            let file = KeymapFile::defaults();
+            let (keymap, mut warnings) = Keymap::from_file(&file);
+            let written = serde_json::to_string_pretty(&file)
+                .map_err(anyhow::Error::from)
+                .and_then(|data| write_atomic(path, data.as_bytes()));
+            if let Err(err) = written {
+                warnings.insert(0, format!("keymap.json not created: {err:#}"));
+            }
+            return (keymap, warnings);
+        }
+        let parsed = fs::read_to_string(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|data| Ok(serde_json::from_str::<KeymapFile>(&data)?));
+        match parsed {
+            Ok(file) => Keymap::from_file(&file),
+            Err(err) => {
+                let (keymap, mut warnings) = Keymap::from_file(&KeymapFile::default());
+                warnings.insert(
+                    0,
+                    format!("keymap.json ignored, using default keys: {err:#}"),
+                );
+                (keymap, warnings)
+            }
+        }
+    }
+
+    fn from_file(file: &KeymapFile) -> (Self, Vec<String>) {
+        let mut bindings: Vec<(KeyContext, KeyChord, KeyAction)> = Vec::new();
+        let mut warnings = Vec::new();
+        for context in KeyContext::ALL {
+            let section = file.section(context);
+            for name in section.keys() {
+                if !key_specs_for(context).any(|spec| spec.name == name) {
+                    warnings.push(format!("Unknown {} action '{name}'", context.label()));
+                }
+            }
+            // Actions configured in the file claim their keys before the defaults do.
+            let (configured, defaulted): (Vec<_>, Vec<_>) =
+                key_specs_for(context).partition(|spec| section.contains_key(spec.name));
+            let entries = configured
+                .into_iter()
+                .map(|spec| (spec, section[spec.name].clone()))
+                .chain(defaulted.into_iter().map(|spec| {
+                    (
+                        spec,
+                        spec.defaults.iter().map(|key| key.to_string()).collect(),
+                    )
+                }));
+            for (spec, keys) in entries {
+                for text in keys {
+                    let Some(chord) = KeyChord::parse(&text) else {
+                        warnings.push(format!(
+                            "Invalid key '{text}' for {} action '{}'",
+                            context.label(),
+                            spec.name
+                        ));
+                        continue;
+                    };
+                    if let Some((_, _, existing)) = bindings
+                        .iter()
+                        .find(|(ctx, bound, _)| *ctx == context && *bound == chord)
+                    {
+                        // Actions that only apply to one field can share a key.
+                        let shared = spec.field_scoped
+                            && key_specs_for(context)
+                                .any(|other| other.action == *existing && other.field_scoped);
+                        if shared && *existing != spec.action {
+                            bindings.push((context, chord, spec.action));
+                            continue;
+                        }
+                        if *existing != spec.action {
+                            let existing_name = key_action_name(context, *existing);
+                            warnings.push(format!(
+                                "{} in {} is bound to both '{existing_name}' and '{}'; keeping '{existing_name}'",
+                                chord.label(),
+                                context.label(),
+                                spec.name
+                            ));
+                        }
+                        continue;
+                    }
+                    if !matches!(context, KeyContext::Main | KeyContext::ColorPicker)
+                        && !spec.field_scoped
+                        && chord.is_plain_char()
+                    {
+                        warnings.push(format!(
+                            "{} for {} action '{}' blocks typing that character",
+                            chord.label(),
+                            context.label(),
+                            spec.name
+                        ));
+                    }
+                    bindings.push((context, chord, spec.action));
+                }
+            }
+        }
+        (Keymap { bindings }, warnings)
+    }
+
+    fn action_for(&self, context: KeyContext, key: &KeyEvent) -> Option<KeyAction> {
+        let chord = KeyChord::from_event(key);
+        self.bindings
+            .iter()
+            .find(|(ctx, bound, _)| *ctx == context && *bound == chord)
+            .map(|(_, _, action)| *action)
+    }
+
+    /// Every action bound to the key, for forms where field-scoped actions
+    /// share one.
+    fn actions_for(&self, context: KeyContext, key: &KeyEvent) -> Vec<KeyAction> {
+        let chord = KeyChord::from_event(key);
+        self.bindings
+            .iter()
+            .filter(|(ctx, bound, _)| *ctx == context && *bound == chord)
+            .map(|(_, _, action)| *action)
+            .collect()
+    }
+
+    fn chords_for(&self, context: KeyContext, action: KeyAction) -> Vec<KeyChord> {
+        self.bindings
+            .iter()
+            .filter(|(ctx, _, bound)| *ctx == context && *bound == action)
+            .map(|(_, chord, _)| *chord)
+            .collect()
+    }
+
+    fn key_label(&self, context: KeyContext, action: KeyAction) -> Option<String> {
+        self.chords_for(context, action)
+            .first()
+            .map(|chord| chord.label())
+    }
+}
+
+fn key_action_name(context: KeyContext, action: KeyAction) -> &'static str {
+    key_specs_for(context)
+        .find(|spec| spec.action == action)
+        .map(|spec| spec.name)
+        .unwrap_or("unknown")
+}
+
+fn color_from_hex(value: &str) -> Option<Color> {
+    let normalized = normalize_hex(value);
+    let bytes = normalized.as_bytes();
+    let r = u8::from_str_radix(std::str::from_utf8(&bytes[1..3]).ok()?, 16).ok()?;
+    let g = u8::from_str_radix(std::str::from_utf8(&bytes[3..5]).ok()?, 16).ok()?;
+    let b = u8::from_str_radix(std::str::from_utf8(&bytes[5..7]).ok()?, 16).ok()?;
+    Some(Color::Rgb(r, g, b))
+}
+
+/// How many colors the terminal can show. Every frame is rendered with
+/// truecolor theme values and then reduced to this mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ColorMode {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+    Monochrome,
+}
+
+impl ColorMode {
+    /// Resolve the color mode from the `color_mode` setting (`auto`,
+    /// `truecolor`, `256`, `16` or `none`), then `NO_COLOR`, then the
+    /// `COLORTERM` and `TERM` environment variables.
+    fn detect(setting: Option<&str>) -> Self {
+        let setting = setting.unwrap_or("auto").trim().to_ascii_lowercase();
+        match setting.as_str() {
+            "truecolor" | "24bit" | "rgb" => return ColorMode::TrueColor,
+            "256" | "ansi256" => return ColorMode::Ansi256,
+            "16" | "ansi16" | "ansi" => return ColorMode::Ansi16,
+            "none" | "mono" | "monochrome" => return ColorMode::Monochrome,
+            _ => {}
+        }
+
+        let env = |name: &str| std::env::var(name).unwrap_or_default();
+        if !env("NO_COLOR").is_empty() {
+            return ColorMode::Monochrome;
+        }
+        let colorterm = env("COLORTERM").to_ascii_lowercase();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            return ColorMode::TrueColor;
+        }
+        let term = env("TERM").to_ascii_lowercase();
+        if term == "dumb" {
+            return ColorMode::Monochrome;
+        }
+        if term.contains("direct") || term.contains("truecolor") {
+            return ColorMode::TrueColor;
+        }
+        if !env("WT_SESSION").is_empty()
+            || matches!(
+                env("TERM_PROGRAM").as_str(),
+                "iTerm.app" | "WezTerm" | "vscode" | "ghostty"
+            )
+        {
+            return ColorMode::TrueColor;
+        }
+        if term.contains("256color") {
+            return ColorMode::Ansi256;
+        }
+        ColorMode::Ansi16
+    }
+
+    fn apply(self, buffer: &mut ratatui::buffer::Buffer) {
+        if self == ColorMode::TrueColor {
             return;
         }
-        if self.theme_index >= self.theme_options.len() {
-            self.theme_index = 0;
+        for cell in buffer.content.iter_mut() {
+            cell.fg = self.adapt(cell.fg);
+            cell.bg = self.adapt(cell.bg);
         }
-        if let Some(option) = self.theme_options.get(self.theme_index) {
-            self.custom_primary = option.primary_hex.clone();
-            self.custom_accent = option.accent_hex.clone();
-            self.custom_highlight = option.highlight_hex.clone();
-            self.custom_background = option.background_hex.clone();
-            self.custom_surface = option.surface_hex.clone();
-            self.custom_text = option.text_hex.clone();
-            if parse_saved_theme_key(&option.key).is_some() {
-                self.custom_theme_name = option.label.clone();
-            } else if option.key != CUSTOM_THEME_KEY {
-                self.custom_theme_name.clear();
+    }
+
+    fn adapt(self, color: Color) -> Color {
+        match (self, color) {
+            (ColorMode::TrueColor, _) => color,
+            (ColorMode::Monochrome, _) => Color::Reset,
+            (ColorMode::Ansi256, Color::Rgb(r, g, b)) => Color::Indexed(nearest_ansi256(r, g, b)),
+            (ColorMode::Ansi16, Color::Rgb(r, g, b)) => nearest_ansi16(r, g, b),
+            (ColorMode::Ansi16, Color::Indexed(index)) => {
+                let (r, g, b) = ansi256_to_rgb(index);
+                nearest_ansi16(r, g, b)
             }
+            _ => color,
         }
     }
+}
+
+const ANSI16_PALETTE: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+const ANSI256_CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn color_distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    // "Redmean" weighting keeps hue closer to what the eye expects than a
+    // plain euclidean distance in RGB space.
+    let mean_red = (u32::from(a.0) + u32::from(b.0)) / 2;
+    let dr = (i32::from(a.0) - i32::from(b.0)).unsigned_abs();
+    let dg = (i32::from(a.1) - i32::from(b.1)).unsigned_abs();
+    let db = (i32::from(a.2) - i32::from(b.2)).unsigned_abs();
+    (((512 + mean_red) * dr * dr) >> 8) + 4 * dg * dg + (((767 - mean_red) * db * db) >> 8)
+}
+
+fn nearest_ansi16(r: u8, g: u8, b: u8) -> Color {
+    ANSI16_PALETTE
+        .iter()
+        .min_by_key(|(_, rgb)| color_distance(*rgb, (r, g, b)))
+        .map(|(color, _)| *color)
+        .unwrap_or(Color::Reset)
+}
+
+fn nearest_ansi256(r: u8, g: u8, b: u8) -> u8 {
+    let cube_step = |value: u8| {
+        ANSI256_CUBE_LEVELS
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, level)| (i32::from(**level) - i32::from(value)).unsigned_abs())
+            .map(|(index, _)| index as u8)
+            .unwrap_or(0)
+    };
+    let (ri, gi, bi) = (cube_step(r), cube_step(g), cube_step(b));
+    let cube_index = 16 + 36 * ri + 6 * gi + bi;
+    let average = (u32::from(r) + u32::from(g) + u32::from(b)) / 3;
+    let gray_step = (average.saturating_sub(3) / 10).min(23) as u8;
+    let gray_index = 232 + gray_step;
+    let target = (r, g, b);
+    if color_distance(ansi256_to_rgb(gray_index), target)
+        < color_distance(ansi256_to_rgb(cube_index), target)
+    {
+        gray_index
+    } else {
+        cube_index
+    }
+}
 
-    fn next_theme(&mut self) {
-        if self.theme_options.is_empty() {
-            return;
+fn ansi256_to_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => ANSI16_PALETTE[usize::from(index)].1,
+        16..=231 => {
+            let offset = index - 16;
+            (
+                ANSI256_CUBE_LEVELS[usize::from(offset / 36)],
+                ANSI256_CUBE_LEVELS[usize::from((offset / 6) % 6)],
+                ANSI256_CUBE_LEVELS[usize::from(offset % 6)],
+            )
+        }
+        _ => {
+            let level = 8 + 10 * (index - 232);
+            (level, level, level)
         }
-        self.theme_index = (self.theme_index + 1) % self.theme_options.len();
-        self.populate_custom_fields_from_selection();
     }
+}
 
-    fn previous_theme(&mut self) {
-        if self.theme_options.is_empty() {
-            return;
+fn hex_to_rgb(value: &str) -> Option<(u8, u8, u8)> {
+    let hex = sanitize_hex_color_input(value)?;
+    let rgb = u32::from_str_radix(&hex[1..], 16).ok()?;
+    Some(((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8))
+}
+
+fn rgb_to_hex((r, g, b): (u8, u8, u8)) -> String {
+    format!("#{r:02X}{g:02X}{b:02X}")
+}
+
+fn rgb_to_hsl((r, g, b): (u8, u8, u8)) -> (f32, f32, f32) {
+    let r = f32::from(r) / 255.0;
+    let g = f32::from(g) / 255.0;
+    let b = f32::from(b) / 255.0;
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let lightness = (max + min) / 2.0;
+    let delta = max - min;
+    if delta == 0.0 {
+        return (0.0, 0.0, lightness);
+    }
+    let saturation = delta / (1.0 - (2.0 * lightness - 1.0).abs());
+    let hue = if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    (hue, saturation.min(1.0), lightness)
+}
+
+fn hsl_to_rgb(hue: f32, saturation: f32, lightness: f32) -> (u8, u8, u8) {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let sector = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (sector.rem_euclid(2.0) - 1.0).abs());
+    let (r, g, b) = match sector as u8 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    let channel = |value: f32| ((value + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+    (channel(r), channel(g), channel(b))
+}
+
+/// Build a theme around `seed`: background and surface are tinted with the
+/// seed hue, and every foreground/background pair the UI draws is pushed
+/// lighter or darker until it reaches at least WCAG AA (text on background
+/// reaches AAA).
+fn derive_theme(name: &str, seed: (u8, u8, u8), light: bool) -> SavedTheme {
+    let (hue, saturation, _) = rgb_to_hsl(seed);
+    let tint = saturation.min(0.3);
+    // Grey seeds stay grey instead of picking up the red at hue 0.
+    let vivid = if saturation < 0.05 {
+        0.0
+    } else {
+        saturation.clamp(0.45, 0.85)
+    };
+    // Foregrounds move away from the background, and fills that carry
+    // background-colored text move the other way.
+    let (background, surface, text_lightness, fill_lightness, mark_lightness) = if light {
+        (
+            hsl_to_rgb(hue, tint, 0.97),
+            hsl_to_rgb(hue, tint, 0.91),
+            0.15,
+            0.82,
+            0.38,
+        )
+    } else {
+        (
+            hsl_to_rgb(hue, tint, 0.09),
+            hsl_to_rgb(hue, tint, 0.15),
+            0.92,
+            0.32,
+            0.65,
+        )
+    };
+    let text = ensure_contrast(
+        (hue, tint.min(0.15), text_lightness),
+        &[background, surface],
+        7.0,
+        !light,
+    );
+    let primary = ensure_contrast((hue, vivid, fill_lightness), &[text], 4.5, light);
+    let highlight = ensure_contrast(
+        ((hue + 30.0) % 360.0, vivid, mark_lightness),
+        &[background],
+        4.5,
+        !light,
+    );
+    let accent = ensure_contrast(
+        ((hue + 180.0) % 360.0, vivid, mark_lightness),
+        &[background, surface],
+        4.5,
+        !light,
+    );
+    // The shortcut bar is drawn on the highlight color, so its keys get their
+    // own accent that moves away from it.
+    let footer_key = ensure_contrast(
+        ((hue + 180.0) % 360.0, vivid, fill_lightness),
+        &[highlight],
+        4.5,
+        light,
+    );
+    SavedTheme {
+        name: name.to_string(),
+        primary: rgb_to_hex(primary),
+        accent: rgb_to_hex(accent),
+        background: rgb_to_hex(background),
+        surface: rgb_to_hex(surface),
+        text: rgb_to_hex(text),
+        highlight: Some(rgb_to_hex(highlight)),
+        chrome: ThemeChrome {
+            footer_key: Some(rgb_to_hex(footer_key)),
+            ..ThemeChrome::default()
+        },
+    }
+}
+
+/// Step the lightness of an HSL color toward white (`lighten`) or black until
+/// it contrasts with every color in `against` by at least `minimum`.
+fn ensure_contrast(
+    (hue, saturation, lightness): (f32, f32, f32),
+    against: &[(u8, u8, u8)],
+    minimum: f32,
+    lighten: bool,
+) -> (u8, u8, u8) {
+    let target = if lighten { 1.0 } else { 0.0 };
+    let mut lightness = lightness;
+    loop {
+        let rgb = hsl_to_rgb(hue, saturation, lightness);
+        let passes = against
+            .iter()
+            .all(|other| contrast_ratio(rgb, *other) >= minimum);
+        if passes || lightness == target {
+            return rgb;
         }
-        if self.theme_index == 0 {
-            self.theme_index = self.theme_options.len() - 1;
+        lightness = if lighten {
+            (lightness + 0.01).min(target)
         } else {
-            self.theme_index -= 1;
+            (lightness - 0.01).max(target)
+        };
+    }
+}
+
+/// WCAG 2 relative luminance of an sRGB color.
+fn relative_luminance((r, g, b): (u8, u8, u8)) -> f32 {
+    let linear = |channel: u8| {
+        let value = f32::from(channel) / 255.0;
+        if value <= 0.03928 {
+            value / 12.92
+        } else {
+            ((value + 0.055) / 1.055).powf(2.4)
         }
-        self.populate_custom_fields_from_selection();
+    };
+    0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+}
+
+fn contrast_ratio(a: (u8, u8, u8), b: (u8, u8, u8)) -> f32 {
+    let (la, lb) = (relative_luminance(a), relative_luminance(b));
+    (la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+}
+
+fn wcag_rating(ratio: f32) -> &'static str {
+    if ratio >= 7.0 {
+        "AAA"
+    } else if ratio >= 4.5 {
+        "AA"
+    } else if ratio >= 3.0 {
+        "AA Large"
+    } else {
+        "Fail"
     }
 }
 
-fn build_settings_shortcut_line(
-    app: &AppState,
-    include_delete: bool,
-) -> (Line<'static>, Vec<SettingsShortcutSegment>, u16) {
-    let mut spans: Vec<Span<'static>> = Vec::new();
-    let mut segments: Vec<SettingsShortcutSegment> = Vec::new();
-    let mut cursor: u16 = 0;
-    let key_style = Style::default()
-        .fg(app.theme.accent)
-        .add_modifier(Modifier::BOLD);
-    let label_style = Style::default().fg(app.theme.surface);
-    let entries: Vec<(&str, &str, SettingsShortcutAction)> = vec![
-        ("Tab", " Move", SettingsShortcutAction::NextField),
-        ("↵", " Save", SettingsShortcutAction::Submit),
-        ("Esc", " Cancel/Exit", SettingsShortcutAction::Cancel),
-    ];
+/// Blend `from` toward `to` by `amount` (0.0 keeps `from`, 1.0 gives `to`).
+fn mix_hex(from: &str, to: &str, amount: f32) -> String {
+    let (Some(a), Some(b)) = (hex_to_rgb(from), hex_to_rgb(to)) else {
+        return normalize_hex(from);
+    };
+    let channel =
+        |x: u8, y: u8| (f32::from(x) + (f32::from(y) - f32::from(x)) * amount).round() as u8;
+    rgb_to_hex((channel(a.0, b.0), channel(a.1, b.1), channel(a.2, b.2)))
+}
 
-    for (idx, (key, label, action)) in entries.iter().enumerate() {
-        if idx > 0 {
-            spans.push(Span::styled(" | ", label_style));
-            cursor = cursor.saturating_add(3);
-        }
-        let entry_start = cursor;
-        spans.push(Span::styled(*key, key_style));
-        cursor = cursor.saturating_add(key.chars().count() as u16);
-        if !label.is_empty() {
-            spans.push(Span::styled(*label, label_style));
-            cursor = cursor.saturating_add(label.chars().count() as u16);
+fn expand_home(path: &str) -> PathBuf {
+    if let Some(rest) = path.strip_prefix("~/") {
+        if let Some(home) = dirs::home_dir() {
+            return home.join(rest);
         }
-        segments.push(SettingsShortcutSegment {
-            start: entry_start,
-            end: cursor,
-            action: *action,
-        });
     }
+    PathBuf::from(path)
+}
 
-    if !entries.is_empty() {
-        spans.push(Span::styled(" | ", label_style));
-        cursor = cursor.saturating_add(3);
+fn normalize_hex(value: &str) -> String {
+    let mut cleaned = value.trim().to_string();
+    if !cleaned.starts_with('#') {
+        cleaned.insert(0, '#');
     }
-
-    let left_start = cursor;
-    spans.push(Span::styled("←", key_style));
-    cursor = cursor.saturating_add("←".chars().count() as u16);
-    segments.push(SettingsShortcutSegment {
-        start: left_start,
-        end: cursor,
-        action: SettingsShortcutAction::PreviousTheme,
-    });
-    spans.push(Span::styled("/", label_style));
-    cursor = cursor.saturating_add(1);
-    let right_start = cursor;
-    spans.push(Span::styled("→", key_style));
-    cursor = cursor.saturating_add("→".chars().count() as u16);
-    spans.push(Span::styled(" Select", label_style));
-    cursor = cursor.saturating_add(" Select".len() as u16);
-    segments.push(SettingsShortcutSegment {
-        start: right_start,
-        end: cursor,
-        action: SettingsShortcutAction::NextTheme,
-    });
-
-    if include_delete {
-        spans.push(Span::styled(" | ", label_style));
-        cursor = cursor.saturating_add(3);
-        let entry_start = cursor;
-        spans.push(Span::styled("d", key_style));
-        cursor = cursor.saturating_add(1);
-        spans.push(Span::styled(" Delete theme", label_style));
-        cursor = cursor.saturating_add(" Delete theme".len() as u16);
-        segments.push(SettingsShortcutSegment {
-            start: entry_start,
-            end: cursor,
-            action: SettingsShortcutAction::DeleteTheme,
-        });
+    if cleaned.len() != 7 {
+        return "#ffffff".into();
     }
+    cleaned
+}
 
-    (Line::from(spans), segments, cursor)
+fn prompt_with_default(prompt: &str, default: &str) -> Result<String> {
+    println!("{prompt} [{default}]: ");
+    print!("> ");
+    io::stdout().flush()?;
+    let mut buf = String::new();
+    io::stdin().read_line(&mut buf)?;
+    let trimmed = buf.trim();
+    if trimmed.is_empty() {
+        Ok(default.to_string())
+    } else {
+        Ok(trimmed.to_string())
+    }
 }
 
-#[derive(Clone)]
-struct Theme {
-    name: String,
-    primary: Color,
-    accent: Color,
-    #[allow(dead_code)]
-    highlight: Color,
-    background: Color,
-    surface: Color,
-    text: Color,
-    primary_hex: String,
-    accent_hex: String,
-    highlight_hex: String,
-    background_hex: String,
-    surface_hex: String,
-    text_hex: String,
+fn prompt_optional(prompt: &str) -> Result<String> {
+    println!("{prompt}: ");
+    print!("> ");
+    io::stdout().flush()?;
+    let mut buf = String::new();
+    io::stdin().read_line(&mut buf)?;
+    Ok(buf.trim().to_string())
 }
 
-impl Theme {
-    fn load(path: &Path) -> Result<Self> {
-        if path.exists() {
-            let data = fs::read_to_string(path)?;
-            match serde_json::from_str::<ThemeFile>(&data) {
-                Ok(file) => {
-                    if let Some(skin) = file.skin {
-                        if let Some(theme) = Theme::from_name(&skin) {
-                            return Ok(theme);
-                        }
-                    }
-                    if let Some(colors) = file.colors {
-                        if let Some(theme) = Theme::from_colors("Custom", colors) {
-                            return Ok(theme);
-                        }
-                    }
-                }
-                Err(_) => {}
-            }
-        }
-        let theme = Theme::from_name("nord").unwrap();
-        theme.save(path)?;
-        Ok(theme)
+fn prompt_bool(prompt: &str, default: bool) -> Result<bool> {
+    let default_hint = if default { "Y/n" } else { "y/N" };
+    println!("{prompt} ({default_hint})");
+    print!("> ");
+    io::stdout().flush()?;
+    let mut buf = String::new();
+    io::stdin().read_line(&mut buf)?;
+    let trimmed = buf.trim().to_ascii_lowercase();
+    if trimmed.is_empty() {
+        Ok(default)
+    } else if trimmed == "y" || trimmed == "yes" {
+        Ok(true)
+    } else if trimmed == "n" || trimmed == "no" {
+        Ok(false)
+    } else {
+        Ok(default)
     }
+}
 
-    fn save(&self, path: &Path) -> Result<()> {
-        let file = ThemeFile {
-            skin: Some(self.name.clone()),
-            colors: Some(ThemeColorOverrides {
-                primary: Some(self.primary_hex.clone()),
-                accent: Some(self.accent_hex.clone()),
-                highlight: Some(self.highlight_hex.clone()),
-                background: Some(self.background_hex.clone()),
-                surface: Some(self.surface_hex.clone()),
-                text: Some(self.text_hex.clone()),
-            }),
-        };
-        if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent)?;
-        }
-        fs::write(path, serde_json::to_string_pretty(&file)?)?;
-        Ok(())
+fn sanitize_hex_color_input(input: &str) -> Option<String> {
+    let mut value = input.trim().to_string();
+    if !value.starts_with('#') {
+        value.insert(0, '#');
+    }
+    if value.len() != 7 {
+        return None;
     }
+    if u32::from_str_radix(&value[1..], 16).is_ok() {
+        Some(value)
+    } else {
+        None
+    }
+}
 
-    fn from_name(name: &str) -> Option<Self> {
-        THEME_PRESETS
-            .iter()
-            .find(|preset| preset.0 == name)
-            .map(|(_, def)| Theme::from_definition(name.to_string(), def))
+fn hex_strings_equal(a: &str, b: &str) -> bool {
+    match (sanitize_hex_color_input(a), sanitize_hex_color_input(b)) {
+        (Some(mut left), Some(mut right)) => {
+            left.make_ascii_lowercase();
+            right.make_ascii_lowercase();
+            left == right
+        }
+        _ => false,
     }
+}
 
-    fn from_definition(name: String, def: &ThemeDefinition) -> Theme {
-        Theme::from_hexes(
-            name,
-            def.primary,
-            def.accent,
-            def.highlight,
-            def.background,
-            def.surface,
-            def.text,
-        )
+fn parse_color_field(value: &str) -> Result<Option<String>, String> {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        return Ok(None);
     }
+    sanitize_hex_color_input(trimmed)
+        .map(Some)
+        .ok_or_else(|| "Colors must use #RRGGBB format".to_string())
+}
 
-    fn from_colors(name: &str, overrides: ThemeColorOverrides) -> Option<Self> {
-        Some(Theme::from_hexes(
-            name.to_string(),
-            overrides.primary.as_deref().unwrap_or("#5E81AC"),
-            overrides.accent.as_deref().unwrap_or("#D08770"),
-            overrides
-                .highlight
-                .as_deref()
-                .or(overrides.accent.as_deref())
-                .unwrap_or("#76B3C5"),
-            overrides.background.as_deref().unwrap_or("#3B4252"),
-            overrides.surface.as_deref().unwrap_or("#4C566A"),
-            overrides.text.as_deref().unwrap_or("#ECEFF4"),
-        ))
+fn require_color_field(value: &str, label: &str) -> Result<String, String> {
+    parse_color_field(value)?
+        .ok_or_else(|| format!("{label} color is required when creating a custom theme"))
+}
+
+/// Upgrade steps for menus.json. Entry `n` turns a version `n` file into
+/// version `n + 1`; version 0 is the Python MenuMaker layout.
+const MENU_MIGRATIONS: &[fn(&mut serde_json::Value) -> Result<()>] =
+    &[import_python_menu, migrate_menu_v1_to_v2];
+const MENU_SCHEMA_VERSION: u32 = MENU_MIGRATIONS.len() as u32;
+
+/// Files without `schema_version` are version 0 if they have one of the
+/// Python MenuMaker layouts and version 1 otherwise, so an empty file or one
+/// holding only settings loads as an empty menu.
+fn menu_schema_version(value: &serde_json::Value) -> u32 {
+    if let Some(version) = value.get("schema_version").and_then(|v| v.as_u64()) {
+        return u32::try_from(version).unwrap_or(u32::MAX);
+    }
+    if is_python_menu(value) {
+        0
+    } else {
+        1
     }
+}
 
-    fn from_hexes(
-        name: String,
-        primary: &str,
-        accent: &str,
-        highlight: &str,
-        background: &str,
-        surface: &str,
-        text: &str,
-    ) -> Theme {
-        Theme {
-            name,
-            primary: color_from_hex(primary).unwrap_or(Color::Blue),
-            accent: color_from_hex(accent).unwrap_or(Color::Cyan),
-            highlight: color_from_hex(highlight).unwrap_or(Color::Cyan),
-            background: color_from_hex(background).unwrap_or(Color::Black),
-            surface: color_from_hex(surface).unwrap_or(Color::DarkGray),
-            text: color_from_hex(text).unwrap_or(Color::White),
-            primary_hex: normalize_hex(primary),
-            accent_hex: normalize_hex(accent),
-            highlight_hex: normalize_hex(highlight),
-            background_hex: normalize_hex(background),
-            surface_hex: normalize_hex(surface),
-            text_hex: normalize_hex(text),
-        }
+/// Fields of the Rust layout, which never name a Python category.
+const MENU_FILE_FIELDS: &[&str] = &[
+    "schema_version",
+    "categories",
+    "app_settings",
+    "custom_colors",
+    "saved_themes",
+];
+
+/// Whether `value` has a layout `import_python_menu` understands: a list of
+/// items, a wrapper around one, or categories mapped to lists of items.
+fn is_python_menu(value: &serde_json::Value) -> bool {
+    let Some(object) = value.as_object() else {
+        return value.is_array();
+    };
+    if object.get("categories").is_some_and(|c| c.is_object()) {
+        return false;
     }
+    ["menus", "items", "entries"]
+        .iter()
+        .any(|key| object.get(*key).is_some_and(|v| v.is_array()))
+        || object
+            .iter()
+            .filter(|(key, _)| !MENU_FILE_FIELDS.contains(&key.as_str()))
+            .any(|(_, entry)| {
+                entry.is_array() || entry.get("items").is_some_and(|items| items.is_array())
+            })
 }
 
-#[derive(Serialize, Deserialize)]
-struct ThemeFile {
-    skin: Option<String>,
-    colors: Option<ThemeColorOverrides>,
+fn migrate_menu_value(value: &mut serde_json::Value, from: u32) -> Result<()> {
+    for step in from..MENU_SCHEMA_VERSION {
+        MENU_MIGRATIONS[step as usize](value)
+            .with_context(|| format!("Upgrading from schema version {step} failed"))?;
+    }
+    if let Some(object) = value.as_object_mut() {
+        object.insert("schema_version".into(), MENU_SCHEMA_VERSION.into());
+    }
+    Ok(())
 }
 
-#[derive(Serialize, Deserialize)]
-struct ThemeColorOverrides {
-    primary: Option<String>,
-    accent: Option<String>,
-    #[serde(default)]
-    highlight: Option<String>,
-    background: Option<String>,
-    surface: Option<String>,
-    text: Option<String>,
+/// Copy `path` aside as `<stem>.v<version>-<timestamp>.<ext>`. The name is
+/// outside the rotating backups so the pre-upgrade file is never pruned.
+fn backup_before_migration(path: &Path, backups_dir: &Path, version: u32) -> Result<()> {
+    let (stem, extension) = backup_name_parts(path);
+    let target = backups_dir.join(format!(
+        "{stem}.v{version}-{}.{extension}",
+        backup_timestamp()
+    ));
+    let data = fs::read(path).with_context(|| format!("Unable to read {}", path.display()))?;
+    write_atomic(&target, &data)
 }
 
-struct ThemeDefinition {
-    name: &'static str,
-    primary: &'static str,
-    accent: &'static str,
-    highlight: &'static str,
-    background: &'static str,
-    surface: &'static str,
-    text: &'static str,
+/// Version 1 stored each item's category both as the map key and in the
+/// item itself.
+fn migrate_menu_v1_to_v2(value: &mut serde_json::Value) -> Result<()> {
+    let Some(categories) = value.get_mut("categories").and_then(|c| c.as_object_mut()) else {
+        return Ok(());
+    };
+    for category in categories.values_mut() {
+        if let Some(items) = category.get_mut("items").and_then(|i| i.as_array_mut()) {
+            for item in items.iter_mut().filter_map(|item| item.as_object_mut()) {
+                item.remove("category");
+            }
+        }
+    }
+    Ok(())
 }
 
-impl ThemeOption {
-    fn from_definition(key: &str, def: &ThemeDefinition) -> Self {
-        Self {
-            key: key.to_string(),
-            label: def.name.to_string(),
-            primary_hex: def.primary.to_string(),
-            accent_hex: def.accent.to_string(),
-            highlight_hex: def.highlight.to_string(),
-            background_hex: def.background.to_string(),
-            surface_hex: def.surface.to_string(),
-            text_hex: def.text.to_string(),
+/// Convert a Python MenuMaker file into the version 1 layout. Those files
+/// are either a list of items carrying their own `category`, a
+/// `{"menus": [...]}`/`{"items": [...]}` wrapper around such a list, or a map
+/// from category name to a list of items. Items may use `name`/`title` for
+/// the label, `command`/`exec` for the command and `description`/`help` for
+/// the info text.
+fn import_python_menu(value: &mut serde_json::Value) -> Result<()> {
+    use serde_json::{json, Map, Value};
+
+    fn text(item: &Map<String, Value>, keys: &[&str]) -> Option<String> {
+        keys.iter()
+            .find_map(|key| item.get(*key).and_then(|v| v.as_str()))
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    }
+
+    let mut title = None;
+    let mut grouped: Vec<(String, Vec<Value>)> = Vec::new();
+    let mut push = |category: String, item: Value| match grouped
+        .iter_mut()
+        .find(|(name, _)| *name == category)
+    {
+        Some((_, items)) => items.push(item),
+        None => grouped.push((category, vec![item])),
+    };
+    let flat_list = match value {
+        Value::Array(items) => Some(items.clone()),
+        Value::Object(object) => {
+            title = object
+                .get("title")
+                .and_then(|t| t.as_str())
+                .map(str::to_string);
+            ["menus", "items", "entries"]
+                .iter()
+                .find_map(|key| object.get(*key).and_then(|v| v.as_array()).cloned())
+        }
+        _ => None,
+    };
+    match (flat_list, &*value) {
+        (Some(items), _) => {
+            for item in items {
+                let Some(object) = item.as_object() else {
+                    continue;
+                };
+                let category = text(object, &["category", "group", "section"])
+                    .unwrap_or_else(|| "Imported".to_string());
+                push(category, item.clone());
+            }
         }
+        (None, Value::Object(object)) => {
+            for (category, entry) in object {
+                let items = entry
+                    .as_array()
+                    .or_else(|| entry.get("items").and_then(|i| i.as_array()));
+                for item in items.into_iter().flatten() {
+                    push(category.clone(), item.clone());
+                }
+            }
+        }
+        _ => anyhow::bail!("Unrecognized menu file layout"),
+    }
+
+    let mut categories = Map::new();
+    for (name, items) in grouped {
+        let items: Vec<Value> = items
+            .iter()
+            .filter_map(|item| item.as_object())
+            .filter_map(|item| {
+                let cmd = text(item, &["cmd", "command", "exec"])?;
+                let label = text(item, &["label", "name", "title"]).unwrap_or_else(|| cmd.clone());
+                Some(json!({
+                    "label": label,
+                    "cmd": cmd,
+                    "info": text(item, &["info", "description", "help"]),
+                    "pause": item.get("pause").or_else(|| item.get("wait")).and_then(|p| p.as_bool()),
+                }))
+            })
+            .collect();
+        categories.insert(
+            name,
+            json!({ "expanded": true, "column": 1, "items": items }),
+        );
+    }
+    if categories.is_empty() {
+        anyhow::bail!("No menu items found");
     }
+    *value = json!({
+        "categories": categories,
+        "app_settings": { "title": title },
+    });
+    Ok(())
 }
 
-const THEME_PRESETS: &[(&str, ThemeDefinition)] = &[
-    (
-        "classic",
-        ThemeDefinition {
-            name: "Midnight Classic",
-            primary: "#6FC6D4",
-            accent: "#0F1A2B",
-            highlight: "#9FE6EC",
-            background: "#314A63",
-            surface: "#416079",
-            text: "#F2F8FF",
-        },
-    ),
-    (
-        "nord",
-        ThemeDefinition {
-            name: "Nord",
-            primary: "#5E81AC",
-            accent: "#D08770",
-            highlight: "#76B3C5",
-            background: "#3B4252",
-            surface: "#4C566A",
-            text: "#ECEFF4",
-        },
-    ),
-    (
-        "gruvbox",
-        ThemeDefinition {
-            name: "Midnight Mist",
-            primary: "#66C3CF",
-            accent: "#0E1828",
-            highlight: "#96DFE8",
-            background: "#2C4156",
-            surface: "#3B5A72",
-            text: "#F4FBFF",
-        },
-    ),
-    (
-        "dracula",
-        ThemeDefinition {
-            name: "Midnight Dusk",
-            primary: "#6BC6D7",
-            accent: "#142033",
-            highlight: "#A1E6EC",
-            background: "#2E475F",
-            surface: "#3E5D78",
-            text: "#F5FBFF",
-        },
-    ),
-    (
-        "monokai",
-        ThemeDefinition {
-            name: "Midnight Deep",
-            primary: "#5FC0CD",
-            accent: "#0D1725",
-            highlight: "#92DDE7",
-            background: "#243A50",
-            surface: "#344F68",
-            text: "#F6FCFF",
-        },
-    ),
-];
+/// Number of menus.json backups kept when the settings do not say otherwise.
+const DEFAULT_BACKUP_COUNT: usize = 10;
 
-fn is_preset_theme_key(key: &str) -> bool {
-    THEME_PRESETS
-        .iter()
-        .any(|(preset_key, _)| preset_key == &key)
+/// Write `data` next to `path`, flush it to disk and rename it over `path`,
+/// so a crash or full disk leaves either the old or the new file, never a
+/// truncated one.
+fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(dir)?;
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("menu-maker");
+    let temp_path = dir.join(format!(".{file_name}.{}.tmp", std::process::id()));
+    let result = (|| -> Result<()> {
+        let mut file = fs::File::create(&temp_path)?;
+        // Keep the mode of the file being replaced, e.g. a private menus.json.
+        if let Ok(metadata) = fs::metadata(path) {
+            file.set_permissions(metadata.permissions())?;
+        }
+        file.write_all(data)?;
+        file.sync_all()?;
+        fs::rename(&temp_path, path)?;
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result.with_context(|| format!("Unable to write {}", path.display()))?;
+    // Persist the rename itself; not every platform can open a directory.
+    #[cfg(unix)]
+    if let Ok(dir) = fs::File::open(dir) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
 }
 
-fn saved_theme_key(index: usize) -> String {
-    format!("{SAVED_THEME_PREFIX}{index}")
+/// Advisory lock on `<file>.lock`, released when dropped, so instances
+/// saving the same file take turns.
+struct FileLock {
+    _file: fs::File,
 }
 
-fn parse_saved_theme_key(key: &str) -> Option<usize> {
-    key.strip_prefix(SAVED_THEME_PREFIX)?.parse::<usize>().ok()
+fn lock_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    path.with_file_name(name)
 }
 
-#[derive(Clone, Copy)]
-struct FooterShortcut {
-    key: &'static str,
-    label: &'static str,
-    action: FooterAction,
+fn lock_file(path: &Path) -> Result<FileLock> {
+    let lock_path = lock_path(path);
+    if let Some(parent) = lock_path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent)?;
+    }
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .with_context(|| format!("Unable to open {}", lock_path.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::io::AsRawFd;
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+            if Instant::now() >= deadline {
+                anyhow::bail!(
+                    "{} is being saved by another instance; try again",
+                    display_path(path)
+                );
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    }
+    Ok(FileLock { _file: file })
 }
 
-#[derive(Clone, Copy)]
-enum FooterAction {
-    Quit,
-    Edit,
-    Execute,
-    NewItem,
-    Delete,
-    Settings,
-    ScanBin,
+fn read_menu_value(path: &Path) -> Option<serde_json::Value> {
+    let data = fs::read_to_string(path).ok()?;
+    MenuFormat::from_path(path).parse(&data).ok()
 }
 
-struct FooterSegment {
-    start: u16,
-    end: u16,
-    action: FooterAction,
+/// A menu without the `expanded` flags, which only record how it was last
+/// shown.
+fn without_view_state(mut value: serde_json::Value) -> serde_json::Value {
+    if let Some(categories) = value
+        .get_mut("categories")
+        .and_then(serde_json::Value::as_object_mut)
+    {
+        for category in categories.values_mut() {
+            if let Some(category) = category.as_object_mut() {
+                category.remove("expanded");
+            }
+        }
+    }
+    value
 }
 
-struct FooterLineData {
-    line: Line<'static>,
-    segments: Vec<FooterSegment>,
-    total_width: u16,
+/// Three-way merge of JSON values; `None` is a missing key. Objects merge
+/// key by key and lists of named entries (items, saved themes) entry by
+/// entry. Where both sides changed the same value ours wins, except that an
+/// edit beats a deletion, and the path is added to `conflicts`.
+fn merge_json(
+    base: Option<&serde_json::Value>,
+    ours: Option<&serde_json::Value>,
+    theirs: Option<&serde_json::Value>,
+    path: &str,
+    conflicts: &mut Vec<String>,
+) -> Option<serde_json::Value> {
+    use serde_json::{Map, Value};
+    if ours == theirs || theirs == base {
+        return ours.cloned();
+    }
+    if ours == base {
+        return theirs.cloned();
+    }
+    match (ours, theirs) {
+        (Some(Value::Object(ours_map)), Some(Value::Object(theirs_map))) => {
+            let empty = Map::new();
+            let base_map = base.and_then(Value::as_object).unwrap_or(&empty);
+            let keys: Vec<&String> = ours_map
+                .keys()
+                .chain(theirs_map.keys().filter(|key| !ours_map.contains_key(*key)))
+                .collect();
+            let mut merged = Map::new();
+            for key in keys {
+                let child = merge_json(
+                    base_map.get(key),
+                    ours_map.get(key),
+                    theirs_map.get(key),
+                    &merge_path(path, key),
+                    conflicts,
+                );
+                if let Some(value) = child {
+                    merged.insert(key.clone(), value);
+                }
+            }
+            Some(Value::Object(merged))
+        }
+        (Some(Value::Array(ours_list)), Some(Value::Array(theirs_list))) => {
+            let empty = Vec::new();
+            let base_list = base.and_then(Value::as_array).unwrap_or(&empty);
+            let (Some(base_keyed), Some(ours_keyed), Some(theirs_keyed)) = (
+                keyed_entries(base_list),
+                keyed_entries(ours_list),
+                keyed_entries(theirs_list),
+            ) else {
+                conflicts.push(path.to_string());
+                return ours.cloned();
+            };
+            // Our order first, then entries only the other side added.
+            let mut keys: Vec<&str> = ours_keyed.iter().map(|(key, _)| *key).collect();
+            for (key, _) in &theirs_keyed {
+                if !keys.contains(key) {
+                    keys.push(key);
+                }
+            }
+            let merged = keys
+                .into_iter()
+                .filter_map(|key| {
+                    merge_json(
+                        find_keyed(&base_keyed, key),
+                        find_keyed(&ours_keyed, key),
+                        find_keyed(&theirs_keyed, key),
+                        &merge_path(path, key),
+                        conflicts,
+                    )
+                })
+                .collect();
+            Some(Value::Array(merged))
+        }
+        (None, Some(_)) => {
+            conflicts.push(path.to_string());
+            theirs.cloned()
+        }
+        _ => {
+            conflicts.push(path.to_string());
+            ours.cloned()
+        }
+    }
 }
 
-const FOOTER_SHORTCUTS: &[FooterShortcut] = &[
-    FooterShortcut {
-        key: "q",
-        label: " Exit",
-        action: FooterAction::Quit,
-    },
-    FooterShortcut {
-        key: "e",
-        label: " Edit",
-        action: FooterAction::Edit,
-    },
-    FooterShortcut {
-        key: "↵",
-        label: " Execute",
-        action: FooterAction::Execute,
-    },
-    FooterShortcut {
-        key: "n",
-        label: " New Item",
-        action: FooterAction::NewItem,
-    },
-    FooterShortcut {
-        key: "d",
-        label: " Delete",
-        action: FooterAction::Delete,
-    },
-    FooterShortcut {
-        key: "s",
-        label: " Settings",
-        action: FooterAction::Settings,
-    },
-    FooterShortcut {
-        key: "^b",
-        label: " Scan ./import",
-        action: FooterAction::ScanBin,
-    },
-];
+/// Entries of a list keyed by their `label` or `name`, or `None` when the
+/// list is not made of uniquely named objects.
+fn keyed_entries(list: &[serde_json::Value]) -> Option<Vec<(&str, &serde_json::Value)>> {
+    let mut keyed: Vec<(&str, &serde_json::Value)> = Vec::new();
+    for entry in list {
+        let key = entry
+            .get("label")
+            .or_else(|| entry.get("name"))
+            .and_then(serde_json::Value::as_str)?;
+        if keyed.iter().any(|(existing, _)| *existing == key) {
+            return None;
+        }
+        keyed.push((key, entry));
+    }
+    Some(keyed)
+}
 
-fn color_from_hex(value: &str) -> Option<Color> {
-    let normalized = normalize_hex(value);
-    let bytes = normalized.as_bytes();
-    let r = u8::from_str_radix(std::str::from_utf8(&bytes[1..3]).ok()?, 16).ok()?;
-    let g = u8::from_str_radix(std::str::from_utf8(&bytes[3..5]).ok()?, 16).ok()?;
-    let b = u8::from_str_radix(std::str::from_utf8(&bytes[5..7]).ok()?, 16).ok()?;
-    Some(Color::Rgb(r, g, b))
+fn find_keyed<'a>(
+    list: &[(&str, &'a serde_json::Value)],
+    key: &str,
+) -> Option<&'a serde_json::Value> {
+    list.iter()
+        .find(|(entry_key, _)| *entry_key == key)
+        .map(|(_, value)| *value)
 }
 
-fn normalize_hex(value: &str) -> String {
-    let mut cleaned = value.trim().to_string();
-    if !cleaned.starts_with('#') {
-        cleaned.insert(0, '#');
+fn merge_path(parent: &str, key: &str) -> String {
+    if parent.is_empty() {
+        key.to_string()
+    } else {
+        format!("{parent}/{key}")
     }
-    if cleaned.len() != 7 {
-        return "#ffffff".into();
+}
+
+/// Status line after a merge. `backed_up` says whether the other
+/// instance's version was kept in the backups.
+fn merge_notice(conflicts: &[String], backed_up: bool) -> String {
+    // "categories/Dev/items/build/cmd" reads as "Dev/build/cmd".
+    let conflicts: Vec<String> = conflicts
+        .iter()
+        .map(|path| {
+            path.strip_prefix("categories/")
+                .unwrap_or(path)
+                .replace("/items/", "/")
+        })
+        .collect();
+    let theirs = if backed_up {
+        " (theirs is in backups)"
+    } else {
+        ""
+    };
+    match conflicts.as_slice() {
+        [] => "Merged changes saved by another instance".into(),
+        [only] => format!(
+            "Merged changes from another instance; kept your version of {only}{theirs}"
+        ),
+        [first, rest @ ..] => format!(
+            "Merged changes from another instance; kept your version of {first} and {} more{theirs}",
+            rest.len()
+        ),
     }
-    cleaned
 }
 
-fn prompt_with_default(prompt: &str, default: &str) -> Result<String> {
-    println!("{prompt} [{default}]: ");
-    print!("> ");
-    io::stdout().flush()?;
-    let mut buf = String::new();
-    io::stdin().read_line(&mut buf)?;
-    let trimmed = buf.trim();
-    if trimmed.is_empty() {
-        Ok(default.to_string())
-    } else {
-        Ok(trimmed.to_string())
+/// Copy `path` into `backup_dir` as `<stem>-<timestamp>.<ext>` before it is
+/// replaced, then prune to the `keep` newest copies. Content identical to the
+/// newest backup is not stored twice. Returns the backup that holds the
+/// current content, or `None` when backups are off or there was no file.
+fn backup_file(path: &Path, backup_dir: &Path, keep: usize) -> Result<Option<PathBuf>> {
+    if keep == 0 {
+        return Ok(None);
+    }
+    let Ok(current) = fs::read(path) else {
+        return Ok(None);
+    };
+    let mut backups = list_backups(backup_dir, path);
+    if let Some(newest) = backups.first() {
+        if fs::read(newest).is_ok_and(|newest| newest == current) {
+            return Ok(Some(newest.clone()));
+        }
     }
+    let (stem, extension) = backup_name_parts(path);
+    let backup_path = backup_dir.join(format!("{stem}-{}.{extension}", backup_timestamp()));
+    write_atomic(&backup_path, &current)?;
+    backups.insert(0, backup_path.clone());
+    for stale in backups.iter().skip(keep) {
+        let _ = fs::remove_file(stale);
+    }
+    Ok(Some(backup_path))
 }
 
-fn prompt_optional(prompt: &str) -> Result<String> {
-    println!("{prompt}: ");
-    print!("> ");
-    io::stdout().flush()?;
-    let mut buf = String::new();
-    io::stdin().read_line(&mut buf)?;
-    Ok(buf.trim().to_string())
+/// `YYYYMMDD-HHMMSS-mmm` in local time; sorts chronologically.
+fn backup_timestamp() -> String {
+    let (date, time) = local_date_time();
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.subsec_millis())
+        .unwrap_or_default();
+    format!(
+        "{}-{}-{millis:03}",
+        date.replace('-', ""),
+        time.replace(':', "")
+    )
 }
 
-fn prompt_bool(prompt: &str, default: bool) -> Result<bool> {
-    let default_hint = if default { "Y/n" } else { "y/N" };
-    println!("{prompt} ({default_hint})");
-    print!("> ");
-    io::stdout().flush()?;
-    let mut buf = String::new();
-    io::stdin().read_line(&mut buf)?;
-    let trimmed = buf.trim().to_ascii_lowercase();
-    if trimmed.is_empty() {
-        Ok(default)
-    } else if trimmed == "y" || trimmed == "yes" {
-        Ok(true)
-    } else if trimmed == "n" || trimmed == "no" {
-        Ok(false)
+fn backup_name_parts(path: &Path) -> (String, String) {
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("backup")
+        .to_string();
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("bak")
+        .to_string();
+    (stem, extension)
+}
+
+/// Contents for `target` from a backup in any menu format: the backup's own
+/// text when the formats match, otherwise the menu rendered in `target`'s.
+fn backup_contents(backup: &Path, target: &Path) -> Result<String> {
+    let menu_file = MenuFile::read(backup)?;
+    if MenuFormat::from_path(backup) == MenuFormat::from_path(target) {
+        Ok(fs::read_to_string(backup)?)
     } else {
-        Ok(default)
+        MenuFormat::from_path(target).render(&menu_file)
     }
 }
 
-fn sanitize_hex_color_input(input: &str) -> Option<String> {
-    let mut value = input.trim().to_string();
-    if !value.starts_with('#') {
-        value.insert(0, '#');
+/// Backups of `path` in every menu format, so they survive a `convert`,
+/// newest first. The timestamp in the name sorts chronologically.
+fn list_backups(backup_dir: &Path, path: &Path) -> Vec<PathBuf> {
+    let (stem, _) = backup_name_parts(path);
+    let prefix = format!("{stem}-");
+    let mut backups: Vec<PathBuf> = fs::read_dir(backup_dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|candidate| {
+                    candidate
+                        .file_name()
+                        .and_then(|name| name.to_str())
+                        .and_then(|name| name.strip_prefix(&prefix)?.rsplit_once('.'))
+                        .is_some_and(|(timestamp, extension)| {
+                            is_backup_timestamp(timestamp)
+                                && MenuFormat::from_name(extension).is_some()
+                        })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    backups.sort();
+    backups.reverse();
+    backups
+}
+
+/// Whether `text` has the `YYYYMMDD-HHMMSS-mmm` shape of [`backup_timestamp`],
+/// so the backups of `work` do not pick up those of `work-laptop`.
+fn is_backup_timestamp(text: &str) -> bool {
+    let digits =
+        |part: &str, len: usize| part.len() == len && part.bytes().all(|b| b.is_ascii_digit());
+    let parts: Vec<&str> = text.split('-').collect();
+    matches!(parts.as_slice(), [date, time, millis] if digits(date, 8) && digits(time, 6) && digits(millis, 3))
+}
+
+/// "2026-10-18 13:04:58" from a name like `menus-20261018-130458-123.json`.
+fn backup_label(path: &Path) -> String {
+    let name = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default();
+    let parts: Vec<&str> = name.rsplitn(4, '-').collect();
+    match parts.as_slice() {
+        [_, time, date, _] if date.len() == 8 && time.len() == 6 => format!(
+            "{}-{}-{} {}:{}:{}",
+            &date[..4],
+            &date[4..6],
+            &date[6..],
+            &time[..2],
+            &time[2..4],
+            &time[4..]
+        ),
+        _ => name.to_string(),
     }
-    if value.len() != 7 {
-        return None;
+}
+
+/// Status bar template used when the settings do not define one.
+const DEFAULT_STATUS_TEMPLATE: &str = "Item {item} | Theme: {theme}";
+
+/// Placeholders understood by the header and status bar templates.
+const TEMPLATE_SEGMENTS: &[(&str, &str)] = &[
+    ("{item}", "Position of the selection, e.g. 3/12"),
+    ("{theme}", "Active theme name"),
+    ("{message}", "Last status message"),
+    ("{command}", "Command of the selected item"),
+    ("{exit_code}", "Exit code of the last command run"),
+    ("{hostname}", "Host name"),
+    ("{user}", "Login name"),
+    ("{cwd}", "Working directory"),
+    ("{git_branch}", "Git branch of the working directory"),
+    ("{clock}", "Local time (HH:MM:SS)"),
+    ("{date}", "Local date (YYYY-MM-DD)"),
+    ("{jobs}", "Background processes started from the menu"),
+];
+
+/// Values for the live `{segment}` placeholders, refreshed each tick.
+#[derive(Default)]
+struct LiveSegments {
+    hostname: String,
+    user: String,
+    cwd: String,
+    git_branch: String,
+    clock: String,
+    date: String,
+    jobs: usize,
+}
+
+/// Replace `{name}` placeholders using `lookup`. Unknown names and unmatched
+/// braces are kept as typed so a typo stays visible.
+fn expand_template(template: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find('}').and_then(|end| {
+            let name = &after[..end];
+            lookup(name).map(|value| (value, end))
+        }) {
+            Some((value, end)) => {
+                output.push_str(&value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                output.push('{');
+                rest = after;
+            }
+        }
     }
-    if u32::from_str_radix(&value[1..], 16).is_ok() {
-        Some(value)
-    } else {
-        None
+    output.push_str(rest);
+    output
+}
+
+fn current_hostname() -> String {
+    for path in ["/proc/sys/kernel/hostname", "/etc/hostname"] {
+        if let Ok(name) = fs::read_to_string(path) {
+            let name = name.trim();
+            if !name.is_empty() {
+                return name.to_string();
+            }
+        }
     }
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .unwrap_or_default()
 }
 
-fn hex_strings_equal(a: &str, b: &str) -> bool {
-    match (sanitize_hex_color_input(a), sanitize_hex_color_input(b)) {
-        (Some(mut left), Some(mut right)) => {
-            left.make_ascii_lowercase();
-            right.make_ascii_lowercase();
-            left == right
+fn display_path(path: &Path) -> String {
+    if let Some(home) = dirs::home_dir() {
+        if let Ok(relative) = path.strip_prefix(&home) {
+            return if relative.as_os_str().is_empty() {
+                "~".into()
+            } else {
+                format!("~/{}", relative.display())
+            };
         }
-        _ => false,
     }
+    path.display().to_string()
 }
 
-fn parse_color_field(value: &str) -> Result<Option<String>, String> {
-    let trimmed = value.trim();
-    if trimmed.is_empty() {
-        return Ok(None);
+/// Branch name from the nearest `.git`, or the short commit hash when HEAD is
+/// detached. Reads the files directly so the tick never spawns `git`.
+fn git_branch_name(start: &Path) -> Option<String> {
+    let git_path = start
+        .ancestors()
+        .map(|dir| dir.join(".git"))
+        .find(|path| path.exists())?;
+    let git_dir = if git_path.is_file() {
+        let contents = fs::read_to_string(&git_path).ok()?;
+        let target = contents.trim().strip_prefix("gitdir:")?.trim();
+        git_path.parent()?.join(target)
+    } else {
+        git_path
+    };
+    let head = fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    let head = head.trim();
+    match head.strip_prefix("ref: ") {
+        Some(reference) => Some(
+            reference
+                .strip_prefix("refs/heads/")
+                .unwrap_or(reference)
+                .to_string(),
+        ),
+        None => Some(head.chars().take(7).collect()),
     }
-    sanitize_hex_color_input(trimmed)
-        .map(Some)
-        .ok_or_else(|| "Colors must use #RRGGBB format".to_string())
 }
 
-fn require_color_field(value: &str, label: &str) -> Result<String, String> {
-    parse_color_field(value)?
-        .ok_or_else(|| format!("{label} color is required when creating a custom theme"))
+/// Current local date and time as `YYYY-MM-DD` and `HH:MM:SS`.
+fn local_date_time() -> (String, String) {
+    #[cfg(unix)]
+    {
+        let now = unsafe { libc::time(std::ptr::null_mut()) };
+        let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+        if !unsafe { libc::localtime_r(&now, &mut tm) }.is_null() {
+            return (
+                format!(
+                    "{:04}-{:02}-{:02}",
+                    tm.tm_year + 1900,
+                    tm.tm_mon + 1,
+                    tm.tm_mday
+                ),
+                format!("{:02}:{:02}:{:02}", tm.tm_hour, tm.tm_min, tm.tm_sec),
+            );
+        }
+    }
+    // Without a C library to ask for the local zone, fall back to UTC.
+    let seconds = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    let (days, time) = (seconds / 86_400, seconds % 86_400);
+    // Civil-from-days conversion for the proleptic Gregorian calendar.
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (
+        format!("{year:04}-{month:02}-{day:02}"),
+        format!(
+            "{:02}:{:02}:{:02}",
+            time / 3600,
+            time % 3600 / 60,
+            time % 60
+        ),
+    )
+}
+
+/// Set to Menu Maker's process id in the environment of commands run from the
+/// menu, so `{jobs}` can find what they left running.
+const JOB_MARKER_VAR: &str = "MENU_MAKER_PID";
+
+/// Processes still running that were started by commands run from the menu,
+/// including ones the command left in the background. Always 0 off Linux.
+fn running_job_count() -> usize {
+    #[cfg(target_os = "linux")]
+    {
+        // Every process inherits the marker from the command that started it,
+        // even after that command exits and the process is reparented.
+        let marker = format!("{JOB_MARKER_VAR}={}", std::process::id());
+        let started_here = |pid: u32| {
+            let running = fs::read_to_string(format!("/proc/{pid}/stat"))
+                .ok()
+                .and_then(|stat| {
+                    let (_, fields) = stat.rsplit_once(')')?;
+                    fields.split_whitespace().next().map(|state| state != "Z")
+                })
+                .unwrap_or(false);
+            running
+                && fs::read(format!("/proc/{pid}/environ"))
+                    .map(|environ| {
+                        environ
+                            .split(|byte| *byte == 0)
+                            .any(|entry| entry == marker.as_bytes())
+                    })
+                    .unwrap_or(false)
+        };
+        let Ok(entries) = fs::read_dir("/proc") else {
+            return 0;
+        };
+        entries
+            .flatten()
+            .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+            .filter(|pid| started_here(*pid))
+            .count()
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        0
+    }
 }
 
 fn filename_to_label(name: &str) -> String {
@@ -4655,3 +13104,1025 @@ fn is_executable_file(entry: &fs::DirEntry) -> bool {
             .unwrap_or(true)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ensure_contrast_reaches_the_minimum_against_every_color() {
+        let dark = (20, 20, 20);
+        let light = (235, 235, 235);
+        let rgb = ensure_contrast((200.0, 0.6, 0.5), &[dark], 7.0, true);
+        assert!(contrast_ratio(rgb, dark) >= 7.0);
+        let rgb = ensure_contrast((200.0, 0.6, 0.5), &[light], 7.0, false);
+        assert!(contrast_ratio(rgb, light) >= 7.0);
+    }
+
+    #[test]
+    fn ensure_contrast_stops_at_white_or_black() {
+        let rgb = ensure_contrast((0.0, 0.0, 0.5), &[(255, 255, 255)], 22.0, true);
+        assert_eq!(rgb, (255, 255, 255));
+        let rgb = ensure_contrast((0.0, 0.0, 0.5), &[(0, 0, 0)], 22.0, false);
+        assert_eq!(rgb, (0, 0, 0));
+    }
+
+    #[test]
+    fn derived_footer_keys_contrast_with_the_footer_background() {
+        for seed in [(0x3B, 0x82, 0xF6), (0xF5, 0x9E, 0x0B), (0x80, 0x80, 0x80)] {
+            for light in [false, true] {
+                let theme = derive_theme("test", seed, light);
+                let footer_background = hex_to_rgb(theme.highlight.as_deref().unwrap()).unwrap();
+                let footer_key = hex_to_rgb(theme.chrome.footer_key.as_deref().unwrap()).unwrap();
+                assert!(
+                    contrast_ratio(footer_key, footer_background) >= 4.5,
+                    "seed {seed:?} light {light}"
+                );
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn running_job_count_sees_processes_started_with_the_marker() {
+        let before = running_job_count();
+        let mut child = Command::new("sleep")
+            .arg("30")
+            .env(JOB_MARKER_VAR, std::process::id().to_string())
+            .spawn()
+            .unwrap();
+        let during = running_job_count();
+        child.kill().unwrap();
+        child.wait().unwrap();
+        assert_eq!(during, before + 1);
+        assert_eq!(running_job_count(), before);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_atomic_keeps_the_permissions_of_the_replaced_file() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = private_temp_dir().unwrap();
+        let path = dir.join("menus.json");
+        fs::write(&path, "{}").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        write_atomic(&path, b"{\"categories\": []}").unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode() & 0o777;
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(mode, 0o600);
+    }
+
+    #[test]
+    fn list_backups_matches_timestamped_backups_of_the_stem_in_any_format() {
+        let dir = private_temp_dir().unwrap();
+        for name in [
+            "work-20261018-130458-123.json",
+            "work-20261019-080000-001.json",
+            "work-laptop-20261018-130458-123.json",
+            "work.v1-20261018-130458-123.json",
+            "work-notes.json",
+            "work-20261018-130458-123.toml",
+            "work-20261018-130458-123.txt",
+        ] {
+            fs::write(dir.join(name), "{}").unwrap();
+        }
+        let backups = list_backups(&dir, Path::new("profiles/work.json"));
+        let names: Vec<String> = backups
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            names,
+            [
+                "work-20261019-080000-001.json",
+                "work-20261018-130458-123.toml",
+                "work-20261018-130458-123.json"
+            ]
+        );
+    }
+
+    #[test]
+    fn backup_timestamps_need_every_digit() {
+        assert!(is_backup_timestamp("20261018-130458-123"));
+        assert!(!is_backup_timestamp("laptop-20261018-130458-123"));
+        assert!(!is_backup_timestamp("20261018-130458"));
+        assert!(!is_backup_timestamp("2026101x-130458-123"));
+    }
+
+    #[test]
+    fn migrate_legacy_tree_resumes_an_interrupted_run() {
+        let root = private_temp_dir().unwrap();
+        let legacy = root.join("legacy");
+        let (config, state, data) = (root.join("config"), root.join("state"), root.join("data"));
+        let old_tool = legacy.join("bin/tool");
+        fs::create_dir_all(legacy.join("bin")).unwrap();
+        fs::write(&old_tool, "").unwrap();
+        fs::write(
+            legacy.join("menus.json"),
+            format!(r#"{{"cmd": "{}"}}"#, old_tool.display()),
+        )
+        .unwrap();
+        // A previous run moved the profiles, then stopped.
+        fs::create_dir_all(config.join("profiles")).unwrap();
+        fs::write(
+            config.join("profiles/work.json"),
+            format!(r#"{{"cmd": "{}"}}"#, old_tool.display()),
+        )
+        .unwrap();
+
+        let migrated = migrate_legacy_tree(&legacy, &config, &state, &data).unwrap();
+        let menu = fs::read_to_string(config.join("menus.json")).unwrap();
+        let profile = fs::read_to_string(config.join("profiles/work.json")).unwrap();
+        let new_tool = data.join("bin/tool");
+        let again = migrate_legacy_tree(&legacy, &config, &state, &data).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(migrated, Some(legacy.clone()));
+        assert!(menu.contains(&new_tool.display().to_string()));
+        assert!(profile.contains(&new_tool.display().to_string()));
+        assert_eq!(again, None);
+    }
+
+    fn merge(
+        base: serde_json::Value,
+        ours: serde_json::Value,
+        theirs: serde_json::Value,
+    ) -> (serde_json::Value, Vec<String>) {
+        let mut conflicts = Vec::new();
+        let merged = merge_json(Some(&base), Some(&ours), Some(&theirs), "", &mut conflicts);
+        (merged.unwrap(), conflicts)
+    }
+
+    #[test]
+    fn merge_json_combines_edits_to_different_keys() {
+        let (merged, conflicts) = merge(
+            serde_json::json!({"title": "a", "columns": 1}),
+            serde_json::json!({"title": "b", "columns": 1}),
+            serde_json::json!({"title": "a", "columns": 2}),
+        );
+        assert_eq!(merged, serde_json::json!({"title": "b", "columns": 2}));
+        assert!(conflicts.is_empty());
+    }
+
+    #[test]
+    fn merge_json_merges_items_by_label() {
+        let (merged, conflicts) = merge(
+            serde_json::json!({"items": [{"label": "x", "cmd": "1"}]}),
+            serde_json::json!({"items": [{"label": "x", "cmd": "2"}, {"label": "y", "cmd": "y"}]}),
+            serde_json::json!({"items": [{"label": "x", "cmd": "1"}, {"label": "z", "cmd": "z"}]}),
+        );
+        assert_eq!(
+            merged,
+            serde_json::json!({"items": [
+                {"label": "x", "cmd": "2"},
+                {"label": "y", "cmd": "y"},
+                {"label": "z", "cmd": "z"}
+            ]})
+        );
+        assert!(conflicts.is_empty());
+    }
+
+    #[test]
+    fn merge_json_keeps_ours_on_conflict_and_an_edit_over_a_deletion() {
+        let (merged, conflicts) = merge(
+            serde_json::json!({"items": [{"label": "x", "cmd": "1"}, {"label": "y", "cmd": "1"}]}),
+            serde_json::json!({"items": [{"label": "x", "cmd": "2"}]}),
+            serde_json::json!({"items": [{"label": "x", "cmd": "3"}, {"label": "y", "cmd": "3"}]}),
+        );
+        assert_eq!(
+            merged,
+            serde_json::json!({"items": [{"label": "x", "cmd": "2"}, {"label": "y", "cmd": "3"}]})
+        );
+        assert_eq!(conflicts, ["items/x/cmd", "items/y"]);
+    }
+
+    #[test]
+    fn merge_json_keeps_ours_for_lists_without_unique_names() {
+        let (merged, conflicts) = merge(
+            serde_json::json!({"items": [{"label": "x"}]}),
+            serde_json::json!({"items": [{"label": "x"}, {"label": "x"}]}),
+            serde_json::json!({"items": []}),
+        );
+        assert_eq!(
+            merged,
+            serde_json::json!({"items": [{"label": "x"}, {"label": "x"}]})
+        );
+        assert_eq!(conflicts, ["items"]);
+    }
+
+    #[test]
+    fn merge_notice_only_points_to_backups_that_exist() {
+        let conflicts = ["categories/Dev/items/build/cmd".to_string()];
+        assert_eq!(
+            merge_notice(&conflicts, true),
+            "Merged changes from another instance; kept your version of Dev/build/cmd (theirs is in backups)"
+        );
+        assert_eq!(
+            merge_notice(&conflicts, false),
+            "Merged changes from another instance; kept your version of Dev/build/cmd"
+        );
+    }
+
+    #[test]
+    fn lock_file_creates_the_directory_of_a_new_profile() {
+        let dir = private_temp_dir().unwrap();
+        let path = dir.join("profiles/work.json");
+        let lock = lock_file(&path);
+        let created = dir.join("profiles/work.json.lock").exists();
+        drop(lock);
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(created);
+    }
+
+    #[test]
+    fn backup_contents_converts_to_the_target_format() {
+        let dir = private_temp_dir().unwrap();
+        let backup = dir.join("menus-20261018-130458-123.json");
+        MenuFile::default_data().save(&backup).unwrap();
+        let toml = backup_contents(&backup, &dir.join("menus.toml"));
+        let json = backup_contents(&backup, &dir.join("menus.json"));
+        let original = fs::read_to_string(&backup).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        let parsed: MenuFile = MenuFormat::Toml.parse(&toml.unwrap()).unwrap();
+        assert_eq!(
+            serde_json::to_value(parsed).unwrap(),
+            serde_json::to_value(MenuFile::default_data()).unwrap()
+        );
+        assert_eq!(json.unwrap(), original);
+    }
+
+    #[test]
+    fn migrate_legacy_tree_skips_a_toml_or_yaml_menu() {
+        let root = private_temp_dir().unwrap();
+        let legacy = root.join("legacy");
+        let config = root.join("config");
+        fs::create_dir_all(&legacy).unwrap();
+        fs::write(legacy.join("menus.json"), "{}").unwrap();
+        fs::create_dir_all(&config).unwrap();
+        fs::write(config.join("menus.yaml"), "categories: {}").unwrap();
+        let migrated = migrate_legacy_tree(&legacy, &config, &root, &root).unwrap();
+        let untouched = legacy.join("menus.json").exists() && !config.join("menus.json").exists();
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(migrated, None);
+        assert!(untouched);
+    }
+
+    fn menu_with_items(items: &[(&str, &str)]) -> MenuFile {
+        let mut menu_file = MenuFile::default_data();
+        menu_file.categories.clear();
+        menu_file.categories.insert(
+            "Tools".to_string(),
+            CategoryConfig {
+                expanded: true,
+                column: Some(1),
+                items: items
+                    .iter()
+                    .map(|(label, cmd)| MenuItemConfig {
+                        label: label.to_string(),
+                        cmd: cmd.to_string(),
+                        info: None,
+                        pause: None,
+                        style: ItemStyle::default(),
+                    })
+                    .collect(),
+                colors: None,
+            },
+        );
+        menu_file
+    }
+
+    #[test]
+    fn slug_falls_back_to_a_hash_for_non_ascii_text() {
+        assert_eq!(slug("Build & Test!"), "build-test");
+        assert_eq!(slug("--Café--"), "caf");
+        let japanese = slug("ビルド");
+        let greek = slug("Δοκιμή");
+        assert_eq!(japanese.len(), 8);
+        assert_ne!(japanese, greek);
+        assert_eq!(japanese, slug("ビルド"));
+    }
+
+    #[test]
+    fn desktop_export_gives_colliding_items_their_own_files() {
+        let dir = private_temp_dir().unwrap();
+        let menu_file =
+            menu_with_items(&[("ビルド", "a"), ("テスト", "b"), ("Run", "c"), ("run", "d")]);
+        let count = export_desktop_entries(&menu_file, &dir).unwrap();
+        let files = fs::read_dir(&dir).unwrap().count();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(count, 4);
+        assert_eq!(files, 4);
+    }
+
+    #[test]
+    fn export_format_rejects_unknown_extensions() {
+        assert_eq!(
+            ExportFormat::from_path(Path::new("menu.md")).unwrap(),
+            ExportFormat::Markdown
+        );
+        assert_eq!(
+            ExportFormat::from_path(Path::new("apps")).unwrap(),
+            ExportFormat::Desktop
+        );
+        let err = ExportFormat::from_path(Path::new("menu.pdf")).unwrap_err();
+        assert!(err.to_string().contains(".md, .html, .sh"));
+    }
+
+    #[test]
+    fn markdown_export_escapes_labels_and_fences_backticks() {
+        let menu_file = menu_with_items(&[("*bold* [link]", "echo ```` done")]);
+        let markdown = export_markdown(&menu_file);
+        assert!(markdown.contains(r"- **\*bold\* \[link\]**"));
+        assert!(markdown.contains("\n  `````sh\n  echo ```` done\n  `````\n"));
+    }
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn shell_commands_splits_at_separators_outside_quotes() {
+        assert_eq!(
+            shell_commands(r#"alias a='x; y' && alias b="p | q" ; echo \; done # note"#),
+            [
+                words(&["alias", "a=x; y"]),
+                words(&["alias", "b=p | q"]),
+                words(&["echo", ";", "done"]),
+            ]
+        );
+        assert_eq!(
+            shell_commands("a|b&c"),
+            [words(&["a"]), words(&["b"]), words(&["c"])]
+        );
+        assert!(shell_commands("  # only a comment").is_empty());
+    }
+
+    fn definition_names(text: &str) -> Vec<String> {
+        parse_rc_definitions(text)
+            .into_iter()
+            .map(|definition| match definition {
+                RcDefinition::Alias(name, body) => format!("alias {name}={body}"),
+                RcDefinition::Function(name) => format!("function {name}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parse_rc_definitions_reads_every_alias_on_a_line() {
+        assert_eq!(
+            definition_names("alias ll='ls -l'; alias la='ls -A'\nalias -g G='| grep'\nalias _x=y"),
+            ["alias ll=ls -l", "alias la=ls -A"]
+        );
+    }
+
+    #[test]
+    fn parse_rc_definitions_needs_a_function_body() {
+        let text = "\
+build() {
+    make
+}
+deploy()
+{
+    ./deploy.sh
+}
+function serve {
+function watch
+# comment
+(
+notes()
+echo not a function
+usage() echo nope
+";
+        assert_eq!(
+            definition_names(text),
+            [
+                "function build",
+                "function deploy",
+                "function serve",
+                "function watch"
+            ]
+        );
+    }
+
+    fn task_names(found: Option<(&'static str, Vec<RunnerTask>)>) -> (&'static str, Vec<String>) {
+        let (runner, tasks) = found.expect("task file should be found");
+        (runner, tasks.into_iter().map(|task| task.name).collect())
+    }
+
+    #[test]
+    fn make_targets_skips_patterns_special_targets_and_variables() {
+        let dir = private_temp_dir().unwrap();
+        fs::write(
+            dir.join("Makefile"),
+            "\
+CC := gcc
+OUT = out
+.PHONY: build test
+build test: ## Build and test
+\tcc main.c
+%.o: %.c
+$(OUT): build
+lint:
+build:
+",
+        )
+        .unwrap();
+        let (runner, tasks) = make_targets(&dir).unwrap().unwrap();
+        assert_eq!(runner, "make");
+        let names: Vec<_> = tasks.iter().map(|task| task.name.as_str()).collect();
+        assert_eq!(names, ["build", "test", "lint"]);
+        assert_eq!(tasks[0].command, "make build");
+        assert_eq!(tasks[0].info, "Build and test");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn package_scripts_use_the_locked_package_manager() {
+        let dir = private_temp_dir().unwrap();
+        fs::write(
+            dir.join("package.json"),
+            r#"{"scripts": {"dev": "vite", "build": "vite build"}}"#,
+        )
+        .unwrap();
+        let (runner, tasks) = package_scripts(&dir).unwrap().unwrap();
+        assert_eq!(runner, "npm");
+        let dev = tasks.iter().find(|task| task.name == "dev").unwrap();
+        assert_eq!(dev.command, "npm run dev");
+        assert_eq!(dev.info, "vite");
+        fs::write(dir.join("yarn.lock"), "").unwrap();
+        assert_eq!(task_names(package_scripts(&dir).unwrap()).0, "yarn");
+        fs::write(dir.join("pnpm-lock.yaml"), "").unwrap();
+        assert_eq!(task_names(package_scripts(&dir).unwrap()).0, "pnpm");
+        fs::write(dir.join("package.json"), "{").unwrap();
+        assert!(package_scripts(&dir).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn just_recipes_skip_private_recipes_and_keep_comments() {
+        let dir = private_temp_dir().unwrap();
+        fs::write(
+            dir.join("justfile"),
+            "\
+#!/usr/bin/env just
+set shell := [\"bash\", \"-c\"]
+version := \"1.0\"
+
+# Run the tests
+test *args:
+    cargo test {{args}}
+
+_helper:
+    true
+
+[private]
+secret:
+    true
+
+@fmt:
+    cargo fmt
+",
+        )
+        .unwrap();
+        let (runner, tasks) = just_recipes(&dir).unwrap().unwrap();
+        assert_eq!(runner, "just");
+        let names: Vec<_> = tasks.iter().map(|task| task.name.as_str()).collect();
+        assert_eq!(names, ["test", "fmt"]);
+        assert_eq!(tasks[0].command, "just test");
+        assert_eq!(tasks[0].info, "Run the tests");
+        assert!(tasks[1].info.starts_with("just recipe in "));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn cargo_aliases_expand_strings_and_lists() {
+        let dir = private_temp_dir().unwrap();
+        assert!(cargo_aliases(&dir).unwrap().is_none());
+        fs::create_dir(dir.join(".cargo")).unwrap();
+        fs::write(
+            dir.join(".cargo/config.toml"),
+            "[alias]\nb = \"build --release\"\nxtask = [\"run\", \"-p\", \"xtask\", \"--\"]\n",
+        )
+        .unwrap();
+        let (runner, tasks) = cargo_aliases(&dir).unwrap().unwrap();
+        assert_eq!(runner, "cargo");
+        let infos: Vec<_> = tasks
+            .iter()
+            .map(|task| (task.command.as_str(), task.info.as_str()))
+            .collect();
+        assert_eq!(
+            infos,
+            [
+                ("cargo b", "cargo build --release"),
+                ("cargo xtask", "cargo run -p xtask --")
+            ]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn detect_tasks_groups_tasks_by_runner() {
+        let dir = private_temp_dir().unwrap();
+        fs::write(dir.join("Makefile"), "all:\n").unwrap();
+        fs::write(dir.join("justfile"), "check:\n    true\n").unwrap();
+        let project = dir.file_name().unwrap().to_string_lossy().into_owned();
+        let candidates = detect_tasks(&dir).unwrap();
+        let found: Vec<_> = candidates
+            .iter()
+            .map(|candidate| (candidate.category.clone(), candidate.label.clone()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (format!("{project}: make"), "all".to_string()),
+                (format!("{project}: just"), "check".to_string())
+            ]
+        );
+        assert!(candidates[0].cmd.starts_with("cd "));
+        assert!(candidates[0].cmd.ends_with(" && make all"));
+        assert!(detect_tasks(&dir.join("missing")).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// An app whose files all live in `dir`, with no system or project menu.
+    fn test_app(dir: &Path, menu_file: &MenuFile) -> AppState {
+        let paths = AppPaths {
+            config_dir: dir.to_path_buf(),
+            data_dir: dir.to_path_buf(),
+            menu_file: dir.join("menus.json"),
+            theme_file: dir.join("theme.json"),
+            keymap_file: dir.join("keymap.json"),
+            themes_dir: dir.join("themes"),
+            backups_dir: dir.join("backups"),
+            profiles_dir: dir.join("profiles"),
+            profile: Some(DEFAULT_PROFILE.to_string()),
+            migrated_from: None,
+            system_menu_file: dir.join("system-menus.json"),
+            project_menu_file: None,
+        };
+        menu_file.save(&paths.menu_file).unwrap();
+        AppState::new(paths).unwrap()
+    }
+
+    #[test]
+    fn save_menu_does_not_back_up_a_collapsed_category() {
+        let dir = private_temp_dir().unwrap();
+        let mut app = test_app(&dir, &menu_with_items(&[("Top", "top")]));
+        let backups = || list_backups(&dir.join("backups"), &dir.join("menus.json")).len();
+        app.save_menu().unwrap();
+        let before = backups();
+        for _ in 0..3 {
+            app.categories[0].expanded = !app.categories[0].expanded;
+            app.save_menu().unwrap();
+        }
+        assert_eq!(backups(), before);
+        app.categories[0].items[0].cmd = "htop".to_string();
+        app.save_menu().unwrap();
+        assert_eq!(backups(), before + 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn keeping_edits_still_merges_an_outside_change_on_save() {
+        let dir = private_temp_dir().unwrap();
+        let mut app = test_app(&dir, &menu_with_items(&[("Top", "top")]));
+        let menu_path = dir.join("menus.json");
+        let mut outside = MenuFile::read(&menu_path).unwrap();
+        outside
+            .categories
+            .get_mut("Tools")
+            .unwrap()
+            .items
+            .push(MenuItemConfig {
+                label: "Disk".to_string(),
+                cmd: "df -h".to_string(),
+                ..MenuItemConfig::default()
+            });
+        outside.save(&menu_path).unwrap();
+        app.external_change = Some(ExternalChange {
+            files: vec![menu_path.clone()],
+        });
+        app.handle_external_change_key(KeyEvent::new(KeyCode::Char('k'), KeyModifiers::NONE));
+        assert!(app.external_change.is_none());
+        app.categories[0].items[0].cmd = "htop".to_string();
+        app.save_menu().unwrap();
+        let saved: Vec<_> = MenuFile::read(&menu_path).unwrap().categories["Tools"]
+            .items
+            .iter()
+            .map(|item| (item.label.clone(), item.cmd.clone()))
+            .collect();
+        assert_eq!(
+            saved,
+            [
+                ("Top".to_string(), "htop".to_string()),
+                ("Disk".to_string(), "df -h".to_string())
+            ]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn save_menu_keeps_user_categories_that_only_show_system_items() {
+        let dir = private_temp_dir().unwrap();
+        let mut system = menu_with_items(&[("Top", "top")]);
+        let extra = system.categories["Tools"].clone();
+        system.categories.insert("Extra".to_string(), extra);
+        system.save(&dir.join("system-menus.json")).unwrap();
+        let mut user = menu_with_items(&[]);
+        let tools = user.categories.get_mut("Tools").unwrap();
+        tools.column = Some(2);
+        tools.colors = Some(ColorConfig {
+            background: Some("#112233".to_string()),
+            text: None,
+        });
+        let app = test_app(&dir, &user);
+        assert!(app
+            .categories
+            .iter()
+            .all(|category| !category.items.is_empty()));
+        app.save_menu().unwrap();
+        let saved = MenuFile::read(&dir.join("menus.json")).unwrap();
+        assert_eq!(saved.categories.keys().collect::<Vec<_>>(), ["Tools"]);
+        let tools = &saved.categories["Tools"];
+        assert!(tools.items.is_empty());
+        assert_eq!(tools.column, Some(2));
+        assert_eq!(
+            tools
+                .colors
+                .as_ref()
+                .and_then(|colors| colors.background.as_deref()),
+            Some("#112233")
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn moving_an_item_to_a_profile_rejects_a_duplicate_label() {
+        let dir = private_temp_dir().unwrap();
+        let mut app = test_app(&dir, &menu_with_items(&[("Top", "top")]));
+        let profile_path = dir.join("profiles/work.json");
+        fs::create_dir_all(dir.join("profiles")).unwrap();
+        menu_with_items(&[("Top", "btop")])
+            .save(&profile_path)
+            .unwrap();
+        let input = ItemFormInput {
+            target: Some((0, 0)),
+            label: "Top".to_string(),
+            command: "top".to_string(),
+            info: String::new(),
+            category: "Tools".to_string(),
+            fallback_category: "Tools".to_string(),
+            pause: false,
+            icon: String::new(),
+            text_color: String::new(),
+            background: String::new(),
+            bold: false,
+            dim: false,
+            italic: false,
+            profile: Some("work".to_string()),
+        };
+        let err = app.apply_item_form_input(input).unwrap_err();
+        assert!(err.contains("Tools/Top already exists"), "{err}");
+        assert_eq!(app.categories[0].items.len(), 1);
+        let profile = MenuFile::read(&profile_path).unwrap();
+        assert_eq!(profile.categories["Tools"].items.len(), 1);
+        assert_eq!(profile.categories["Tools"].items[0].cmd, "btop");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn desktop_entries_are_not_rendered_as_a_single_file() {
+        let menu_file = menu_with_items(&[("Top", "top")]);
+        let err = ExportFormat::Desktop.render(&menu_file).unwrap_err();
+        assert_eq!(err.to_string(), "Desktop entries need a directory");
+        assert!(ExportFormat::Script
+            .render(&menu_file)
+            .unwrap()
+            .starts_with("#!"));
+    }
+
+    #[test]
+    fn python_menus_are_version_zero_and_upgrade_to_the_latest_version() {
+        let mut value = serde_json::json!({
+            "title": "Old menu",
+            "menus": [
+                {"name": "Top", "command": "top", "category": "Tools"},
+                {"name": "Disk", "command": "df -h", "description": "Free space"}
+            ]
+        });
+        assert_eq!(menu_schema_version(&value), 0);
+        migrate_menu_value(&mut value, 0).unwrap();
+        let menu_file: MenuFile = serde_json::from_value(value).unwrap();
+        assert_eq!(menu_file.schema_version, MENU_SCHEMA_VERSION);
+        assert_eq!(menu_file.app_settings.title.as_deref(), Some("Old menu"));
+        assert_eq!(menu_file.categories["Tools"].items[0].cmd, "top");
+        let imported = &menu_file.categories["Imported"].items[0];
+        assert_eq!(imported.label, "Disk");
+        assert_eq!(imported.info.as_deref(), Some("Free space"));
+
+        let by_category = serde_json::json!({"Tools": [{"name": "Top", "exec": "top"}]});
+        assert_eq!(menu_schema_version(&by_category), 0);
+        let bare_list = serde_json::json!([{"label": "Top", "cmd": "top"}]);
+        assert_eq!(menu_schema_version(&bare_list), 0);
+    }
+
+    #[test]
+    fn migrating_a_latest_version_menu_changes_nothing() {
+        let menu_file = menu_with_items(&[("Top", "top")]);
+        let mut value = serde_json::to_value(&menu_file).unwrap();
+        let before = value.clone();
+        assert_eq!(menu_schema_version(&value), MENU_SCHEMA_VERSION);
+        migrate_menu_value(&mut value, MENU_SCHEMA_VERSION).unwrap();
+        assert_eq!(value, before);
+
+        let dir = private_temp_dir().unwrap();
+        let path = dir.join("menus.json");
+        menu_file.save(&path).unwrap();
+        let saved = fs::read(&path).unwrap();
+        MenuFile::load(&path, &dir.join("backups")).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), saved);
+        assert!(!dir.join("backups").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn empty_and_settings_only_menus_load_as_empty_menus() {
+        let dir = private_temp_dir().unwrap();
+        let path = dir.join("menus.json");
+        for text in ["{}", r#"{"app_settings": {"title": "Mine"}}"#] {
+            fs::write(&path, text).unwrap();
+            assert_eq!(menu_schema_version(&serde_json::from_str(text).unwrap()), 1);
+            let menu_file = MenuFile::load(&path, &dir.join("backups")).unwrap();
+            assert!(menu_file.categories.is_empty());
+            assert_eq!(menu_file.schema_version, MENU_SCHEMA_VERSION);
+        }
+        assert_eq!(
+            MenuFile::read(&path).unwrap().app_settings.title.as_deref(),
+            Some("Mine")
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn chord(code: KeyCode, modifiers: KeyModifiers) -> Option<KeyChord> {
+        Some(KeyChord::new(code, modifiers))
+    }
+
+    #[test]
+    fn key_chords_parse_names_modifiers_and_shorthand() {
+        let ctrl = KeyModifiers::CONTROL;
+        let none = KeyModifiers::NONE;
+        assert_eq!(KeyChord::parse("^x"), chord(KeyCode::Char('x'), ctrl));
+        assert_eq!(KeyChord::parse("Ctrl+X"), chord(KeyCode::Char('x'), ctrl));
+        assert_eq!(KeyChord::parse("Ctrl++"), chord(KeyCode::Char('+'), ctrl));
+        assert_eq!(KeyChord::parse("+"), chord(KeyCode::Char('+'), none));
+        assert_eq!(KeyChord::parse("Shift+Tab"), chord(KeyCode::BackTab, none));
+        assert_eq!(KeyChord::parse("Shift+a"), chord(KeyCode::Char('A'), none));
+        assert_eq!(KeyChord::parse("F5"), chord(KeyCode::F(5), none));
+        assert_eq!(
+            KeyChord::parse("alt+PgDn"),
+            chord(KeyCode::PageDown, KeyModifiers::ALT)
+        );
+        for invalid in ["", "F0", "F25", "Hyper+x", "Ctrl+", "enterr"] {
+            assert_eq!(KeyChord::parse(invalid), None, "{invalid}");
+        }
+        for label in ["^x", "Ctrl+Alt+Del", "Shift+Tab", "F12", "Space"] {
+            assert_eq!(KeyChord::parse(label).unwrap().label(), label);
+        }
+    }
+
+    fn keymap_with(context: KeyContext, entries: &[(&str, &[&str])]) -> (Keymap, Vec<String>) {
+        let mut file = KeymapFile::default();
+        for (name, keys) in entries {
+            file.section_mut(context).insert(
+                name.to_string(),
+                keys.iter().map(|key| key.to_string()).collect(),
+            );
+        }
+        Keymap::from_file(&file)
+    }
+
+    fn press(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn default_keymap_has_no_warnings() {
+        let (keymap, warnings) = Keymap::from_file(&KeymapFile::defaults());
+        assert_eq!(warnings, Vec::<String>::new());
+        // Field-scoped actions share a key without a conflict.
+        assert_eq!(
+            keymap.actions_for(KeyContext::ItemForm, &press(KeyCode::Char(' '))),
+            [KeyAction::TogglePause, KeyAction::ToggleEmphasis]
+        );
+    }
+
+    #[test]
+    fn keymap_warns_about_conflicts_and_keeps_the_first_action() {
+        let (keymap, warnings) =
+            keymap_with(KeyContext::Main, &[("quit", &["x"]), ("reload", &["x"])]);
+        assert_eq!(
+            warnings,
+            ["x in main view is bound to both 'quit' and 'reload'; keeping 'quit'"]
+        );
+        assert_eq!(
+            keymap.action_for(KeyContext::Main, &press(KeyCode::Char('x'))),
+            Some(KeyAction::Quit)
+        );
+    }
+
+    #[test]
+    fn configured_keys_shadow_default_ones() {
+        let (keymap, warnings) = keymap_with(KeyContext::Main, &[("reload", &["q"])]);
+        assert_eq!(
+            warnings,
+            ["q in main view is bound to both 'reload' and 'quit'; keeping 'reload'"]
+        );
+        assert_eq!(
+            keymap.action_for(KeyContext::Main, &press(KeyCode::Char('q'))),
+            Some(KeyAction::Reload)
+        );
+        assert_eq!(
+            keymap.action_for(KeyContext::Main, &press(KeyCode::Esc)),
+            Some(KeyAction::Quit)
+        );
+    }
+
+    #[test]
+    fn keymap_warns_about_unknown_actions_bad_keys_and_blocked_typing() {
+        let (_, warnings) = keymap_with(
+            KeyContext::ItemForm,
+            &[("launch", &["F3"]), ("submit", &["Hyper+s", "s"])],
+        );
+        assert_eq!(
+            warnings,
+            [
+                "Unknown item form action 'launch'",
+                "Invalid key 'Hyper+s' for item form action 'submit'",
+                "s for item form action 'submit' blocks typing that character"
+            ]
+        );
+    }
+
+    const BASE16_SLOTS: &str = "
+base00: \"002b36\"
+base01: \"073642\"
+base05: \"93a1a1\"
+base09: \"cb4b16\"
+base0C: \"2aa198\"
+base0D: \"268bd2\"
+";
+
+    #[test]
+    fn base16_schemes_read_flat_and_nested_palettes() {
+        let flat = format!("scheme: \"Solarized\"{BASE16_SLOTS}");
+        let theme = import_base16_scheme(&flat, "Fallback").unwrap();
+        assert_eq!(theme.name, "Solarized");
+        assert_eq!(theme.background, "#002b36");
+        assert_eq!(theme.surface, "#073642");
+        assert_eq!(theme.text, "#93a1a1");
+        assert_eq!(theme.primary, "#268bd2");
+        assert_eq!(theme.accent, "#cb4b16");
+        assert_eq!(theme.highlight.as_deref(), Some("#2aa198"));
+
+        let nested = format!(
+            "name: Tinted\npalette:{}",
+            BASE16_SLOTS
+                .replace('\n', "\n  ")
+                .replace("\"268bd2\"", "\"0x268BD2\"")
+        );
+        let theme = import_base16_scheme(&nested, "Fallback").unwrap();
+        assert_eq!(theme.name, "Tinted");
+        assert_eq!(theme.primary, "#268BD2");
+    }
+
+    #[test]
+    fn base16_schemes_reject_malformed_colors() {
+        let broken = BASE16_SLOTS.replace("268bd2", "268bzz");
+        let err = import_base16_scheme(&broken, "Fallback").unwrap_err();
+        assert_eq!(err.to_string(), "base16 scheme is missing base0D");
+        let short = BASE16_SLOTS.replace("002b36", "02b36");
+        assert!(import_base16_scheme(&short, "Fallback").is_err());
+        assert!(import_base16_scheme("base00: [", "Fallback").is_err());
+        let theme = import_base16_scheme(BASE16_SLOTS, "Fallback").unwrap();
+        assert_eq!(theme.name, "Fallback");
+    }
+
+    const ALACRITTY_SCHEME: &str = r##"
+[colors.primary]
+background = "#1d1f21"
+foreground = "0xc5c8c6"
+
+[colors.normal]
+blue = "#81a2be"
+yellow = "#f0c674"
+cyan = "#8abeb7"
+"##;
+
+    #[test]
+    fn alacritty_schemes_fill_the_surface_when_bright_black_is_missing() {
+        let theme = import_alacritty_scheme(ALACRITTY_SCHEME, "Tomorrow Night").unwrap();
+        assert_eq!(theme.name, "Tomorrow Night");
+        assert_eq!(theme.background, "#1d1f21");
+        assert_eq!(theme.text, "#c5c8c6");
+        assert_eq!(theme.primary, "#81a2be");
+        assert_eq!(theme.accent, "#f0c674");
+        assert_eq!(theme.highlight.as_deref(), Some("#8abeb7"));
+        assert_eq!(theme.surface, mix_hex("#1d1f21", "#c5c8c6", 0.12));
+
+        let with_bright = format!("{ALACRITTY_SCHEME}\n[colors.bright]\nblack = \"#373b41\"\n");
+        let theme = import_alacritty_scheme(&with_bright, "Tomorrow Night").unwrap();
+        assert_eq!(theme.surface, "#373b41");
+    }
+
+    #[test]
+    fn alacritty_schemes_reject_malformed_colors() {
+        let broken = ALACRITTY_SCHEME.replace("#81a2be", "#81a2b");
+        let err = import_alacritty_scheme(&broken, "Theme").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Alacritty theme is missing colors.normal.blue"
+        );
+        let err = import_alacritty_scheme("[window]\nopacity = 0.9\n", "Theme").unwrap_err();
+        assert_eq!(err.to_string(), "Alacritty theme has no [colors] table");
+        assert!(import_alacritty_scheme("[colors", "Theme").is_err());
+    }
+
+    fn windows_terminal_scheme(name: &str, blue: &str) -> serde_json::Value {
+        serde_json::json!({
+            "name": name,
+            "background": "#0C0C0C",
+            "foreground": "#CCCCCC",
+            "blue": blue,
+            "yellow": "#C19C00",
+            "cyan": "#3A96DD",
+            "brightBlack": "#767676"
+        })
+    }
+
+    #[test]
+    fn windows_terminal_schemes_skip_broken_entries() {
+        let settings = serde_json::json!({
+            "profiles": {},
+            "schemes": [
+                windows_terminal_scheme("Campbell", "#0037DA"),
+                windows_terminal_scheme("Broken", "blue")
+            ]
+        })
+        .to_string();
+        let (themes, skipped) = import_windows_terminal_schemes(&settings, "Settings").unwrap();
+        assert_eq!(themes.len(), 1);
+        assert_eq!(themes[0].name, "Campbell");
+        assert_eq!(themes[0].primary, "#0037DA");
+        assert_eq!(themes[0].surface, "#767676");
+        assert_eq!(
+            skipped,
+            ["skipped Broken: Windows Terminal scheme is missing blue"]
+        );
+
+        let single = windows_terminal_scheme("One", "#0037DA").to_string();
+        let (themes, skipped) = import_windows_terminal_schemes(&single, "Settings").unwrap();
+        assert_eq!((themes.len(), skipped.len()), (1, 0));
+        assert_eq!(themes[0].name, "One");
+    }
+
+    #[test]
+    fn windows_terminal_files_without_usable_schemes_fail() {
+        let broken = serde_json::json!({
+            "schemes": [windows_terminal_scheme("Broken", "#00GG00")]
+        })
+        .to_string();
+        let err = import_windows_terminal_schemes(&broken, "Settings").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "No usable color schemes: skipped Broken: Windows Terminal scheme is missing blue"
+        );
+        let empty = r#"{"schemes": []}"#;
+        assert!(import_windows_terminal_schemes(empty, "Settings").is_err());
+        assert!(import_windows_terminal_schemes("{", "Settings").is_err());
+    }
+
+    #[test]
+    fn a_running_app_follows_a_converted_menu_file() {
+        let dir = private_temp_dir().unwrap();
+        let mut app = test_app(&dir, &menu_with_items(&[("Top", "top")]));
+        let json_path = dir.join("menus.json");
+        let toml_path = dir.join("menus.toml");
+        {
+            let _lock = lock_file(&json_path).unwrap();
+            convert_menu_file(&app.paths, MenuFormat::Toml).unwrap();
+        }
+        assert!(lock_path(&json_path).exists());
+        app.categories[0].items[0].cmd = "htop".to_string();
+        assert!(app.save_menu().is_err());
+        assert!(!json_path.exists());
+        app.check_config_files();
+        assert_eq!(app.paths.menu_file, toml_path);
+        app.categories[0].items[0].cmd = "htop".to_string();
+        app.save_menu().unwrap();
+        assert!(!json_path.exists());
+        let saved = MenuFile::read(&toml_path).unwrap();
+        assert_eq!(saved.categories["Tools"].items[0].cmd, "htop");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}